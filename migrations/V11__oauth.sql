@@ -0,0 +1,37 @@
+-- client apps registered to act on behalf of local users
+CREATE TABLE oauth_apps (
+	client_id		TEXT NOT NULL PRIMARY KEY UNIQUE,
+	client_secret	TEXT NOT NULL,
+	name			TEXT NOT NULL,
+	-- whitespace separated, a redirect must match one of them exactly
+	redirect_uris	TEXT NOT NULL,
+	-- whitespace separated scopes the app may request
+	scopes			TEXT NOT NULL,
+	website			TEXT NULL,
+	created_at		BIGINT NOT NULL
+);
+
+-- authorization codes waiting to be exchanged for a token. they
+-- can only be used once and are removed when exchanged
+CREATE TABLE oauth_codes (
+	code					TEXT NOT NULL PRIMARY KEY UNIQUE,
+	client_id				TEXT NOT NULL REFERENCES oauth_apps(client_id) ON DELETE CASCADE,
+	uid						TEXT NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	redirect_uri			TEXT NOT NULL,
+	scopes					TEXT NOT NULL,
+	-- pkce, only S256 is accepted
+	code_challenge			TEXT NULL,
+	code_challenge_method	TEXT NULL,
+	expires					BIGINT NOT NULL
+);
+
+-- access tokens issued to apps. only the sha256 of the token is
+-- stored so a leaked database can't be used to act as users
+CREATE TABLE oauth_tokens (
+	token_hash		TEXT NOT NULL PRIMARY KEY UNIQUE,
+	client_id		TEXT NOT NULL REFERENCES oauth_apps(client_id) ON DELETE CASCADE,
+	-- null for tokens of the app itself through client credentials
+	uid				TEXT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	scopes			TEXT NOT NULL,
+	created_at		BIGINT NOT NULL
+);
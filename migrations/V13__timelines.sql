@@ -0,0 +1,2 @@
+-- timelines are read newest first and paged by when posts were published
+CREATE INDEX posts_published ON posts (published, id);
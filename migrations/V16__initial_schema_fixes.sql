@@ -0,0 +1,11 @@
+-- columns the code relied on that the initial schema was missing
+-- when the user signed up or was first seen, 0 for rows from before
+ALTER TABLE users ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+-- the initial schema keyed users on a column that never existed
+CREATE UNIQUE INDEX users_domain_username ON users (domain, username);
+
+-- content warning
+ALTER TABLE posts ADD COLUMN subject TEXT NULL;
+ALTER TABLE posts ADD COLUMN is_sensitive BOOLEAN NOT NULL DEFAULT false;
+-- the post being shared if this is a share
+ALTER TABLE posts ADD COLUMN shared TEXT NULL;
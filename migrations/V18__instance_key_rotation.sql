@@ -0,0 +1,6 @@
+-- the keys the instance actor had before its last rotation. they stay
+-- valid until they expire so requests signed before the change still
+-- verify
+ALTER TABLE ap_instance_actor ADD COLUMN previous_public_key_pem TEXT NULL;
+ALTER TABLE ap_instance_actor ADD COLUMN previous_versia_public_key_pem TEXT NULL;
+ALTER TABLE ap_instance_actor ADD COLUMN previous_key_expires BIGINT NULL;
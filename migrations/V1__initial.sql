@@ -40,9 +40,7 @@ CREATE TABLE users (
 	password			TEXT NULL, 	--stored with argon2
 	email				TEXT NULL,
 	private_key_pem		TEXT NULL,
-	permission_level 	SMALLINT NULL,
-
-	UNIQUE (domain, preferred_username)
+	permission_level 	SMALLINT NULL
 );
 
 CREATE TABLE ap_instance_actor (
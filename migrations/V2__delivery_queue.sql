@@ -0,0 +1,19 @@
+-- outgoing requests waiting to be delivered to a federated inbox
+CREATE TABLE deliveries (
+	id				TEXT NOT NULL PRIMARY KEY UNIQUE,
+	inbox			TEXT NOT NULL,
+	-- the protocol the body is meant for, decides how it is signed
+	protocol		TEXT NOT NULL,
+	body			TEXT NOT NULL,
+	-- the uid of the local user signing the request. not a foreign
+	-- key so that deliveries can outlive the user being deleted
+	signer			TEXT NOT NULL,
+	attempts		INTEGER NOT NULL DEFAULT 0,
+	created_at		BIGINT NOT NULL,
+	next_attempt	BIGINT NOT NULL,
+	last_error		TEXT NULL,
+	-- given up on after failing for too long, kept for inspection
+	dead			BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX deliveries_due ON deliveries (dead, next_attempt);
@@ -0,0 +1,3 @@
+-- the shared inbox of activitypub users, deliveries to users
+-- sharing one are only sent once
+ALTER TABLE users ADD COLUMN shared_inbox TEXT NULL;
@@ -0,0 +1,2 @@
+-- a link to the user's avatar image
+ALTER TABLE users ADD COLUMN avatar TEXT NULL;
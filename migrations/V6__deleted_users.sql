@@ -0,0 +1,3 @@
+-- local users that deleted their account. the row is kept with their
+-- keys so the delete can still be signed and the name can't be reused
+ALTER TABLE users ADD COLUMN deleted BOOLEAN NOT NULL DEFAULT false;
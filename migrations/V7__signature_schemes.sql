@@ -0,0 +1,2 @@
+-- the http signature scheme an instance accepted our deliveries with
+ALTER TABLE instances ADD COLUMN signature_scheme TEXT NULL;
@@ -0,0 +1,4 @@
+-- the key a user had before their last rotation. it stays valid
+-- until it expires so requests signed before the change still verify
+ALTER TABLE users ADD COLUMN previous_public_key_pem TEXT NULL;
+ALTER TABLE users ADD COLUMN previous_key_expires BIGINT NULL;
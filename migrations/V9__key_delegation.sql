@@ -0,0 +1,3 @@
+-- the user a versia profile's key belongs to when it isn't their own,
+-- that user may sign on their behalf
+ALTER TABLE users ADD COLUMN key_delegate TEXT NULL;
@@ -0,0 +1,6 @@
+-- versia only supports ed25519 while the instance actor uses rsa, so the
+-- instance signs versia requests with a key of its own. these are filled
+-- in the first time the instance actor is loaded
+ALTER TABLE ap_instance_actor ADD COLUMN versia_private_key_pem TEXT NULL;
+ALTER TABLE ap_instance_actor ADD COLUMN versia_public_key_pem TEXT NULL;
+ALTER TABLE ap_instance_actor ADD COLUMN created_at BIGINT NULL;
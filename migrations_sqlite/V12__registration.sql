@@ -0,0 +1,27 @@
+-- local users vetted through an invite, an application or a moderator.
+-- users that signed up while registration was open aren't and get
+-- demoted when the instance switches to manual approval
+ALTER TABLE users ADD COLUMN approved BOOLEAN NOT NULL DEFAULT true;
+
+-- the registration mode the instance last ran with, used to notice
+-- the switch to manual approval
+CREATE TABLE registration_mode (
+	mode			TEXT NOT NULL
+);
+
+CREATE TABLE invites (
+	code			TEXT NOT NULL PRIMARY KEY UNIQUE,
+	created_by		TEXT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	-- unlimited when null
+	max_uses		INTEGER NULL,
+	uses			INTEGER NOT NULL DEFAULT 0,
+	expires			BIGINT NULL,
+	created_at		BIGINT NOT NULL
+);
+
+-- why a user wants to join, waiting for a moderator to review it
+CREATE TABLE applications (
+	uid				TEXT NOT NULL PRIMARY KEY UNIQUE REFERENCES users(uid) ON DELETE CASCADE,
+	reason			TEXT NOT NULL,
+	created_at		BIGINT NOT NULL
+);
@@ -0,0 +1,21 @@
+CREATE TABLE notifications (
+	-- uuid v7, so ordering by id orders by creation
+	id				TEXT NOT NULL PRIMARY KEY UNIQUE,
+	recipient		TEXT NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	kind			TEXT NOT NULL,
+	-- the user that caused it, none for polls ending
+	actor			TEXT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	post			TEXT NULL REFERENCES posts(id) ON DELETE CASCADE,
+	created_at		BIGINT NOT NULL
+);
+CREATE INDEX notifications_recipient ON notifications (recipient, id);
+
+-- the newest notification each user has seen
+CREATE TABLE notification_markers (
+	uid				TEXT NOT NULL PRIMARY KEY UNIQUE REFERENCES users(uid) ON DELETE CASCADE,
+	last_read_id	TEXT NOT NULL,
+	updated_at		BIGINT NOT NULL
+);
+
+-- set once a closed poll has been picked up to notify its author
+ALTER TABLE posts ADD COLUMN end_notified BOOLEAN NOT NULL DEFAULT false;
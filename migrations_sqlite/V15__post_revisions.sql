@@ -0,0 +1,17 @@
+-- earlier versions of edited posts
+CREATE TABLE post_revisions (
+	-- uuid v7, so ordering by id orders by creation
+	id				TEXT NOT NULL PRIMARY KEY UNIQUE,
+	post			TEXT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+	content			TEXT NULL,
+	subject			TEXT NULL,
+	is_sensitive	BOOLEAN NOT NULL DEFAULT false,
+	-- the json array of the poll options if the post is a poll
+	options			TEXT NULL,
+	-- when this version was published, or edited into the post
+	created_at		BIGINT NOT NULL
+);
+CREATE INDEX post_revisions_post ON post_revisions (post, id);
+
+-- set when the post was last edited
+ALTER TABLE posts ADD COLUMN edited_at BIGINT NULL;
@@ -0,0 +1,8 @@
+-- approved now only says if a local user may use the instance. users
+-- vetted through an invite or a moderator are told apart from open
+-- signups by vetted, which are approved but still get demoted when the
+-- instance switches to manual approval
+ALTER TABLE users ADD COLUMN vetted BOOLEAN NOT NULL DEFAULT false;
+UPDATE users SET vetted = approved;
+UPDATE users SET approved = true
+WHERE approved = false AND (permission_level IS NULL OR permission_level <> 6);
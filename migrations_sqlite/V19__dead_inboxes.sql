@@ -0,0 +1,6 @@
+-- inboxes that are gone or stayed unreachable for the whole delivery
+-- window. deliveries to them are given up on without being sent
+CREATE TABLE dead_inboxes (
+	inbox			TEXT NOT NULL PRIMARY KEY UNIQUE,
+	died_at			BIGINT NOT NULL
+);
@@ -0,0 +1,9 @@
+-- votes in our polls. a voter counts once per option
+CREATE TABLE poll_votes (
+	poll		TEXT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+	voter		TEXT NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+	-- the index of the option voted for
+	choice		INTEGER NOT NULL,
+	published	BIGINT NOT NULL,
+	PRIMARY KEY(poll, voter, choice)
+);
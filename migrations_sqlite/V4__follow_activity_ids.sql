@@ -0,0 +1,4 @@
+-- the id of the activity that created the follow, lets undos
+-- that only link the follow find it
+ALTER TABLE following ADD COLUMN activity_id TEXT NULL;
+CREATE UNIQUE INDEX following_activity_id ON following (activity_id);
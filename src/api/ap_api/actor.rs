@@ -1,5 +1,5 @@
 use actix_web::{
-    error::{ErrorNotFound, ErrorUnauthorized},
+    error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized},
     get,
     web::{self, Data},
     HttpRequest, HttpResponse, Result,
@@ -10,7 +10,9 @@ use crate::{
         conn::{Conn, EntityOrigin},
         utility::{instance_actor::InstanceActor, new_actor::NewLocal},
     },
-    protocols::protocol::{ap_protocol::verification::verify_get, headers::ActixHeaders},
+    protocols::protocol::{
+        ap_protocol::verification::verify_get, headers::ActixHeaders, key_cache::KeyCache,
+    },
 };
 
 #[get("/users/{preferred_username}")]
@@ -18,6 +20,7 @@ pub async fn get_actor(
     path: web::Path<String>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
     request: HttpRequest,
 ) -> Result<HttpResponse> {
     dbg!(&request);
@@ -27,13 +30,17 @@ pub async fn get_actor(
         let headers = ActixHeaders {
             headermap: request.headers().clone(),
         };
-        let instance_key = conn.get_instance_actor().await;
+        let instance_key = conn
+            .get_instance_actor()
+            .await
+            .map_err(ErrorInternalServerError)?;
         let verified = verify_get(
             &headers,
             request.path(),
             &state.instance_domain,
             &InstanceActor::get_key_id(&state.instance_domain),
             &mut instance_key.get_private_key(),
+            &keys,
         )
         .await;
 
@@ -43,7 +50,10 @@ pub async fn get_actor(
     }
 
     let actor = conn
-        .get_actor(&preferred_username, &EntityOrigin::Local(&state.instance_domain))
+        .get_actor(
+            &preferred_username,
+            &EntityOrigin::Local(&state.instance_domain),
+        )
         .await;
 
     let Some(actor) = actor else {
@@ -72,7 +82,7 @@ pub async fn create_test(
         .await
         .unwrap();
 
-    Ok(HttpResponse::Ok().body(format!("{x}")))
+    Ok(HttpResponse::Ok().body(x))
 }
 
 #[get("/actor")]
@@ -88,6 +98,7 @@ pub async fn get_instance_actor(
                 &conn
                     .get_instance_actor()
                     .await
+                    .map_err(ErrorInternalServerError)?
                     .to_actor(&state.instance_domain)
                     .wrap_context(),
             )
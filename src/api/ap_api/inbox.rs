@@ -1,7 +1,7 @@
 use std::sync::Mutex;
 
 use actix_web::{
-    error::{Error, ErrorUnauthorized},
+    error::{Error, ErrorInternalServerError, ErrorUnauthorized},
     get,
     http::StatusCode,
     post,
@@ -13,14 +13,24 @@ use actix_web::{
 use crate::{
     db::{
         conn::{Conn, EntityOrigin},
-        utility::instance_actor::InstanceActor,
+        utility::{
+            follows::{handle_follow, handle_follow_response},
+            instance_actor::InstanceActor,
+            interactions::{handle_announce, handle_like},
+            notifications::notify_mentions,
+            revisions::handle_ap_edit,
+            stored_user::StoredUser,
+            streaming::{delete_and_publish, publish_post, StreamBus},
+            undo::handle_undo,
+        },
     },
     protocols::{
         protocol::{
             ap_protocol::verification::{verify_post, RequestVerificationError},
             headers::ActixHeaders,
+            key_cache::KeyCache,
         },
-        types::activitystream_objects::inboxable::VerifiedInboxable,
+        types::activitystream_objects::{inboxable::VerifiedInboxable, update::UpdateObject},
     },
 };
 pub struct Inbox {
@@ -41,39 +51,47 @@ pub async fn shared_inbox(
     // inbox: Data<Inbox>,
     body: web::Bytes,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
     state: Data<crate::config::Config>,
 ) -> Result<HttpResponse, Error> {
     dbg!(&request);
-    inbox(request, body, conn, state).await
+    inbox(request, body, conn, keys, bus, state).await
 }
 
 #[post("/users/{preferred_username}/inbox")]
 pub async fn private_inbox(
     request: HttpRequest,
-    path: web::Path<String>,
+    _path: web::Path<String>,
     // inbox: Data<Inbox>,
     body: web::Bytes,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
     state: Data<crate::config::Config>,
 ) -> Result<HttpResponse, Error> {
     println!("private inbox");
-    let preferred_username = path.into_inner();
-    let path = format!("/ap/users/{}/inbox", &preferred_username);
 
-    inbox(request, body, conn, state).await
+    inbox(request, body, conn, keys, bus, state).await
 }
 
 async fn inbox(
     request: HttpRequest,
     body: web::Bytes,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
     state: Data<crate::config::Config>,
 ) -> Result<HttpResponse, Error> {
     let Ok(body) = String::from_utf8(body.to_vec()) else {
         return Ok(HttpResponse::Unauthorized()
             .body(serde_json::to_string(&RequestVerificationError::BadMessageBody).unwrap()));
     };
-    let mut instance_actor_key = conn.get_instance_actor().await.get_private_key();
+    let mut instance_actor_key = conn
+        .get_instance_actor()
+        .await
+        .map_err(ErrorInternalServerError)?
+        .get_private_key();
 
     let headers = ActixHeaders {
         headermap: request.headers().clone(),
@@ -85,6 +103,8 @@ async fn inbox(
         &state.instance_domain,
         &InstanceActor::get_key_id(&state.instance_domain),
         &mut instance_actor_key,
+        &keys,
+        state.signature_skew,
     )
     .await
     {
@@ -92,29 +112,139 @@ async fn inbox(
         Err(err) => return Err(ErrorUnauthorized(serde_json::to_string(&err).unwrap())),
     };
 
-    spawn(handle_inbox(conn, state, verified));
+    spawn(handle_inbox(conn, bus, state, verified));
 
     Ok(HttpResponse::Ok().status(StatusCode::ACCEPTED).body(""))
 }
 
-#[allow(unused_variables)]
 async fn handle_inbox(
     conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
     state: Data<crate::config::Config>,
     item: VerifiedInboxable,
 ) {
     match item {
         VerifiedInboxable::Postable(postable) => {
             let id = postable.id().clone();
-            let _ = conn
-                .create_ap_post(
-                    postable,
-                    &EntityOrigin::Federated(id.domain().expect("verified post missing domain")),
-                )
+            if let Some(existing) = conn.get_post(&id).await {
+                if let Err(x) =
+                    handle_ap_edit(conn.as_ref().as_ref(), &bus, &existing, &postable).await
+                {
+                    eprintln!("failed to edit {}: {}", id, x);
+                }
+                return;
+            }
+            let Some(domain) = id.domain().map(str::to_string) else {
+                return;
+            };
+            let mentions = postable.mentions();
+            let created = conn
+                .create_ap_post(postable, &EntityOrigin::Federated(&domain))
                 .await;
+            if created.is_err() {
+                return;
+            }
+            if let Err(x) = publish_post(conn.as_ref().as_ref(), &bus, &id).await {
+                eprintln!("failed to publish {}: {}", id, x);
+            }
+            if let Err(x) = notify_mentions(
+                conn.as_ref().as_ref(),
+                &bus,
+                &id,
+                &mentions,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to notify mentions of {}: {}", id, x);
+            }
+        }
+        VerifiedInboxable::Delete(delete) => {
+            let Some(domain) = delete.actor.domain().map(str::to_string) else {
+                return;
+            };
+            let origin = EntityOrigin::Federated(&domain);
+            // actors delete their account by deleting themselves
+            let result = match delete.object_id().eq(&delete.actor) {
+                true => conn.delete_user(&delete.actor, &origin).await,
+                false => {
+                    // posts can only be deleted by whoever posted them
+                    let Some(post) = conn.get_post(delete.object_id()).await else {
+                        return;
+                    };
+                    let owner = conn.get_user(&post.actor).await;
+                    if owner
+                        .and_then(|x| x.ap_id())
+                        .ne(&Some(delete.actor.clone()))
+                    {
+                        return;
+                    }
+                    delete_and_publish(conn.as_ref().as_ref(), &bus, delete.object_id(), &origin)
+                        .await
+                }
+            };
+            if let Err(x) = result {
+                eprintln!("failed to handle delete {}: {}", delete.id, x);
+            }
+        }
+        VerifiedInboxable::Follow(follow) => {
+            let id = follow.id.clone();
+            if let Err(x) =
+                handle_follow(conn.as_ref().as_ref(), &bus, follow, &state.instance_domain).await
+            {
+                eprintln!("failed to handle follow {}: {}", id, x);
+            }
+        }
+        VerifiedInboxable::FollowResponse(follow_response) => {
+            let id = follow_response.id.clone();
+            if let Err(x) = handle_follow_response(
+                conn.as_ref().as_ref(),
+                follow_response,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to handle follow response {}: {}", id, x);
+            }
+        }
+        VerifiedInboxable::Undo(undo) => {
+            let id = undo.id.clone();
+            if let Err(x) = handle_undo(conn.as_ref().as_ref(), undo, &state.instance_domain).await
+            {
+                eprintln!("failed to handle undo {}: {}", id, x);
+            }
+        }
+        VerifiedInboxable::Update(update) => {
+            let UpdateObject::Actor(actor) = &update.object else {
+                return;
+            };
+            let Some(user) = StoredUser::from_actor(actor) else {
+                return;
+            };
+            if let Err(x) = conn.update_federated_user(&user).await {
+                eprintln!("failed to update {}: {}", user.resource_link, x);
+            }
+        }
+        VerifiedInboxable::Like(like) => {
+            let id = like.id.clone();
+            if let Err(x) =
+                handle_like(conn.as_ref().as_ref(), &bus, like, &state.instance_domain).await
+            {
+                eprintln!("failed to handle like {}: {}", id, x);
+            }
+        }
+        VerifiedInboxable::Announce(announce) => {
+            let id = announce.id.clone();
+            if let Err(x) = handle_announce(
+                conn.as_ref().as_ref(),
+                &bus,
+                announce,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to handle announce {}: {}", id, x);
+            }
         }
-        VerifiedInboxable::Delete(delete) => todo!(),
-        VerifiedInboxable::Follow(follow) => todo!(),
-        VerifiedInboxable::FollowResponse(follow_response) => todo!(),
     }
 }
@@ -1,23 +1,24 @@
-use actix_web::{
-    error::{ErrorNotFound, ErrorUnauthorized},
-    get,
-    web::{self, Data},
-    HttpRequest, HttpResponse, Result,
-};
-use url::Url;
-
 use crate::{
     db::{conn::Conn, utility::instance_actor::InstanceActor},
     protocols::{
-        protocol::{ap_protocol::verification::verify_get, headers::ActixHeaders},
-        types::activitystream_objects::{create::Create, link::RangeLinkItem},
+        protocol::{
+            ap_protocol::verification::verify_get, headers::ActixHeaders, key_cache::KeyCache,
+        },
+        types::activitystream_objects::create::Create,
     },
 };
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized},
+    get,
+    web::{self, Data},
+    HttpRequest, HttpResponse, Result,
+};
 
 #[get("/users/{preferred_username}/statuses/{id}")]
 pub async fn get_object(
     path: web::Path<(String, String)>,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
     request: HttpRequest,
     state: Data<crate::config::Config>,
 ) -> Result<HttpResponse> {
@@ -30,13 +31,17 @@ pub async fn get_object(
         let headers = ActixHeaders {
             headermap: request.headers().clone(),
         };
-        let instance_key = conn.get_instance_actor().await;
+        let instance_key = conn
+            .get_instance_actor()
+            .await
+            .map_err(ErrorInternalServerError)?;
         let verified = verify_get(
             &headers,
             path,
             &state.instance_domain,
             &InstanceActor::get_key_id(&state.instance_domain),
             &mut instance_key.get_private_key(),
+            &keys,
         )
         .await;
 
@@ -64,6 +69,7 @@ pub async fn get_object(
 pub async fn get_object_create(
     path: web::Path<(String, String)>,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
     request: HttpRequest,
     state: Data<crate::config::Config>,
 ) -> Result<HttpResponse> {
@@ -76,13 +82,17 @@ pub async fn get_object_create(
         let headers = ActixHeaders {
             headermap: request.headers().clone(),
         };
-        let instance_key = conn.get_instance_actor().await;
+        let instance_key = conn
+            .get_instance_actor()
+            .await
+            .map_err(ErrorInternalServerError)?;
         let verified = verify_get(
             &headers,
             path,
             &state.instance_domain,
             &InstanceActor::get_key_id(&state.instance_domain),
             &mut instance_key.get_private_key(),
+            &keys,
         )
         .await;
 
@@ -100,14 +110,7 @@ pub async fn get_object_create(
 
     match object {
         Some(object) => {
-            let activity = Create {
-                type_field:
-                    crate::protocols::types::activitystream_objects::create::CreateType::Create,
-                id: Url::parse(&format!("https://{}{}", &state.instance_domain, path))
-                    .expect("generated invalid url"),
-                actor: object.actor().clone(),
-                object: RangeLinkItem::Item(object),
-            };
+            let activity = Create::new(object);
 
             Ok(HttpResponse::Ok()
                 .content_type("application/activity+json; charset=utf-8")
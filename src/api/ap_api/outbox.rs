@@ -1,25 +1,44 @@
 use actix_web::{
-    error::{ErrorNotFound, ErrorUnauthorized},
+    error::{
+        ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound,
+        ErrorUnauthorized, ErrorUnprocessableEntity,
+    },
     get,
-    http::Error,
+    http::header::LOCATION,
     post,
     web::{self, Data},
     HttpRequest, HttpResponse, Result,
 };
+use url::Url;
 
 use crate::{
-    api::page_query::Page,
+    api::{oauth::authenticate::authenticate, page_query::Page},
     db::{
-        conn::{Conn, EntityOrigin},
-        utility::instance_actor::InstanceActor,
+        conn::{Conn, DbErr, EntityOrigin},
+        utility::{
+            compose::{compose, Draft},
+            instance_actor::InstanceActor,
+            new_actor::generate_ap_links,
+            streaming::StreamBus,
+        },
+    },
+    protocols::{
+        protocol::{
+            ap_protocol::verification::verify_get, headers::ActixHeaders, key_cache::KeyCache,
+        },
+        types::activitystream_objects::{
+            collections::{CollectionPage, PageType, StupidWrap},
+            context::{Context, ContextWrap, ACTIVITYSTREAMS},
+            new_post::NewPost,
+        },
     },
-    protocols::protocol::{ap_protocol::verification::verify_get, headers::ActixHeaders},
 };
 
 #[get("/users/{preferred_username}/outbox")]
 pub async fn ap_outbox(
     path: web::Path<String>,
     conn: Data<Box<dyn Conn + Sync>>,
+    keys: Data<KeyCache>,
     state: Data<crate::config::Config>,
     request: HttpRequest,
     page: actix_web::web::Query<Option<Page>>,
@@ -38,13 +57,17 @@ pub async fn ap_outbox(
         let headers = ActixHeaders {
             headermap: request.headers().clone(),
         };
-        let instance_key = conn.get_instance_actor().await;
+        let instance_key = conn
+            .get_instance_actor()
+            .await
+            .map_err(ErrorInternalServerError)?;
         let verified = verify_get(
             &headers,
             path,
             &state.instance_domain,
             &InstanceActor::get_key_id(&state.instance_domain),
             &mut instance_key.get_private_key(),
+            &keys,
         )
         .await;
 
@@ -54,7 +77,10 @@ pub async fn ap_outbox(
     }
 
     let Some(count) = conn
-        .get_user_post_count(&preferred_username, &EntityOrigin::Local(&state.instance_domain))
+        .get_user_post_count(
+            &preferred_username,
+            &EntityOrigin::Local(&state.instance_domain),
+        )
         .await
     else {
         return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
@@ -72,37 +98,89 @@ pub async fn ap_outbox(
     match posts {
         Some(posts) => {
             let Some(user) = conn
-                .get_actor(&preferred_username, &EntityOrigin::Local(&state.instance_domain))
+                .get_actor(
+                    &preferred_username,
+                    &EntityOrigin::Local(&state.instance_domain),
+                )
                 .await
             else {
                 return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
             };
 
-            todo!()
+            let outbox = user.outbox;
+            let page_link = |page: u64| format!("{outbox}?page={page}");
+            let last_page = count.div_ceil(state.outbox_pagnation_size).max(1);
 
-            // let collection = Collection::new(
-            //     posts,
-            //     count,
-            //     state.outbox_pagnation_size,
-            //     page,
-            //     Some(user.uri),
-            //     &state.instance_domain,
-            //     &format!("users/{uuid}/outbox/versia"),
-            // );
-            // Ok(HttpResponse::Ok()
-            //     .content_type("application/json; charset=UTF-8")
-            //     .body(serde_json::to_string(&collection).unwrap()))
+            let collection = CollectionPage {
+                type_field: PageType::OrderedCollectionPage,
+                id: Url::parse(&page_link(page)).ok(),
+                total_items: Some(count as u32),
+                part_of: Some(outbox.to_string()),
+                next: (page < last_page).then(|| page_link(page + 1)),
+                prev: (page > 1).then(|| page_link(page - 1)),
+                current: None,
+                first: Some(page_link(1)),
+                last: Some(page_link(last_page)),
+                items: Some(StupidWrap::OrderedItems(
+                    posts.into_iter().map(Box::new).collect(),
+                )),
+            };
+            let collection = ContextWrap {
+                context: Context::Single(ACTIVITYSTREAMS.to_string()),
+                item: collection,
+            };
+            Ok(HttpResponse::Ok()
+                .content_type("application/activity+json; charset=utf-8")
+                .body(serde_json::to_string(&collection).unwrap()))
         }
         None => Err(ErrorNotFound(r#"{"error":"Not Found"}"#)),
     }
 }
 
+/// publishes a note or question a client sends to the outbox of the
+/// authenticated user, answering with the post as other servers see it
 #[post("/users/{preferred_username}/outbox")]
 pub async fn create_ap_post(
     path: web::Path<String>,
     body: web::Bytes,
     conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
     state: Data<crate::config::Config>,
-) -> Result<HttpResponse, Error> {
-    todo!()
+    request: HttpRequest,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "write:statuses").await?;
+    if user.username.ne(path.as_str()) {
+        return Err(ErrorForbidden(r#"{"error":"Forbidden"}"#));
+    }
+    let Ok(post) = serde_json::from_slice::<NewPost>(&body) else {
+        return Err(ErrorBadRequest(r#"{"error":"invalid post"}"#));
+    };
+    let author = match &post {
+        NewPost::NewNote(note) => &note.attributed_to,
+        NewPost::NewQuestion(question) => &question.actor,
+    };
+    if author.ne(&generate_ap_links(&state.instance_domain, &user.username).id) {
+        return Err(ErrorForbidden(r#"{"error":"Forbidden"}"#));
+    }
+    let post = match compose(conn, &bus, &user, Draft::from_new_post(post)).await {
+        Ok(x) => x,
+        Err(DbErr::InvalidType) => {
+            return Err(ErrorUnprocessableEntity(r#"{"error":"invalid post"}"#))
+        }
+        Err(x) => return Err(ErrorInternalServerError(x)),
+    };
+    let (Some(id), Some(object)) = (post.ap_id(&user), post.to_ap(&user)) else {
+        return Err(ErrorInternalServerError(
+            r#"{"error":"failed to render post"}"#,
+        ));
+    };
+    let object = ContextWrap {
+        context: Context::Single(ACTIVITYSTREAMS.to_string()),
+        item: object,
+    };
+    Ok(HttpResponse::Created()
+        .insert_header((LOCATION, id.as_str()))
+        .content_type("application/activity+json; charset=utf-8")
+        .body(serde_json::to_string(&object).unwrap()))
 }
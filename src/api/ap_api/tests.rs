@@ -1,85 +1,79 @@
-// use actix_web::rt::spawn;
-// use serial_test::serial;
-// use url::Url;
+use actix_web::{test, web::Data, App};
 
-// use crate::{
-//     activitystream_objects::{actors::Actor, context::ContextWrap},
-//     app::start_application,
-//     config::get_config,
-//     db::utility::{instance_actor::InstanceActor, new_actor::NewLocal},
-//     protocol::ap_protocol::fetch::authorized_fetch,
-// };
+use crate::{
+    api::routes::get_routes,
+    config::get_config,
+    db::{
+        conn::{Conn, EntityOrigin},
+        memory::memory_conn::InMemoryConn,
+        utility::new_actor::NewLocal,
+    },
+    protocols::{
+        protocol::key_cache::{KeyCache, KEY_CACHE_SIZE},
+        types::activitystream_objects::actors::Actor,
+    },
+};
 
-// #[actix_web::test]
-// #[serial]
-// #[ignore]
-// async fn test_actor_endpoint() -> Result<(), String> {
-//     let config = get_config().unwrap();
-//     let conn = config.create_conn();
-//     conn.init().await.unwrap();
+#[actix_web::test]
+async fn test_actor_endpoint() -> Result<(), String> {
+    let mut config = get_config().unwrap();
+    config.force_auth_fetch = false;
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
 
-//     let handle = spawn(start_application(config.clone()));
+    let preferred_username = "test_actor_endpoint".to_string();
+    conn.create_user(
+        &config.instance_domain,
+        &NewLocal::new(preferred_username.clone(), "filler".to_string(), None, None),
+    )
+    .await
+    .unwrap();
+    let Some(actor) = conn
+        .get_actor(
+            &preferred_username,
+            &EntityOrigin::Local(&config.instance_domain),
+        )
+        .await
+    else {
+        return Err(
+            "failed to retrieve actor with get_actor, may have failed to insert".to_string(),
+        );
+    };
 
-//     let preferred_username = "test_actor_endpoint".to_string();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
 
-//     let uid = conn
-//         .create_local_user(&NewLocal::new(
-//             preferred_username.clone(),
-//             "filler".to_string(),
-//             None,
-//             None,
-//             None,
-//         ))
-//         .await
-//         .unwrap();
-//     let actor = conn.get_actor(uid, &config.instance_domain).await;
-//     let Some(actor) = actor else {
-//         return Err(
-//             "failed to retrieve actor with get_actor, may have failed to insert".to_string(),
-//         );
-//     };
+    let request = test::TestRequest::get()
+        .uri(&format!("/ap/users/{}", preferred_username))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if !response.status().is_success() {
+        return Err(format!(
+            "failed to retrieve the actor from the endpoint: {}",
+            response.status()
+        ));
+    }
+    let fetched: Actor = test::read_body_json(response).await;
+    if fetched.id.ne(&actor.id) {
+        return Err("id's of the retrieved actors don't match".to_string());
+    }
 
-//     // dbg!(&actor);
-//     println!("actor id: {}", actor.id);
+    let request = test::TestRequest::get()
+        .uri("/ap/users/does_not_exist")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if response.status().is_success() {
+        return Err("retrieved an actor that doesn't exist".to_string());
+    }
 
-//     let instance_actor = conn.get_instance_actor().await;
-
-//     let local_url = &Url::parse(&format!(
-//         "http://{}:{}/users/{}",
-//         config.bind_address, config.port, actor.preferred_username
-//     ))
-//     .unwrap();
-//     println!("local url: {}", &local_url);
-//     let result: Result<ContextWrap<Actor>, crate::protocol::errors::FetchErr> = authorized_fetch(
-//         local_url,
-//         &InstanceActor::pub_key_id(&config.instance_domain),
-//         &mut instance_actor.get_private_key(),
-//     )
-//     .await;
-//     if result.is_err() {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err(format!(
-//             "failed to retrieve the actor from the local address {}",
-//             result.unwrap_err()
-//         ));
-//     }
-
-//     // let result = authorized_fetch(
-//     //     &actor.id,
-//     //     &InstanceActor::pub_key_id(&config.instance_domain),
-//     //     &instance_actor.get_rsa(),
-//     // )
-//     // .await;
-//     // if result.is_err() {
-//     //     conn.delete_actor(uid, None).await.unwrap();
-//     //     return Err(format!("failed to retrieve the actor from the id, don't worry if you see this and your dev env is not exposed to the internet {}", result.unwrap_err()));
-//     // }
-
-//     let deleted = conn.delete_actor(uid, None).await;
-//     if deleted.is_err() {
-//         return Err("failed to delete the test user".to_string());
-//     }
-
-//     handle.abort();
-//     Ok(())
-// }
+    Ok(())
+}
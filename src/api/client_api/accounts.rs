@@ -0,0 +1,134 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorUnprocessableEntity},
+    post,
+    web::{Data, Form, Json},
+    Either, HttpRequest, HttpResponse, Result,
+};
+use serde::Deserialize;
+
+use crate::{
+    api::oauth::{authenticate::authenticate_token, token::TokenResponse},
+    db::{
+        conn::{Conn, DbErr, InsertErr},
+        utility::{
+            new_actor::NewLocal,
+            oauth::OAuthToken,
+            registration::{Application, RegistrationMode},
+        },
+    },
+};
+
+/// usernames end up in links so they are kept to a safe set of characters
+const MAX_USERNAME_LENGTH: usize = 30;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Deserialize, Debug)]
+pub struct Registration {
+    pub username: String,
+    pub password: String,
+    pub email: Option<String>,
+    /// what the user tells the moderators when registration needs approval
+    pub reason: Option<String>,
+    pub invite_code: Option<String>,
+}
+
+fn valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= MAX_USERNAME_LENGTH
+        && username
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_')
+}
+
+/// signs up a new user through an app, returning a token for them with
+/// the scopes of the app's token short of admin ones. users that need
+/// approval can't use the token until a moderator approves them
+#[post("/accounts")]
+pub async fn register(
+    request: HttpRequest,
+    body: Either<Json<Registration>, Form<Registration>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    state: Data<crate::config::Config>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let app_token = authenticate_token(&request, conn, Some("write:accounts")).await?;
+    let body = body.into_inner();
+
+    let invite = body.invite_code.as_deref().filter(|x| !x.is_empty());
+    let Some((permission_level, approved)) = state.registration_mode.new_user(invite.is_some())
+    else {
+        return Err(ErrorForbidden(r#"{"error":"Registration is closed"}"#));
+    };
+    let reason = body.reason.as_deref().map(str::trim).unwrap_or_default();
+    let needs_application = state.registration_mode == RegistrationMode::Approval && !approved;
+    if needs_application && reason.is_empty() {
+        return Err(ErrorUnprocessableEntity(
+            r#"{"error":"A reason is required to sign up"}"#,
+        ));
+    }
+    if !valid_username(&body.username) {
+        return Err(ErrorUnprocessableEntity(r#"{"error":"Invalid username"}"#));
+    }
+    if body.password.len() < MIN_PASSWORD_LENGTH {
+        return Err(ErrorUnprocessableEntity(
+            r#"{"error":"Password is too short"}"#,
+        ));
+    }
+
+    if conn
+        .get_local_user_by_username(&body.username)
+        .await
+        .is_some()
+    {
+        return Err(ErrorUnprocessableEntity(r#"{"error":"Username is taken"}"#));
+    }
+    // the invite is used up before the user exists so nobody gets an
+    // account out of an invite that doesn't work
+    if let Some(code) = invite {
+        match conn.use_invite(code).await {
+            Ok(()) => {}
+            Err(DbErr::NotFound) => {
+                return Err(ErrorBadRequest(
+                    r#"{"error":"The invite is invalid, expired or used up"}"#,
+                ))
+            }
+            Err(x) => return Err(ErrorInternalServerError(x)),
+        }
+    }
+
+    let mut new = NewLocal::new(
+        body.username,
+        body.password,
+        body.email,
+        Some(permission_level),
+    );
+    new.approved = approved;
+    new.vetted = invite.is_some();
+    let uid = match conn.create_user(&state.instance_domain, &new).await {
+        Ok(x) => x,
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => {
+            return Err(ErrorUnprocessableEntity(r#"{"error":"Username is taken"}"#))
+        }
+        Err(x) => return Err(ErrorInternalServerError(x)),
+    };
+    if needs_application {
+        conn.create_application(&Application::new(&uid, reason))
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+
+    let (access_token, token) = OAuthToken::issue(
+        &app_token.client_id,
+        Some(&uid),
+        app_token.scopes.without_admin(),
+    );
+    conn.create_oauth_token(&token)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        scope: token.scopes.to_string(),
+        created_at: token.created_at / 1000,
+    }))
+}
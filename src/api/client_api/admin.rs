@@ -0,0 +1,173 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    get, post,
+    web::{self, Data},
+    HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::oauth::authenticate::authenticate,
+    cryptography::key::KeyType,
+    db::{
+        conn::{Conn, DbErr},
+        utility::{
+            delete_account::delete_local_account,
+            permission::PermissionLevel,
+            rotate_key::{rotate_instance_key, rotate_local_key},
+            stored_user::StoredUser,
+        },
+    },
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PendingAccount {
+    pub id: String,
+    pub username: String,
+    pub email: Option<String>,
+    pub reason: String,
+    /// milliseconds since the epoch
+    pub created_at: i64,
+}
+
+/// authenticates a moderator with the scope
+async fn authenticate_moderator(
+    request: &HttpRequest,
+    conn: &dyn Conn,
+    scope: &str,
+) -> Result<StoredUser> {
+    let user = authenticate(request, conn, scope).await?;
+    match user.permission_level.is_some_and(|x| x.is_moderator()) {
+        true => Ok(user),
+        false => Err(ErrorForbidden(r#"{"error":"Forbidden"}"#)),
+    }
+}
+
+/// the kind of key a rotation switches to. users get ed25519 keys as they
+/// do when signing up and the instance actor an rsa key, unless another
+/// algorithm is asked for
+#[derive(Deserialize, Debug)]
+pub struct RotateKey {
+    pub algorithm: Option<KeyType>,
+}
+
+/// authenticates an admin with the scope, keys are only theirs to rotate
+async fn authenticate_admin(
+    request: &HttpRequest,
+    conn: &dyn Conn,
+    scope: &str,
+) -> Result<StoredUser> {
+    let user = authenticate(request, conn, scope).await?;
+    let is_admin = user
+        .permission_level
+        .is_some_and(|x| x.is_at_least(PermissionLevel::AdminTwo));
+    match is_admin {
+        true => Ok(user),
+        false => Err(ErrorForbidden(r#"{"error":"Forbidden"}"#)),
+    }
+}
+
+/// the applications waiting for review, oldest first
+#[get("/admin/accounts/pending")]
+pub async fn pending_accounts(
+    request: HttpRequest,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    authenticate_moderator(&request, conn, "admin:read:accounts").await?;
+
+    let applications = conn
+        .get_applications()
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let mut pending = Vec::new();
+    for application in applications {
+        let Some(user) = conn.get_local_user(&application.uid).await else {
+            continue;
+        };
+        pending.push(PendingAccount {
+            id: user.uid,
+            username: user.username,
+            email: user.email,
+            reason: application.reason,
+            created_at: application.created_at,
+        });
+    }
+    Ok(HttpResponse::Ok().json(pending))
+}
+
+#[post("/admin/accounts/{uid}/approve")]
+pub async fn approve_account(
+    request: HttpRequest,
+    path: web::Path<String>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    authenticate_moderator(&request, conn, "admin:write:accounts").await?;
+
+    match conn.approve_user(&path).await {
+        Ok(()) => Ok(HttpResponse::Ok().json(serde_json::json!({}))),
+        Err(DbErr::NotFound) => Err(ErrorNotFound(r#"{"error":"Not Found"}"#)),
+        Err(x) => Err(ErrorInternalServerError(x)),
+    }
+}
+
+/// deletes the account of a rejected applicant
+#[post("/admin/accounts/{uid}/reject")]
+pub async fn reject_account(
+    request: HttpRequest,
+    path: web::Path<String>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    authenticate_moderator(&request, conn, "admin:write:accounts").await?;
+
+    if conn.get_application(&path).await.is_none() {
+        return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
+    }
+    delete_local_account(conn, &path)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({})))
+}
+
+/// gives a local user a new keypair, for when their key may have leaked.
+/// their updated profile goes out to the instances that know them
+#[post("/admin/accounts/{uid}/rotate_key")]
+pub async fn rotate_account_key(
+    request: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<RotateKey>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    authenticate_admin(&request, conn, "admin:write:accounts").await?;
+
+    let Some(user) = conn.get_local_user(&path).await.filter(|x| !x.deleted) else {
+        return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
+    };
+    let algorithm = query.into_inner().algorithm.unwrap_or(KeyType::Ed25519);
+    match rotate_local_key(conn, &user.uid, algorithm).await {
+        Ok(queued) => Ok(HttpResponse::Ok().json(serde_json::json!({ "deliveries": queued }))),
+        Err(DbErr::NotFound) => Err(ErrorNotFound(r#"{"error":"Not Found"}"#)),
+        Err(x) => Err(ErrorInternalServerError(x)),
+    }
+}
+
+/// gives the instance actor new keys, pushing them to every known instance
+#[post("/admin/instance/rotate_key")]
+pub async fn rotate_instance_actor_key(
+    request: HttpRequest,
+    query: web::Query<RotateKey>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    state: Data<crate::config::Config>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    authenticate_admin(&request, conn, "admin:write").await?;
+
+    let algorithm = query.into_inner().algorithm.unwrap_or(KeyType::Rsa256);
+    let queued = rotate_instance_key(conn, &state.instance_domain, algorithm)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "deliveries": queued })))
+}
@@ -0,0 +1,100 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get, post,
+    web::{Data, Form, Json},
+    Either, HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::oauth::{authenticate::authenticate_token, oauth_error},
+    db::{
+        conn::Conn,
+        utility::oauth::{OAuthApp, Scopes},
+    },
+};
+
+#[derive(Deserialize, Debug)]
+pub struct NewApp {
+    pub client_name: String,
+    /// whitespace separated
+    pub redirect_uris: String,
+    pub scopes: Option<String>,
+    pub website: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegisteredApp {
+    pub id: String,
+    pub name: String,
+    pub website: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// registers a client app, accepts json or a form
+#[post("/apps")]
+pub async fn create_app(
+    body: Either<Json<NewApp>, Form<NewApp>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let body = body.into_inner();
+    let redirect_uris: Vec<String> = body
+        .redirect_uris
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    if body.client_name.trim().is_empty() || redirect_uris.is_empty() {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_request",
+            "a client name and redirect uri are required",
+        )));
+    }
+    let Some(scopes) = Scopes::parse(body.scopes.as_deref().unwrap_or_default()) else {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_scope",
+            "the requested scope is invalid",
+        )));
+    };
+
+    let app = OAuthApp::new(body.client_name, redirect_uris, scopes, body.website);
+    conn.create_oauth_app(&app)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(RegisteredApp {
+        id: app.client_id.clone(),
+        name: app.name,
+        website: app.website,
+        redirect_uri: app.redirect_uris.join("\n"),
+        scopes: app.scopes.to_string(),
+        client_id: app.client_id,
+        client_secret: app.client_secret,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AppCredentials {
+    pub name: String,
+    pub website: Option<String>,
+    pub scopes: String,
+}
+
+/// lets an app check that its token works
+#[get("/apps/verify_credentials")]
+pub async fn verify_app_credentials(
+    request: HttpRequest,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let token = authenticate_token(&request, conn.as_ref().as_ref(), None).await?;
+    let Some(app) = conn.get_oauth_app(&token.client_id).await else {
+        return Err(ErrorInternalServerError("token belongs to a missing app"));
+    };
+    Ok(HttpResponse::Ok().json(AppCredentials {
+        name: app.name,
+        website: app.website,
+        scopes: token.scopes.to_string(),
+    }))
+}
@@ -0,0 +1,68 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnprocessableEntity},
+    post,
+    web::{Data, Form, Json},
+    Either, HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::oauth::authenticate::authenticate,
+    db::{
+        conn::Conn,
+        utility::{permission::PermissionLevel, registration::Invite},
+    },
+};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct NewInvite {
+    /// unlimited when left out
+    pub max_uses: Option<i32>,
+    /// seconds until the invite expires, never when left out
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreatedInvite {
+    pub code: String,
+    pub max_uses: Option<i32>,
+    /// milliseconds since the epoch
+    pub expires_at: Option<i64>,
+}
+
+fn invalid_expiry() -> actix_web::Error {
+    ErrorUnprocessableEntity(r#"{"error":"expires_in must be a positive number of seconds"}"#)
+}
+
+/// lets trusted users invite others
+#[post("/invites")]
+pub async fn create_invite(
+    request: HttpRequest,
+    body: Option<Either<Json<NewInvite>, Form<NewInvite>>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "write:invites").await?;
+    let trusted = user
+        .permission_level
+        .is_some_and(|x| x.is_at_least(PermissionLevel::TrustedUser));
+    if !trusted {
+        return Err(ErrorForbidden(r#"{"error":"Forbidden"}"#));
+    }
+    let body = body.map(|x| x.into_inner()).unwrap_or_default();
+
+    let expires_in = match body.expires_in {
+        Some(x) => Some(x.checked_mul(1000).ok_or_else(invalid_expiry)?),
+        None => None,
+    };
+    let invite =
+        Invite::new(Some(&user.uid), body.max_uses, expires_in).ok_or_else(invalid_expiry)?;
+    conn.create_invite(&invite)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(CreatedInvite {
+        code: invite.code,
+        max_uses: invite.max_uses,
+        expires_at: invite.expires,
+    }))
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get, post,
+    web::{Data, Form, Json},
+    Either, HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::oauth::authenticate::authenticate,
+    db::{
+        conn::Conn,
+        utility::{notifications::NotificationMarker, stored_post::format_time},
+    },
+};
+
+/// the read position of a timeline. notifications are the only
+/// timeline markers are kept for
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Marker {
+    pub last_read_id: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MarkerPosition {
+    pub last_read_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MarkerUpdate {
+    pub notifications: Option<MarkerPosition>,
+}
+
+fn markers_response(marker: Option<NotificationMarker>) -> HttpResponse {
+    let mut markers = HashMap::new();
+    if let Some(marker) = marker {
+        markers.insert(
+            "notifications",
+            Marker {
+                last_read_id: marker.last_read_id,
+                updated_at: format_time(marker.updated_at),
+            },
+        );
+    }
+    HttpResponse::Ok().json(markers)
+}
+
+/// the markers of the authenticated user. users that never marked
+/// anything as read get an empty object
+#[get("/markers")]
+pub async fn get_markers(
+    request: HttpRequest,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "read:statuses").await?;
+    Ok(markers_response(
+        conn.get_notification_marker(&user.uid).await,
+    ))
+}
+
+/// moves the notification marker of the authenticated user. accepts
+/// json or a form with the key `notifications[last_read_id]`
+#[post("/markers")]
+pub async fn set_markers(
+    request: HttpRequest,
+    body: Either<Json<MarkerUpdate>, Form<Vec<(String, String)>>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "write:statuses").await?;
+    let last_read_id = match body {
+        Either::Left(json) => json.into_inner().notifications.map(|x| x.last_read_id),
+        Either::Right(form) => form
+            .into_inner()
+            .into_iter()
+            .find(|(key, _)| key.eq("notifications[last_read_id]"))
+            .map(|(_, value)| value),
+    };
+    let Some(last_read_id) = last_read_id.filter(|x| !x.is_empty()) else {
+        return Err(ErrorBadRequest(
+            r#"{"error":"a notifications last_read_id is required"}"#,
+        ));
+    };
+    let marker = NotificationMarker::new(&user.uid, &last_read_id);
+    conn.set_notification_marker(&marker)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(markers_response(Some(marker)))
+}
@@ -0,0 +1,12 @@
+pub mod accounts;
+pub mod admin;
+pub mod apps;
+pub mod invites;
+pub mod markers;
+pub mod notifications;
+pub mod routes;
+pub mod statuses;
+pub mod streaming;
+#[cfg(test)]
+mod tests;
+pub mod timelines;
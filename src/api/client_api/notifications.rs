@@ -0,0 +1,153 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get,
+    http::header::LINK,
+    web::{Data, Query},
+    HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    api::oauth::authenticate::authenticate,
+    config::Config,
+    db::{
+        conn::Conn,
+        utility::{
+            notifications::{
+                Notification, NotificationKind, NotificationQuery, DEFAULT_NOTIFICATION_LIMIT,
+            },
+            stored_post::format_time,
+        },
+    },
+};
+
+use super::statuses::{Account, Status};
+
+/// a notification as shown to client apps
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClientNotification {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: NotificationKind,
+    pub created_at: Option<String>,
+    /// the user that caused it, none for polls ending
+    pub account: Option<Account>,
+    /// the post it is about if any
+    pub status: Option<Status>,
+}
+
+impl ClientNotification {
+    /// fills in the account and post of the notification. ones that were
+    /// removed since are left out rather than failing the page
+    pub async fn hydrate(conn: &dyn Conn, notification: Notification) -> Self {
+        let account = match &notification.actor {
+            Some(actor) => conn.get_user(actor).await.map(|x| Account::new(&x)),
+            None => None,
+        };
+        let mut status = None;
+        if let Some(post) = notification
+            .post
+            .as_deref()
+            .and_then(|x| Url::parse(x).ok())
+        {
+            if let Some(post) = conn.get_post(&post).await {
+                if let Some(author) = conn.get_user(&post.actor).await {
+                    status = Some(Status::new(&post, &author));
+                }
+            }
+        }
+        ClientNotification {
+            id: notification.id,
+            kind: notification.kind,
+            created_at: format_time(notification.created_at),
+            account,
+            status,
+        }
+    }
+}
+
+/// parses the query by hand as the type filters are repeated keys,
+/// `types[]=mention&types[]=follow`
+fn to_query(params: &[(String, String)]) -> Result<NotificationQuery> {
+    let get = |key: &str| {
+        params
+            .iter()
+            .find(|(x, _)| x.eq(key))
+            .map(|(_, value)| value.clone())
+    };
+    let kinds = |key: &str| -> Result<Option<Vec<NotificationKind>>> {
+        let mut kinds = Vec::new();
+        for (_, value) in params
+            .iter()
+            .filter(|(x, _)| x.eq(key) || x.eq(&format!("{key}[]")))
+        {
+            match NotificationKind::parse_str(value) {
+                Some(kind) => kinds.push(kind),
+                None => return Err(ErrorBadRequest(r#"{"error":"unknown notification type"}"#)),
+            }
+        }
+        Ok(match kinds.is_empty() {
+            true => None,
+            false => Some(kinds),
+        })
+    };
+    let limit = match get("limit") {
+        Some(x) => x
+            .parse()
+            .map_err(|_| ErrorBadRequest(r#"{"error":"invalid limit"}"#))?,
+        None => DEFAULT_NOTIFICATION_LIMIT,
+    };
+    let mut included = kinds("types")?;
+    if let Some(excluded) = kinds("exclude_types")? {
+        let base = included.unwrap_or(NotificationKind::ALL.to_vec());
+        included = Some(base.into_iter().filter(|x| !excluded.contains(x)).collect());
+    }
+    let min_id = get("min_id");
+    Ok(NotificationQuery {
+        before: get("max_id"),
+        adjacent: min_id.is_some(),
+        after: min_id.or(get("since_id")),
+        limit,
+        kinds: included,
+    })
+}
+
+/// notifications of the authenticated user, newest first. pages are
+/// linked the same way as timelines
+#[get("/notifications")]
+pub async fn get_notifications(
+    request: HttpRequest,
+    params: Query<Vec<(String, String)>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    config: Data<Config>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "read:notifications").await?;
+    let query = to_query(&params)?;
+    // excluding every type leaves nothing to get
+    if query.kinds.as_ref().is_some_and(Vec::is_empty) {
+        return Ok(HttpResponse::Ok().json(Vec::<ClientNotification>::new()));
+    }
+    let page = conn
+        .get_notifications(&user.uid, &query)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let mut notifications = Vec::with_capacity(page.len());
+    for notification in page {
+        notifications.push(ClientNotification::hydrate(conn, notification).await);
+    }
+
+    let mut response = HttpResponse::Ok();
+    if let (Some(newest), Some(oldest)) = (notifications.first(), notifications.last()) {
+        let base = format!("https://{}{}", config.instance_domain, request.path());
+        response.insert_header((
+            LINK,
+            format!(
+                r#"<{base}?max_id={}>; rel="next", <{base}?min_id={}>; rel="prev""#,
+                oldest.id, newest.id
+            ),
+        ));
+    }
+    Ok(response.json(notifications))
+}
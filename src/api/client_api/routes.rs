@@ -0,0 +1,37 @@
+use super::{
+    accounts::register,
+    admin::{
+        approve_account, pending_accounts, reject_account, rotate_account_key,
+        rotate_instance_actor_key,
+    },
+    apps::{create_app, verify_app_credentials},
+    invites::create_invite,
+    markers::{get_markers, set_markers},
+    notifications::get_notifications,
+    statuses::{create_status, edit_status, status_history},
+    streaming::{stream_events, stream_websocket},
+    timelines::{home_timeline, public_timeline},
+};
+
+pub fn get_client_routes() -> actix_web::Scope {
+    actix_web::web::scope("/api/v1")
+        .service(create_app)
+        .service(verify_app_credentials)
+        .service(register)
+        .service(create_invite)
+        .service(pending_accounts)
+        .service(approve_account)
+        .service(reject_account)
+        .service(rotate_account_key)
+        .service(rotate_instance_actor_key)
+        .service(home_timeline)
+        .service(public_timeline)
+        .service(get_notifications)
+        .service(get_markers)
+        .service(set_markers)
+        .service(create_status)
+        .service(edit_status)
+        .service(status_history)
+        .service(stream_websocket)
+        .service(stream_events)
+}
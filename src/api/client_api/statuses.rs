@@ -0,0 +1,344 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound, ErrorUnprocessableEntity},
+    get, post, put,
+    web::{Data, Form, Json, Path},
+    Either, HttpRequest, HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    api::oauth::{authenticate::authenticate, authorize::escape_html},
+    db::{
+        conn::{Conn, DbErr},
+        utility::{
+            compose::{compose, Draft, DraftPoll},
+            revisions::{edit_local_post, PostRevision},
+            stored_post::{format_time, StoredPost},
+            stored_user::{now_millis, StoredUser},
+            streaming::StreamBus,
+            timeline::TimelineCursor,
+        },
+    },
+};
+
+/// a user as shown to client apps
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Account {
+    pub id: String,
+    pub username: String,
+    /// the username for local users, username@domain for everyone else
+    pub acct: String,
+    pub display_name: String,
+    pub note: String,
+    pub url: String,
+    pub avatar: Option<String>,
+    pub locked: bool,
+    pub created_at: Option<String>,
+}
+
+impl Account {
+    pub fn new(user: &StoredUser) -> Self {
+        let acct = match user.is_local() {
+            true => user.username.clone(),
+            false => format!("{}@{}", user.username, user.domain),
+        };
+        Account {
+            id: user.uid.clone(),
+            username: user.username.clone(),
+            acct,
+            display_name: user
+                .display_name
+                .clone()
+                .unwrap_or_else(|| user.username.clone()),
+            note: user.summary.clone().unwrap_or_default(),
+            url: user.url.clone(),
+            avatar: user.avatar.clone(),
+            locked: user.manual_followers,
+            created_at: format_time(user.created_at),
+        }
+    }
+}
+
+/// a post as shown to client apps
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Status {
+    /// the timeline cursor of the post so it can be passed back
+    /// as max_id, since_id or min_id
+    pub id: String,
+    pub uri: String,
+    pub url: String,
+    pub created_at: Option<String>,
+    pub account: Account,
+    pub content: String,
+    pub spoiler_text: String,
+    pub sensitive: bool,
+    /// public or private, private being followers only
+    pub visibility: String,
+    /// if the post is hidden from other instances
+    pub local_only: bool,
+    pub edited_at: Option<String>,
+    pub favourites_count: i64,
+    pub reblogs_count: i64,
+}
+
+impl Status {
+    pub fn new(post: &StoredPost, author: &StoredUser) -> Self {
+        let uri = post
+            .ap_id(author)
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| post.id.clone());
+        let visibility = match post.followers_only {
+            true => "private",
+            false => "public",
+        };
+        Status {
+            id: TimelineCursor::new(post).encode(),
+            url: uri.clone(),
+            uri,
+            created_at: format_time(post.published),
+            account: Account::new(author),
+            content: post.content.clone().unwrap_or_default(),
+            spoiler_text: post.subject.clone().unwrap_or_default(),
+            sensitive: post.is_sensitive,
+            visibility: visibility.to_string(),
+            local_only: post.local_only,
+            edited_at: post.edited_at.and_then(format_time),
+            favourites_count: post.likes,
+            reblogs_count: post.boosts,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewStatusPoll {
+    pub options: Vec<String>,
+    /// seconds until voting ends
+    pub expires_in: i64,
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+impl NewStatusPoll {
+    fn into_draft(self) -> DraftPoll {
+        DraftPoll {
+            options: self.options,
+            multi_select: self.multiple,
+            closed: Some(now_millis() + self.expires_in * 1000),
+            local_only_voting: false,
+        }
+    }
+}
+
+/// a post as client apps send it. the poll can only be given as json
+#[derive(Deserialize, Debug)]
+pub struct NewStatus {
+    /// plain text
+    pub status: Option<String>,
+    pub spoiler_text: Option<String>,
+    #[serde(default)]
+    pub sensitive: bool,
+    pub visibility: Option<String>,
+    pub in_reply_to_id: Option<String>,
+    #[serde(default)]
+    pub local_only: bool,
+    pub poll: Option<NewStatusPoll>,
+}
+
+/// an edit of a post as client apps send it. who can see the post and
+/// what it replies to can't be changed
+#[derive(Deserialize, Debug)]
+pub struct EditedStatus {
+    /// plain text
+    pub status: Option<String>,
+    pub spoiler_text: Option<String>,
+    #[serde(default)]
+    pub sensitive: bool,
+    /// replaces the poll of a poll, keeping the votes isn't supported
+    pub poll: Option<NewStatusPoll>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatusEditPollOption {
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatusEditPoll {
+    pub options: Vec<StatusEditPollOption>,
+}
+
+/// one version of an edited post
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatusEdit {
+    pub content: String,
+    pub spoiler_text: String,
+    pub sensitive: bool,
+    pub created_at: Option<String>,
+    pub account: Account,
+    pub poll: Option<StatusEditPoll>,
+}
+
+impl StatusEdit {
+    pub fn new(revision: &PostRevision, author: &StoredUser) -> Self {
+        StatusEdit {
+            content: revision.content.clone().unwrap_or_default(),
+            spoiler_text: revision.subject.clone().unwrap_or_default(),
+            sensitive: revision.is_sensitive,
+            created_at: format_time(revision.created_at),
+            account: Account::new(author),
+            poll: revision.options.as_ref().map(|options| StatusEditPoll {
+                options: options
+                    .iter()
+                    .map(|title| StatusEditPollOption {
+                        title: title.clone(),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// turns the plain text clients send into html, blank lines separate
+/// paragraphs and single newlines become line breaks
+fn text_to_html(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let lines: Vec<String> = x.lines().map(escape_html).collect();
+            format!("<p>{}</p>", lines.join("<br>"))
+        })
+        .collect()
+}
+
+/// publishes a post for the authenticated user. unlisted and direct
+/// posts aren't supported, replies need a post we know of
+#[post("/statuses")]
+pub async fn create_status(
+    request: HttpRequest,
+    body: Either<Json<NewStatus>, Form<NewStatus>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "write:statuses").await?;
+    let status = body.into_inner();
+    let followers_only = match status.visibility.as_deref() {
+        None | Some("public") => false,
+        Some("private") => true,
+        Some(_) => {
+            return Err(ErrorUnprocessableEntity(
+                r#"{"error":"unsupported visibility"}"#,
+            ))
+        }
+    };
+    let in_reply_to = match status.in_reply_to_id.as_deref().filter(|x| !x.is_empty()) {
+        Some(id) => Url::parse(&find_status(conn, id).await?.id).ok(),
+        None => None,
+    };
+    let subject = status.spoiler_text.filter(|x| !x.is_empty());
+    let draft = Draft {
+        content: status.status.as_deref().map(text_to_html),
+        is_sensitive: status.sensitive || subject.is_some(),
+        subject,
+        in_reply_to,
+        followers_only,
+        local_only: status.local_only,
+        poll: status.poll.map(NewStatusPoll::into_draft),
+    };
+    let post = match compose(conn, &bus, &user, draft).await {
+        Ok(x) => x,
+        Err(DbErr::InvalidType) => {
+            return Err(ErrorUnprocessableEntity(
+                r#"{"error":"a post needs text or a poll with at least two options"}"#,
+            ))
+        }
+        Err(x) => return Err(ErrorInternalServerError(x)),
+    };
+    Ok(HttpResponse::Ok().json(Status::new(&post, &user)))
+}
+
+/// the post a status id stands for, status ids being timeline cursors
+async fn find_status(conn: &dyn Conn, id: &str) -> Result<StoredPost> {
+    let link = TimelineCursor::decode(id)
+        .and_then(|x| Url::parse(&x.id).ok())
+        .ok_or(ErrorNotFound(r#"{"error":"Record not found"}"#))?;
+    conn.get_post(&link)
+        .await
+        .ok_or(ErrorNotFound(r#"{"error":"Record not found"}"#))
+}
+
+/// edits a post of the authenticated user, the earlier version is kept
+/// in its history
+#[put("/statuses/{id}")]
+pub async fn edit_status(
+    request: HttpRequest,
+    id: Path<String>,
+    body: Either<Json<EditedStatus>, Form<EditedStatus>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "write:statuses").await?;
+    let post = find_status(conn, &id).await?;
+    if post.actor.ne(&user.uid) {
+        return Err(ErrorForbidden(r#"{"error":"This action is not allowed"}"#));
+    }
+    let status = body.into_inner();
+    let subject = status.spoiler_text.filter(|x| !x.is_empty());
+    let draft = Draft {
+        content: status.status.as_deref().map(text_to_html),
+        is_sensitive: status.sensitive || subject.is_some(),
+        subject,
+        poll: status.poll.map(NewStatusPoll::into_draft),
+        ..Default::default()
+    };
+    let post = match edit_local_post(conn, &bus, &user, &post, draft).await {
+        Ok(x) => x,
+        Err(DbErr::InvalidType) => {
+            return Err(ErrorUnprocessableEntity(
+                r#"{"error":"a post needs text or a poll with at least two options"}"#,
+            ))
+        }
+        Err(x) => return Err(ErrorInternalServerError(x)),
+    };
+    Ok(HttpResponse::Ok().json(Status::new(&post, &user)))
+}
+
+/// every version of a post, oldest first and ending with the current
+/// one. followers only posts are only shown to the author's followers
+#[get("/statuses/{id}/history")]
+pub async fn status_history(
+    request: HttpRequest,
+    id: Path<String>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "read:statuses").await?;
+    let post = find_status(conn, &id).await?;
+    if post.followers_only
+        && post.actor.ne(&user.uid)
+        && !conn
+            .is_following(&user.uid, &post.actor)
+            .await
+            .map_err(ErrorInternalServerError)?
+    {
+        return Err(ErrorNotFound(r#"{"error":"Record not found"}"#));
+    }
+    let author = conn
+        .get_user(&post.actor)
+        .await
+        .ok_or(ErrorNotFound(r#"{"error":"Record not found"}"#))?;
+    let mut revisions = conn
+        .get_post_revisions(&post.id)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    revisions.push(PostRevision::new(&post));
+    let history: Vec<StatusEdit> = revisions
+        .iter()
+        .map(|x| StatusEdit::new(x, &author))
+        .collect();
+    Ok(HttpResponse::Ok().json(history))
+}
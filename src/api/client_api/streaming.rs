@@ -0,0 +1,379 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use actix_web::{
+    error::ErrorBadRequest,
+    get,
+    http::header::{CacheControl, CacheDirective},
+    rt::{
+        spawn,
+        time::{timeout, Instant},
+    },
+    web::{Bytes, Data, Path, Payload, Query},
+    HttpRequest, HttpResponse, Result,
+};
+use actix_ws::Message;
+use futures_util::stream::unfold;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{
+    api::oauth::authenticate::{authenticate_access_token, bearer_token},
+    config::Config,
+    db::{
+        conn::Conn,
+        utility::{
+            stored_post::StoredPost,
+            streaming::{StreamBus, StreamEvent, StreamKind},
+            timeline::TimelineCursor,
+        },
+    },
+};
+
+use super::{notifications::ClientNotification, statuses::Status};
+
+/// how long a stream can go quiet before it is pinged, keeping proxies
+/// from dropping it and noticing clients that went away
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// how often a stream checks that its token is still good and reloads
+/// who the viewer follows
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug)]
+pub struct TokenParams {
+    pub access_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StreamParams {
+    pub stream: String,
+    pub access_token: Option<String>,
+}
+
+/// an event the way mastodon streams send it. the payload is json
+/// itself for updates and notifications and the status id for deletes
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StreamMessage {
+    pub stream: Vec<String>,
+    pub event: String,
+    pub payload: String,
+}
+
+enum Pushed {
+    Message(StreamMessage),
+    /// nothing happened for [`HEARTBEAT_INTERVAL`]
+    Heartbeat,
+}
+
+/// one client listening to one stream
+struct Subscription {
+    kind: StreamKind,
+    viewer: String,
+    token: Option<String>,
+    scope: &'static str,
+    /// uids of the users the viewer follows, only kept for the home stream
+    following: HashSet<String>,
+    refresh_at: Instant,
+    instance_domain: String,
+    receiver: Receiver<Arc<StreamEvent>>,
+}
+
+impl Subscription {
+    /// authenticates the client and starts listening straight away so
+    /// nothing published while the response is set up gets missed
+    async fn open(
+        request: &HttpRequest,
+        conn: &dyn Conn,
+        bus: &StreamBus,
+        config: &Config,
+        stream: &str,
+        access_token: Option<&str>,
+    ) -> Result<Self> {
+        let Some(kind) = StreamKind::parse_str(stream) else {
+            return Err(ErrorBadRequest(r#"{"error":"unknown stream"}"#));
+        };
+        let scope = match kind {
+            StreamKind::UserNotification => "read:notifications",
+            _ => "read:statuses",
+        };
+        let token = access_token.or(bearer_token(request));
+        let viewer = authenticate_access_token(conn, token, scope).await?;
+        let mut subscription = Subscription {
+            kind,
+            viewer: viewer.uid,
+            token: token.map(str::to_string),
+            scope,
+            following: HashSet::new(),
+            refresh_at: Instant::now(),
+            instance_domain: config.instance_domain.clone(),
+            receiver: bus.subscribe(),
+        };
+        subscription.load_following(conn).await;
+        Ok(subscription)
+    }
+
+    /// waits for the next event of the stream. none once the bus is gone
+    /// or the token stopped being good, revoked or of a banned user
+    async fn next(&mut self, conn: &dyn Conn) -> Option<Pushed> {
+        loop {
+            if Instant::now() >= self.refresh_at {
+                authenticate_access_token(conn, self.token.as_deref(), self.scope)
+                    .await
+                    .ok()?;
+                self.load_following(conn).await;
+            }
+            let event = match timeout(HEARTBEAT_INTERVAL, self.receiver.recv()).await {
+                Err(_) => return Some(Pushed::Heartbeat),
+                Ok(Ok(x)) => x,
+                // a client that fell behind just misses those events
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                Ok(Err(RecvError::Closed)) => return None,
+            };
+            if let Some(message) = self.render(conn, &event).await {
+                return Some(Pushed::Message(message));
+            }
+        }
+    }
+
+    /// a failed load keeps the follows loaded last
+    async fn load_following(&mut self, conn: &dyn Conn) {
+        self.refresh_at = Instant::now() + REFRESH_INTERVAL;
+        // only the home stream cares who the viewer follows
+        if self.kind.ne(&StreamKind::User) {
+            return;
+        }
+        if let Ok(following) = conn.get_following(&self.viewer).await {
+            self.following = following.into_iter().map(|x| x.uid).collect();
+        }
+    }
+
+    async fn render(&self, conn: &dyn Conn, event: &StreamEvent) -> Option<StreamMessage> {
+        let (event, payload) = match event {
+            StreamEvent::Post { post, author } => {
+                if !self.shows_post(post) {
+                    return None;
+                }
+                let status = Status::new(post, author);
+                ("update", serde_json::to_string(&status).unwrap())
+            }
+            StreamEvent::Edit { post, author } => {
+                if !self.shows_post(post) {
+                    return None;
+                }
+                let status = Status::new(post, author);
+                ("status.update", serde_json::to_string(&status).unwrap())
+            }
+            StreamEvent::Delete { post, author: _ } => {
+                if !self.shows_post(post) {
+                    return None;
+                }
+                ("delete", TimelineCursor::new(post).encode())
+            }
+            StreamEvent::Notification(notification) => {
+                if !self.kind.shows_notification(notification, &self.viewer) {
+                    return None;
+                }
+                let notification = ClientNotification::hydrate(conn, notification.clone()).await;
+                (
+                    "notification",
+                    serde_json::to_string(&notification).unwrap(),
+                )
+            }
+        };
+        Some(StreamMessage {
+            stream: vec![self.kind.name().to_string()],
+            event: event.to_string(),
+            payload,
+        })
+    }
+
+    fn shows_post(&self, post: &StoredPost) -> bool {
+        let follows_author = self.following.contains(&post.actor);
+        self.kind
+            .shows_post(post, &self.viewer, follows_author, &self.instance_domain)
+    }
+}
+
+/// streams events as server sent events. the stream is part of the path
+/// the way mastodon has it, `user/notification` or `public/local`
+#[get("/streaming/{stream:.+}")]
+pub async fn stream_events(
+    request: HttpRequest,
+    stream: Path<String>,
+    params: Query<TokenParams>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
+    config: Data<Config>,
+) -> Result<HttpResponse> {
+    let subscription = Subscription::open(
+        &request,
+        conn.as_ref().as_ref(),
+        &bus,
+        &config,
+        &stream.replace('/', ":"),
+        params.access_token.as_deref(),
+    )
+    .await?;
+    let conn = conn.into_inner();
+    let body = unfold(
+        (subscription, conn),
+        |(mut subscription, conn)| async move {
+            let chunk = match subscription.next(conn.as_ref().as_ref()).await? {
+                Pushed::Message(x) => format!("event: {}\ndata: {}\n\n", x.event, x.payload),
+                Pushed::Heartbeat => ":thump\n\n".to_string(),
+            };
+            Some((
+                Ok::<_, actix_web::Error>(Bytes::from(chunk)),
+                (subscription, conn),
+            ))
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(body))
+}
+
+/// streams events over a websocket, one stream per connection picked
+/// with the `stream` query parameter
+#[get("/streaming")]
+pub async fn stream_websocket(
+    request: HttpRequest,
+    body: Payload,
+    params: Query<StreamParams>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    bus: Data<StreamBus>,
+    config: Data<Config>,
+) -> Result<HttpResponse> {
+    let mut subscription = Subscription::open(
+        &request,
+        conn.as_ref().as_ref(),
+        &bus,
+        &config,
+        &params.stream,
+        params.access_token.as_deref(),
+    )
+    .await?;
+    let (response, mut session, mut messages) = actix_ws::handle(&request, body)?;
+
+    let mut pushing = session.clone();
+    spawn(async move {
+        while let Some(pushed) = subscription.next(conn.as_ref().as_ref()).await {
+            let sent = match pushed {
+                Pushed::Message(x) => pushing.text(serde_json::to_string(&x).unwrap()).await,
+                Pushed::Heartbeat => pushing.ping(b"").await,
+            };
+            // the client went away
+            if sent.is_err() {
+                return;
+            }
+        }
+        let _ = pushing.close(None).await;
+    });
+    spawn(async move {
+        while let Some(Ok(message)) = messages.recv().await {
+            match message {
+                Message::Ping(x) if session.pong(&x).await.is_err() => return,
+                Message::Close(reason) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use crate::{
+        config::get_config,
+        db::{
+            memory::memory_conn::InMemoryConn,
+            utility::{
+                compose::Draft,
+                new_actor::NewLocal,
+                oauth::{hash_token, OAuthApp, OAuthToken, Scopes},
+                stored_user::now_millis,
+                streaming::STREAM_BUFFER,
+            },
+        },
+    };
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_refresh() -> Result<(), String> {
+        let config = get_config().unwrap();
+        let conn = InMemoryConn::new(&config.instance_domain);
+        conn.init().await.unwrap();
+        let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+        let viewer = conn
+            .create_user(&config.instance_domain, &new("refresh_viewer"))
+            .await
+            .unwrap();
+        let author = conn
+            .create_user(&config.instance_domain, &new("refresh_author"))
+            .await
+            .unwrap();
+        let author = conn.get_local_user(&author).await.unwrap();
+
+        let client = OAuthApp::new(
+            "test client".to_string(),
+            vec!["https://client.example/callback".to_string()],
+            Scopes::parse("read").unwrap(),
+            None,
+        );
+        conn.create_oauth_app(&client).await.unwrap();
+        let scopes = Scopes::parse("read").unwrap();
+        let (token, stored) = OAuthToken::issue(&client.client_id, Some(&viewer), scopes);
+        conn.create_oauth_token(&stored).await.unwrap();
+
+        let bus = StreamBus::new(STREAM_BUFFER);
+        let mut subscription = Subscription::open(
+            &TestRequest::default().to_http_request(),
+            &conn,
+            &bus,
+            &config,
+            "user",
+            Some(&token),
+        )
+        .await
+        .map_err(|x| x.to_string())?;
+        let draft = Draft {
+            content: Some("<p>hello</p>".to_string()),
+            ..Default::default()
+        };
+        let post = draft.into_post(&author, now_millis());
+        if subscription.shows_post(&post) {
+            return Err("showed a post of someone the viewer doesn't follow".to_string());
+        }
+
+        // follows are picked up on the next refresh, not on every post
+        conn.create_follow(&viewer, &author.uid, false, None)
+            .await
+            .unwrap();
+        if subscription.shows_post(&post) {
+            return Err("reloaded the follows for a post".to_string());
+        }
+        subscription.refresh_at = Instant::now();
+        bus.publish(StreamEvent::Post {
+            post,
+            author: author.clone(),
+        });
+        match subscription.next(&conn).await {
+            Some(Pushed::Message(x)) if x.event.eq("update") => {}
+            _ => return Err("the follow wasn't picked up on refresh".to_string()),
+        }
+
+        conn.revoke_oauth_token(&hash_token(&token)).await.unwrap();
+        subscription.refresh_at = Instant::now();
+        if subscription.next(&conn).await.is_some() {
+            return Err("the stream outlived its token".to_string());
+        }
+        Ok(())
+    }
+}
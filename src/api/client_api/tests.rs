@@ -0,0 +1,594 @@
+use std::{future::poll_fn, pin::Pin};
+
+use actix_web::{body::MessageBody, http::StatusCode, test, web::Data, App};
+
+use crate::{
+    api::{oauth::token::TokenResponse, routes::get_routes},
+    config::get_config,
+    db::{
+        conn::Conn,
+        memory::memory_conn::InMemoryConn,
+        utility::{
+            new_actor::NewLocal,
+            notifications::{notify, NotificationKind},
+            oauth::{OAuthApp, OAuthToken, Scopes},
+            permission::PermissionLevel,
+            registration::RegistrationMode,
+            streaming::{StreamBus, STREAM_BUFFER},
+        },
+    },
+    protocols::protocol::key_cache::{KeyCache, KEY_CACHE_SIZE},
+};
+
+use super::{
+    admin::PendingAccount,
+    statuses::{Status, StatusEdit},
+};
+
+#[actix_web::test]
+async fn test_approval_registration() -> Result<(), String> {
+    let mut config = get_config().unwrap();
+    config.registration_mode = RegistrationMode::Approval;
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("read write admin").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let scopes = Scopes::parse("write admin").unwrap();
+    let (app_token, stored) = OAuthToken::issue(&client.client_id, None, scopes);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let moderator = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new(
+                "registration_mod".to_string(),
+                "filler".to_string(),
+                None,
+                Some(PermissionLevel::ModOne),
+            ),
+        )
+        .await
+        .unwrap();
+    let scopes = Scopes::parse("admin").unwrap();
+    let (mod_token, stored) = OAuthToken::issue(&client.client_id, Some(&moderator), scopes);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(StreamBus::new(STREAM_BUFFER)))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+
+    let app_bearer = format!("Bearer {}", app_token);
+    let register = |reason: &'static str| {
+        test::TestRequest::post()
+            .uri("/api/v1/accounts")
+            .insert_header(("Authorization", app_bearer.as_str()))
+            .set_form([
+                ("username", "applicant"),
+                ("password", "long enough"),
+                ("reason", reason),
+            ])
+            .to_request()
+    };
+    let response = test::call_service(&app, register("")).await;
+    if response.status().ne(&StatusCode::UNPROCESSABLE_ENTITY) {
+        return Err(format!("signed up without a reason: {}", response.status()));
+    }
+    let token: TokenResponse = test::call_and_read_body_json(&app, register("hi")).await;
+    if token.scope.contains("admin") {
+        return Err("the applicant was given admin scopes".to_string());
+    }
+    let applicant_bearer = format!("Bearer {}", token.access_token);
+    let post_status = || {
+        test::TestRequest::post()
+            .uri("/api/v1/statuses")
+            .insert_header(("Authorization", applicant_bearer.as_str()))
+            .set_form([("status", "hello")])
+            .to_request()
+    };
+    let response = test::call_service(&app, post_status()).await;
+    if response.status().ne(&StatusCode::FORBIDDEN) {
+        return Err(format!(
+            "a pending applicant could post: {}",
+            response.status()
+        ));
+    }
+
+    let mod_bearer = format!("Bearer {}", mod_token);
+    let request = test::TestRequest::get()
+        .uri("/api/v1/admin/accounts/pending")
+        .insert_header(("Authorization", format!("Bearer {}", token.access_token)))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if response.status().is_success() {
+        return Err("an applicant could review applications".to_string());
+    }
+    let request = test::TestRequest::get()
+        .uri("/api/v1/admin/accounts/pending")
+        .insert_header(("Authorization", mod_bearer.as_str()))
+        .to_request();
+    let pending: Vec<PendingAccount> = test::call_and_read_body_json(&app, request).await;
+    let [applicant] = pending.as_slice() else {
+        return Err("the application was not listed".to_string());
+    };
+    if applicant.username.ne("applicant") || applicant.reason.ne("hi") {
+        return Err("listed the wrong application".to_string());
+    }
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/v1/admin/accounts/{}/approve", applicant.id))
+        .insert_header(("Authorization", mod_bearer.as_str()))
+        .to_request();
+    if !test::call_service(&app, request)
+        .await
+        .status()
+        .is_success()
+    {
+        return Err("failed to approve the applicant".to_string());
+    }
+    let request = test::TestRequest::get()
+        .uri("/api/v1/admin/accounts/pending")
+        .insert_header(("Authorization", mod_bearer.as_str()))
+        .to_request();
+    let pending: Vec<PendingAccount> = test::call_and_read_body_json(&app, request).await;
+    if !pending.is_empty() {
+        return Err("the approved application was still pending".to_string());
+    }
+    let response = test::call_service(&app, post_status()).await;
+    if !response.status().is_success() {
+        return Err(format!(
+            "the approved applicant could not post: {}",
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_notification_stream() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let listener = conn
+        .create_user(&config.instance_domain, &new("stream_listener"))
+        .await
+        .unwrap();
+    let follower = conn
+        .create_user(&config.instance_domain, &new("stream_follower"))
+        .await
+        .unwrap();
+    let listener = conn.get_local_user(&listener).await.unwrap();
+    let follower = conn.get_local_user(&follower).await.unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("read").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let scopes = Scopes::parse("read:notifications").unwrap();
+    let (token, stored) = OAuthToken::issue(&client.client_id, Some(&listener.uid), scopes);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let conn = Data::new(Box::new(conn) as Box<dyn Conn + Sync>);
+    let bus = Data::new(StreamBus::new(STREAM_BUFFER));
+    let app = test::init_service(
+        App::new()
+            .app_data(conn.clone())
+            .app_data(bus.clone())
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+
+    let bearer = format!("Bearer {}", token);
+    let open = |stream: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/streaming/{}", stream))
+            .insert_header(("Authorization", bearer.as_str()))
+            .to_request()
+    };
+    let response = test::call_service(&app, open("public")).await;
+    if response.status().ne(&StatusCode::FORBIDDEN) {
+        return Err(format!(
+            "opened a stream outside of the token's scope: {}",
+            response.status()
+        ));
+    }
+    let response = test::call_service(&app, open("user/notification")).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to open the stream: {}", response.status()));
+    }
+
+    notify(
+        conn.as_ref().as_ref(),
+        &bus,
+        &listener,
+        NotificationKind::Follow,
+        Some(&follower),
+        None,
+    )
+    .await
+    .unwrap();
+    let mut body = response.into_body();
+    let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx))
+        .await
+        .ok_or("the stream ended")?
+        .map_err(|x| x.to_string())?;
+    let chunk = String::from_utf8(chunk.to_vec()).map_err(|x| x.to_string())?;
+    if !chunk.starts_with("event: notification\ndata: ") || !chunk.contains("stream_follower") {
+        return Err(format!("streamed the wrong event: {}", chunk));
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_create_status() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    let author = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new(
+                "status_author".to_string(),
+                "filler".to_string(),
+                None,
+                None,
+            ),
+        )
+        .await
+        .unwrap();
+    let author = conn.get_local_user(&author).await.unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("write").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let scopes = Scopes::parse("write:statuses").unwrap();
+    let (token, stored) = OAuthToken::issue(&client.client_id, Some(&author.uid), scopes);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(StreamBus::new(STREAM_BUFFER)))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+    let bearer = format!("Bearer {}", token);
+    let post = || {
+        test::TestRequest::post()
+            .uri("/api/v1/statuses")
+            .insert_header(("Authorization", bearer.as_str()))
+    };
+
+    let request = post()
+        .set_form([("status", "a <b>\nline\n\nand more")])
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to post: {}", response.status()));
+    }
+    let parent: Status = test::read_body_json(response).await;
+    if parent
+        .content
+        .ne("<p>a &lt;b&gt;<br>line</p><p>and more</p>")
+        || parent.visibility.ne("public")
+    {
+        return Err(format!("posted the wrong status: {:?}", parent));
+    }
+
+    let request = post()
+        .set_json(serde_json::json!({
+            "status": "replying",
+            "spoiler_text": "cw",
+            "visibility": "private",
+            "in_reply_to_id": parent.id,
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to reply: {}", response.status()));
+    }
+    let reply: Status = test::read_body_json(response).await;
+    if reply.visibility.ne("private") || !reply.sensitive || reply.spoiler_text.ne("cw") {
+        return Err(format!("posted the wrong reply: {:?}", reply));
+    }
+
+    let request = post()
+        .set_json(serde_json::json!({"status": "hidden", "visibility": "direct"}))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if response.status().ne(&StatusCode::UNPROCESSABLE_ENTITY) {
+        return Err(format!(
+            "posted with an unsupported visibility: {}",
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_edit_status() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let author = conn
+        .create_user(&config.instance_domain, &new("status_editor"))
+        .await
+        .unwrap();
+    let other = conn
+        .create_user(&config.instance_domain, &new("status_bystander"))
+        .await
+        .unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("read write").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let issue = |uid: &str| {
+        let scopes = Scopes::parse("read:statuses write:statuses").unwrap();
+        OAuthToken::issue(&client.client_id, Some(uid), scopes)
+    };
+    let (token, stored) = issue(&author);
+    conn.create_oauth_token(&stored).await.unwrap();
+    let (other_token, stored) = issue(&other);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(StreamBus::new(STREAM_BUFFER)))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+    let bearer = format!("Bearer {}", token);
+    let other_bearer = format!("Bearer {}", other_token);
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/statuses")
+        .insert_header(("Authorization", bearer.as_str()))
+        .set_json(serde_json::json!({"status": "frist"}))
+        .to_request();
+    let status: Status = test::read_body_json(test::call_service(&app, request).await).await;
+    let edit = |bearer: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/v1/statuses/{}", status.id))
+            .insert_header(("Authorization", bearer))
+            .set_json(serde_json::json!({"status": "first", "spoiler_text": "typo"}))
+            .to_request()
+    };
+
+    let response = test::call_service(&app, edit(&other_bearer)).await;
+    if response.status().ne(&StatusCode::FORBIDDEN) {
+        return Err(format!(
+            "edited the post of another user: {}",
+            response.status()
+        ));
+    }
+    let response = test::call_service(&app, edit(&bearer)).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to edit: {}", response.status()));
+    }
+    let edited: Status = test::read_body_json(response).await;
+    if edited.id.ne(&status.id)
+        || edited.content.ne("<p>first</p>")
+        || edited.spoiler_text.ne("typo")
+        || edited.edited_at.is_none()
+    {
+        return Err(format!("edited the status wrong: {:?}", edited));
+    }
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/statuses/{}/history", status.id))
+        .insert_header(("Authorization", other_bearer.as_str()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to get the history: {}", response.status()));
+    }
+    let history: Vec<StatusEdit> = test::read_body_json(response).await;
+    let versions: Vec<&str> = history.iter().map(|x| x.content.as_str()).collect();
+    if versions.ne(&["<p>frist</p>", "<p>first</p>"]) {
+        return Err(format!("got the wrong history: {:?}", versions));
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_rotate_key() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    let new = |name: &str, level: PermissionLevel| {
+        NewLocal::new(name.to_string(), "filler".to_string(), None, Some(level))
+    };
+    let admin = conn
+        .create_user(
+            &config.instance_domain,
+            &new("rotate_admin", PermissionLevel::AdminTwo),
+        )
+        .await
+        .unwrap();
+    let moderator = conn
+        .create_user(
+            &config.instance_domain,
+            &new("rotate_mod", PermissionLevel::ModOne),
+        )
+        .await
+        .unwrap();
+    let user = conn
+        .create_user(
+            &config.instance_domain,
+            &new("rotate_user", PermissionLevel::TrustedUser),
+        )
+        .await
+        .unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("admin").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let mut bearers = Vec::new();
+    for uid in [&admin, &moderator] {
+        let scopes = Scopes::parse("admin:write").unwrap();
+        let (token, stored) = OAuthToken::issue(&client.client_id, Some(uid), scopes);
+        conn.create_oauth_token(&stored).await.unwrap();
+        bearers.push(format!("Bearer {}", token));
+    }
+    let [admin_bearer, mod_bearer] = bearers.as_slice() else {
+        return Err("failed to issue tokens".to_string());
+    };
+    let old_key = conn.get_local_user(&user).await.unwrap().public_key_pem;
+    let old_instance_key = conn.get_instance_actor().await.unwrap().public_key_pem;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn.clone()) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+    let rotate = |uri: &str, bearer: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("Authorization", bearer))
+            .to_request()
+    };
+    let user_uri = format!("/api/v1/admin/accounts/{}/rotate_key", user);
+
+    let response = test::call_service(&app, rotate(&user_uri, mod_bearer)).await;
+    if response.status().ne(&StatusCode::FORBIDDEN) {
+        return Err(format!("a moderator rotated a key: {}", response.status()));
+    }
+    let response = test::call_service(&app, rotate(&user_uri, admin_bearer)).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to rotate the key: {}", response.status()));
+    }
+    let response = test::call_service(
+        &app,
+        rotate("/api/v1/admin/instance/rotate_key", admin_bearer),
+    )
+    .await;
+    if !response.status().is_success() {
+        return Err(format!(
+            "failed to rotate the instance key: {}",
+            response.status()
+        ));
+    }
+
+    if conn.get_local_user(&user).await.unwrap().public_key_pem == old_key {
+        return Err("the key of the user was not replaced".to_string());
+    }
+    if conn.get_instance_actor().await.unwrap().public_key_pem == old_instance_key {
+        return Err("the key of the instance was not replaced".to_string());
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_invite_expiry() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    let inviter = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new(
+                "inviter".to_string(),
+                "filler".to_string(),
+                None,
+                Some(PermissionLevel::TrustedUser),
+            ),
+        )
+        .await
+        .unwrap();
+
+    let client = OAuthApp::new(
+        "test client".to_string(),
+        vec!["https://client.example/callback".to_string()],
+        Scopes::parse("write").unwrap(),
+        None,
+    );
+    conn.create_oauth_app(&client).await.unwrap();
+    let scopes = Scopes::parse("write:invites").unwrap();
+    let (token, stored) = OAuthToken::issue(&client.client_id, Some(&inviter), scopes);
+    conn.create_oauth_token(&stored).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(config.clone()))
+            .service(get_routes()),
+    )
+    .await;
+    let bearer = format!("Bearer {}", token);
+    let invite = |expires_in: i64| {
+        test::TestRequest::post()
+            .uri("/api/v1/invites")
+            .insert_header(("Authorization", bearer.as_str()))
+            .set_json(serde_json::json!({ "expires_in": expires_in }))
+            .to_request()
+    };
+
+    for expires_in in [0, -60, i64::MAX, i64::MAX / 1000] {
+        let response = test::call_service(&app, invite(expires_in)).await;
+        if response.status().ne(&StatusCode::UNPROCESSABLE_ENTITY) {
+            return Err(format!(
+                "accepted expires_in {}: {}",
+                expires_in,
+                response.status()
+            ));
+        }
+    }
+    let response = test::call_service(&app, invite(60)).await;
+    if !response.status().is_success() {
+        return Err(format!("failed to invite: {}", response.status()));
+    }
+    Ok(())
+}
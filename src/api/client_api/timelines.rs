@@ -0,0 +1,129 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get,
+    http::header::LINK,
+    web::{Data, Query},
+    HttpRequest, HttpResponse, Result,
+};
+use serde::Deserialize;
+
+use crate::{
+    api::oauth::authenticate::{authenticate, bearer_token},
+    config::Config,
+    db::{
+        conn::Conn,
+        utility::timeline::{Timeline, TimelineCursor, TimelineQuery, DEFAULT_TIMELINE_LIMIT},
+    },
+};
+
+use super::statuses::Status;
+
+#[derive(Deserialize, Debug)]
+pub struct TimelineParams {
+    /// posts older than this status
+    pub max_id: Option<String>,
+    /// the newest posts that are newer than this status
+    pub since_id: Option<String>,
+    /// the posts right after this status
+    pub min_id: Option<String>,
+    pub limit: Option<u64>,
+    /// only posts of this instance, used by the public timeline
+    #[serde(default)]
+    pub local: bool,
+}
+
+impl TimelineParams {
+    fn to_query(&self, viewer: Option<String>) -> Result<TimelineQuery> {
+        let decode = |cursor: &Option<String>| match cursor {
+            Some(x) => TimelineCursor::decode(x)
+                .map(Some)
+                .ok_or_else(|| ErrorBadRequest(r#"{"error":"invalid status id"}"#)),
+            None => Ok(None),
+        };
+        let min_id = decode(&self.min_id)?;
+        Ok(TimelineQuery {
+            before: decode(&self.max_id)?,
+            adjacent: min_id.is_some(),
+            after: min_id.or(decode(&self.since_id)?),
+            limit: self.limit.unwrap_or(DEFAULT_TIMELINE_LIMIT),
+            viewer,
+        })
+    }
+}
+
+/// gets the page and links the pages around it the way mastodon does
+async fn timeline_response(
+    request: &HttpRequest,
+    conn: &dyn Conn,
+    config: &Config,
+    timeline: Timeline<'_>,
+    query: TimelineQuery,
+    local: bool,
+) -> Result<HttpResponse> {
+    let page = conn
+        .get_timeline(&timeline, &query)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let statuses: Vec<Status> = page.iter().map(|(x, y)| Status::new(x, y)).collect();
+
+    let mut response = HttpResponse::Ok();
+    if let (Some(newest), Some(oldest)) = (statuses.first(), statuses.last()) {
+        let base = format!("https://{}{}", config.instance_domain, request.path());
+        let local = match local {
+            true => "&local=true",
+            false => "",
+        };
+        response.insert_header((
+            LINK,
+            format!(
+                r#"<{base}?max_id={}{local}>; rel="next", <{base}?min_id={}{local}>; rel="prev""#,
+                oldest.id, newest.id
+            ),
+        ));
+    }
+    Ok(response.json(statuses))
+}
+
+/// posts of the users the authenticated user follows, along with their own
+#[get("/timelines/home")]
+pub async fn home_timeline(
+    request: HttpRequest,
+    params: Query<TimelineParams>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    config: Data<Config>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let user = authenticate(&request, conn, "read:statuses").await?;
+    let query = params.to_query(Some(user.uid.clone()))?;
+    timeline_response(
+        &request,
+        conn,
+        &config,
+        Timeline::Home(&user.uid),
+        query,
+        false,
+    )
+    .await
+}
+
+/// the federated timeline, or the local one with `local=true`. anyone
+/// can read it, local only posts are included for local users
+#[get("/timelines/public")]
+pub async fn public_timeline(
+    request: HttpRequest,
+    params: Query<TimelineParams>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    config: Data<Config>,
+) -> Result<HttpResponse> {
+    let conn = conn.as_ref().as_ref();
+    let viewer = match bearer_token(&request) {
+        Some(_) => Some(authenticate(&request, conn, "read:statuses").await?.uid),
+        None => None,
+    };
+    let query = params.to_query(viewer)?;
+    let timeline = match params.local {
+        true => Timeline::Local,
+        false => Timeline::Federated,
+    };
+    timeline_response(&request, conn, &config, timeline, query, params.local).await
+}
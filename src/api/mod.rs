@@ -1,4 +1,6 @@
 pub mod ap_api;
+pub mod client_api;
+pub mod oauth;
 pub mod page_query;
 pub mod routes;
 pub mod versia_api;
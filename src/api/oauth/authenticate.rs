@@ -0,0 +1,103 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorUnauthorized},
+    http::header::AUTHORIZATION,
+    HttpRequest, Result,
+};
+
+use crate::db::{
+    conn::Conn,
+    utility::{
+        oauth::{hash_token, OAuthToken},
+        registration::is_pending,
+        stored_user::StoredUser,
+    },
+};
+
+use super::oauth_error;
+
+/// the token of an `Authorization: Bearer` header
+pub fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    let header = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    match scheme.eq_ignore_ascii_case("bearer") {
+        true => Some(token.trim()),
+        false => None,
+    }
+}
+
+/// checks the bearer token of the request, along with its scope when one
+/// is required. used directly by endpoints that app tokens can call
+pub async fn authenticate_token(
+    request: &HttpRequest,
+    conn: &dyn Conn,
+    scope: Option<&str>,
+) -> Result<OAuthToken> {
+    verify_token(conn, bearer_token(request), scope).await
+}
+
+/// checks a token along with its scope, wherever the token came from
+async fn verify_token(
+    conn: &dyn Conn,
+    token: Option<&str>,
+    scope: Option<&str>,
+) -> Result<OAuthToken> {
+    let invalid = || ErrorUnauthorized(oauth_error("invalid_token", "the access token is invalid"));
+    let token = token.ok_or_else(invalid)?;
+    let token = conn
+        .get_oauth_token(&hash_token(token))
+        .await
+        .ok_or_else(invalid)?;
+    if let Some(scope) = scope {
+        if !token.scopes.allows(scope) {
+            return Err(ErrorForbidden(oauth_error(
+                "insufficient_scope",
+                "this action is outside the authorized scopes",
+            )));
+        }
+    }
+    Ok(token)
+}
+
+/// authenticates a client api request made on behalf of a local user,
+/// returning the user. tokens of apps alone are rejected
+pub async fn authenticate(
+    request: &HttpRequest,
+    conn: &dyn Conn,
+    scope: &str,
+) -> Result<StoredUser> {
+    authenticate_access_token(conn, bearer_token(request), scope).await
+}
+
+/// [`authenticate`] for clients that can't set headers, like browsers
+/// opening a websocket, and pass the token in the query instead. banned
+/// users and users still waiting to be approved are refused
+pub async fn authenticate_access_token(
+    conn: &dyn Conn,
+    token: Option<&str>,
+    scope: &str,
+) -> Result<StoredUser> {
+    let token = verify_token(conn, token, Some(scope)).await?;
+    let user = match &token.uid {
+        Some(uid) => conn.get_local_user(uid).await,
+        None => None,
+    };
+    let user = user.filter(|x| !x.deleted).ok_or_else(|| {
+        ErrorUnauthorized(oauth_error(
+            "invalid_token",
+            "the access token does not belong to a user",
+        ))
+    })?;
+    if user.banned {
+        return Err(ErrorForbidden(oauth_error(
+            "access_denied",
+            "this account is suspended",
+        )));
+    }
+    if is_pending(conn, &user).await {
+        return Err(ErrorForbidden(oauth_error(
+            "access_denied",
+            "this account is waiting to be approved",
+        )));
+    }
+    Ok(user)
+}
@@ -0,0 +1,283 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get,
+    http::{header::LOCATION, StatusCode},
+    post,
+    web::{Data, Form, Query},
+    HttpResponse, Result,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::db::{
+    conn::Conn,
+    utility::{
+        oauth::{OAuthApp, OAuthCode, Scopes, OOB_REDIRECT},
+        permission::PermissionLevel,
+        registration::{is_pending, Application, RegistrationMode},
+    },
+};
+
+use super::oauth_error;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthorizeQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginForm {
+    #[serde(flatten)]
+    pub query: AuthorizeQuery,
+    pub username: String,
+    pub password: String,
+    /// the application of users that need to be approved
+    pub reason: Option<String>,
+}
+
+/// checks the request against the app it is for, giving the app and the
+/// scopes being asked for
+async fn validate(conn: &dyn Conn, query: &AuthorizeQuery) -> Result<(OAuthApp, Scopes)> {
+    if query.response_type.ne("code") {
+        return Err(ErrorBadRequest(oauth_error(
+            "unsupported_response_type",
+            "only the code response type is supported",
+        )));
+    }
+    let Some(app) = conn.get_oauth_app(&query.client_id).await else {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_client",
+            "the client is unknown",
+        )));
+    };
+    if !app.allows_redirect(&query.redirect_uri) {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_request",
+            "the redirect uri is not registered for this client",
+        )));
+    }
+    let scopes = match &query.scope {
+        Some(scope) => Scopes::parse(scope),
+        None => Some(app.scopes.clone()),
+    };
+    let Some(scopes) = scopes.filter(|x| x.is_subset_of(&app.scopes)) else {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_scope",
+            "the requested scope is invalid or not allowed for this client",
+        )));
+    };
+    let method_supported = matches!(
+        (
+            &query.code_challenge,
+            query.code_challenge_method.as_deref()
+        ),
+        (None, None) | (Some(_), Some("S256"))
+    );
+    if !method_supported {
+        return Err(ErrorBadRequest(oauth_error(
+            "invalid_request",
+            "pkce requires a code challenge using S256",
+        )));
+    }
+    Ok((app, scopes))
+}
+
+pub(crate) fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+/// the login form, the request is passed along in hidden fields. users
+/// waiting to be approved are also asked why they want to join
+fn login_page(
+    app: &OAuthApp,
+    scopes: &Scopes,
+    query: &AuthorizeQuery,
+    error: Option<&str>,
+    ask_reason: bool,
+) -> String {
+    let hidden = [
+        ("response_type", Some(&query.response_type)),
+        ("client_id", Some(&query.client_id)),
+        ("redirect_uri", Some(&query.redirect_uri)),
+        ("scope", Some(&scopes.to_string())),
+        ("state", query.state.as_ref()),
+        ("code_challenge", query.code_challenge.as_ref()),
+        (
+            "code_challenge_method",
+            query.code_challenge_method.as_ref(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        value.map(|x| {
+            format!(
+                r#"<input type="hidden" name="{name}" value="{}">"#,
+                escape_html(x)
+            )
+        })
+    })
+    .collect::<Vec<String>>()
+    .join("\n");
+    let error = error
+        .map(|x| format!("<p>{}</p>", escape_html(x)))
+        .unwrap_or_default();
+    let reason = match ask_reason {
+        true => {
+            r#"<label>Why do you want to join? <textarea name="reason" required></textarea></label>"#
+        }
+        false => "",
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Authorize {name}</title></head>
+<body>
+<h1>Authorize {name}</h1>
+<p>{name} is requesting: {scopes}</p>
+{error}
+<form method="post" action="/oauth/authorize">
+{hidden}
+<label>Username <input type="text" name="username" autocomplete="username" required></label>
+<label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+{reason}
+<button type="submit">Authorize</button>
+</form>
+</body>
+</html>"#,
+        name = escape_html(&app.name),
+        scopes = escape_html(&scopes.to_string()),
+    )
+}
+
+#[get("/authorize")]
+pub async fn authorize_form(
+    query: Query<AuthorizeQuery>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let (app, scopes) = validate(conn.as_ref().as_ref(), &query).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(login_page(&app, &scopes, &query, None, false)))
+}
+
+/// logs the user in and sends them back to the app with a code
+#[post("/authorize")]
+pub async fn authorize(
+    form: Form<LoginForm>,
+    conn: Data<Box<dyn Conn + Sync>>,
+    state: Data<crate::config::Config>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let (app, scopes) = validate(conn.as_ref().as_ref(), &form.query).await?;
+
+    let user = conn
+        .get_local_user_by_username(&form.username)
+        .await
+        .filter(|x| x.check_password(&form.password));
+    let Some(user) = user else {
+        return Ok(HttpResponse::build(StatusCode::UNAUTHORIZED)
+            .content_type("text/html; charset=utf-8")
+            .body(login_page(
+                &app,
+                &scopes,
+                &form.query,
+                Some("invalid username or password"),
+                false,
+            )));
+    };
+
+    if user.banned {
+        return Ok(HttpResponse::build(StatusCode::FORBIDDEN)
+            .content_type("text/html; charset=utf-8")
+            .body(login_page(
+                &app,
+                &scopes,
+                &form.query,
+                Some("this account is suspended"),
+                false,
+            )));
+    }
+
+    // users demoted when the instance switched to manual approval are
+    // asked for the application they never sent
+    let unapproved = state.registration_mode == RegistrationMode::Approval
+        && user.permission_level == Some(PermissionLevel::UntrustedUser)
+        && conn.get_application(&user.uid).await.is_none();
+    if unapproved {
+        let reason = form.reason.as_deref().map(str::trim).unwrap_or_default();
+        if reason.is_empty() {
+            return Ok(HttpResponse::build(StatusCode::FORBIDDEN)
+                .content_type("text/html; charset=utf-8")
+                .body(login_page(
+                    &app,
+                    &scopes,
+                    &form.query,
+                    Some("your account needs to be approved by a moderator"),
+                    true,
+                )));
+        }
+        conn.create_application(&Application::new(&user.uid, reason))
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    // apps only get a code once a moderator approved the user
+    if is_pending(conn.as_ref().as_ref(), &user).await {
+        return Ok(HttpResponse::build(StatusCode::FORBIDDEN)
+            .content_type("text/html; charset=utf-8")
+            .body(login_page(
+                &app,
+                &scopes,
+                &form.query,
+                Some("your account is waiting to be approved by a moderator"),
+                false,
+            )));
+    }
+
+    let query = form.query;
+    let code = OAuthCode::new(
+        &app.client_id,
+        &user.uid,
+        &query.redirect_uri,
+        scopes,
+        query.code_challenge,
+        query.code_challenge_method,
+    );
+    conn.create_oauth_code(&code)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    if query.redirect_uri.eq(OOB_REDIRECT) {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(format!(
+                "<!DOCTYPE html>\n<html>\n<body>\n<p>Copy this code into {}:</p>\n<code>{}</code>\n</body>\n</html>",
+                escape_html(&app.name),
+                escape_html(&code.code),
+            )));
+    }
+
+    let mut redirect = Url::parse(&query.redirect_uri).map_err(|_| {
+        ErrorBadRequest(oauth_error(
+            "invalid_request",
+            "the redirect uri is not a valid url",
+        ))
+    })?;
+    redirect.query_pairs_mut().append_pair("code", &code.code);
+    if let Some(state) = &query.state {
+        redirect.query_pairs_mut().append_pair("state", state);
+    }
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, redirect.as_str()))
+        .finish())
+}
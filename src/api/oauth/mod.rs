@@ -0,0 +1,24 @@
+pub mod authenticate;
+pub mod authorize;
+pub mod routes;
+#[cfg(test)]
+mod tests;
+pub mod token;
+
+use serde::Serialize;
+
+/// an error response as described in rfc 6749
+#[derive(Serialize, Debug)]
+pub struct OAuthError {
+    pub error: &'static str,
+    pub error_description: &'static str,
+}
+
+/// the body of an error response
+pub fn oauth_error(error: &'static str, error_description: &'static str) -> String {
+    serde_json::to_string(&OAuthError {
+        error,
+        error_description,
+    })
+    .unwrap()
+}
@@ -0,0 +1,12 @@
+use super::{
+    authorize::{authorize, authorize_form},
+    token::{revoke, token},
+};
+
+pub fn get_oauth_routes() -> actix_web::Scope {
+    actix_web::web::scope("/oauth")
+        .service(authorize_form)
+        .service(authorize)
+        .service(token)
+        .service(revoke)
+}
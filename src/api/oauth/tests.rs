@@ -0,0 +1,174 @@
+use actix_web::{http::StatusCode, test, web::Data, App};
+use url::Url;
+
+use crate::{
+    api::{client_api::apps::RegisteredApp, routes::get_routes},
+    config::get_config,
+    db::{
+        conn::Conn,
+        memory::memory_conn::InMemoryConn,
+        utility::{
+            new_actor::NewLocal,
+            streaming::{StreamBus, STREAM_BUFFER},
+        },
+    },
+    protocols::protocol::key_cache::{KeyCache, KEY_CACHE_SIZE},
+};
+
+use super::token::TokenResponse;
+
+#[actix_web::test]
+async fn test_authorization_code_flow() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+    conn.create_user(
+        &config.instance_domain,
+        &NewLocal::new("oauth_flow".to_string(), "hunter2".to_string(), None, None),
+    )
+    .await
+    .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Box::new(conn) as Box<dyn Conn + Sync>))
+            .app_data(Data::new(StreamBus::new(STREAM_BUFFER)))
+            .app_data(Data::new(config.clone()))
+            .app_data(Data::new(KeyCache::new(
+                config.key_cache_ttl,
+                KEY_CACHE_SIZE,
+            )))
+            .service(get_routes()),
+    )
+    .await;
+
+    let redirect_uri = "https://client.example/callback";
+    let request = test::TestRequest::post()
+        .uri("/api/v1/apps")
+        .set_form([
+            ("client_name", "test client"),
+            ("redirect_uris", redirect_uri),
+            ("scopes", "read write"),
+        ])
+        .to_request();
+    let registered: RegisteredApp = test::call_and_read_body_json(&app, request).await;
+
+    // rfc 7636 appendix b
+    let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+    let authorize = |password: &'static str| {
+        test::TestRequest::post()
+            .uri("/oauth/authorize")
+            .set_form([
+                ("response_type", "code"),
+                ("client_id", registered.client_id.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("scope", "read"),
+                ("state", "xyz"),
+                ("code_challenge", challenge),
+                ("code_challenge_method", "S256"),
+                ("username", "oauth_flow"),
+                ("password", password),
+            ])
+            .to_request()
+    };
+
+    let response = test::call_service(&app, authorize("wrong")).await;
+    if response.status().ne(&StatusCode::UNAUTHORIZED) {
+        return Err(format!(
+            "logged in with a wrong password: {}",
+            response.status()
+        ));
+    }
+    let response = test::call_service(&app, authorize("hunter2")).await;
+    if response.status().ne(&StatusCode::FOUND) {
+        return Err(format!("failed to log in: {}", response.status()));
+    }
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let location = Url::parse(location).map_err(|x| x.to_string())?;
+    let pairs: Vec<(String, String)> = location.query_pairs().into_owned().collect();
+    let Some((_, code)) = pairs.iter().find(|(key, _)| key.eq("code")) else {
+        return Err("redirect did not include a code".to_string());
+    };
+    if !pairs.contains(&("state".to_string(), "xyz".to_string())) {
+        return Err("redirect did not pass the state back".to_string());
+    }
+
+    let exchange = |verifier: &'static str| {
+        test::TestRequest::post()
+            .uri("/oauth/token")
+            .set_form([
+                ("grant_type", "authorization_code"),
+                ("client_id", registered.client_id.as_str()),
+                ("code", code.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", verifier),
+            ])
+            .to_request()
+    };
+    let token: TokenResponse = test::call_and_read_body_json(&app, exchange(verifier)).await;
+    if token.scope.ne("read") {
+        return Err(format!(
+            "token was granted the wrong scope: {}",
+            token.scope
+        ));
+    }
+    let response = test::call_service(&app, exchange(verifier)).await;
+    if response.status().is_success() {
+        return Err("exchanged a code twice".to_string());
+    }
+
+    let bearer = format!("Bearer {}", token.access_token);
+    let request = test::TestRequest::get()
+        .uri("/api/v1/apps/verify_credentials")
+        .insert_header(("Authorization", bearer.as_str()))
+        .to_request();
+    if !test::call_service(&app, request)
+        .await
+        .status()
+        .is_success()
+    {
+        return Err("the issued token was rejected".to_string());
+    }
+    let request = test::TestRequest::post()
+        .uri("/ap/users/oauth_flow/outbox")
+        .insert_header(("Authorization", bearer.as_str()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if response.status().ne(&StatusCode::FORBIDDEN) {
+        return Err(format!(
+            "a read token was allowed to post: {}",
+            response.status()
+        ));
+    }
+
+    let request = test::TestRequest::post()
+        .uri("/oauth/revoke")
+        .set_form([
+            ("client_id", registered.client_id.as_str()),
+            ("client_secret", registered.client_secret.as_str()),
+            ("token", token.access_token.as_str()),
+        ])
+        .to_request();
+    if !test::call_service(&app, request)
+        .await
+        .status()
+        .is_success()
+    {
+        return Err("failed to revoke the token".to_string());
+    }
+    let request = test::TestRequest::get()
+        .uri("/api/v1/apps/verify_credentials")
+        .insert_header(("Authorization", bearer.as_str()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    if response.status().ne(&StatusCode::UNAUTHORIZED) {
+        return Err("a revoked token was accepted".to_string());
+    }
+    Ok(())
+}
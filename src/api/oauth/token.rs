@@ -0,0 +1,188 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    post,
+    web::{Data, Form, Json},
+    Either, HttpResponse, Result,
+};
+use openssl::memcmp;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{
+    conn::Conn,
+    utility::oauth::{hash_token, OAuthApp, OAuthToken, Scopes},
+};
+
+use super::oauth_error;
+
+#[derive(Deserialize, Debug)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    /// public clients leave this out and use pkce instead
+    pub client_secret: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub scope: String,
+    /// seconds since the epoch
+    pub created_at: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RevokeRequest {
+    pub client_id: String,
+    pub client_secret: String,
+    pub token: String,
+}
+
+fn invalid_client() -> actix_web::Error {
+    ErrorUnauthorized(oauth_error(
+        "invalid_client",
+        "client authentication failed",
+    ))
+}
+
+fn invalid_grant() -> actix_web::Error {
+    ErrorBadRequest(oauth_error(
+        "invalid_grant",
+        "the authorization code is invalid, expired or was already used",
+    ))
+}
+
+/// the app the request is for, checking its secret if one was given
+async fn get_client(
+    conn: &dyn Conn,
+    client_id: &str,
+    client_secret: Option<&str>,
+) -> Result<OAuthApp> {
+    let app = conn
+        .get_oauth_app(client_id)
+        .await
+        .ok_or_else(invalid_client)?;
+    match client_secret {
+        Some(secret) if !secret_matches(secret, &app.client_secret) => Err(invalid_client()),
+        _ => Ok(app),
+    }
+}
+
+/// compared in constant time so the response time doesn't tell how much
+/// of a guess was right. only the length can leak, which isn't a secret
+fn secret_matches(given: &str, secret: &str) -> bool {
+    given.len() == secret.len() && memcmp::eq(given.as_bytes(), secret.as_bytes())
+}
+
+/// exchanges a code for an access token or issues a token for the app
+/// itself through client credentials
+#[post("/token")]
+pub async fn token(
+    body: Either<Json<TokenRequest>, Form<TokenRequest>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let body = body.into_inner();
+    let conn = conn.as_ref().as_ref();
+    let app = get_client(conn, &body.client_id, body.client_secret.as_deref()).await?;
+
+    let (access_token, stored) = match body.grant_type.as_str() {
+        "authorization_code" => {
+            let code = match &body.code {
+                Some(code) => conn.take_oauth_code(code).await,
+                None => None,
+            };
+            let code = code
+                .filter(|x| x.client_id.eq(&app.client_id) && !x.is_expired())
+                .ok_or_else(invalid_grant)?;
+            if body.redirect_uri.as_deref() != Some(code.redirect_uri.as_str()) {
+                return Err(invalid_grant());
+            }
+            // clients without their secret have to prove they started the flow
+            if body.client_secret.is_none() && code.code_challenge.is_none() {
+                return Err(invalid_client());
+            }
+            if !code.verify_pkce(body.code_verifier.as_deref()) {
+                return Err(invalid_grant());
+            }
+            OAuthToken::issue(&app.client_id, Some(&code.uid), code.scopes)
+        }
+        "client_credentials" => {
+            if body.client_secret.is_none() {
+                return Err(invalid_client());
+            }
+            let scopes = Scopes::parse(body.scope.as_deref().unwrap_or_default())
+                .filter(|x| x.is_subset_of(&app.scopes))
+                .ok_or_else(|| {
+                    ErrorBadRequest(oauth_error(
+                        "invalid_scope",
+                        "the requested scope is invalid or not allowed for this client",
+                    ))
+                })?;
+            OAuthToken::issue(&app.client_id, None, scopes)
+        }
+        _ => {
+            return Err(ErrorBadRequest(oauth_error(
+                "unsupported_grant_type",
+                "only authorization_code and client_credentials are supported",
+            )))
+        }
+    };
+
+    conn.create_oauth_token(&stored)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        scope: stored.scopes.to_string(),
+        created_at: stored.created_at / 1000,
+    }))
+}
+
+/// revokes a token issued to the client. unknown tokens are treated as
+/// already revoked as described in rfc 7009
+#[post("/revoke")]
+pub async fn revoke(
+    body: Either<Json<RevokeRequest>, Form<RevokeRequest>>,
+    conn: Data<Box<dyn Conn + Sync>>,
+) -> Result<HttpResponse> {
+    let body = body.into_inner();
+    let conn = conn.as_ref().as_ref();
+    let app = get_client(conn, &body.client_id, Some(&body.client_secret)).await?;
+
+    let token_hash = hash_token(&body.token);
+    if let Some(existing) = conn.get_oauth_token(&token_hash).await {
+        if existing.client_id.ne(&app.client_id) {
+            return Err(ErrorForbidden(oauth_error(
+                "unauthorized_client",
+                "the token was issued to another client",
+            )));
+        }
+        conn.revoke_oauth_token(&token_hash)
+            .await
+            .map_err(ErrorInternalServerError)?;
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_matches() -> Result<(), String> {
+        if !secret_matches("hunter2", "hunter2") {
+            return Err("the right secret was refused".to_string());
+        }
+        for guess in ["hunter3", "hunter", "hunter22", ""] {
+            if secret_matches(guess, "hunter2") {
+                return Err(format!("{:?} was accepted", guess));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use super::{
     ap_api::routes::get_ap_routes,
+    client_api::routes::get_client_routes,
+    oauth::routes::get_oauth_routes,
     versia_api::{instance_discovery::versia_metadata, routes::get_versia_routes},
     webfinger::webfinger,
 };
@@ -10,4 +12,6 @@ pub fn get_routes() -> actix_web::Scope {
         .service(versia_metadata)
         .service(get_versia_routes())
         .service(get_ap_routes())
+        .service(get_oauth_routes())
+        .service(get_client_routes())
 }
@@ -1,11 +1,28 @@
 use crate::{
     cryptography::digest::sha256_hash,
-    db::conn::{Conn, EntityOrigin, VersiaConn},
+    db::{
+        conn::{Conn, EntityOrigin, VersiaConn},
+        utility::{
+            backfill::versia_key_to_openssl,
+            follows::{handle_versia_follow, handle_versia_follow_response},
+            interactions::{handle_share, handle_versia_dislike, handle_versia_like, handle_vote},
+            notifications::notify_mentions,
+            revisions::handle_versia_edit,
+            stored_user::StoredUser,
+            streaming::{delete_and_publish, publish_post, StreamBus},
+        },
+    },
     protocols::{
         protocol::{
+            errors::VerifyRequestErr,
             headers::ActixHeaders,
             http_method::HttpMethod,
-            versia_protocol::{requests::Signer, verify::verify_request},
+            key_cache::KeyCache,
+            versia_protocol::{
+                nonce_cache::NonceCache,
+                requests::Signer,
+                verify::{authorize_author, verify_request},
+            },
         },
         types::versia_types::{
             entities::{
@@ -15,11 +32,16 @@ use crate::{
                 instance_metadata::InstanceMetadata,
                 user::User,
             },
+            extensions::{dislike::Dislike, like::Like, vote::Vote},
             postable::VersiaPostable,
         },
     },
 };
-use actix_web::{error::ErrorBadRequest, http::StatusCode, rt::spawn};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorServiceUnavailable},
+    http::StatusCode,
+    rt::spawn,
+};
 
 use actix_web::{
     dev::ResourcePath, error::ErrorUnauthorized, post, web::Data, HttpRequest, HttpResponse, Result,
@@ -34,38 +56,53 @@ pub enum VersiaInboxItem {
     Delete(Delete),
     ChangeFollowing(ChangeFollowing),
     FollowResponse(FollowResponse),
+    Like(Like),
+    Dislike(Dislike),
+    Vote(Vote),
     /// used when a user updates their profile
     User(Box<User>),
     InstanceMetadata(Box<InstanceMetadata>),
 }
 
 #[post("/users/{uuid}/inbox")]
+#[allow(clippy::too_many_arguments)]
 pub async fn versia_user_inbox(
     request: HttpRequest,
     body: actix_web::web::Bytes,
     actix_path: actix_web::web::Path<String>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
 ) -> Result<HttpResponse> {
-    inbox(request, body, actix_path, state, conn).await
+    inbox(request, body, actix_path, state, conn, nonces, keys, bus).await
 }
 #[post("/inbox")]
+#[allow(clippy::too_many_arguments)]
 pub async fn versia_shared_inbox(
     request: HttpRequest,
     body: actix_web::web::Bytes,
     actix_path: actix_web::web::Path<String>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
 ) -> Result<HttpResponse> {
-    inbox(request, body, actix_path, state, conn).await
+    inbox(request, body, actix_path, state, conn, nonces, keys, bus).await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn inbox(
     request: HttpRequest,
     body: actix_web::web::Bytes,
     actix_path: actix_web::web::Path<String>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
 ) -> Result<HttpResponse> {
     let path = actix_path.path().to_string();
 
@@ -80,15 +117,21 @@ pub async fn inbox(
 
     let authorized = verify_request(
         &headers,
-        HttpMethod::Get,
+        HttpMethod::Post,
         &path,
         &hash,
-        &VersiaConn { conn: &conn },
+        &VersiaConn {
+            conn: &conn,
+            nonces: &nonces,
+            keys: &keys,
+        },
     )
     .await;
 
     let signer = match authorized {
         Ok(x) => x,
+        // the sender can try again once some nonces expire
+        Err(VerifyRequestErr::Busy) => return Err(ErrorServiceUnavailable("Busy")),
         Err(err) => return Err(ErrorUnauthorized(err)),
     };
 
@@ -96,7 +139,7 @@ pub async fn inbox(
 
     match deserialized {
         Ok(x) => {
-            spawn(handle_inbox(signer, x, state, conn));
+            spawn(handle_inbox(signer, x, state, conn, nonces, keys, bus));
             Ok(HttpResponse::Ok()
                 .status(StatusCode::ACCEPTED)
                 .content_type("application/json; charset=UTF-8")
@@ -112,7 +155,15 @@ pub async fn handle_inbox(
     entity: VersiaInboxItem,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
+    bus: Data<StreamBus>,
 ) {
+    let versia_conn = VersiaConn {
+        conn: &conn,
+        nonces: &nonces,
+        keys: &keys,
+    };
     // all signers should have a domain. federation with an ip address will
     // never be supported as they can 1. be dynamic, 2. be used to skirt defeds
     let Some(authoratative_domain) = signer.domain() else {
@@ -122,25 +173,210 @@ pub async fn handle_inbox(
         VersiaInboxItem::Post(postable) => {
             // another instance is trying to impersonate this user
             // we could log this in the future
-            if postable.get_author().domain().ne(&signer.domain()) {
+            if authorize_author(&versia_conn, &signer, postable.get_author())
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let note = match postable {
+                VersiaPostable::Note(note) => note,
+                VersiaPostable::Share(share) => {
+                    let id = share.uri.clone();
+                    if let Err(x) =
+                        handle_share(conn.as_ref().as_ref(), &bus, share, &state.instance_domain)
+                            .await
+                    {
+                        eprintln!("failed to handle share {}: {}", id, x);
+                    }
+                    return;
+                }
+            };
+            // a delegate may post for an author on another instance, the
+            // note still belongs to the author's instance
+            let Some(author_domain) = note.author.domain().map(str::to_string) else {
+                return;
+            };
+            let uri = note.uri.clone();
+            let mentions = note.mentions.clone().unwrap_or_default();
+            let note = VersiaPostable::Note(note);
+            if let Some(existing) = conn.get_post(&uri).await {
+                if let Err(x) =
+                    handle_versia_edit(conn.as_ref().as_ref(), &bus, &existing, &note).await
+                {
+                    eprintln!("failed to edit {}: {}", uri, x);
+                }
+                return;
+            }
+            if conn
+                .create_versia_post(note, &EntityOrigin::Federated(&author_domain))
+                .await
+                .is_err()
+            {
                 return;
             }
-            let post = conn
-                .create_versia_post(postable, &EntityOrigin::Federated(authoratative_domain))
+            if let Err(x) = publish_post(conn.as_ref().as_ref(), &bus, &uri).await {
+                eprintln!("failed to publish {}: {}", uri, x);
+            }
+            if let Err(x) = notify_mentions(
+                conn.as_ref().as_ref(),
+                &bus,
+                &uri,
+                &mentions,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to notify mentions of {}: {}", uri, x);
+            }
+        }
+        VersiaInboxItem::Delete(delete) => {
+            // only the owner of what is deleted, someone they delegated
+            // to or their instance may delete it
+            let owner = match delete.deleted_type {
+                DeletedType::Note | DeletedType::Share => {
+                    let Some(post) = conn.get_post(&delete.deleted).await else {
+                        return;
+                    };
+                    let owner = conn.get_user(&post.actor).await;
+                    owner.and_then(|x| x.versia_uri())
+                }
+                DeletedType::User => Some(delete.deleted.clone()),
+            };
+            let Some(owner) = owner else {
+                return;
+            };
+            if authorize_author(&versia_conn, &signer, &owner)
                 .await
-                .expect("failed to insert post");
+                .is_err()
+            {
+                return;
+            }
+            let Some(owner_domain) = owner.domain() else {
+                return;
+            };
+            let origin = EntityOrigin::Federated(owner_domain);
+            let result = match delete.deleted_type {
+                DeletedType::Note | DeletedType::Share => {
+                    delete_and_publish(conn.as_ref().as_ref(), &bus, &delete.deleted, &origin).await
+                }
+                DeletedType::User => conn.delete_user(&delete.deleted, &origin).await,
+            };
+            if let Err(x) = result {
+                eprintln!("failed to delete {}: {}", delete.deleted, x);
+            }
         }
-        VersiaInboxItem::Delete(delete) => match delete.deleted_type {
-            DeletedType::Note | DeletedType::Share => {
-                conn.delete_post(&delete.id, &EntityOrigin::Federated(authoratative_domain))
-                    .await
-                    .expect("failed to delete post");
+        VersiaInboxItem::ChangeFollowing(change_following) => {
+            if authorize_author(&versia_conn, &signer, &change_following.author)
+                .await
+                .is_err()
+            {
+                return;
             }
-            DeletedType::User => todo!(),
-        },
-        VersiaInboxItem::ChangeFollowing(change_following) => todo!(),
-        VersiaInboxItem::FollowResponse(follow_response) => todo!(),
-        VersiaInboxItem::User(user) => todo!(),
-        VersiaInboxItem::InstanceMetadata(instance_metadata) => todo!(),
+            let id = change_following.id.clone();
+            if let Err(x) = handle_versia_follow(
+                conn.as_ref().as_ref(),
+                &bus,
+                change_following,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to handle versia follow {}: {}", id, x);
+            }
+        }
+        VersiaInboxItem::FollowResponse(follow_response) => {
+            if authorize_author(&versia_conn, &signer, &follow_response.author)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let id = follow_response.id.clone();
+            if let Err(x) = handle_versia_follow_response(
+                conn.as_ref().as_ref(),
+                follow_response,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to handle versia follow response {}: {}", id, x);
+            }
+        }
+        VersiaInboxItem::Like(like) => {
+            if authorize_author(&versia_conn, &signer, &like.author)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let id = like.uri.clone();
+            if let Err(x) =
+                handle_versia_like(conn.as_ref().as_ref(), &bus, like, &state.instance_domain).await
+            {
+                eprintln!("failed to handle versia like {}: {}", id, x);
+            }
+        }
+        VersiaInboxItem::Dislike(dislike) => {
+            if authorize_author(&versia_conn, &signer, &dislike.author)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let id = dislike.uri.clone();
+            if let Err(x) = handle_versia_dislike(
+                conn.as_ref().as_ref(),
+                &bus,
+                dislike,
+                &state.instance_domain,
+            )
+            .await
+            {
+                eprintln!("failed to handle versia dislike {}: {}", id, x);
+            }
+        }
+        VersiaInboxItem::Vote(vote) => {
+            if authorize_author(&versia_conn, &signer, &vote.author)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let id = vote.uri.clone();
+            if let Err(x) =
+                handle_vote(conn.as_ref().as_ref(), &bus, vote, &state.instance_domain).await
+            {
+                eprintln!("failed to handle vote {}: {}", id, x);
+            }
+        }
+        VersiaInboxItem::User(user) => {
+            // only the user or their instance may change their profile,
+            // which includes who they delegate to
+            let authorized = match &signer {
+                Signer::User(x) => user.uri.eq(x),
+                Signer::Instance(x) => user.uri.domain().eq(&Some(x.as_str())),
+            };
+            if !authorized {
+                return;
+            }
+            let Some(user) = StoredUser::from_versia(&user) else {
+                return;
+            };
+            if let Err(x) = conn.update_federated_user(&user).await {
+                eprintln!("failed to update {}: {}", user.resource_link, x);
+            }
+        }
+        VersiaInboxItem::InstanceMetadata(instance_metadata) => {
+            // only an instance may announce changes to its own metadata
+            if !matches!(&signer, Signer::Instance(x) if instance_metadata.host.eq(x)) {
+                return;
+            }
+            // the instance vouched for the key by signing the push, so
+            // later requests don't have to fetch it again
+            if let Some(key) = versia_key_to_openssl(&instance_metadata.public_key) {
+                keys.insert(&signer.to_string(), vec![key]);
+            }
+        }
     }
 }
@@ -3,4 +3,6 @@ pub mod instance_discovery;
 pub mod outbox;
 pub mod posts;
 pub mod routes;
+#[cfg(test)]
+mod tests;
 pub mod users;
@@ -4,7 +4,10 @@ use crate::{
     db::conn::{Conn, EntityOrigin, VersiaConn},
     protocols::{
         protocol::{
-            headers::ActixHeaders, http_method::HttpMethod, versia_protocol::verify::verify_request,
+            headers::ActixHeaders,
+            http_method::HttpMethod,
+            key_cache::KeyCache,
+            versia_protocol::{nonce_cache::NonceCache, verify::verify_request},
         },
         types::versia_types::structures::collection::Collection,
     },
@@ -16,7 +19,8 @@ use actix_web::{
     HttpRequest, HttpResponse, Result,
 };
 
-#[get("/users/{uuid}/outbox/versia")]
+#[get("/users/{uname}/outbox/versia")]
+#[allow(clippy::too_many_arguments)]
 pub async fn versia_outbox(
     request: HttpRequest,
     body: actix_web::web::Bytes,
@@ -24,6 +28,8 @@ pub async fn versia_outbox(
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
     page: actix_web::web::Query<Option<Page>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
 ) -> Result<HttpResponse> {
     let page = match page.into_inner() {
         Some(x) => x.page,
@@ -33,8 +39,8 @@ pub async fn versia_outbox(
         return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
     }
 
-    let uuid = actix_path.into_inner();
-    let path = format!("/users/{}/outbox/versia", &uuid);
+    let uname = actix_path.into_inner();
+    let path = format!("/versia/users/{}/outbox/versia", &uname);
 
     let Ok(body) = String::from_utf8(body.to_vec()) else {
         return Err(ErrorUnauthorized("bad request body"));
@@ -48,7 +54,11 @@ pub async fn versia_outbox(
         HttpMethod::Get,
         &path,
         &hash,
-        &VersiaConn { conn: &conn },
+        &VersiaConn {
+            conn: &conn,
+            nonces: &nonces,
+            keys: &keys,
+        },
     )
     .await;
 
@@ -57,7 +67,7 @@ pub async fn versia_outbox(
     }
 
     let Some(count) = conn
-        .get_user_post_count(&uuid, &EntityOrigin::Local(&state.instance_domain))
+        .get_user_post_count(&uname, &EntityOrigin::Local(&state.instance_domain))
         .await
     else {
         return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
@@ -65,7 +75,7 @@ pub async fn versia_outbox(
 
     let posts = conn
         .get_user_posts_versia(
-            &uuid,
+            &uname,
             &EntityOrigin::Local(&state.instance_domain),
             state.outbox_pagnation_size,
             page,
@@ -75,7 +85,7 @@ pub async fn versia_outbox(
     match posts {
         Some(posts) => {
             let Some(user) = conn
-                .get_versia_user(&uuid, &EntityOrigin::Local(&state.instance_domain))
+                .get_versia_user(&uname, &EntityOrigin::Local(&state.instance_domain))
                 .await
             else {
                 return Err(ErrorNotFound(r#"{"error":"Not Found"}"#));
@@ -88,7 +98,7 @@ pub async fn versia_outbox(
                 page,
                 Some(user.uri),
                 &state.instance_domain,
-                &format!("users/{uuid}/outbox/versia"),
+                &format!("versia/users/{uname}/outbox/versia"),
             );
             Ok(HttpResponse::Ok()
                 .content_type("application/json; charset=UTF-8")
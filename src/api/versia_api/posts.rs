@@ -1,12 +1,18 @@
 use crate::{
     cryptography::digest::sha256_hash,
-    db::conn::{Conn, EntityOrigin, VersiaConn},
+    db::{
+        conn::{Conn, EntityOrigin, VersiaConn},
+        utility::polls::with_tally,
+    },
     protocols::protocol::{
-        headers::ActixHeaders, http_method::HttpMethod, versia_protocol::verify::verify_request,
+        headers::ActixHeaders,
+        http_method::HttpMethod,
+        key_cache::KeyCache,
+        versia_protocol::{nonce_cache::NonceCache, verify::verify_request},
     },
 };
 use actix_web::{
-    error::{ErrorNotFound, ErrorUnauthorized},
+    error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized},
     get,
     web::Data,
     HttpRequest, HttpResponse, Result,
@@ -19,6 +25,8 @@ pub async fn versia_posts(
     actix_path: actix_web::web::Path<(String, String)>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
 ) -> Result<HttpResponse> {
     let (uname, pid) = actix_path.into_inner();
     let path = format!("/users/@{}/statuses/{}/versia", &uname, &pid);
@@ -35,7 +43,11 @@ pub async fn versia_posts(
         HttpMethod::Get,
         &path,
         &hash,
-        &VersiaConn { conn: &conn },
+        &VersiaConn {
+            conn: &conn,
+            nonces: &nonces,
+            keys: &keys,
+        },
     )
     .await;
 
@@ -46,6 +58,14 @@ pub async fn versia_posts(
     let post = conn
         .get_versia_post(&pid, &EntityOrigin::Local(&state.instance_domain))
         .await;
+    let post = match post {
+        Some(x) => Some(
+            with_tally(conn.as_ref().as_ref(), x)
+                .await
+                .map_err(ErrorInternalServerError)?,
+        ),
+        None => None,
+    };
 
     match post {
         Some(x) => Ok(HttpResponse::Ok()
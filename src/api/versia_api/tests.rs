@@ -0,0 +1,212 @@
+use actix_web::web::Data;
+use url::Url;
+
+use crate::{
+    config::{get_config, Config},
+    db::{
+        conn::{Conn, EntityOrigin},
+        memory::memory_conn::InMemoryConn,
+        utility::{
+            compose::Draft,
+            new_actor::{generate_versia_links, NewLocal},
+            stored_user::{new_uid, now_millis, StoredUser},
+            streaming::{StreamBus, STREAM_BUFFER},
+        },
+    },
+    protocols::{
+        protocol::{
+            key_cache::{KeyCache, KEY_CACHE_SIZE},
+            versia_protocol::{
+                nonce_cache::{NonceCache, NONCE_CACHE_SIZE},
+                requests::Signer,
+            },
+        },
+        types::versia_types::entities::delete::{Delete, DeleteTypeField, DeletedType},
+    },
+};
+
+use super::inbox::{handle_inbox, VersiaInboxItem};
+
+/// a versia user of another instance, optionally delegating to a user
+/// that may act for them
+fn federated_user(
+    conn: &InMemoryConn,
+    domain: &str,
+    name: &str,
+    delegate: Option<&Url>,
+) -> StoredUser {
+    let mut user = StoredUser::new_local(
+        domain,
+        &NewLocal::new(name.to_string(), "filler".to_string(), None, None),
+    );
+    user.resource_link = generate_versia_links(domain, name).id.to_string();
+    user.private_key_pem = None;
+    user.password = None;
+    user.key_delegate = delegate.map(Url::to_string);
+    conn.insert_federated_user(user).unwrap()
+}
+
+async fn deliver(
+    config: &Config,
+    conn: &Data<Box<dyn Conn + Sync>>,
+    signer: Signer,
+    entity: VersiaInboxItem,
+) {
+    handle_inbox(
+        signer,
+        entity,
+        Data::new(config.clone()),
+        conn.clone(),
+        Data::new(NonceCache::new(config.signature_skew, NONCE_CACHE_SIZE)),
+        Data::new(KeyCache::new(config.key_cache_ttl, KEY_CACHE_SIZE)),
+        Data::new(StreamBus::new(STREAM_BUFFER)),
+    )
+    .await;
+}
+
+#[actix_web::test]
+async fn test_delegated_note() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+
+    let delegate = generate_versia_links("delegate.remote", "delegate").id;
+    let author = federated_user(&conn, "author.remote", "author", Some(&delegate));
+    let stranger = generate_versia_links("stranger.remote", "stranger").id;
+    let note = |content: &str| {
+        let draft = Draft {
+            content: Some(content.to_string()),
+            ..Default::default()
+        };
+        let post = draft.into_post(&author, 1000);
+        (
+            Url::parse(&post.id).unwrap(),
+            post.to_versia(&author).unwrap(),
+        )
+    };
+    let (delegated_id, delegated) = note("<p>from the delegate</p>");
+    let (forged_id, forged) = note("<p>from a stranger</p>");
+    let conn: Data<Box<dyn Conn + Sync>> = Data::new(Box::new(conn));
+
+    deliver(
+        &config,
+        &conn,
+        Signer::User(stranger),
+        VersiaInboxItem::Post(forged),
+    )
+    .await;
+    if conn.get_post(&forged_id).await.is_some() {
+        return Err("stored a note signed by someone the author didn't delegate to".to_string());
+    }
+
+    deliver(
+        &config,
+        &conn,
+        Signer::User(delegate),
+        VersiaInboxItem::Post(delegated),
+    )
+    .await;
+    let Some(stored) = conn.get_post(&delegated_id).await else {
+        return Err("the note of the delegate was not stored".to_string());
+    };
+    if stored.domain.ne("author.remote") || stored.actor.ne(&author.uid) {
+        return Err("the note was not stored under its author".to_string());
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_delegated_delete() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+
+    let delegate = generate_versia_links("delete-delegate.remote", "delegate").id;
+    let author = federated_user(&conn, "delete-author.remote", "author", Some(&delegate));
+    // a user of the same instance as the delegate, which the author
+    // never delegated to
+    let stranger = generate_versia_links("delete-delegate.remote", "stranger").id;
+    let draft = Draft {
+        content: Some("<p>soon gone</p>".to_string()),
+        ..Default::default()
+    };
+    let post = draft.into_post(&author, 1000);
+    let id = Url::parse(&post.id).unwrap();
+    conn.create_versia_post(
+        post.to_versia(&author).unwrap(),
+        &EntityOrigin::Federated("delete-author.remote"),
+    )
+    .await
+    .unwrap();
+    let conn: Data<Box<dyn Conn + Sync>> = Data::new(Box::new(conn));
+    let delete = |signer: &Url| Delete {
+        type_field: DeleteTypeField::Delete,
+        id: new_uid(),
+        created_at: now_millis(),
+        author: Some(signer.clone()),
+        deleted_type: DeletedType::Note,
+        deleted: id.clone(),
+    };
+
+    deliver(
+        &config,
+        &conn,
+        Signer::User(stranger.clone()),
+        VersiaInboxItem::Delete(delete(&stranger)),
+    )
+    .await;
+    if conn.get_post(&id).await.is_none() {
+        return Err("a user the author didn't delegate to deleted the note".to_string());
+    }
+
+    deliver(
+        &config,
+        &conn,
+        Signer::User(delegate.clone()),
+        VersiaInboxItem::Delete(delete(&delegate)),
+    )
+    .await;
+    if conn.get_post(&id).await.is_some() {
+        return Err("the delegate failed to delete the note".to_string());
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_profile_update() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+
+    let owner = federated_user(&conn, "profile.remote", "profile_owner", None);
+    let neighbour = generate_versia_links("profile.remote", "profile_neighbour").id;
+    let conn: Data<Box<dyn Conn + Sync>> = Data::new(Box::new(conn));
+    let renamed = |name: &str| {
+        let mut user = owner.to_versia().unwrap();
+        user.display_name = Some(name.to_string());
+        VersiaInboxItem::User(Box::new(user))
+    };
+    let display_name = || async { conn.get_user(&owner.uid).await.unwrap().display_name };
+
+    deliver(
+        &config,
+        &conn,
+        Signer::User(neighbour),
+        renamed("taken over"),
+    )
+    .await;
+    if display_name().await.is_some() {
+        return Err("another user of the instance changed the profile".to_string());
+    }
+    let uri = owner.versia_uri().unwrap();
+    deliver(&config, &conn, Signer::User(uri), renamed("renamed")).await;
+    if display_name().await.as_deref().ne(&Some("renamed")) {
+        return Err("the user failed to change their own profile".to_string());
+    }
+    let instance = Signer::Instance("profile.remote".to_string());
+    deliver(&config, &conn, instance, renamed("by the instance")).await;
+    if display_name().await.as_deref().ne(&Some("by the instance")) {
+        return Err("the instance failed to change the profile".to_string());
+    }
+    Ok(())
+}
@@ -2,7 +2,10 @@ use crate::{
     cryptography::digest::sha256_hash,
     db::conn::{Conn, EntityOrigin, VersiaConn},
     protocols::protocol::{
-        headers::ActixHeaders, http_method::HttpMethod, versia_protocol::verify::verify_request,
+        headers::ActixHeaders,
+        http_method::HttpMethod,
+        key_cache::KeyCache,
+        versia_protocol::{nonce_cache::NonceCache, verify::verify_request},
     },
 };
 use actix_web::{
@@ -13,16 +16,18 @@ use actix_web::{
     HttpRequest, HttpResponse, Result,
 };
 
-#[get("/users/{uuid}/versia")]
+#[get("/users/{uname}/versia")]
 pub async fn versia_user(
     request: HttpRequest,
     body: actix_web::web::Bytes,
     actix_path: actix_web::web::Path<String>,
     state: Data<crate::config::Config>,
     conn: Data<Box<dyn Conn + Sync>>,
+    nonces: Data<NonceCache>,
+    keys: Data<KeyCache>,
 ) -> Result<HttpResponse> {
     let path = actix_path.path().to_string();
-    let uuid = actix_path.into_inner();
+    let uname = actix_path.into_inner();
 
     let Ok(body) = String::from_utf8(body.to_vec()) else {
         return Err(ErrorUnauthorized("bad request body"));
@@ -36,7 +41,11 @@ pub async fn versia_user(
         HttpMethod::Get,
         &path,
         &hash,
-        &VersiaConn { conn: &conn },
+        &VersiaConn {
+            conn: &conn,
+            nonces: &nonces,
+            keys: &keys,
+        },
     )
     .await;
 
@@ -45,7 +54,7 @@ pub async fn versia_user(
     }
 
     let user = conn
-        .get_versia_user(&uuid, &EntityOrigin::Local(&state.instance_domain))
+        .get_versia_user(&uname, &EntityOrigin::Local(&state.instance_domain))
         .await;
 
     match user {
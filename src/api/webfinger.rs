@@ -1,5 +1,5 @@
 use actix_web::{
-    error::{ErrorBadRequest, ErrorNotFound},
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
     get,
     web::{self, Data},
     HttpResponse, Result,
@@ -62,17 +62,30 @@ impl WebfingerQuery {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WebfingerResult {
-    subject: String,
-    aliases: Option<Vec<String>>,
-    links: Vec<WebfingerLink>,
+    pub subject: String,
+    pub aliases: Option<Vec<String>>,
+    pub links: Vec<WebfingerLink>,
+}
+
+impl WebfingerResult {
+    /// gets the self link with the given content type
+    pub fn get_self_link(&self, type_field: &str) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|x| x.rel.eq("self") && x.type_field.eq(type_field))
+            .map(|x| x.href.as_str())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WebfingerLink {
-    rel: String,
+    pub rel: String,
+    // some links such as subscribe templates have no type or href
     #[serde(rename = "type")]
-    type_field: String,
-    href: String,
+    #[serde(default)]
+    pub type_field: String,
+    #[serde(default)]
+    pub href: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -104,19 +117,22 @@ async fn webfinger(
         true => conn
             .get_instance_actor()
             .await
+            .map_err(ErrorInternalServerError)?
             .to_actor(&state.instance_domain),
         //not the instance actor
         false => {
             let actor = conn
-                .get_actor(&preferred_username, &EntityOrigin::Local(&state.instance_domain))
+                .get_actor(
+                    &preferred_username,
+                    &EntityOrigin::Local(&state.instance_domain),
+                )
                 .await;
-            let actor = match actor {
+            match actor {
                 Some(x) => x,
                 None => {
                     return Err(ErrorNotFound("not found"));
                 }
-            };
-            actor
+            }
         }
     };
 
@@ -128,26 +144,28 @@ async fn webfinger(
 
     let id = actor.id.as_str();
 
+    let mut links = vec![WebfingerLink {
+        rel: "self".to_string(),
+        type_field: "application/activity+json".to_string(),
+        href: id.to_string(),
+    }];
+    if let Some(versia_url) = &actor.versia_url {
+        links.push(WebfingerLink {
+            rel: "self".to_string(),
+            type_field: "application/json".to_string(),
+            href: versia_url.to_string(),
+        });
+    }
+    links.push(WebfingerLink {
+        rel: "http://webfinger.net/rel/profile-page".to_string(),
+        type_field: "text/html".to_string(),
+        href: profile_page.clone(),
+    });
+
     let webfinger = WebfingerResult {
         subject,
-        aliases: Some(vec![id.to_string(), profile_page.clone()]),
-        links: vec![
-            WebfingerLink {
-                rel: "self".to_string(),
-                type_field: "application/activity+json".to_string(),
-                href: format!("{}/ap", id),
-            },
-            WebfingerLink {
-                rel: "self".to_string(),
-                type_field: "application/json".to_string(),
-                href: format!("{}/versia", id),
-            },
-            WebfingerLink {
-                rel: "http://webfinger.net/rel/profile-page".to_string(),
-                type_field: "text/html".to_string(),
-                href: profile_page,
-            },
-        ],
+        aliases: Some(vec![id.to_string(), profile_page]),
+        links,
     };
     let webfinger = serde_json::to_string(&webfinger).unwrap();
 
@@ -1,10 +1,24 @@
-use std::sync::Mutex;
+use std::{sync::Mutex, time::Duration};
 
 use actix_web::{get, web::Data, App, HttpResponse, HttpServer, Responder};
 
 use crate::{
     api::{ap_api::inbox::Inbox, routes::get_routes},
     config::Config,
+    db::{
+        conn::Conn,
+        utility::{
+            delivery::process_due_deliveries,
+            notifications::notify_ended_polls,
+            registration::apply_registration_mode,
+            stored_user::now_millis,
+            streaming::{StreamBus, STREAM_BUFFER},
+        },
+    },
+    protocols::protocol::{
+        key_cache::{KeyCache, KEY_CACHE_SIZE},
+        versia_protocol::nonce_cache::{NonceCache, NONCE_CACHE_SIZE},
+    },
 };
 
 #[get("/")]
@@ -12,26 +26,98 @@ async fn hello() -> impl Responder {
     HttpResponse::Ok().body("Hello world!")
 }
 
+/// how often the delivery queue is checked for due deliveries
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(10);
+const DELIVERY_BATCH_SIZE: u64 = 50;
+
+/// how often polls are checked for having ended
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// works through the delivery queue until the app stops. a full
+/// batch is followed up straight away instead of waiting
+async fn run_delivery_worker(
+    conn: Data<Box<dyn Conn + Sync>>,
+    instance_domain: String,
+    dead_after: i64,
+) {
+    loop {
+        match process_due_deliveries(
+            conn.as_ref().as_ref(),
+            &instance_domain,
+            DELIVERY_BATCH_SIZE,
+            dead_after,
+        )
+        .await
+        {
+            Ok(x) if x as u64 == DELIVERY_BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(x) => eprintln!("failed to process the delivery queue: {}", x),
+        }
+        actix_web::rt::time::sleep(DELIVERY_INTERVAL).await;
+    }
+}
+
+/// lets authors know about polls that closed since the last
+/// pass, until the app stops. kept apart from deliveries so a long
+/// queue doesn't hold up the notifications
+async fn run_poll_worker(conn: Data<Box<dyn Conn + Sync>>, bus: Data<StreamBus>) {
+    let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(x) = notify_ended_polls(conn.as_ref().as_ref(), &bus, now_millis()).await {
+            eprintln!("failed to notify ended polls: {}", x);
+        }
+    }
+}
+
 pub async fn start_application(config: Config) -> std::io::Result<()> {
+    let conn = config.create_conn().await;
+    run_application(config, conn).await
+}
+
+/// runs the app on an existing conn. the conn is shared between
+/// all of the workers
+pub async fn run_application(config: Config, conn: Box<dyn Conn + Sync>) -> std::io::Result<()> {
     //init the conn and instance actor
-    {
-        let conn = config.create_conn();
-        if let Err(x) = conn.init().await {
-            eprintln!("{}", x);
+    if let Err(x) = conn.init().await {
+        eprintln!("{}", x);
+        return Ok(());
+    }
+    if let Err(x) = conn.get_instance_actor().await {
+        eprintln!("failed to get the instance actor: {}", x);
+        return Ok(());
+    }
+    match apply_registration_mode(conn.as_ref(), config.registration_mode).await {
+        Ok(0) => {}
+        Ok(x) => println!("demoted {} users that need approval", x),
+        Err(x) => {
+            eprintln!("failed to apply the registration mode: {}", x);
             return Ok(());
         }
-        conn.get_instance_actor().await;
     }
+    let conn = Data::new(conn);
+    let bus = Data::new(StreamBus::new(STREAM_BUFFER));
+    actix_web::rt::spawn(run_delivery_worker(
+        conn.clone(),
+        config.instance_domain.clone(),
+        config.delivery_dead_after as i64 * 1000,
+    ));
+    actix_web::rt::spawn(run_poll_worker(conn.clone(), bus.clone()));
 
     let bind = config.bind_address.clone();
     let port = config.port;
     let inbox = Data::new(Inbox {
         inbox: Mutex::new(Vec::new()),
     });
+    let nonces = Data::new(NonceCache::new(config.signature_skew, NONCE_CACHE_SIZE));
+    let keys = Data::new(KeyCache::new(config.key_cache_ttl, KEY_CACHE_SIZE));
     HttpServer::new(move || {
         App::new()
-            .app_data(Data::new(config.create_conn()))
+            .app_data(conn.clone())
             .app_data(inbox.clone())
+            .app_data(nonces.clone())
+            .app_data(keys.clone())
+            .app_data(bus.clone())
             .app_data(Data::new(config.to_owned()))
             .service(get_routes())
     })
@@ -1,7 +1,44 @@
 use config::ConfigError;
 use serde::Deserialize;
 
-use crate::db::conn::Conn;
+use crate::db::{
+    conn::Conn, memory::memory_conn::InMemoryConn, postgres::pg_conn::PgConn,
+    sqlite::sqlite_conn::SqliteConn, utility::registration::RegistrationMode,
+};
+
+/// which implementation of [`Conn`] the instance stores its data with
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DbBackend {
+    #[default]
+    Postgres,
+    Sqlite,
+    /// nothing is persisted, useful for throwaway dev instances
+    Memory,
+}
+
+fn default_pg_port() -> u16 {
+    5432
+}
+
+/// two days
+fn default_delivery_dead_after() -> u64 {
+    2 * 24 * 60 * 60
+}
+
+/// five minutes
+fn default_signature_skew() -> u64 {
+    5 * 60
+}
+
+/// one hour
+fn default_key_cache_ttl() -> u64 {
+    60 * 60
+}
+
+fn default_sqlite_path() -> String {
+    "bayou.db".to_string()
+}
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
@@ -12,27 +49,68 @@ pub struct Config {
     pub contact_email: String,
     pub port: u16,
     pub outbox_pagnation_size: u64,
+    /// seconds a delivery can keep failing before it is marked dead
+    #[serde(default = "default_delivery_dead_after")]
+    pub delivery_dead_after: u64,
+    /// seconds the timestamp of a signed request may differ from our clock
+    #[serde(default = "default_signature_skew")]
+    pub signature_skew: u64,
+    /// seconds the public keys of remote signers are cached for
+    #[serde(default = "default_key_cache_ttl")]
+    pub key_cache_ttl: u64,
+    /// how new users can sign up, closed by default
+    #[serde(default)]
+    pub registration_mode: RegistrationMode,
+
+    #[serde(default)]
+    pub db_backend: DbBackend,
 
+    // only needed for the postgres backend
+    #[serde(default)]
     pub pg_user: String,
+    #[serde(default)]
     pub pg_password: String,
+    #[serde(default)]
     pub pg_host: String,
+    #[serde(default = "default_pg_port")]
     pub pg_port: u16,
+    #[serde(default)]
     pub pg_dbname: String,
+
+    /// the database file used by the sqlite backend
+    #[serde(default = "default_sqlite_path")]
+    pub sqlite_path: String,
 }
 
 impl Config {
-    pub fn create_conn(&self) -> Box<dyn Conn + Sync> {
+    pub async fn create_conn(&self) -> Box<dyn Conn + Sync> {
+        match self.db_backend {
+            DbBackend::Postgres => self.create_pg_conn(),
+            DbBackend::Sqlite => Box::new(
+                SqliteConn::open(&self.sqlite_path, &self.instance_domain)
+                    .await
+                    .expect("failed to open the sqlite database"),
+            ),
+            DbBackend::Memory => Box::new(InMemoryConn::new(&self.instance_domain)),
+        }
+    }
+
+    fn create_pg_conn(&self) -> Box<dyn Conn + Sync> {
         let db_config = deadpool_postgres::Config {
             user: Some(self.pg_user.clone()),
             password: Some(self.pg_password.clone()),
             host: Some(self.pg_host.clone()),
+            port: Some(self.pg_port),
             dbname: Some(self.pg_dbname.clone()),
 
             ..Default::default()
         };
 
         let pool = db_config.create_pool(None, tokio_postgres::NoTls).unwrap();
-        Box::new(crate::db::postgres::pg_conn::PgConn { db: pool.clone() }) as Box<dyn Conn + Sync>
+        Box::new(PgConn {
+            db: pool,
+            instance_domain: self.instance_domain.clone(),
+        }) as Box<dyn Conn + Sync>
     }
 }
 
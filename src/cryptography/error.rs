@@ -11,4 +11,10 @@ pub enum Error {
     /// Invalid UTF-8
     #[error("Invalid UTF-8")]
     Utf8(#[from] FromUtf8Error),
+    /// Invalid PKCS#8 private key
+    #[error("Invalid private key")]
+    Pkcs8(#[from] ed25519_dalek::pkcs8::Error),
+    /// Invalid SPKI public key
+    #[error("Invalid public key")]
+    Spki(#[from] ed25519_dalek::pkcs8::spki::Error),
 }
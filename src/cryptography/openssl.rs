@@ -1,7 +1,8 @@
 use openssl::{
     hash::MessageDigest,
-    pkey::{PKey, Private, Public},
+    pkey::{Id, PKey, Private, Public},
     rsa::Rsa,
+    sign::{Signer, Verifier},
 };
 
 use super::key::{Key, KeyType, PrivateKey, PublicKey};
@@ -23,9 +24,19 @@ impl Key for OpenSSLPrivate {
 
 impl PrivateKey for OpenSSLPrivate {
     fn sign(&mut self, content: &str) -> String {
-        let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), &self.0).unwrap();
-        signer.update(content.as_bytes()).unwrap();
-        openssl::base64::encode_block(&signer.sign_to_vec().unwrap())
+        // ed25519 hashes internally and can only sign in one shot
+        let signature = match self.0.id() {
+            Id::ED25519 => {
+                let mut signer = Signer::new_without_digest(&self.0).unwrap();
+                signer.sign_oneshot_to_vec(content.as_bytes()).unwrap()
+            }
+            _ => {
+                let mut signer = Signer::new(MessageDigest::sha256(), &self.0).unwrap();
+                signer.update(content.as_bytes()).unwrap();
+                signer.sign_to_vec().unwrap()
+            }
+        };
+        openssl::base64::encode_block(&signature)
     }
 
     fn generate(algorithm: KeyType) -> Self {
@@ -62,9 +73,46 @@ impl Key for OpenSSLPublic {
 
 impl PublicKey for OpenSSLPublic {
     fn verify(&self, plain_content: &[u8], signature: &[u8]) -> bool {
-        let mut verifier =
-            openssl::sign::Verifier::new(openssl::hash::MessageDigest::sha256(), &self.0).unwrap();
-        verifier.update(plain_content).unwrap();
-        verifier.verify(signature).unwrap()
+        let verified = match self.0.id() {
+            Id::ED25519 => Verifier::new_without_digest(&self.0)
+                .and_then(|mut x| x.verify_oneshot(signature, plain_content)),
+            _ => Verifier::new(MessageDigest::sha256(), &self.0).and_then(|mut x| {
+                x.update(plain_content)?;
+                x.verify(signature)
+            }),
+        };
+        // a malformed signature is just one that doesn't verify
+        verified.unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(algorithm: KeyType) -> Result<(), String> {
+        let mut private = OpenSSLPrivate::generate(algorithm);
+        let public = private.public_key_pem().map_err(|x| x.to_string())?;
+        let public = OpenSSLPublic::from_pem(public.as_bytes()).map_err(|x| x.to_string())?;
+
+        let signature = private.sign("GET /users/test nonce hash");
+        let signature = openssl::base64::decode_block(&signature).map_err(|x| x.to_string())?;
+        if !public.verify(b"GET /users/test nonce hash", &signature) {
+            return Err("signature failed to verify".to_string());
+        }
+        if public.verify(b"GET /users/other nonce hash", &signature) {
+            return Err("signature verified different content".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_sign() -> Result<(), String> {
+        round_trip(KeyType::Rsa256)
+    }
+
+    #[test]
+    fn test_ed25519_sign() -> Result<(), String> {
+        round_trip(KeyType::Ed25519)
     }
 }
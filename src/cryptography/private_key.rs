@@ -1,69 +1,101 @@
-// use ed25519::{
-//     pkcs8::{spki::der::pem::LineEnding, DecodePrivateKey, EncodePrivateKey, EncodePublicKey},
-//     signature::SignerMut,
-// };
-// use ed25519_dalek::SigningKey;
+use base64::Engine;
+use ed25519_dalek::{
+    pkcs8::{
+        spki::der::pem::LineEnding, DecodePrivateKey, EncodePrivateKey, EncodePublicKey,
+        KeypairBytes,
+    },
+    Signer, SigningKey,
+};
+use rand::rngs::OsRng;
 
-// use rand::rngs::OsRng;
-
-// ed25519_dalek::pkcs8::spki::der::EncodePem;
-// use super::key::{KeyType, PrivateKey};
+use super::{
+    error::Error,
+    key::{Key, KeyType, PrivateKey},
+};
 
+/// private keys backed by pure rust implementations. versia keys
+/// are always ed25519
 #[derive(Debug, Clone, PartialEq)]
 pub enum AlgorithmsPrivateKey {
-    Ed25519(ed25519_dalek::SigningKey),
+    Ed25519(SigningKey),
 }
 
-// impl PrivateKey for AlgorithmsPrivateKey {
-//     fn sign(&mut self, content: &str) -> String {
-//         match self {
-//             AlgorithmsPrivateKey::Ed25519(signing_key) => {
-//                 let signature = signing_key.sign(content.as_bytes());
-//                 signature.to_string()
-//             }
-//         }
-//     }
+impl Key for AlgorithmsPrivateKey {
+    fn from_pem(pem: &[u8]) -> Result<Self, Error> {
+        let pem = String::from_utf8(pem.to_vec())?;
+        Ok(AlgorithmsPrivateKey::Ed25519(SigningKey::from_pkcs8_pem(
+            &pem,
+        )?))
+    }
+
+    fn to_pem(&self) -> Result<String, Error> {
+        match self {
+            // openssl can't read the v2 encoding that embeds the public key,
+            // so only the secret is written out like openssl does
+            AlgorithmsPrivateKey::Ed25519(signing_key) => {
+                let keypair = KeypairBytes {
+                    secret_key: signing_key.to_bytes(),
+                    public_key: None,
+                };
+                Ok(keypair.to_pkcs8_pem(LineEnding::LF)?.to_string())
+            }
+        }
+    }
+}
 
-//     fn from_pem(
-//         pem: &str,
-//         algorithm: crate::cryptography::key::KeyType,
-//     ) -> Result<Self, super::key::ParseErr> {
-//         match algorithm {
-//             KeyType::Ed25519 => {
-//                 let Ok(val) = ed25519_dalek::SigningKey::from_pkcs8_pem(pem) else {
-//                     return Err(crate::cryptography::key::ParseErr::Failure);
-//                 };
-//                 Ok(AlgorithmsPrivateKey::Ed25519(val))
-//             }
-//         }
-//     }
+impl PrivateKey for AlgorithmsPrivateKey {
+    fn sign(&mut self, content: &str) -> String {
+        match self {
+            AlgorithmsPrivateKey::Ed25519(signing_key) => {
+                let signature = signing_key.sign(content.as_bytes());
+                base64::prelude::BASE64_STANDARD.encode(signature.to_bytes())
+            }
+        }
+    }
 
-//     fn generate(algorithm: KeyType) -> Self {
-//         match algorithm {
-//             KeyType::Ed25519 => {
-//                 let mut csprng = OsRng;
-//                 AlgorithmsPrivateKey::Ed25519(SigningKey::generate(&mut csprng))
-//             }
-//         }
-//     }
+    /// panics for rsa, use [`super::openssl::OpenSSLPrivate`] for those
+    fn generate(algorithm: KeyType) -> Self {
+        match algorithm {
+            KeyType::Ed25519 => AlgorithmsPrivateKey::Ed25519(SigningKey::generate(&mut OsRng)),
+            KeyType::Rsa256 => panic!("rsa keys are only supported through openssl"),
+        }
+    }
 
-//     fn private_key_pem(&self) -> String {
-//         match self {
-//             AlgorithmsPrivateKey::Ed25519(signing_key) => {
-//                 let test = signing_key.to_pkcs8_pem(LineEnding::default());
-//                 test.expect("for some reason the private key failed to encode")
-//                     .to_string()
-//             }
-//         }
-//     }
+    fn public_key_pem(&self) -> Result<String, Error> {
+        match self {
+            AlgorithmsPrivateKey::Ed25519(signing_key) => Ok(signing_key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)?),
+        }
+    }
+}
 
-//     fn public_key_pem(&self) -> String {
-//         match self {
-//             AlgorithmsPrivateKey::Ed25519(signing_key) => {
-//                 let val = signing_key.verifying_key();
-//                 let test = val.to_public_key_pem(LineEnding::default());
-//                 test.expect("for some reason the verifying key failed to encode as a public key pem. please send this along to the bayou project for further investigation")
-//             }
-//         }
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::{
+        key::PublicKey,
+        openssl::{OpenSSLPrivate, OpenSSLPublic},
+    };
+
+    #[test]
+    fn test_openssl_interop() -> Result<(), String> {
+        // keys generated by either backend must be usable by the other
+        let openssl = OpenSSLPrivate::generate(KeyType::Ed25519);
+        let pem = openssl.to_pem().map_err(|x| x.to_string())?;
+        let mut key = AlgorithmsPrivateKey::from_pem(pem.as_bytes()).map_err(|x| x.to_string())?;
+        let pem = key.to_pem().map_err(|x| x.to_string())?;
+        OpenSSLPrivate::from_pem(pem.as_bytes()).map_err(|x| x.to_string())?;
+
+        let public = key.public_key_pem().map_err(|x| x.to_string())?;
+        let public = OpenSSLPublic::from_pem(public.as_bytes()).map_err(|x| x.to_string())?;
+        let signature = key.sign("POST /inbox nonce hash");
+        let signature = base64::prelude::BASE64_STANDARD
+            .decode(signature)
+            .map_err(|x| x.to_string())?;
+        if !public.verify(b"POST /inbox nonce hash", &signature) {
+            return Err("openssl failed to verify an ed25519-dalek signature".to_string());
+        }
+        Ok(())
+    }
+}
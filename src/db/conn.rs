@@ -3,7 +3,10 @@ use crate::{
     protocols::{
         protocol::{
             errors::FetchErr,
-            versia_protocol::{requests::Signer, verify::VersiaVerificationCache},
+            key_cache::KeyCache,
+            versia_protocol::{
+                nonce_cache::NonceCache, requests::Signer, verify::VersiaVerificationCache,
+            },
         },
         types::{
             activitystream_objects::{actors::Actor, postable::ApPostable},
@@ -19,7 +22,20 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use super::utility::{instance_actor::InstanceActor, new_actor::NewLocal, protocols::Protocol};
+use super::utility::{
+    delivery::Delivery,
+    instance_actor::InstanceActor,
+    new_actor::NewLocal,
+    notifications::{Notification, NotificationMarker, NotificationQuery},
+    oauth::{OAuthApp, OAuthCode, OAuthToken},
+    polls::PollVote,
+    protocols::{Protocol, SignatureScheme},
+    registration::{Application, Invite, RegistrationMode},
+    revisions::PostRevision,
+    stored_post::StoredPost,
+    stored_user::StoredUser,
+    timeline::{Timeline, TimelineQuery},
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum InsertErr {
@@ -31,6 +47,10 @@ pub enum DbErr {
     FetchErr(FetchErr),
     InsertErr(InsertErr),
     InvalidType,
+    /// the requested entity does not exist
+    NotFound,
+    /// the backend failed to run a query, contains the backend's message
+    QueryErr(String),
 }
 
 impl std::fmt::Display for DbErr {
@@ -47,21 +67,43 @@ pub enum EntityOrigin<'a> {
 
 pub struct VersiaConn<'a> {
     pub conn: &'a Data<Box<dyn Conn + Sync>>,
+    pub nonces: &'a NonceCache,
+    pub keys: &'a KeyCache,
 }
 
 impl VersiaVerificationCache for VersiaConn<'_> {
-    async fn get_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic> {
-        self.conn.get_public_key(signed_by).await
+    type Key = OpenSSLPublic;
+    async fn get_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        let signer = signed_by.to_string();
+        if let Some(keys) = self.keys.get(&signer) {
+            return keys;
+        }
+        let keys = self.conn.get_public_keys(signed_by).await;
+        self.keys.insert(&signer, keys.clone());
+        keys
+    }
+    async fn refetch_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        let keys = self.conn.refetch_public_keys(signed_by).await;
+        self.keys.insert(&signed_by.to_string(), keys.clone());
+        keys
+    }
+    async fn get_delegate(&self, user: &Url) -> Option<Url> {
+        let user = self.conn.resolve_user(user).await.ok()?;
+        Url::parse(user.key_delegate.as_ref()?).ok()
+    }
+    fn nonces(&self) -> &NonceCache {
+        self.nonces
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum ProtoUser {
     Versia(User),
     ActivityPub(Actor),
 }
 
 #[async_trait]
-pub trait Conn: Sync {
+pub trait Conn: Send + Sync {
     // async fn get_actor_post_count(&self, uname: &str, origin: &EntityOrigin) -> Option<u64>;
     async fn get_user_posts_ap(
         &self,
@@ -72,43 +114,77 @@ pub trait Conn: Sync {
     ) -> Option<Vec<ApPostable>>;
     async fn get_ap_post(&self, post_id: &str, origin: &EntityOrigin) -> Option<ApPostable>;
     /// inserts a federated post into the db and returns the uuid if successful
-    async fn create_ap_post(&self, post: ApPostable, origin: &EntityOrigin) -> Result<String, ()>;
+    async fn create_ap_post(
+        &self,
+        post: ApPostable,
+        origin: &EntityOrigin,
+    ) -> Result<String, DbErr>;
     /// run any prep for the database, for example running migrations
     async fn init(&self) -> Result<(), String>;
     /// gets the instance actor. creates one if its not present
-    async fn get_instance_actor(&self) -> InstanceActor;
+    async fn get_instance_actor(&self) -> Result<InstanceActor, DbErr>;
+    /// replaces the keys of the instance actor
+    async fn set_instance_actor(&self, actor: &InstanceActor) -> Result<(), DbErr>;
 
     /// returns the uid if sucessful
-    async fn create_user(&self, domain: &str, content: &NewLocal) -> Result<String, ()>;
+    async fn create_user(&self, domain: &str, content: &NewLocal) -> Result<String, DbErr>;
     /// gets actor, backfills if not in db
     async fn backfill_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor>;
     async fn get_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor>;
-    /// only gets an actor we have authority over, does not backfill
+    /// only returns users we have the private key of
+    async fn get_local_user(&self, uid: &str) -> Option<StoredUser>;
+    /// a local user by the name they log in with, deleted users are skipped
+    async fn get_local_user_by_username(&self, username: &str) -> Option<StoredUser>;
+    /// any stored user, local or federated. never backfills
+    async fn get_user(&self, uid: &str) -> Option<StoredUser>;
+    /// gets a user by their link, backfilling them if they are
+    /// federated and not in the db yet
+    async fn resolve_user(&self, link: &Url) -> Result<StoredUser, DbErr>;
+    /// refreshes the profile of a stored federated user, matched by
+    /// their resource link. local users can't be updated this way
+    async fn update_federated_user(&self, user: &StoredUser) -> Result<(), DbErr>;
+    // only gets an actor we have authority over, does not backfill
     // async fn get_local_actor(&self, username: &str, domain: &str) -> Option<Actor>;
 
     /// signed_by will always be user for activitypub users
-    /// this will backfill the user if they aren't in the db yet
-    async fn get_public_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic>;
+    /// this will backfill the user if they aren't in the db yet.
+    /// users that recently rotated their key also have the old one
+    async fn get_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic>;
+    /// like get_public_keys but refetches federated users first so a
+    /// rotated key is picked up
+    async fn refetch_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic>;
 
     //-------------------------versia---------------------
 
-    // TODO make versia routes just use unames
     async fn get_user_post_count(&self, uname: &str, origin: &EntityOrigin) -> Option<u64>;
     /// ofset is one based
     async fn get_user_posts_versia(
         &self,
-        uuid: &str,
+        uname: &str,
         origin: &EntityOrigin,
         page_size: u64,
         ofset: u64,
     ) -> Option<Vec<VersiaPostable>>;
+    /// only checks keys already in the db, does not backfill
     async fn get_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic>;
     /// gets the metadata of an instance, backfills if not present
     async fn get_versia_instance_metadata(&self, instance_domain: &str)
         -> Option<InstanceMetadata>;
     /// get the protocol of the given instance. will backfill if the instance isn't in the db
     async fn get_protocol(&self, instance: &str) -> Protocol;
-    async fn get_versia_user(&self, uuid: &str, origin: &EntityOrigin) -> Option<User>;
+    /// every known federated instance speaking the protocol
+    async fn get_instances(&self, protocol: Protocol) -> Result<Vec<String>, DbErr>;
+    /// the shared inboxes known activitypub users listed, reaching every
+    /// instance they are on that has one
+    async fn get_shared_inboxes(&self) -> Result<Vec<String>, DbErr>;
+    /// the signature scheme the instance accepted our requests with, if known
+    async fn get_signature_scheme(&self, instance: &str) -> Option<SignatureScheme>;
+    async fn set_signature_scheme(
+        &self,
+        instance: &str,
+        scheme: SignatureScheme,
+    ) -> Result<(), DbErr>;
+    async fn get_versia_user(&self, uname: &str, origin: &EntityOrigin) -> Option<User>;
     async fn get_versia_post(&self, post_id: &str, origin: &EntityOrigin)
         -> Option<VersiaPostable>;
     /// create a post and return the post
@@ -116,9 +192,150 @@ pub trait Conn: Sync {
         &self,
         post: VersiaPostable,
         origin: &EntityOrigin,
-    ) -> Result<VersiaPostable, ()>;
-    async fn delete_post(&self, post_id: &str, origin: &EntityOrigin) -> Result<(), ()>;
-    async fn delete_user(&self, uid: &Url, origin: &EntityOrigin) -> Result<(), ()>;
+    ) -> Result<VersiaPostable, DbErr>;
+    async fn delete_post(&self, post_id: &str, origin: &EntityOrigin) -> Result<(), DbErr>;
+    /// stores a post written by a local user as it is, ids included.
+    /// it isn't sent out to followers, see [`super::utility::compose`]
+    async fn create_local_post(&self, post: &StoredPost) -> Result<(), DbErr>;
+    /// a stored post by its id. our own posts are also found by their
+    /// activitypub link. never backfills
+    async fn get_post(&self, link: &Url) -> Option<StoredPost>;
+    /// overwrites what can be edited of a stored post, keeping the version
+    /// it replaces as a revision. errors with not found if the author has
+    /// no post with the id. see [`super::utility::revisions`]
+    async fn edit_post(&self, post: &StoredPost) -> Result<(), DbErr>;
+    /// the earlier versions of the post with the given id, oldest first
+    async fn get_post_revisions(&self, post: &str) -> Result<Vec<PostRevision>, DbErr>;
+    /// polls that closed by the given time and weren't marked as ended yet
+    async fn get_ended_polls(&self, now: i64) -> Result<Vec<StoredPost>, DbErr>;
+    /// keeps the poll from being picked up by get_ended_polls again
+    async fn mark_poll_ended(&self, id: &str) -> Result<(), DbErr>;
+    async fn delete_user(&self, uid: &Url, origin: &EntityOrigin) -> Result<(), DbErr>;
+    /// removes everything a local user owns and wipes their profile. the
+    /// row is kept with their keys so their delete can still be signed
+    async fn tombstone_user(&self, uid: &str) -> Result<(), DbErr>;
+    /// swaps in a new keypair for a local user. the old public key is
+    /// kept as their previous key for the grace period, which only our
+    /// own checks look at as it isn't published
+    async fn rotate_user_key(
+        &self,
+        uid: &str,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), DbErr>;
+
+    //-------------------------follows---------------------
+
+    /// follower and target are uids. pending follows are
+    /// requests waiting to be approved. the activity id is the
+    /// link of the follow activity if it has one
+    async fn create_follow(
+        &self,
+        follower: &str,
+        target: &str,
+        pending: bool,
+        activity_id: Option<&str>,
+    ) -> Result<(), DbErr>;
+    /// the users with an accepted follow of the given uid
+    async fn get_followers(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr>;
+    /// the users the given uid has an accepted follow of
+    async fn get_following(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr>;
+    /// if the follower has an accepted follow of the target
+    async fn is_following(&self, follower: &str, target: &str) -> Result<bool, DbErr>;
+    /// approves a pending follow, errors with not found if there is no follow
+    async fn accept_follow(&self, follower: &str, target: &str) -> Result<(), DbErr>;
+    /// used for both rejected requests and unfollows
+    async fn remove_follow(&self, follower: &str, target: &str) -> Result<(), DbErr>;
+    /// removes the follow made by the follower with the given activity
+    async fn remove_follow_activity(&self, follower: &str, activity_id: &str) -> Result<(), DbErr>;
+
+    //-------------------------likes---------------------
+
+    /// actor and post are ids, url is the link of the like activity
+    async fn create_like(&self, actor: &str, post: &str, url: &str) -> Result<(), DbErr>;
+    /// url is the link of the like activity
+    async fn remove_like(&self, actor: &str, url: &str) -> Result<(), DbErr>;
+
+    //-------------------------polls---------------------
+
+    /// a voter counts once per option, voting for one again is an
+    /// AlreadyExists error
+    async fn create_vote(&self, vote: &PollVote) -> Result<(), DbErr>;
+    /// every vote in the poll, oldest first
+    async fn get_votes(&self, poll: &str) -> Result<Vec<PollVote>, DbErr>;
+
+    //-------------------------delivery queue---------------------
+
+    async fn queue_delivery(&self, delivery: &Delivery) -> Result<(), DbErr>;
+    /// deliveries that aren't dead and are due by the given time, oldest first
+    async fn get_due_deliveries(&self, now: i64, limit: u64) -> Result<Vec<Delivery>, DbErr>;
+    /// saves the attempts, schedule and status of an existing delivery
+    async fn update_delivery(&self, delivery: &Delivery) -> Result<(), DbErr>;
+    /// removes a delivery once it has been accepted
+    async fn remove_delivery(&self, id: &str) -> Result<(), DbErr>;
+    /// stops deliveries to an inbox that is gone or stayed unreachable
+    async fn mark_inbox_dead(&self, inbox: &str) -> Result<(), DbErr>;
+    async fn is_inbox_dead(&self, inbox: &str) -> Result<bool, DbErr>;
+
+    //-------------------------oauth---------------------
+
+    async fn create_oauth_app(&self, app: &OAuthApp) -> Result<(), DbErr>;
+    async fn get_oauth_app(&self, client_id: &str) -> Option<OAuthApp>;
+    async fn create_oauth_code(&self, code: &OAuthCode) -> Result<(), DbErr>;
+    /// removes the code while getting it so it can only be exchanged once
+    async fn take_oauth_code(&self, code: &str) -> Option<OAuthCode>;
+    async fn create_oauth_token(&self, token: &OAuthToken) -> Result<(), DbErr>;
+    /// tokens are looked up by their hash, see [`super::utility::oauth::hash_token`]
+    async fn get_oauth_token(&self, token_hash: &str) -> Option<OAuthToken>;
+    async fn revoke_oauth_token(&self, token_hash: &str) -> Result<(), DbErr>;
+
+    //-------------------------registration---------------------
+
+    /// the mode last set, none before the first start
+    async fn get_registration_mode(&self) -> Result<Option<RegistrationMode>, DbErr>;
+    async fn set_registration_mode(&self, mode: RegistrationMode) -> Result<(), DbErr>;
+    /// makes every trusted local user that wasn't vetted untrusted and
+    /// unapproved, returning how many were
+    async fn demote_unvetted_users(&self) -> Result<u64, DbErr>;
+    /// trusts and approves every local user that wasn't vetted and is
+    /// still waiting on a moderator, removing their applications.
+    /// returns how many were
+    async fn promote_unvetted_users(&self) -> Result<u64, DbErr>;
+    /// trusts and vets the user and removes their application
+    async fn approve_user(&self, uid: &str) -> Result<(), DbErr>;
+    async fn create_invite(&self, invite: &Invite) -> Result<(), DbErr>;
+    /// counts a use of the invite, fails with not found if it doesn't
+    /// exist, expired or has no uses left
+    async fn use_invite(&self, code: &str) -> Result<(), DbErr>;
+    /// replaces any application the user already sent
+    async fn create_application(&self, application: &Application) -> Result<(), DbErr>;
+    async fn get_application(&self, uid: &str) -> Option<Application>;
+    /// every application waiting for review, oldest first
+    async fn get_applications(&self) -> Result<Vec<Application>, DbErr>;
+
+    //-------------------------notifications---------------------
+
+    async fn create_notification(&self, notification: &Notification) -> Result<(), DbErr>;
+    /// notifications of the given local user, newest first. the limit
+    /// is capped at [`super::utility::notifications::MAX_NOTIFICATION_LIMIT`]
+    async fn get_notifications(
+        &self,
+        uid: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, DbErr>;
+    async fn get_notification_marker(&self, uid: &str) -> Option<NotificationMarker>;
+    /// replaces the marker of the user
+    async fn set_notification_marker(&self, marker: &NotificationMarker) -> Result<(), DbErr>;
+
+    //-------------------------timelines---------------------
+
+    /// a page of the timeline with the author of each post, newest
+    /// first. the limit is capped at [`super::utility::timeline::MAX_TIMELINE_LIMIT`]
+    async fn get_timeline(
+        &self,
+        timeline: &Timeline<'_>,
+        query: &TimelineQuery,
+    ) -> Result<Vec<(StoredPost, StoredUser)>, DbErr>;
 
     // //----------------------actors---------------------------
 
@@ -0,0 +1,1415 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+use crate::{
+    cryptography::openssl::OpenSSLPublic,
+    db::{
+        conn::{Conn, DbErr, EntityOrigin, InsertErr},
+        utility::{
+            backfill::{
+                detect_protocol, fetch_federated_handle, fetch_federated_user,
+                refresh_federated_user, versia_key_to_openssl,
+            },
+            delivery::Delivery,
+            instance_actor::InstanceActor,
+            new_actor::{local_post_id, local_username, versia_post_link, NewLocal},
+            notifications::{
+                Notification, NotificationMarker, NotificationQuery, MAX_NOTIFICATION_LIMIT,
+            },
+            oauth::{OAuthApp, OAuthCode, OAuthToken},
+            permission::PermissionLevel,
+            polls::PollVote,
+            protocols::{Protocol, SignatureScheme},
+            registration::{Application, Invite, RegistrationMode},
+            revisions::PostRevision,
+            stored_post::StoredPost,
+            stored_user::{new_uid, now_millis, StoredUser},
+            timeline::{Timeline, TimelineQuery, MAX_TIMELINE_LIMIT},
+        },
+    },
+    protocols::{
+        protocol::versia_protocol::{discovery::fetch_instance_metadata, requests::Signer},
+        types::{
+            activitystream_objects::{actors::Actor, postable::ApPostable},
+            versia_types::{
+                entities::{instance_metadata::InstanceMetadata, user::User},
+                postable::VersiaPostable,
+            },
+        },
+    },
+};
+
+use super::store::{Store, StoredLike};
+
+/// keeps everything in process, nothing survives a restart. intended
+/// for tests and throwaway instances. clones share the same data
+#[derive(Clone, Default)]
+pub struct InMemoryConn {
+    store: Arc<RwLock<Store>>,
+    /// the domain of this instance, used to sign fetches made while backfilling
+    pub instance_domain: String,
+}
+
+impl InMemoryConn {
+    pub fn new(instance_domain: &str) -> Self {
+        InMemoryConn {
+            store: Default::default(),
+            instance_domain: instance_domain.to_string(),
+        }
+    }
+
+    // the locks are never held across an await so a poisoned lock
+    // can only come from a panic while modifying the store
+    fn read(&self) -> RwLockReadGuard<'_, Store> {
+        self.store.read().expect("memory store poisoned")
+    }
+    fn write(&self) -> RwLockWriteGuard<'_, Store> {
+        self.store.write().expect("memory store poisoned")
+    }
+
+    fn get_origin_user(&self, username: &str, origin: &EntityOrigin<'_>) -> Option<StoredUser> {
+        let domain = match origin {
+            EntityOrigin::Local(x) => x,
+            EntityOrigin::Federated(x) => x,
+        };
+        // deleted accounts are only kept around to sign their delete
+        self.read()
+            .get_user_by_username(username, domain)
+            .filter(|x| !x.deleted)
+            .cloned()
+    }
+
+    pub(crate) fn insert_federated_user(&self, user: StoredUser) -> Result<StoredUser, DbErr> {
+        let mut store = self.write();
+        // the user may have been fetched through their key id so
+        // make sure we don't already have them under their actual id
+        if let Some(existing) = store.get_user_by_link(&user.resource_link) {
+            return Ok(existing.clone());
+        }
+        store.insert_user(user.clone())?;
+        Ok(user)
+    }
+
+    fn get_origin_post(
+        &self,
+        post_id: &str,
+        origin: &EntityOrigin<'_>,
+    ) -> Option<(StoredPost, StoredUser)> {
+        let store = self.read();
+        let post = match origin {
+            EntityOrigin::Local(domain) => store.get_local_post(post_id, domain),
+            EntityOrigin::Federated(_) => store.posts.get(post_id),
+        }?;
+        let author = store.users.get(&post.actor)?;
+        Some((post.clone(), author.clone()))
+    }
+
+    /// local posts get their ids generated, federated posts must come
+    /// from the instance they claim to be from
+    async fn insert_post(
+        &self,
+        mut post: StoredPost,
+        author: &StoredUser,
+        origin: &EntityOrigin<'_>,
+    ) -> Result<StoredPost, DbErr> {
+        match origin {
+            EntityOrigin::Local(domain) => {
+                if !author.is_local() {
+                    return Err(DbErr::InvalidType);
+                }
+                post.versia_id = new_uid();
+                post.id = versia_post_link(domain, &author.username, &post.versia_id).to_string();
+                post.domain = domain.to_string();
+            }
+            EntityOrigin::Federated(domain) => {
+                if post.domain.ne(domain) || author.domain.ne(domain) {
+                    return Err(DbErr::InvalidType);
+                }
+            }
+        }
+        self.write().insert_post(post.clone())?;
+        Ok(post)
+    }
+
+    /// page is one based
+    fn get_user_posts(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin<'_>,
+        page_size: u64,
+        page: u64,
+    ) -> Option<(Vec<StoredPost>, StoredUser)> {
+        let user = self.get_origin_user(uname, origin)?;
+        let skip = (page.saturating_sub(1) * page_size) as usize;
+        let posts = self
+            .read()
+            .get_public_posts(&user.uid)
+            .into_iter()
+            .skip(skip)
+            .take(page_size as usize)
+            .cloned()
+            .collect();
+        Some((posts, user))
+    }
+}
+
+#[async_trait]
+impl Conn for InMemoryConn {
+    async fn backfill_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor> {
+        if let Some(actor) = self.get_actor(username, origin).await {
+            return Some(actor);
+        }
+        let EntityOrigin::Federated(domain) = origin else {
+            return None;
+        };
+        let protocol = self.get_protocol(domain).await;
+        let instance_actor = self.get_instance_actor().await.ok()?;
+        let user = fetch_federated_handle(
+            username,
+            domain,
+            protocol,
+            &instance_actor,
+            &self.instance_domain,
+        )
+        .await
+        .ok()?;
+        self.insert_federated_user(user).ok()?.to_actor()
+    }
+    async fn get_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor> {
+        self.get_origin_user(username, origin)?.to_actor()
+    }
+    async fn get_user(&self, uid: &str) -> Option<StoredUser> {
+        self.read().users.get(uid).cloned()
+    }
+    async fn get_local_user(&self, uid: &str) -> Option<StoredUser> {
+        self.read().users.get(uid).filter(|x| x.is_local()).cloned()
+    }
+    async fn get_local_user_by_username(&self, username: &str) -> Option<StoredUser> {
+        self.read()
+            .get_user_by_username(username, &self.instance_domain)
+            .filter(|x| x.is_local() && !x.deleted)
+            .cloned()
+    }
+    async fn resolve_user(&self, link: &Url) -> Result<StoredUser, DbErr> {
+        if let Some(username) = local_username(&self.instance_domain, link) {
+            return self
+                .read()
+                .get_user_by_username(&username, &self.instance_domain)
+                .filter(|x| !x.deleted)
+                .cloned()
+                .ok_or(DbErr::NotFound);
+        }
+        if let Some(user) = self.read().get_user_by_link(link.as_str()) {
+            return Ok(user.clone());
+        }
+        let Some(domain) = link.domain() else {
+            return Err(DbErr::NotFound);
+        };
+        let protocol = self.get_protocol(domain).await;
+        let instance_actor = self.get_instance_actor().await?;
+        let user =
+            fetch_federated_user(link, protocol, &instance_actor, &self.instance_domain).await?;
+        self.insert_federated_user(user)
+    }
+    async fn update_federated_user(&self, user: &StoredUser) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let stored = store
+            .users
+            .values_mut()
+            .find(|x| x.resource_link.eq(&user.resource_link) && !x.is_local())
+            .ok_or(DbErr::NotFound)?;
+        stored.update_profile(user);
+        Ok(())
+    }
+    async fn get_user_posts_ap(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin,
+        page_size: u64,
+        ofset: u64,
+    ) -> Option<Vec<ApPostable>> {
+        let (posts, user) = self.get_user_posts(uname, origin, page_size, ofset)?;
+        Some(posts.iter().filter_map(|x| x.to_ap(&user)).collect())
+    }
+    async fn get_ap_post(&self, post_id: &str, origin: &EntityOrigin) -> Option<ApPostable> {
+        let (post, author) = self.get_origin_post(post_id, origin)?;
+        post.to_ap(&author)
+    }
+    async fn create_ap_post(
+        &self,
+        post: ApPostable,
+        origin: &EntityOrigin,
+    ) -> Result<String, DbErr> {
+        let author = self.resolve_user(post.actor()).await?;
+        let stored = StoredPost::from_ap(&post, &author).ok_or(DbErr::InvalidType)?;
+        let stored = self.insert_post(stored, &author, origin).await?;
+        Ok(stored.versia_id)
+    }
+    async fn create_user(&self, domain: &str, content: &NewLocal) -> Result<String, DbErr> {
+        let user = StoredUser::new_local(domain, content);
+        let uid = user.uid.clone();
+        self.write().insert_user(user)?;
+        Ok(uid)
+    }
+    async fn get_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => self.read().get_user_by_link(link.as_str())?.public_key(),
+            Signer::Instance(_) => None,
+        }
+    }
+    async fn get_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => match self.resolve_user(link).await {
+                Ok(user) => user.verifying_keys(),
+                Err(_) => Vec::new(),
+            },
+            // our own previous key stays good for the grace period
+            Signer::Instance(domain) if domain.eq(&self.instance_domain) => self
+                .get_instance_actor()
+                .await
+                .map(|x| x.versia_verifying_keys())
+                .unwrap_or_default(),
+            Signer::Instance(domain) => {
+                let metadata = self.get_versia_instance_metadata(domain).await;
+                metadata
+                    .and_then(|x| versia_key_to_openssl(&x.public_key))
+                    .into_iter()
+                    .collect()
+            }
+        }
+    }
+    async fn refetch_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => {
+                match refresh_federated_user(self, link, &self.instance_domain).await {
+                    Ok(user) => user.verifying_keys(),
+                    Err(_) => Vec::new(),
+                }
+            }
+            // instance metadata isn't stored so it is always fresh
+            Signer::Instance(_) => self.get_public_keys(signed_by).await,
+        }
+    }
+    async fn get_user_post_count(&self, uname: &str, origin: &EntityOrigin) -> Option<u64> {
+        let user = self.get_origin_user(uname, origin)?;
+        Some(self.read().get_public_posts(&user.uid).len() as u64)
+    }
+    async fn get_user_posts_versia(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin,
+        page_size: u64,
+        ofset: u64,
+    ) -> Option<Vec<VersiaPostable>> {
+        let (posts, user) = self.get_user_posts(uname, origin, page_size, ofset)?;
+        Some(posts.iter().filter_map(|x| x.to_versia(&user)).collect())
+    }
+    async fn get_versia_user(&self, uname: &str, origin: &EntityOrigin) -> Option<User> {
+        self.get_origin_user(uname, origin)?.to_versia()
+    }
+    async fn create_local_post(&self, post: &StoredPost) -> Result<(), DbErr> {
+        let author = self
+            .get_local_user(&post.actor)
+            .await
+            .ok_or(DbErr::NotFound)?;
+        if author.deleted || post.domain.ne(&author.domain) {
+            return Err(DbErr::InvalidType);
+        }
+        self.write().insert_post(post.clone())?;
+        Ok(())
+    }
+    async fn get_post(&self, link: &Url) -> Option<StoredPost> {
+        let store = self.read();
+        match local_post_id(&self.instance_domain, link) {
+            Some(pid) => store.get_local_post(&pid, &self.instance_domain),
+            None => store.posts.get(link.as_str()),
+        }
+        .cloned()
+    }
+    async fn edit_post(&self, post: &StoredPost) -> Result<(), DbErr> {
+        self.write().edit_post(post)
+    }
+    async fn get_post_revisions(&self, post: &str) -> Result<Vec<PostRevision>, DbErr> {
+        Ok(self
+            .read()
+            .post_revisions
+            .get(post)
+            .cloned()
+            .unwrap_or_default())
+    }
+    async fn get_ended_polls(&self, now: i64) -> Result<Vec<StoredPost>, DbErr> {
+        let store = self.read();
+        Ok(store
+            .posts
+            .values()
+            .filter(|x| x.closed.is_some_and(|closed| closed <= now))
+            .filter(|x| !store.ended_polls.contains(&x.id))
+            .cloned()
+            .collect())
+    }
+    async fn mark_poll_ended(&self, id: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.posts.contains_key(id) {
+            return Err(DbErr::NotFound);
+        }
+        store.ended_polls.insert(id.to_string());
+        Ok(())
+    }
+    async fn delete_user(&self, uid: &Url, origin: &EntityOrigin) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let user = match origin {
+            EntityOrigin::Local(domain) => {
+                let username = local_username(domain, uid).ok_or(DbErr::NotFound)?;
+                store.get_user_by_username(&username, domain)
+            }
+            EntityOrigin::Federated(domain) => store
+                .get_user_by_link(uid.as_str())
+                .filter(|x| x.domain.eq(domain)),
+        };
+        let uid = user.ok_or(DbErr::NotFound)?.uid.clone();
+        store.delete_user(&uid)
+    }
+    async fn tombstone_user(&self, uid: &str) -> Result<(), DbErr> {
+        self.write().tombstone_user(uid)
+    }
+    async fn rotate_user_key(
+        &self,
+        uid: &str,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let user = store
+            .users
+            .get_mut(uid)
+            .filter(|x| x.is_local() && !x.deleted)
+            .ok_or(DbErr::NotFound)?;
+        user.private_key_pem = Some(private_key_pem.to_string());
+        user.rotate_public_key(public_key_pem.to_string());
+        Ok(())
+    }
+    async fn get_versia_post(&self, pid: &str, origin: &EntityOrigin) -> Option<VersiaPostable> {
+        let (post, author) = self.get_origin_post(pid, origin)?;
+        post.to_versia(&author)
+    }
+    async fn create_versia_post(
+        &self,
+        post: VersiaPostable,
+        origin: &EntityOrigin,
+    ) -> Result<VersiaPostable, DbErr> {
+        let author = self.resolve_user(post.get_author()).await?;
+        let stored = StoredPost::from_versia(&post, &author).ok_or(DbErr::InvalidType)?;
+        let stored = self.insert_post(stored, &author, origin).await?;
+        stored.to_versia(&author).ok_or(DbErr::InvalidType)
+    }
+    async fn delete_post(&self, post_id: &str, origin: &EntityOrigin) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let id = match origin {
+            EntityOrigin::Local(domain) => store.get_local_post(post_id, domain),
+            EntityOrigin::Federated(domain) => {
+                store.posts.get(post_id).filter(|x| x.domain.eq(domain))
+            }
+        }
+        .ok_or(DbErr::NotFound)?
+        .id
+        .clone();
+        store.remove_post(&id);
+        Ok(())
+    }
+
+    //-------------------follows------------------------------
+    async fn create_follow(
+        &self,
+        follower: &str,
+        target: &str,
+        pending: bool,
+        activity_id: Option<&str>,
+    ) -> Result<(), DbErr> {
+        self.write()
+            .insert_follow(follower, target, pending, activity_id)
+    }
+    async fn get_followers(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+        let store = self.read();
+        Ok(store
+            .follows
+            .iter()
+            .filter(|(key, follow)| key.1.eq(uid) && !follow.pending)
+            .filter_map(|(key, _)| store.users.get(&key.0).cloned())
+            .collect())
+    }
+    async fn get_following(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+        let store = self.read();
+        Ok(store
+            .follows
+            .iter()
+            .filter(|(key, follow)| key.0.eq(uid) && !follow.pending)
+            .filter_map(|(key, _)| store.users.get(&key.1).cloned())
+            .collect())
+    }
+    async fn is_following(&self, follower: &str, target: &str) -> Result<bool, DbErr> {
+        let key = (follower.to_string(), target.to_string());
+        Ok(self.read().follows.get(&key).is_some_and(|x| !x.pending))
+    }
+    async fn accept_follow(&self, follower: &str, target: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let key = (follower.to_string(), target.to_string());
+        let follow = store.follows.get_mut(&key).ok_or(DbErr::NotFound)?;
+        follow.pending = false;
+        Ok(())
+    }
+    async fn remove_follow(&self, follower: &str, target: &str) -> Result<(), DbErr> {
+        let key = (follower.to_string(), target.to_string());
+        match self.write().follows.remove(&key) {
+            Some(_) => Ok(()),
+            None => Err(DbErr::NotFound),
+        }
+    }
+    async fn remove_follow_activity(&self, follower: &str, activity_id: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let key = store
+            .follows
+            .iter()
+            .find(|(key, follow)| {
+                key.0.eq(follower) && follow.activity_id.as_deref() == Some(activity_id)
+            })
+            .map(|(key, _)| key.clone())
+            .ok_or(DbErr::NotFound)?;
+        store.follows.remove(&key);
+        Ok(())
+    }
+
+    //-------------------likes------------------------------
+    async fn create_like(&self, actor: &str, post: &str, url: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.users.contains_key(actor) || !store.posts.contains_key(post) {
+            return Err(DbErr::NotFound);
+        }
+        let duplicate = store.likes.contains_key(url)
+            || store
+                .likes
+                .values()
+                .any(|x| x.actor.eq(actor) && x.post.eq(post));
+        if duplicate {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        store.likes.insert(
+            url.to_string(),
+            StoredLike {
+                actor: actor.to_string(),
+                post: post.to_string(),
+            },
+        );
+        Ok(())
+    }
+    async fn remove_like(&self, actor: &str, url: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        match store.likes.get(url) {
+            Some(like) if like.actor.eq(actor) => {
+                store.likes.remove(url);
+                Ok(())
+            }
+            _ => Err(DbErr::NotFound),
+        }
+    }
+
+    //-------------------polls------------------------------
+    async fn create_vote(&self, vote: &PollVote) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.users.contains_key(&vote.voter) || !store.posts.contains_key(&vote.poll) {
+            return Err(DbErr::NotFound);
+        }
+        let votes = store.votes.entry(vote.poll.clone()).or_default();
+        if votes
+            .iter()
+            .any(|x| x.voter.eq(&vote.voter) && x.choice == vote.choice)
+        {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        votes.push(vote.clone());
+        Ok(())
+    }
+    async fn get_votes(&self, poll: &str) -> Result<Vec<PollVote>, DbErr> {
+        Ok(self.read().votes.get(poll).cloned().unwrap_or_default())
+    }
+
+    //-------------------delivery queue------------------------------
+    async fn queue_delivery(&self, delivery: &Delivery) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if store.deliveries.contains_key(&delivery.id) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        store
+            .deliveries
+            .insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+    async fn get_due_deliveries(&self, now: i64, limit: u64) -> Result<Vec<Delivery>, DbErr> {
+        let mut due: Vec<Delivery> = self
+            .read()
+            .deliveries
+            .values()
+            .filter(|x| !x.dead && x.next_attempt <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|x| x.next_attempt);
+        due.truncate(limit as usize);
+        Ok(due)
+    }
+    async fn update_delivery(&self, delivery: &Delivery) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let existing = store
+            .deliveries
+            .get_mut(&delivery.id)
+            .ok_or(DbErr::NotFound)?;
+        existing.attempts = delivery.attempts;
+        existing.next_attempt = delivery.next_attempt;
+        existing.last_error.clone_from(&delivery.last_error);
+        existing.dead = delivery.dead;
+        Ok(())
+    }
+    async fn remove_delivery(&self, id: &str) -> Result<(), DbErr> {
+        self.write().deliveries.remove(id);
+        Ok(())
+    }
+    async fn mark_inbox_dead(&self, inbox: &str) -> Result<(), DbErr> {
+        self.write()
+            .dead_inboxes
+            .entry(inbox.to_string())
+            .or_insert_with(now_millis);
+        Ok(())
+    }
+    async fn is_inbox_dead(&self, inbox: &str) -> Result<bool, DbErr> {
+        Ok(self.read().dead_inboxes.contains_key(inbox))
+    }
+
+    //-------------------oauth------------------------------
+    async fn create_oauth_app(&self, app: &OAuthApp) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if store.oauth_apps.contains_key(&app.client_id) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        store.oauth_apps.insert(app.client_id.clone(), app.clone());
+        Ok(())
+    }
+    async fn get_oauth_app(&self, client_id: &str) -> Option<OAuthApp> {
+        self.read().oauth_apps.get(client_id).cloned()
+    }
+    async fn create_oauth_code(&self, code: &OAuthCode) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if store.oauth_codes.contains_key(&code.code) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        if !store.oauth_apps.contains_key(&code.client_id) || !store.users.contains_key(&code.uid) {
+            return Err(DbErr::NotFound);
+        }
+        store.oauth_codes.insert(code.code.clone(), code.clone());
+        Ok(())
+    }
+    async fn take_oauth_code(&self, code: &str) -> Option<OAuthCode> {
+        self.write().oauth_codes.remove(code)
+    }
+    async fn create_oauth_token(&self, token: &OAuthToken) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if store.oauth_tokens.contains_key(&token.token_hash) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        let user_exists = token
+            .uid
+            .as_ref()
+            .is_none_or(|x| store.users.contains_key(x));
+        if !store.oauth_apps.contains_key(&token.client_id) || !user_exists {
+            return Err(DbErr::NotFound);
+        }
+        store
+            .oauth_tokens
+            .insert(token.token_hash.clone(), token.clone());
+        Ok(())
+    }
+    async fn get_oauth_token(&self, token_hash: &str) -> Option<OAuthToken> {
+        self.read().oauth_tokens.get(token_hash).cloned()
+    }
+    async fn revoke_oauth_token(&self, token_hash: &str) -> Result<(), DbErr> {
+        self.write()
+            .oauth_tokens
+            .remove(token_hash)
+            .map(|_| ())
+            .ok_or(DbErr::NotFound)
+    }
+
+    //-------------------registration------------------------------
+    async fn get_registration_mode(&self) -> Result<Option<RegistrationMode>, DbErr> {
+        Ok(self.read().registration_mode)
+    }
+    async fn set_registration_mode(&self, mode: RegistrationMode) -> Result<(), DbErr> {
+        self.write().registration_mode = Some(mode);
+        Ok(())
+    }
+    async fn demote_unvetted_users(&self) -> Result<u64, DbErr> {
+        let mut demoted = 0;
+        for user in self.write().users.values_mut() {
+            let unvetted = user.is_local() && !user.deleted && !user.vetted;
+            if unvetted && user.permission_level == Some(PermissionLevel::TrustedUser) {
+                user.permission_level = Some(PermissionLevel::UntrustedUser);
+                user.approved = false;
+                demoted += 1;
+            }
+        }
+        Ok(demoted)
+    }
+    async fn promote_unvetted_users(&self) -> Result<u64, DbErr> {
+        let mut store = self.write();
+        let mut promoted = Vec::new();
+        for user in store.users.values_mut() {
+            let waiting = user.is_local() && !user.deleted && !user.vetted && !user.approved;
+            if waiting && user.permission_level == Some(PermissionLevel::UntrustedUser) {
+                user.permission_level = Some(PermissionLevel::TrustedUser);
+                user.approved = true;
+                promoted.push(user.uid.clone());
+            }
+        }
+        for uid in &promoted {
+            store.applications.remove(uid);
+        }
+        Ok(promoted.len() as u64)
+    }
+    async fn approve_user(&self, uid: &str) -> Result<(), DbErr> {
+        let mut store = self.write();
+        let user = store
+            .users
+            .get_mut(uid)
+            .filter(|x| x.is_local() && !x.deleted)
+            .ok_or(DbErr::NotFound)?;
+        user.approved = true;
+        user.vetted = true;
+        let keeps_level = user
+            .permission_level
+            .is_some_and(|x| x.is_at_least(PermissionLevel::TrustedUser));
+        if !keeps_level {
+            user.permission_level = Some(PermissionLevel::TrustedUser);
+        }
+        store.applications.remove(uid);
+        Ok(())
+    }
+    async fn create_invite(&self, invite: &Invite) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if store.invites.contains_key(&invite.code) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        store.invites.insert(invite.code.clone(), invite.clone());
+        Ok(())
+    }
+    async fn use_invite(&self, code: &str) -> Result<(), DbErr> {
+        let now = now_millis();
+        let mut store = self.write();
+        let invite = store
+            .invites
+            .get_mut(code)
+            .filter(|x| x.max_uses.is_none_or(|max| x.uses < max))
+            .filter(|x| x.expires.is_none_or(|expires| expires > now))
+            .ok_or(DbErr::NotFound)?;
+        invite.uses += 1;
+        Ok(())
+    }
+    async fn create_application(&self, application: &Application) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.users.contains_key(&application.uid) {
+            return Err(DbErr::NotFound);
+        }
+        store
+            .applications
+            .insert(application.uid.clone(), application.clone());
+        Ok(())
+    }
+    async fn get_application(&self, uid: &str) -> Option<Application> {
+        self.read().applications.get(uid).cloned()
+    }
+    async fn get_applications(&self) -> Result<Vec<Application>, DbErr> {
+        let mut applications: Vec<Application> =
+            self.read().applications.values().cloned().collect();
+        applications.sort_by_key(|x| x.created_at);
+        Ok(applications)
+    }
+
+    //-------------------notifications------------------------------
+    async fn create_notification(&self, notification: &Notification) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.users.contains_key(&notification.recipient) {
+            return Err(DbErr::NotFound);
+        }
+        if store.notifications.contains_key(&notification.id) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        store
+            .notifications
+            .insert(notification.id.clone(), notification.clone());
+        Ok(())
+    }
+    async fn get_notifications(
+        &self,
+        uid: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, DbErr> {
+        let store = self.read();
+        let mut notifications: Vec<&Notification> = store
+            .notifications
+            .values()
+            .filter(|x| x.recipient.eq(uid) && query.matches(x))
+            .collect();
+        notifications.sort_by(|a, b| b.id.cmp(&a.id));
+
+        let limit = query.limit.min(MAX_NOTIFICATION_LIMIT) as usize;
+        let page = match query.adjacent && query.after.is_some() {
+            true => {
+                let skip = notifications.len().saturating_sub(limit);
+                notifications.into_iter().skip(skip).cloned().collect()
+            }
+            false => notifications.into_iter().take(limit).cloned().collect(),
+        };
+        Ok(page)
+    }
+    async fn get_notification_marker(&self, uid: &str) -> Option<NotificationMarker> {
+        self.read().notification_markers.get(uid).cloned()
+    }
+    async fn set_notification_marker(&self, marker: &NotificationMarker) -> Result<(), DbErr> {
+        let mut store = self.write();
+        if !store.users.contains_key(&marker.uid) {
+            return Err(DbErr::NotFound);
+        }
+        store
+            .notification_markers
+            .insert(marker.uid.clone(), marker.clone());
+        Ok(())
+    }
+
+    //-------------------timelines------------------------------
+    async fn get_timeline(
+        &self,
+        timeline: &Timeline<'_>,
+        query: &TimelineQuery,
+    ) -> Result<Vec<(StoredPost, StoredUser)>, DbErr> {
+        let store = self.read();
+        let show_local_only = matches!(timeline, Timeline::Home(_)) || query.viewer.is_some();
+        let visible = |post: &StoredPost| match timeline {
+            Timeline::Home(uid) => {
+                let key = (uid.to_string(), post.actor.clone());
+                post.actor.eq(uid) || store.follows.get(&key).is_some_and(|x| !x.pending)
+            }
+            Timeline::Local => !post.followers_only && post.domain.eq(&self.instance_domain),
+            Timeline::Federated => !post.followers_only,
+        };
+        let mut posts: Vec<&StoredPost> = store
+            .posts
+            .values()
+            .filter(|x| (show_local_only || !x.local_only) && query.in_range(x) && visible(x))
+            .collect();
+        posts.sort_by(|a, b| (b.published, &b.id).cmp(&(a.published, &a.id)));
+
+        let limit = query.limit.min(MAX_TIMELINE_LIMIT) as usize;
+        let page: Vec<&StoredPost> = match query.adjacent && query.after.is_some() {
+            true => {
+                let skip = posts.len().saturating_sub(limit);
+                posts.into_iter().skip(skip).collect()
+            }
+            false => posts.into_iter().take(limit).collect(),
+        };
+        Ok(page
+            .into_iter()
+            .filter_map(|post| {
+                let author = store.users.get(&post.actor)?;
+                Some((post.clone(), author.clone()))
+            })
+            .collect())
+    }
+
+    async fn init(&self) -> Result<(), String> {
+        self.write().ensure_instance(&self.instance_domain);
+        Ok(())
+    }
+
+    //-------------------instance actor------------------------------
+    async fn get_instance_actor(&self) -> Result<InstanceActor, DbErr> {
+        let mut store = self.write();
+        if let Some(x) = &store.instance_actor {
+            return Ok(x.clone());
+        }
+        let actor = InstanceActor::generate();
+        store.instance_actor = Some(actor.clone());
+        Ok(actor)
+    }
+    async fn set_instance_actor(&self, actor: &InstanceActor) -> Result<(), DbErr> {
+        self.write().instance_actor = Some(actor.clone());
+        Ok(())
+    }
+
+    async fn get_versia_instance_metadata(
+        &self,
+        instance_domain: &str,
+    ) -> Option<InstanceMetadata> {
+        if instance_domain.eq(&self.instance_domain) {
+            let instance_actor = self.get_instance_actor().await.ok()?;
+            return Some(instance_actor.to_versia_metadata(instance_domain));
+        }
+        let metadata = fetch_instance_metadata(instance_domain).await.ok()?;
+        self.write()
+            .instances
+            .insert(instance_domain.to_string(), Some(Protocol::Versia));
+        Some(metadata)
+    }
+
+    async fn get_protocol(&self, instance: &str) -> Protocol {
+        if let Some(Some(protocol)) = self.read().instances.get(instance) {
+            return *protocol;
+        }
+        let protocol = detect_protocol(instance).await;
+        self.write()
+            .instances
+            .insert(instance.to_string(), Some(protocol));
+        protocol
+    }
+    async fn get_instances(&self, protocol: Protocol) -> Result<Vec<String>, DbErr> {
+        Ok(self
+            .read()
+            .instances
+            .iter()
+            .filter(|(domain, x)| domain.ne(&&self.instance_domain) && x.eq(&&Some(protocol)))
+            .map(|(domain, _)| domain.clone())
+            .collect())
+    }
+    async fn get_shared_inboxes(&self) -> Result<Vec<String>, DbErr> {
+        let inboxes: BTreeSet<String> = self
+            .read()
+            .users
+            .values()
+            .filter(|x| !x.is_local())
+            .filter_map(|x| x.shared_inbox.clone())
+            .collect();
+        Ok(inboxes.into_iter().collect())
+    }
+
+    async fn get_signature_scheme(&self, instance: &str) -> Option<SignatureScheme> {
+        self.read().signature_schemes.get(instance).copied()
+    }
+
+    async fn set_signature_scheme(
+        &self,
+        instance: &str,
+        scheme: SignatureScheme,
+    ) -> Result<(), DbErr> {
+        self.write()
+            .signature_schemes
+            .insert(instance.to_string(), scheme);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::utility::{
+            compose::{compose, Draft},
+            delete_account::delete_local_account,
+            follows::{
+                handle_follow, handle_follow_response, handle_versia_follow,
+                handle_versia_follow_response,
+            },
+            notify_followers::notify_followers,
+            post_types::PostSupertype,
+            streaming::{StreamBus, STREAM_BUFFER},
+            undo::handle_undo,
+        },
+        protocols::types::{
+            activitystream_objects::{
+                follow_and_response::{Follow, FollowResponse, ResponseType},
+                link::{LinkSimpleOrExpanded, RangeLinkItem},
+                undo::{Undo, UndoType, UndoneActivity, UndoneType},
+            },
+            versia_types::entities::{
+                change_follow::{ChangeFollowType, ChangeFollowing},
+                follow_response::{FollowResponse as VersiaFollowResponse, FollowResponseType},
+                notes::Category,
+            },
+        },
+    };
+
+    use super::{super::store::StoredLike, *};
+
+    /// a federated activitypub user, inserted directly so that no fetch is needed
+    fn remote_user(conn: &InMemoryConn, username: &str, shared_inbox: Option<&str>) -> String {
+        let local = StoredUser::new_local(
+            "remote.com",
+            &NewLocal::new(username.to_string(), "filler".to_string(), None, None),
+        );
+        let user = StoredUser {
+            inbox: format!("https://remote.com/users/{username}/inbox"),
+            shared_inbox: shared_inbox.map(|x| x.to_string()),
+            private_key_pem: None,
+            ..local
+        };
+        let uid = user.uid.clone();
+        let mut store = conn.write();
+        store
+            .instances
+            .insert("remote.com".to_string(), Some(Protocol::ActivityPub));
+        store.insert_user(user).unwrap();
+        uid
+    }
+
+    #[actix_web::test]
+    async fn test_notify_followers() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let author = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("author".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let local_follower = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let shared = Some("https://remote.com/inbox");
+        let followers = [
+            remote_user(&conn, "one", shared),
+            remote_user(&conn, "two", shared),
+            remote_user(&conn, "three", None),
+            local_follower,
+        ];
+        for follower in &followers {
+            conn.create_follow(follower, &author, false, None)
+                .await
+                .unwrap();
+        }
+        // pending follows don't get posts
+        let pending = remote_user(&conn, "pending", None);
+        conn.create_follow(&pending, &author, true, None)
+            .await
+            .unwrap();
+
+        let author = conn.get_local_user(&author).await.unwrap();
+        let post = StoredPost {
+            id: String::new(),
+            versia_id: String::new(),
+            domain: "example.com".to_string(),
+            surtype: PostSupertype::Object,
+            subtype: r#""Note""#.to_string(),
+            category: Category::Microblog,
+            likes: 0,
+            boosts: 0,
+            local_only: false,
+            followers_only: false,
+            published: 0,
+            is_reply: false,
+            in_reply_to: None,
+            content: Some("<p>hello</p>".to_string()),
+            subject: None,
+            is_sensitive: false,
+            shared: None,
+            multi_select: None,
+            options: None,
+            closed: None,
+            local_only_voting: None,
+            edited_at: None,
+            actor: author.uid.clone(),
+        };
+        let post = conn
+            .insert_post(post, &author, &EntityOrigin::Local("example.com"))
+            .await
+            .unwrap();
+        // storing a post has no federation side effects
+        if !conn
+            .get_due_deliveries(i64::MAX, 10)
+            .await
+            .unwrap()
+            .is_empty()
+        {
+            return Err("inserting a post queued deliveries".to_string());
+        }
+        notify_followers(&conn, &post, &author).await.unwrap();
+
+        let mut inboxes: Vec<String> = conn
+            .get_due_deliveries(i64::MAX, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| x.inbox)
+            .collect();
+        inboxes.sort();
+        assert_eq!(
+            inboxes,
+            vec![
+                "https://remote.com/inbox".to_string(),
+                "https://remote.com/users/three/inbox".to_string(),
+            ]
+        );
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_compose_fan_out() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let author = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("composer".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let follower = remote_user(&conn, "reader", None);
+        conn.create_follow(&follower, &author, false, None)
+            .await
+            .unwrap();
+
+        let author = conn.get_local_user(&author).await.unwrap();
+        let draft = Draft {
+            content: Some("<p>hello</p>".to_string()),
+            ..Default::default()
+        };
+        compose(&conn, &StreamBus::new(STREAM_BUFFER), &author, draft)
+            .await
+            .unwrap();
+
+        let deliveries = conn.get_due_deliveries(i64::MAX, 10).await.unwrap();
+        let [delivery] = deliveries.as_slice() else {
+            return Err(format!("expected one delivery, got {}", deliveries.len()));
+        };
+        if delivery.inbox.ne("https://remote.com/users/reader/inbox") {
+            return Err(format!("delivered to {}", delivery.inbox));
+        }
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_ap_follows() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let local = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let manual = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("manual".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        conn.write()
+            .users
+            .get_mut(&manual)
+            .unwrap()
+            .manual_followers = true;
+        let remote = remote_user(&conn, "remote", None);
+
+        let local = conn.get_local_user(&local).await.unwrap();
+        let manual = conn.get_local_user(&manual).await.unwrap();
+        let remote = conn.read().users.get(&remote).unwrap().clone();
+        let remote_id = remote.ap_id().unwrap();
+
+        // automatic approval gets an accept back
+        let follow = Follow::new(remote_id.clone(), local.ap_id().unwrap());
+        handle_follow(&conn, &StreamBus::new(STREAM_BUFFER), follow, "example.com")
+            .await
+            .unwrap();
+        let followers = conn.get_followers(&local.uid).await.unwrap();
+        assert_eq!(followers.len(), 1);
+        let deliveries = conn.get_due_deliveries(i64::MAX, 10).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].inbox, remote.inbox);
+        assert_eq!(deliveries[0].signer, local.uid);
+        let accept: FollowResponse = serde_json::from_str(&deliveries[0].body).unwrap();
+        assert!(matches!(accept.type_field, ResponseType::Accept));
+
+        // manual approval stays pending without a response
+        let follow = Follow::new(remote_id.clone(), manual.ap_id().unwrap());
+        handle_follow(&conn, &StreamBus::new(STREAM_BUFFER), follow, "example.com")
+            .await
+            .unwrap();
+        assert!(conn.get_followers(&manual.uid).await.unwrap().is_empty());
+        assert_eq!(
+            conn.get_due_deliveries(i64::MAX, 10).await.unwrap().len(),
+            1
+        );
+
+        // responses to our own follows, linking the follow by id
+        conn.create_follow(&local.uid, &remote.uid, true, None)
+            .await
+            .unwrap();
+        let follow = Follow::new(local.ap_id().unwrap(), remote_id.clone());
+        let response = |type_field| FollowResponse {
+            type_field,
+            id: Url::parse("https://remote.com/accepts/1").unwrap(),
+            actor: remote_id.clone(),
+            object: RangeLinkItem::Link(LinkSimpleOrExpanded::Simple(follow.id.clone())),
+        };
+        handle_follow_response(&conn, response(ResponseType::Accept), "example.com")
+            .await
+            .unwrap();
+        assert_eq!(conn.get_followers(&remote.uid).await.unwrap().len(), 1);
+        handle_follow_response(&conn, response(ResponseType::Reject), "example.com")
+            .await
+            .unwrap();
+        assert!(conn.get_followers(&remote.uid).await.unwrap().is_empty());
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_versia_follows() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let local = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let local = conn.get_local_user(&local).await.unwrap();
+        let remote = remote_user(&conn, "remote", None);
+        let remote = conn.read().users.get(&remote).unwrap().clone();
+        let remote_uri = remote.versia_uri().unwrap();
+
+        let change = |type_field| ChangeFollowing {
+            type_field,
+            id: new_uid(),
+            author: remote_uri.clone(),
+            created_at: 0,
+            followee: local.versia_uri().unwrap(),
+        };
+        handle_versia_follow(
+            &conn,
+            &StreamBus::new(STREAM_BUFFER),
+            change(ChangeFollowType::Follow),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        assert_eq!(conn.get_followers(&local.uid).await.unwrap().len(), 1);
+        let deliveries = conn.get_due_deliveries(i64::MAX, 10).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert!(matches!(deliveries[0].protocol, Protocol::Versia));
+        let accept: VersiaFollowResponse = serde_json::from_str(&deliveries[0].body).unwrap();
+        assert_eq!(accept.type_field, FollowResponseType::FollowAccept);
+        assert_eq!(accept.follower, remote_uri);
+
+        handle_versia_follow(
+            &conn,
+            &StreamBus::new(STREAM_BUFFER),
+            change(ChangeFollowType::Unfollow),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        assert!(conn.get_followers(&local.uid).await.unwrap().is_empty());
+
+        // responses to our own follows
+        conn.create_follow(&local.uid, &remote.uid, true, None)
+            .await
+            .unwrap();
+        let response = |type_field| VersiaFollowResponse {
+            type_field,
+            id: new_uid(),
+            author: remote_uri.clone(),
+            created_at: 0,
+            follower: local.versia_uri().unwrap(),
+        };
+        handle_versia_follow_response(
+            &conn,
+            response(FollowResponseType::FollowAccept),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        assert_eq!(conn.get_followers(&remote.uid).await.unwrap().len(), 1);
+        handle_versia_follow_response(
+            &conn,
+            response(FollowResponseType::FollowReject),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        assert!(conn.get_followers(&remote.uid).await.unwrap().is_empty());
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_ap_undo() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let local = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let local = conn.get_local_user(&local).await.unwrap();
+        let remote = remote_user(&conn, "remote", None);
+        let remote = conn.read().users.get(&remote).unwrap().clone();
+        let remote_id = remote.ap_id().unwrap();
+
+        let undo = |object| Undo {
+            type_field: UndoType::Undo,
+            id: Url::parse("https://remote.com/undos/1").unwrap(),
+            actor: remote_id.clone(),
+            object,
+        };
+        let link = |x: &Url| RangeLinkItem::Link(LinkSimpleOrExpanded::Simple(x.clone()));
+
+        // an embedded follow
+        let follow = Follow::new(remote_id.clone(), local.ap_id().unwrap());
+        handle_follow(
+            &conn,
+            &StreamBus::new(STREAM_BUFFER),
+            follow.clone(),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        let undone = UndoneActivity {
+            type_field: UndoneType::Follow,
+            id: follow.id,
+            actor: follow.actor,
+            object: follow.object,
+        };
+        handle_undo(&conn, undo(RangeLinkItem::Item(undone)), "example.com")
+            .await
+            .unwrap();
+        assert!(conn.get_followers(&local.uid).await.unwrap().is_empty());
+
+        // a follow only linked by its id
+        let follow = Follow::new(remote_id.clone(), local.ap_id().unwrap());
+        handle_follow(
+            &conn,
+            &StreamBus::new(STREAM_BUFFER),
+            follow.clone(),
+            "example.com",
+        )
+        .await
+        .unwrap();
+        handle_undo(&conn, undo(link(&follow.id)), "example.com")
+            .await
+            .unwrap();
+        assert!(conn.get_followers(&local.uid).await.unwrap().is_empty());
+
+        // a linked like
+        let like = Url::parse("https://remote.com/likes/1").unwrap();
+        conn.write().likes.insert(
+            like.to_string(),
+            StoredLike {
+                actor: remote.uid.clone(),
+                post: "https://example.com/filler".to_string(),
+            },
+        );
+        handle_undo(&conn, undo(link(&like)), "example.com")
+            .await
+            .unwrap();
+        assert!(conn.read().likes.is_empty());
+
+        // nothing left to undo
+        assert!(handle_undo(&conn, undo(link(&like)), "example.com")
+            .await
+            .is_err());
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_profile_update() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let local = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let local = conn.get_local_user(&local).await.unwrap();
+        let remote = remote_user(&conn, "remote", None);
+        let remote = conn.read().users.get(&remote).unwrap().clone();
+
+        // the update arrives as a fresh copy with a rotated key
+        let rotated = StoredUser::new_local(
+            "remote.com",
+            &NewLocal::new("remote".to_string(), "filler".to_string(), None, None),
+        );
+        let update = StoredUser {
+            uid: new_uid(),
+            display_name: Some("renamed".to_string()),
+            avatar: Some("https://remote.com/avatar.png".to_string()),
+            public_key_pem: rotated.public_key_pem.clone(),
+            private_key_pem: None,
+            ..remote.clone()
+        };
+        conn.update_federated_user(&update).await.unwrap();
+
+        let updated = conn.resolve_user(&remote.ap_id().unwrap()).await.unwrap();
+        assert_eq!(updated.uid, remote.uid);
+        assert_eq!(updated.display_name.as_deref(), Some("renamed"));
+        assert_eq!(updated.public_key_pem, rotated.public_key_pem);
+        let actor = updated.to_actor().unwrap();
+        assert_eq!(
+            actor.icon.map(|x| x.url.to_string()).as_deref(),
+            Some("https://remote.com/avatar.png")
+        );
+
+        // local users are only changed through their own account
+        let forged = StoredUser {
+            private_key_pem: None,
+            ..local
+        };
+        assert!(conn.update_federated_user(&forged).await.is_err());
+        Ok(())
+    }
+    #[actix_web::test]
+    async fn test_delete_federates() -> Result<(), String> {
+        let conn = InMemoryConn::new("example.com");
+        conn.init().await.unwrap();
+
+        let local = conn
+            .create_user(
+                "example.com",
+                &NewLocal::new("local".to_string(), "filler".to_string(), None, None),
+            )
+            .await
+            .unwrap();
+        let follower = remote_user(&conn, "follower", None);
+        let followed = remote_user(&conn, "followed", None);
+        conn.create_follow(&follower, &local, false, None)
+            .await
+            .unwrap();
+        conn.create_follow(&local, &followed, false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(delete_local_account(&conn, &local).await.unwrap(), 2);
+        for delivery in conn.get_due_deliveries(i64::MAX, 10).await.unwrap() {
+            assert_eq!(delivery.signer, local);
+            let delete: serde_json::Value = serde_json::from_str(&delivery.body).unwrap();
+            assert_eq!(delete["type"], "Delete");
+            assert_eq!(delete["actor"], delete["object"]);
+        }
+        // the tombstone can still sign the deliveries
+        assert!(conn
+            .get_local_user(&local)
+            .await
+            .unwrap()
+            .private_key()
+            .is_some());
+        Ok(())
+    }
+}
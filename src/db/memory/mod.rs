@@ -0,0 +1,2 @@
+pub mod memory_conn;
+mod store;
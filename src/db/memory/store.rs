@@ -0,0 +1,261 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use crate::db::{
+    conn::{DbErr, InsertErr},
+    utility::{
+        delivery::Delivery,
+        instance_actor::InstanceActor,
+        notifications::{Notification, NotificationMarker},
+        oauth::{OAuthApp, OAuthCode, OAuthToken},
+        polls::PollVote,
+        protocols::{Protocol, SignatureScheme},
+        registration::{Application, Invite, RegistrationMode},
+        revisions::PostRevision,
+        stored_post::StoredPost,
+        stored_user::StoredUser,
+    },
+};
+
+pub struct StoredFollow {
+    pub pending: bool,
+    pub activity_id: Option<String>,
+}
+
+pub struct StoredLike {
+    pub actor: String,
+    pub post: String,
+}
+
+/// the tables of the memory backend. mirrors the constraints of the
+/// postgres schema that the rest of the app relies on
+#[derive(Default)]
+pub struct Store {
+    pub instance_actor: Option<InstanceActor>,
+    /// domain to the protocol of the instance if known
+    pub instances: HashMap<String, Option<Protocol>>,
+    /// domain to the signature scheme the instance accepted
+    pub signature_schemes: HashMap<String, SignatureScheme>,
+    /// keyed by uid
+    pub users: HashMap<String, StoredUser>,
+    /// keyed by id
+    pub posts: HashMap<String, StoredPost>,
+    /// keyed by (follower, target_user)
+    pub follows: HashMap<(String, String), StoredFollow>,
+    /// keyed by url
+    pub likes: HashMap<String, StoredLike>,
+    /// keyed by id
+    pub deliveries: HashMap<String, Delivery>,
+    /// inbox to when it was marked dead
+    pub dead_inboxes: HashMap<String, i64>,
+    /// keyed by client id
+    pub oauth_apps: HashMap<String, OAuthApp>,
+    /// keyed by code
+    pub oauth_codes: HashMap<String, OAuthCode>,
+    /// keyed by the hash of the token
+    pub oauth_tokens: HashMap<String, OAuthToken>,
+    pub registration_mode: Option<RegistrationMode>,
+    /// keyed by code
+    pub invites: HashMap<String, Invite>,
+    /// keyed by uid
+    pub applications: HashMap<String, Application>,
+    /// keyed by id
+    pub notifications: HashMap<String, Notification>,
+    /// keyed by uid
+    pub notification_markers: HashMap<String, NotificationMarker>,
+    /// ids of the closed polls that were already marked as ended
+    pub ended_polls: HashSet<String>,
+    /// keyed by poll id, oldest first
+    pub votes: HashMap<String, Vec<PollVote>>,
+    /// keyed by post id, oldest first
+    pub post_revisions: HashMap<String, Vec<PostRevision>>,
+}
+
+impl Store {
+    pub fn ensure_instance(&mut self, domain: &str) {
+        self.instances.entry(domain.to_string()).or_insert(None);
+    }
+
+    pub fn insert_user(&mut self, user: StoredUser) -> Result<(), DbErr> {
+        let exists = self.users.values().any(|x| {
+            x.uid.eq(&user.uid)
+                || x.resource_link.eq(&user.resource_link)
+                || (x.domain.eq(&user.domain) && x.username.eq(&user.username))
+        });
+        if exists {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        self.ensure_instance(&user.domain);
+        self.users.insert(user.uid.clone(), user);
+        Ok(())
+    }
+
+    pub fn get_user_by_username(&self, username: &str, domain: &str) -> Option<&StoredUser> {
+        self.users
+            .values()
+            .find(|x| x.username.eq(username) && x.domain.eq(domain))
+    }
+
+    pub fn get_user_by_link(&self, link: &str) -> Option<&StoredUser> {
+        self.users
+            .values()
+            .find(|x| x.resource_link.eq(link) || x.public_key_id.eq(link))
+    }
+
+    pub fn insert_follow(
+        &mut self,
+        follower: &str,
+        target: &str,
+        pending: bool,
+        activity_id: Option<&str>,
+    ) -> Result<(), DbErr> {
+        if !self.users.contains_key(follower) || !self.users.contains_key(target) {
+            return Err(DbErr::NotFound);
+        }
+        let key = (follower.to_string(), target.to_string());
+        let duplicate_activity = activity_id.is_some()
+            && self
+                .follows
+                .values()
+                .any(|x| x.activity_id.as_deref() == activity_id);
+        if self.follows.contains_key(&key) || duplicate_activity {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        self.follows.insert(
+            key,
+            StoredFollow {
+                pending,
+                activity_id: activity_id.map(|x| x.to_string()),
+            },
+        );
+        Ok(())
+    }
+
+    /// the follows, likes, votes and posts of a user
+    fn remove_owned(&mut self, uid: &str) {
+        self.follows
+            .retain(|(follower, target), _| follower.ne(uid) && target.ne(uid));
+        self.likes.retain(|_, like| like.actor.ne(uid));
+        for votes in self.votes.values_mut() {
+            votes.retain(|vote| vote.voter.ne(uid));
+        }
+        self.oauth_codes.retain(|_, code| code.uid.ne(uid));
+        self.oauth_tokens
+            .retain(|_, token| token.uid.as_deref() != Some(uid));
+        self.applications.remove(uid);
+        self.invites
+            .retain(|_, invite| invite.created_by.as_deref() != Some(uid));
+        self.notifications.retain(|_, notification| {
+            notification.recipient.ne(uid) && notification.actor.as_deref() != Some(uid)
+        });
+        self.notification_markers.remove(uid);
+        let removed: Vec<String> = self
+            .posts
+            .values()
+            .filter(|x| x.actor.eq(uid))
+            .map(|x| x.id.clone())
+            .collect();
+        for id in removed {
+            self.remove_post(&id);
+        }
+    }
+
+    /// removes the user along with everything that belongs to them
+    pub fn delete_user(&mut self, uid: &str) -> Result<(), DbErr> {
+        self.users.remove(uid).ok_or(DbErr::NotFound)?;
+        self.remove_owned(uid);
+        Ok(())
+    }
+
+    /// clears a local user while keeping their keys, mirrors the backends
+    pub fn tombstone_user(&mut self, uid: &str) -> Result<(), DbErr> {
+        let user = self
+            .users
+            .get_mut(uid)
+            .filter(|x| x.is_local())
+            .ok_or(DbErr::NotFound)?;
+        user.display_name = None;
+        user.summary = None;
+        user.avatar = None;
+        user.password = None;
+        user.email = None;
+        user.deleted = true;
+        self.remove_owned(uid);
+        Ok(())
+    }
+
+    /// replies to posts we don't have lose the link to the parent
+    pub fn insert_post(&mut self, mut post: StoredPost) -> Result<(), DbErr> {
+        if self.posts.contains_key(&post.id) {
+            return Err(DbErr::InsertErr(InsertErr::AlreadyExists));
+        }
+        if !self.users.contains_key(&post.actor) {
+            return Err(DbErr::NotFound);
+        }
+        if let Some(parent) = &post.in_reply_to {
+            if !self.posts.contains_key(parent) {
+                post.in_reply_to = None;
+            }
+        }
+        self.ensure_instance(&post.domain);
+        self.posts.insert(post.id.clone(), post);
+        Ok(())
+    }
+
+    /// keeps the version being replaced as a revision
+    pub fn edit_post(&mut self, post: &StoredPost) -> Result<(), DbErr> {
+        let existing = self
+            .posts
+            .get_mut(&post.id)
+            .filter(|x| x.actor.eq(&post.actor))
+            .ok_or(DbErr::NotFound)?;
+        let revision = PostRevision::new(existing);
+        existing.content = post.content.clone();
+        existing.subject = post.subject.clone();
+        existing.is_sensitive = post.is_sensitive;
+        existing.multi_select = post.multi_select;
+        existing.options = post.options.clone();
+        existing.closed = post.closed;
+        existing.edited_at = post.edited_at;
+        self.post_revisions
+            .entry(post.id.clone())
+            .or_default()
+            .push(revision);
+        Ok(())
+    }
+
+    pub fn get_local_post(&self, pid: &str, domain: &str) -> Option<&StoredPost> {
+        self.posts
+            .values()
+            .find(|x| x.versia_id.eq(pid) && x.domain.eq(domain))
+    }
+
+    /// only includes posts visible to the public, newest first
+    pub fn get_public_posts(&self, uid: &str) -> Vec<&StoredPost> {
+        let mut posts: Vec<&StoredPost> = self
+            .posts
+            .values()
+            .filter(|x| x.actor.eq(uid) && !x.local_only && !x.followers_only)
+            .collect();
+        posts.sort_by_key(|x| Reverse(x.published));
+        posts
+    }
+
+    pub fn remove_post(&mut self, id: &str) -> Option<StoredPost> {
+        let post = self.posts.remove(id)?;
+        self.likes.retain(|_, like| like.post.ne(id));
+        self.notifications
+            .retain(|_, notification| notification.post.as_deref() != Some(id));
+        self.ended_polls.remove(id);
+        self.votes.remove(id);
+        self.post_revisions.remove(id);
+        for reply in self.posts.values_mut() {
+            if reply.in_reply_to.as_deref() == Some(id) {
+                reply.in_reply_to = None;
+            }
+        }
+        Some(post)
+    }
+}
@@ -1,7 +1,9 @@
 use crate::protocols::protocol::protocols::Protocols;
 
 pub mod conn;
+pub mod memory;
 pub mod postgres;
+pub mod sqlite;
 #[cfg(test)]
 pub mod tests;
 pub mod utility;
@@ -0,0 +1,134 @@
+use tokio_postgres::Row;
+
+use crate::db::{
+    conn::DbErr,
+    utility::{delivery::Delivery, stored_user::now_millis},
+};
+
+use super::pg_conn::PgConn;
+
+fn to_delivery(row: &Row) -> Result<Delivery, DbErr> {
+    let protocol: String = row.get("protocol");
+    Ok(Delivery {
+        id: row.get("id"),
+        inbox: row.get("inbox"),
+        protocol: serde_json::from_str(&protocol).map_err(|_| DbErr::InvalidType)?,
+        body: row.get("body"),
+        signer: row.get("signer"),
+        attempts: row.get("attempts"),
+        created_at: row.get("created_at"),
+        next_attempt: row.get("next_attempt"),
+        last_error: row.get("last_error"),
+        dead: row.get("dead"),
+    })
+}
+
+pub async fn insert_delivery(conn: &PgConn, delivery: &Delivery) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO deliveries
+        (
+            id, inbox, protocol, body, signer,
+            attempts, created_at, next_attempt, last_error, dead
+        )
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let protocol = serde_json::to_string(&delivery.protocol).unwrap();
+    client
+        .execute(
+            &stmt,
+            &[
+                &delivery.id,
+                &delivery.inbox,
+                &protocol,
+                &delivery.body,
+                &delivery.signer,
+                &delivery.attempts,
+                &delivery.created_at,
+                &delivery.next_attempt,
+                &delivery.last_error,
+                &delivery.dead,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_due_deliveries(
+    conn: &PgConn,
+    now: i64,
+    limit: u64,
+) -> Result<Vec<Delivery>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM deliveries
+        WHERE dead = false AND next_attempt <= $1
+        ORDER BY next_attempt ASC
+        LIMIT $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let limit = limit as i64;
+    let result = client.query(&stmt, &[&now, &limit]).await?;
+    result.iter().map(to_delivery).collect()
+}
+
+pub async fn update_delivery(conn: &PgConn, delivery: &Delivery) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        UPDATE deliveries
+        SET attempts = $2, next_attempt = $3, last_error = $4, dead = $5
+        WHERE id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let updated = client
+        .execute(
+            &stmt,
+            &[
+                &delivery.id,
+                &delivery.attempts,
+                &delivery.next_attempt,
+                &delivery.last_error,
+                &delivery.dead,
+            ],
+        )
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn remove_delivery(conn: &PgConn, id: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM deliveries WHERE id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client.execute(&stmt, &[&id]).await?;
+    Ok(())
+}
+
+pub async fn mark_inbox_dead(conn: &PgConn, inbox: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO dead_inboxes
+        (inbox, died_at)
+        VALUES
+        ($1, $2)
+        ON CONFLICT (inbox) DO NOTHING;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client.execute(&stmt, &[&inbox, &now_millis()]).await?;
+    Ok(())
+}
+
+pub async fn is_inbox_dead(conn: &PgConn, inbox: &str) -> Result<bool, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT inbox FROM dead_inboxes WHERE inbox = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    Ok(client.query_opt(&stmt, &[&inbox]).await?.is_some())
+}
@@ -1,134 +1,108 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::db::{conn::DbErr, utility::stored_user::StoredUser};
 
-use tokio_postgres::Row;
+use super::{pg_conn::PgConn, users::to_user};
 
-use crate::db::Follower;
-
-use super::pg_conn::PgConn;
-
-///requires following joined on unified_users
-fn to_follower(row: Row) -> Follower {
-    Follower {
-        uid: row.get("follower"),
-        is_local: row.get("is_local"),
-        protocol: serde_json::from_str(row.get("protocol"))
-            .expect("failed to deserialize protocol"),
-    }
-}
-
-pub async fn create_follow_request(
+pub async fn create_follow(
     conn: &PgConn,
-    from_id: i64,
-    to_id: i64,
+    follower: &str,
+    target: &str,
     pending: bool,
-) -> Result<(), ()> {
-    let client = conn.db.get().await.expect("failed to get client");
+    activity_id: Option<&str>,
+    published: i64,
+) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
-        INSERT INTO following 
-        (
-            follower, target_user,
-            pending, published
-        )
+        INSERT INTO following
+        (follower, target_user, pending, activity_id, published)
         VALUES
-        (
-            $1, $2, 
-            $3, $4
-        );
+        ($1, $2, $3, $4, $5);
         "#;
-    let stmt = client.prepare(stmt).await.unwrap();
-
-    let created = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as i64;
-
-    let result = client
-        .query(&stmt, &[&from_id, &to_id, &pending, &created])
-        .await;
-    // .expect("failed to create follow");
-    match result {
-        Ok(_) => Ok(()),
-        Err(_) => Err(()),
-    }
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[&follower, &target, &pending, &activity_id, &published],
+        )
+        .await?;
+    Ok(())
 }
 
-pub async fn approve_follow_request(conn: &PgConn, from_id: i64, to_id: i64) -> Result<(), ()> {
-    let client = conn.db.get().await.expect("failed to get client");
+pub async fn get_followers(conn: &PgConn, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
-        UPDATE following 
-        SET pending = false
-        WHERE
-        follower = $1
-        target_user = $2;
+        SELECT users.* FROM following
+        JOIN users ON users.uid = following.follower
+        WHERE following.target_user = $1 AND following.pending = false;
         "#;
-    let stmt = client.prepare(stmt).await.unwrap();
-
-    let _result = client
-        .query(&stmt, &[&from_id, &to_id])
-        .await
-        .expect("failed to approve follow");
-
-    Ok(())
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&uid]).await?;
+    Ok(result.iter().map(to_user).collect())
 }
 
-pub async fn get_followers(conn: &PgConn, user: i64) -> Result<Vec<Follower>, ()> {
-    let client = conn.db.get().await.expect("failed to get client");
+pub async fn get_following(conn: &PgConn, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
-            SELECT * FROM following INNER JOIN unified_users ON follower = uid
-            WHERE
-            target_user = $1;
-            "#;
-    let stmt = client.prepare(stmt).await.unwrap();
-
-    // let (target_fedi, target_local) = user.parts();
-
-    let result = client.query(&stmt, &[&user]).await;
-    // .expect("failed to get followers");
-    // dbg!(&result);
-    let result = result.unwrap();
-
-    let x = result.into_iter().map(to_follower).collect();
-    dbg!(&x);
-
-    Ok(x)
+        SELECT users.* FROM following
+        JOIN users ON users.uid = following.target_user
+        WHERE following.follower = $1 AND following.pending = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&uid]).await?;
+    Ok(result.iter().map(to_user).collect())
 }
 
-pub async fn get_follower_count(conn: &PgConn, user: i64) -> Result<i64, ()> {
-    let client = conn.db.get().await.expect("failed to get client");
+pub async fn is_following(conn: &PgConn, follower: &str, target: &str) -> Result<bool, DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
-        SELECT COUNT(*) FROM following 
-        WHERE
-        target_user = $1;
+        SELECT pending FROM following
+        WHERE follower = $1 AND target_user = $2 AND pending = false;
         "#;
-    let stmt = client.prepare(stmt).await.unwrap();
-
-    let result: i64 = client
-        .query(&stmt, &[&user])
-        .await
-        .expect("failed to get follow count")
-        .pop()
-        .expect("did not return row for follow count")
-        .get("count");
-
-    Ok(result)
+    let stmt = client.prepare(stmt).await?;
+    Ok(client
+        .query_opt(&stmt, &[&follower, &target])
+        .await?
+        .is_some())
 }
 
-pub async fn get_follow(conn: &PgConn, from_id: i64, to_id: i64) -> Option<Follower> {
-    let client = conn.db.get().await.expect("failed to get client");
-
+pub async fn accept_follow(conn: &PgConn, follower: &str, target: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
-        SELECT * FROM following NATURAL JOIN unified_users
-        WHERE
-        follower = $1 AND
-        target_user = $2;
+        UPDATE following SET pending = false
+        WHERE follower = $1 AND target_user = $2;
         "#;
-    let stmt = client.prepare(stmt).await.unwrap();
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&follower, &target]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
 
-    let result = client
-        .query(&stmt, &[&from_id, &to_id])
-        .await
-        .expect("failed to get follow count")
-        .pop();
+pub async fn remove_follow(conn: &PgConn, follower: &str, target: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM following
+        WHERE follower = $1 AND target_user = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&follower, &target]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
 
-    result.map(to_follower)
+pub async fn remove_follow_activity(
+    conn: &PgConn,
+    follower: &str,
+    activity_id: &str,
+) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM following
+        WHERE follower = $1 AND activity_id = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&follower, &activity_id]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
 }
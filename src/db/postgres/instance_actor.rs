@@ -1,38 +1,97 @@
-use crate::db::utility::instance_actor::InstanceActor;
+use crate::db::{
+    conn::DbErr,
+    utility::{instance_actor::InstanceActor, stored_user::now_millis},
+};
 
 use super::pg_conn::PgConn;
 
-#[allow(dead_code)]
-pub async fn get_instance_actor(conn: &PgConn) -> Option<InstanceActor> {
-    let client = conn.db.get().await.expect("failed to get client");
+pub async fn get_instance_actor(conn: &PgConn) -> Result<Option<InstanceActor>, DbErr> {
+    let client = conn.db.get().await?;
     let stmt = r#"
     SELECT * FROM ap_instance_actor;
     "#;
-    let stmt = client.prepare(stmt).await.unwrap();
+    let stmt = client.prepare(stmt).await?;
 
-    let result = client
-        .query(&stmt, &[])
-        .await
-        .expect("failed to get instance actor")
-        .pop();
-    result.map(|result| InstanceActor {
+    let Some(result) = client.query(&stmt, &[]).await?.pop() else {
+        return Ok(None);
+    };
+    let versia_private_key_pem: Option<String> = result.get("versia_private_key_pem");
+    let created_at: Option<i64> = result.get("created_at");
+    let mut actor = InstanceActor {
         private_key_pem: result.get("private_key_pem"),
         public_key_pem: result.get("public_key_pem"),
-    })
+        versia_private_key_pem: versia_private_key_pem.clone().unwrap_or_default(),
+        versia_public_key_pem: result
+            .get::<_, Option<String>>("versia_public_key_pem")
+            .unwrap_or_default(),
+        previous_public_key_pem: result.get("previous_public_key_pem"),
+        previous_versia_public_key_pem: result.get("previous_versia_public_key_pem"),
+        previous_key_expires: result.get("previous_key_expires"),
+        created_at: created_at.unwrap_or_else(now_millis),
+    };
+    // instance actors created before the versia key existed
+    if versia_private_key_pem.is_none() {
+        actor.generate_versia_key();
+        update_instance_actor(conn, &actor).await?;
+    }
+    Ok(Some(actor))
 }
 
-// pub async fn create_instance_actor(conn: &PgConn, private_key_pem: &str, public_key_pem: &str) {
-//     let client = conn.db.get().await.expect("failed to get client");
-//     let stmt = r#"
-//     INSERT INTO ap_instance_actor
-//     (private_key_pem, public_key_pem)
-//     VALUES
-//     ($1, $2);
-//     "#;
-//     let stmt = client.prepare(stmt).await.unwrap();
+pub async fn create_instance_actor(conn: &PgConn) -> Result<InstanceActor, DbErr> {
+    let actor = InstanceActor::generate();
 
-//     let result = client
-//         .query(&stmt, &[&private_key_pem, &public_key_pem])
-//         .await;
-//     result.unwrap();
-// }
+    let client = conn.db.get().await?;
+    let stmt = r#"
+    INSERT INTO ap_instance_actor
+    (private_key_pem, public_key_pem, versia_private_key_pem, versia_public_key_pem, created_at)
+    VALUES
+    ($1, $2, $3, $4, $5);
+    "#;
+    let stmt = client.prepare(stmt).await?;
+
+    client
+        .execute(
+            &stmt,
+            &[
+                &actor.private_key_pem,
+                &actor.public_key_pem,
+                &actor.versia_private_key_pem,
+                &actor.versia_public_key_pem,
+                &actor.created_at,
+            ],
+        )
+        .await?;
+    Ok(actor)
+}
+
+/// replaces the keys of the instance actor
+pub async fn update_instance_actor(conn: &PgConn, actor: &InstanceActor) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+    UPDATE ap_instance_actor SET
+        private_key_pem = $1, public_key_pem = $2,
+        versia_private_key_pem = $3, versia_public_key_pem = $4, created_at = $5,
+        previous_public_key_pem = $6, previous_versia_public_key_pem = $7,
+        previous_key_expires = $8;
+    "#;
+    let stmt = client.prepare(stmt).await?;
+    let updated = client
+        .execute(
+            &stmt,
+            &[
+                &actor.private_key_pem,
+                &actor.public_key_pem,
+                &actor.versia_private_key_pem,
+                &actor.versia_public_key_pem,
+                &actor.created_at,
+                &actor.previous_public_key_pem,
+                &actor.previous_versia_public_key_pem,
+                &actor.previous_key_expires,
+            ],
+        )
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
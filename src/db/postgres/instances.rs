@@ -0,0 +1,103 @@
+use crate::db::{
+    conn::DbErr,
+    utility::protocols::{Protocol, SignatureScheme},
+};
+
+use super::pg_conn::PgConn;
+
+/// creates the instance if it doesn't exist yet. users and posts
+/// reference their instance so this must be done before inserting them
+pub async fn ensure_instance(conn: &PgConn, domain: &str, is_local: bool) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO instances
+        (domain, is_primary, is_authoratative)
+        VALUES
+        ($1, $2, $2)
+        ON CONFLICT (domain) DO NOTHING;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client.execute(&stmt, &[&domain, &is_local]).await?;
+    Ok(())
+}
+
+pub async fn get_protocol(conn: &PgConn, domain: &str) -> Result<Option<Protocol>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT protocol FROM instances WHERE domain = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&domain]).await?;
+    let protocol: Option<String> = result.and_then(|x| x.get("protocol"));
+    Ok(protocol.and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_protocol(conn: &PgConn, domain: &str, protocol: Protocol) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO instances
+        (domain, protocol)
+        VALUES
+        ($1, $2)
+        ON CONFLICT (domain) DO UPDATE SET protocol = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let protocol = serde_json::to_string(&protocol).unwrap();
+    client.execute(&stmt, &[&domain, &protocol]).await?;
+    Ok(())
+}
+
+pub async fn get_instances(conn: &PgConn, protocol: Protocol) -> Result<Vec<String>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT domain FROM instances WHERE protocol = $1 AND is_primary = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let protocol = serde_json::to_string(&protocol).unwrap();
+    let result = client.query(&stmt, &[&protocol]).await?;
+    Ok(result.iter().map(|x| x.get("domain")).collect())
+}
+
+pub async fn get_shared_inboxes(conn: &PgConn) -> Result<Vec<String>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT DISTINCT shared_inbox FROM users
+        WHERE shared_inbox IS NOT NULL AND private_key_pem IS NULL;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[]).await?;
+    Ok(result.iter().map(|x| x.get("shared_inbox")).collect())
+}
+
+pub async fn get_signature_scheme(
+    conn: &PgConn,
+    domain: &str,
+) -> Result<Option<SignatureScheme>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT signature_scheme FROM instances WHERE domain = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&domain]).await?;
+    let scheme: Option<String> = result.and_then(|x| x.get("signature_scheme"));
+    Ok(scheme.and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_signature_scheme(
+    conn: &PgConn,
+    domain: &str,
+    scheme: SignatureScheme,
+) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO instances
+        (domain, signature_scheme)
+        VALUES
+        ($1, $2)
+        ON CONFLICT (domain) DO UPDATE SET signature_scheme = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let scheme = serde_json::to_string(&scheme).unwrap();
+    client.execute(&stmt, &[&domain, &scheme]).await?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use crate::db::{conn::DbErr, utility::stored_user::now_millis};
+
+use super::pg_conn::PgConn;
+
+pub async fn remove_like(conn: &PgConn, actor: &str, url: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM likes WHERE actor = $1 AND url = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&actor, &url]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// the like is identified by the link of its activity
+pub async fn insert_like(conn: &PgConn, actor: &str, post: &str, url: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO likes (id, url, actor, post, published)
+        VALUES ($1, $1, $2, $3, $4);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(&stmt, &[&url, &actor, &post, &now_millis()])
+        .await?;
+    Ok(())
+}
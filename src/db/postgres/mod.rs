@@ -1,8 +1,13 @@
-mod acct_mgmt;
-mod actors;
+mod deliveries;
 mod follows;
 mod init;
 mod instance_actor;
-// mod local_users;
+mod instances;
+mod likes;
+mod notifications;
+mod oauth;
 pub mod pg_conn;
-// mod posts;
+mod posts;
+mod registration;
+mod users;
+mod votes;
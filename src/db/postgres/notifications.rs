@@ -0,0 +1,120 @@
+use tokio_postgres::Row;
+
+use crate::db::{
+    conn::DbErr,
+    utility::notifications::{Notification, NotificationMarker, NotificationQuery},
+};
+
+use super::pg_conn::PgConn;
+
+fn to_notification(row: &Row) -> Result<Notification, DbErr> {
+    let kind: String = row.get("kind");
+    Ok(Notification {
+        id: row.get("id"),
+        recipient: row.get("recipient"),
+        kind: serde_json::from_str(&kind).map_err(|_| DbErr::InvalidType)?,
+        actor: row.get("actor"),
+        post: row.get("post"),
+        created_at: row.get("created_at"),
+    })
+}
+
+pub async fn insert_notification(conn: &PgConn, notification: &Notification) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO notifications
+        (id, recipient, kind, actor, post, created_at)
+        VALUES
+        ($1, $2, $3, $4, $5, $6);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let kind = serde_json::to_string(&notification.kind).unwrap();
+    client
+        .execute(
+            &stmt,
+            &[
+                &notification.id,
+                &notification.recipient,
+                &kind,
+                &notification.actor,
+                &notification.post,
+                &notification.created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// newest first, the limit has already been capped
+pub async fn get_notifications(
+    conn: &PgConn,
+    uid: &str,
+    query: &NotificationQuery,
+) -> Result<Vec<Notification>, DbErr> {
+    let client = conn.db.get().await?;
+    let ascending = query.adjacent && query.after.is_some();
+    let order = match ascending {
+        true => "ASC",
+        false => "DESC",
+    };
+    let stmt = format!(
+        r#"
+        SELECT * FROM notifications
+        WHERE recipient = $1
+        AND ($2::TEXT IS NULL OR id < $2)
+        AND ($3::TEXT IS NULL OR id > $3)
+        AND ($4::TEXT[] IS NULL OR kind = ANY($4))
+        ORDER BY id {order}
+        LIMIT $5;
+        "#
+    );
+    let stmt = client.prepare(&stmt).await?;
+    let kinds: Option<Vec<String>> = query.kinds.as_ref().map(|x| {
+        x.iter()
+            .map(|kind| serde_json::to_string(kind).unwrap())
+            .collect()
+    });
+    let limit = query.limit as i64;
+    let result = client
+        .query(&stmt, &[&uid, &query.before, &query.after, &kinds, &limit])
+        .await?;
+    let mut notifications = result
+        .iter()
+        .map(to_notification)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ascending {
+        notifications.reverse();
+    }
+    Ok(notifications)
+}
+
+pub async fn get_marker(conn: &PgConn, uid: &str) -> Result<Option<NotificationMarker>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM notification_markers WHERE uid = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&uid]).await?;
+    Ok(result.map(|row| NotificationMarker {
+        uid: row.get("uid"),
+        last_read_id: row.get("last_read_id"),
+        updated_at: row.get("updated_at"),
+    }))
+}
+
+pub async fn set_marker(conn: &PgConn, marker: &NotificationMarker) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO notification_markers (uid, last_read_id, updated_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (uid) DO UPDATE SET last_read_id = $2, updated_at = $3;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[&marker.uid, &marker.last_read_id, &marker.updated_at],
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use tokio_postgres::Row;
+
+use crate::db::{
+    conn::DbErr,
+    utility::oauth::{OAuthApp, OAuthCode, OAuthToken, Scopes},
+};
+
+use super::pg_conn::PgConn;
+
+fn to_scopes(row: &Row) -> Result<Scopes, DbErr> {
+    let scopes: String = row.get("scopes");
+    Scopes::parse(&scopes).ok_or(DbErr::InvalidType)
+}
+
+fn to_app(row: &Row) -> Result<OAuthApp, DbErr> {
+    let redirect_uris: String = row.get("redirect_uris");
+    Ok(OAuthApp {
+        client_id: row.get("client_id"),
+        client_secret: row.get("client_secret"),
+        name: row.get("name"),
+        redirect_uris: redirect_uris
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        scopes: to_scopes(row)?,
+        website: row.get("website"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn to_code(row: &Row) -> Result<OAuthCode, DbErr> {
+    Ok(OAuthCode {
+        code: row.get("code"),
+        client_id: row.get("client_id"),
+        uid: row.get("uid"),
+        redirect_uri: row.get("redirect_uri"),
+        scopes: to_scopes(row)?,
+        code_challenge: row.get("code_challenge"),
+        code_challenge_method: row.get("code_challenge_method"),
+        expires: row.get("expires"),
+    })
+}
+
+fn to_token(row: &Row) -> Result<OAuthToken, DbErr> {
+    Ok(OAuthToken {
+        token_hash: row.get("token_hash"),
+        client_id: row.get("client_id"),
+        uid: row.get("uid"),
+        scopes: to_scopes(row)?,
+        created_at: row.get("created_at"),
+    })
+}
+
+pub async fn insert_app(conn: &PgConn, app: &OAuthApp) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO oauth_apps
+        (client_id, client_secret, name, redirect_uris, scopes, website, created_at)
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[
+                &app.client_id,
+                &app.client_secret,
+                &app.name,
+                &app.redirect_uris.join(" "),
+                &app.scopes.to_string(),
+                &app.website,
+                &app.created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_app(conn: &PgConn, client_id: &str) -> Result<Option<OAuthApp>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM oauth_apps WHERE client_id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&client_id]).await?;
+    result.as_ref().map(to_app).transpose()
+}
+
+pub async fn insert_code(conn: &PgConn, code: &OAuthCode) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO oauth_codes
+        (
+            code, client_id, uid, redirect_uri, scopes,
+            code_challenge, code_challenge_method, expires
+        )
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7, $8);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[
+                &code.code,
+                &code.client_id,
+                &code.uid,
+                &code.redirect_uri,
+                &code.scopes.to_string(),
+                &code.code_challenge,
+                &code.code_challenge_method,
+                &code.expires,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn take_code(conn: &PgConn, code: &str) -> Result<Option<OAuthCode>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM oauth_codes WHERE code = $1 RETURNING *;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&code]).await?;
+    result.as_ref().map(to_code).transpose()
+}
+
+pub async fn insert_token(conn: &PgConn, token: &OAuthToken) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO oauth_tokens
+        (token_hash, client_id, uid, scopes, created_at)
+        VALUES
+        ($1, $2, $3, $4, $5);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[
+                &token.token_hash,
+                &token.client_id,
+                &token.uid,
+                &token.scopes.to_string(),
+                &token.created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_token(conn: &PgConn, token_hash: &str) -> Result<Option<OAuthToken>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM oauth_tokens WHERE token_hash = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&token_hash]).await?;
+    result.as_ref().map(to_token).transpose()
+}
+
+pub async fn delete_token(conn: &PgConn, token_hash: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM oauth_tokens WHERE token_hash = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let deleted = client.execute(&stmt, &[&token_hash]).await?;
+    match deleted {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
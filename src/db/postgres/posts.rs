@@ -1,137 +1,364 @@
-use url::Url;
+use tokio_postgres::Row;
 
-use crate::{
-    activitystream_objects::object::{Object, ObjectType},
-    db::utility::post_types::PostSupertype,
+use crate::db::{
+    conn::DbErr,
+    utility::{
+        post_types::PostSupertype,
+        revisions::PostRevision,
+        stored_post::StoredPost,
+        timeline::{Timeline, TimelineQuery},
+    },
 };
 
 use super::pg_conn::PgConn;
 
-pub async fn create_new_post(
+fn to_post(row: &Row) -> Result<StoredPost, DbErr> {
+    let surtype: String = row.get("surtype");
+    let category: String = row.get("category");
+    let options: Option<String> = row.get("options");
+    let options = match options {
+        Some(x) => Some(serde_json::from_str(&x).map_err(|_| DbErr::InvalidType)?),
+        None => None,
+    };
+    Ok(StoredPost {
+        id: row.get("id"),
+        versia_id: row.get("versia_id"),
+        domain: row.get("domain"),
+        surtype: PostSupertype::parse_str(&surtype).map_err(|_| DbErr::InvalidType)?,
+        subtype: row.get("subtype"),
+        category: serde_json::from_str(&category).map_err(|_| DbErr::InvalidType)?,
+        likes: row.get("likes"),
+        boosts: row.get("boosts"),
+        local_only: row.get("local_only"),
+        followers_only: row.get("followers_only"),
+        published: row.get("published"),
+        is_reply: row.get("is_reply"),
+        in_reply_to: row.get("in_reply_to"),
+        content: row.get("content"),
+        subject: row.get("subject"),
+        is_sensitive: row.get("is_sensitive"),
+        shared: row.get("shared"),
+        multi_select: row.get("multi_select"),
+        options,
+        closed: row.get("closed"),
+        local_only_voting: row.get("local_only_voting"),
+        edited_at: row.get("edited_at"),
+        actor: row.get("actor"),
+    })
+}
+
+fn to_revision(row: &Row) -> Result<PostRevision, DbErr> {
+    let options: Option<String> = row.get("options");
+    let options = match options {
+        Some(x) => Some(serde_json::from_str(&x).map_err(|_| DbErr::InvalidType)?),
+        None => None,
+    };
+    Ok(PostRevision {
+        id: row.get("id"),
+        post: row.get("post"),
+        content: row.get("content"),
+        subject: row.get("subject"),
+        is_sensitive: row.get("is_sensitive"),
+        options,
+        created_at: row.get("created_at"),
+    })
+}
+
+pub async fn insert_post(conn: &PgConn, post: &StoredPost) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    // replies to posts we don't have are still stored as replies
+    // but without the link to the parent
+    let stmt = r#"
+        INSERT INTO posts
+        (
+            id, versia_id, domain, surtype, subtype, category,
+            local_only, followers_only, published,
+            is_reply, in_reply_to,
+            content, subject, is_sensitive, shared,
+            multi_select, options, closed, local_only_voting,
+            edited_at, actor
+        )
+        VALUES
+        (
+            $1, $2, $3, $4, $5, $6,
+            $7, $8, $9,
+            $10, (SELECT id FROM posts WHERE id = $11),
+            $12, $13, $14, $15,
+            $16, $17, $18, $19,
+            $20, $21
+        );
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let surtype = serde_json::to_string(&post.surtype).unwrap();
+    let category = serde_json::to_string(&post.category).unwrap();
+    let options = post
+        .options
+        .as_ref()
+        .map(|x| serde_json::to_string(x).unwrap());
+
+    client
+        .execute(
+            &stmt,
+            &[
+                &post.id,
+                &post.versia_id,
+                &post.domain,
+                &surtype,
+                &post.subtype,
+                &category,
+                &post.local_only,
+                &post.followers_only,
+                &post.published,
+                &post.is_reply,
+                &post.in_reply_to,
+                &post.content,
+                &post.subject,
+                &post.is_sensitive,
+                &post.shared,
+                &post.multi_select,
+                &options,
+                &post.closed,
+                &post.local_only_voting,
+                &post.edited_at,
+                &post.actor,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// gets a local post by its uuid
+pub async fn get_local_post(
     conn: &PgConn,
-    post: &crate::db::PostType,
-    instance_domain: &str,
-    uid: i64,
-    is_local: bool,
-    in_reply_to: Option<i64>,
-) -> i64 {
-    todo!()
-    //     let published = get_published(is_local, post);
-    //     let post_id = match is_local {
-    //         true => None,
-    //         false => Some(post.get_id().to_string()),
-    //     };
-
-    //     match &post {
-    //         crate::db::PostType::Object(x) => {
-    //             let client = conn.db.get().await.expect("failed to get client");
-
-    //             let stmt = r#"
-    // INSERT INTO posts
-    // (
-    // is_local, fedi_id, surtype, subtype,
-    // local_only, published, in_reply_to,
-    // content, domain,
-    // actor
-    // )
-    // VALUES
-    // (
-    // $1, $2, $3, $4,
-    // $5, $6, $7,
-    // $8, $9,
-    // $10
-    // )
-    // RETURNING obj_id;
-    //     "#;
-    //             let stmt = client.prepare(stmt).await.unwrap();
-
-    //             let result: i64 = client
-    //                 .query(
-    //                     &stmt,
-    //                     &[
-    //                         &is_local,
-    //                         &post_id,
-    //                         &post.get_surtype(),
-    //                         &post.get_subtype(),
-    //                         &false,
-    //                         &published,
-    //                         &in_reply_to,
-    //                         &x.object.content,
-    //                         &instance_domain,
-    //                         &uid,
-    //                     ],
-    //                 )
-    //                 .await
-    //                 .expect("failed to insert post")
-    //                 .pop()
-    //                 .expect("did not return obj_id")
-    //                 .get("obj_id");
-    //             result
-    //         }
-    //         crate::db::PostType::Question(_x) => todo!(),
-    //     }
+    pid: &str,
+    domain: &str,
+) -> Result<Option<StoredPost>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM posts WHERE versia_id = $1 AND domain = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&pid, &domain]).await?;
+    result.as_ref().map(to_post).transpose()
+}
+
+pub async fn get_post(conn: &PgConn, id: &str) -> Result<Option<StoredPost>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM posts WHERE id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&id]).await?;
+    result.as_ref().map(to_post).transpose()
+}
+
+/// only includes posts visible to the public. page is one based
+pub async fn get_user_posts(
+    conn: &PgConn,
+    uid: &str,
+    page_size: u64,
+    page: u64,
+) -> Result<Vec<StoredPost>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM posts
+        WHERE actor = $1 AND local_only = false AND followers_only = false
+        ORDER BY published DESC
+        LIMIT $2 OFFSET $3;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let limit = page_size as i64;
+    let offset = (page.saturating_sub(1) * page_size) as i64;
+    let result = client.query(&stmt, &[&uid, &limit, &offset]).await?;
+    result.iter().map(to_post).collect()
+}
+
+/// newest first. local and federated timelines share a query,
+/// the local one being limited to our domain
+pub async fn get_timeline(
+    conn: &PgConn,
+    timeline: &Timeline<'_>,
+    query: &TimelineQuery,
+) -> Result<Vec<StoredPost>, DbErr> {
+    let client = conn.db.get().await?;
+    let (scope, subject) = match timeline {
+        Timeline::Home(uid) => (
+            r#"(actor = $6 OR actor IN (
+                SELECT target_user FROM following WHERE follower = $6 AND pending = false
+            ))"#,
+            Some(uid.to_string()),
+        ),
+        Timeline::Local => (
+            "followers_only = false AND ($6::TEXT IS NULL OR domain = $6)",
+            Some(conn.instance_domain.clone()),
+        ),
+        Timeline::Federated => (
+            "followers_only = false AND ($6::TEXT IS NULL OR domain = $6)",
+            None,
+        ),
+    };
+    let ascending = query.adjacent && query.after.is_some();
+    let order = match ascending {
+        true => "ASC",
+        false => "DESC",
+    };
+    let stmt = format!(
+        r#"
+        SELECT * FROM posts
+        WHERE {scope}
+        AND (local_only = false OR $1)
+        AND ($2::BIGINT IS NULL OR (published, id) < ($2, $3::TEXT))
+        AND ($4::BIGINT IS NULL OR (published, id) > ($4, $5::TEXT))
+        ORDER BY published {order}, id {order}
+        LIMIT $7;
+        "#
+    );
+    let stmt = client.prepare(&stmt).await?;
+    let show_local_only = matches!(timeline, Timeline::Home(_)) || query.viewer.is_some();
+    let before = query.before.as_ref();
+    let after = query.after.as_ref();
+    let limit = query.limit as i64;
+    let result = client
+        .query(
+            &stmt,
+            &[
+                &show_local_only,
+                &before.map(|x| x.published),
+                &before.map(|x| x.id.as_str()),
+                &after.map(|x| x.published),
+                &after.map(|x| x.id.as_str()),
+                &subject,
+                &limit,
+            ],
+        )
+        .await?;
+    let mut posts = result.iter().map(to_post).collect::<Result<Vec<_>, _>>()?;
+    if ascending {
+        posts.reverse();
+    }
+    Ok(posts)
+}
+
+pub async fn count_user_posts(conn: &PgConn, uid: &str) -> Result<u64, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT COUNT(*) FROM posts
+        WHERE actor = $1 AND local_only = false AND followers_only = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_one(&stmt, &[&uid]).await?;
+    let count: i64 = result.get(0);
+    Ok(count as u64)
+}
+
+/// returns not found if no post was deleted
+pub async fn delete_post(conn: &PgConn, id: &str, domain: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM posts WHERE id = $1 AND domain = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&id, &domain]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// every poll closed by the given time that wasn't marked as ended yet
+pub async fn get_ended_polls(conn: &PgConn, now: i64) -> Result<Vec<StoredPost>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM posts
+        WHERE closed IS NOT NULL AND closed <= $1 AND end_notified = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&now]).await?;
+    result.iter().map(to_post).collect()
+}
+
+pub async fn mark_poll_ended(conn: &PgConn, id: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        UPDATE posts SET end_notified = true WHERE id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&id]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// returns not found if the author has no post with the id
+pub async fn edit_post(conn: &PgConn, post: &StoredPost) -> Result<(), DbErr> {
+    let mut client = conn.db.get().await?;
+    let transaction = client.transaction().await?;
+    let existing = transaction
+        .query_opt(
+            "SELECT * FROM posts WHERE id = $1 AND actor = $2 FOR UPDATE;",
+            &[&post.id, &post.actor],
+        )
+        .await?;
+    let Some(existing) = existing else {
+        return Err(DbErr::NotFound);
+    };
+    let revision = PostRevision::new(&to_post(&existing)?);
+    let options = revision
+        .options
+        .as_ref()
+        .map(|x| serde_json::to_string(x).unwrap());
+    transaction
+        .execute(
+            r#"
+            INSERT INTO post_revisions
+            (id, post, content, subject, is_sensitive, options, created_at)
+            VALUES
+            ($1, $2, $3, $4, $5, $6, $7);
+            "#,
+            &[
+                &revision.id,
+                &revision.post,
+                &revision.content,
+                &revision.subject,
+                &revision.is_sensitive,
+                &options,
+                &revision.created_at,
+            ],
+        )
+        .await?;
+    let options = post
+        .options
+        .as_ref()
+        .map(|x| serde_json::to_string(x).unwrap());
+    transaction
+        .execute(
+            r#"
+            UPDATE posts SET
+                content = $2, subject = $3, is_sensitive = $4,
+                multi_select = $5, options = $6, closed = $7, edited_at = $8
+            WHERE id = $1;
+            "#,
+            &[
+                &post.id,
+                &post.content,
+                &post.subject,
+                &post.is_sensitive,
+                &post.multi_select,
+                &options,
+                &post.closed,
+                &post.edited_at,
+            ],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
 }
 
-pub async fn get_post(conn: &PgConn, object_id: i64) -> Option<crate::db::PostType> {
-    todo!()
-    // let client = conn.db.get().await.expect("failed to get client");
-    // let stmt = r#"
-    // SELECT * FROM posts INNER JOIN unified_users ON actor = uid NATURAL JOIN internal_users WHERE obj_id = $1;
-    // "#;
-    // let stmt = client.prepare(stmt).await.unwrap();
-
-    // let result = client
-    //     .query(&stmt, &[&object_id])
-    //     .await
-    //     .expect("failed to get post")
-    //     .pop();
-    // let result = result?;
-
-    // let supertype: String = result.get("surtype");
-    // let supertype = PostSupertype::parse_str(&supertype).expect("unkown supertype in posts");
-    // match supertype {
-    //     PostSupertype::Object => {
-    //         let is_local: bool = result.get("is_local");
-    //         let (id, attributed_to) = match is_local {
-    //             true => {
-    //                 let preferred_username: String = result.get("preferred_username");
-    //                 let domain: String = result.get("domain");
-    //                 (
-    //                     format!(
-    //                         "https://{}/users/{}/statuses/{}",
-    //                         &domain, &preferred_username, object_id
-    //                     ),
-    //                     format!("https://{}/users/{}", &domain, &preferred_username),
-    //                 )
-    //             }
-    //             false => {
-    //                 // (result.get("posts.fedi_id"), )
-    //                 todo!()
-    //             }
-    //         };
-    //         let subtype: String = result.get("subtype");
-    //         let subtype: ObjectType =
-    //             serde_json::from_str(&subtype).expect("unkown object type stored in db");
-    //         // dbg!(&attributed_to);
-    //         let attributed_to = Url::parse(&attributed_to).expect("invalid attributed to");
-
-    //         let replied_obj: Option<i64> = result.get("in_reply_to");
-    //         let replied_obj: Option<String> = match replied_obj {
-    //             Some(_x) => {
-    //                 todo!()
-    //             }
-    //             None => None,
-    //         };
-
-    //         let object = Object::new(Url::parse(&id).unwrap(), attributed_to)
-    //             .content(result.get("content"))
-    //             .in_reply_to(replied_obj)
-    //             .published_milis(result.get("published"))
-    //             .to_public()
-    //             .wrap(subtype);
-
-    //         Some(crate::db::PostType::Object(object))
-    //     }
-    //     PostSupertype::Question => todo!(),
-    // }
+pub async fn get_post_revisions(conn: &PgConn, post: &str) -> Result<Vec<PostRevision>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM post_revisions WHERE post = $1 ORDER BY id ASC;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&post]).await?;
+    result.iter().map(to_revision).collect()
 }
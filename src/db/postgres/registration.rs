@@ -0,0 +1,200 @@
+use tokio_postgres::Row;
+
+use crate::db::{
+    conn::DbErr,
+    utility::{
+        permission::PermissionLevel,
+        registration::{Application, Invite, RegistrationMode},
+        stored_user::now_millis,
+    },
+};
+
+use super::pg_conn::PgConn;
+
+fn to_application(row: &Row) -> Application {
+    Application {
+        uid: row.get("uid"),
+        reason: row.get("reason"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_registration_mode(conn: &PgConn) -> Result<Option<RegistrationMode>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT mode FROM registration_mode;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[]).await?;
+    let mode: Option<String> = result.map(|x| x.get("mode"));
+    Ok(mode.and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_registration_mode(conn: &PgConn, mode: RegistrationMode) -> Result<(), DbErr> {
+    let mut client = conn.db.get().await?;
+    let transaction = client.transaction().await?;
+    let mode = serde_json::to_string(&mode).unwrap();
+    transaction
+        .execute("DELETE FROM registration_mode;", &[])
+        .await?;
+    transaction
+        .execute(
+            "INSERT INTO registration_mode (mode) VALUES ($1);",
+            &[&mode],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+pub async fn demote_unvetted_users(conn: &PgConn) -> Result<u64, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        UPDATE users SET permission_level = $1, approved = false
+        WHERE permission_level = $2 AND vetted = false
+        AND private_key_pem IS NOT NULL AND deleted = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let untrusted: i16 = PermissionLevel::UntrustedUser.into();
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    Ok(client.execute(&stmt, &[&untrusted, &trusted]).await?)
+}
+
+pub async fn promote_unvetted_users(conn: &PgConn) -> Result<u64, DbErr> {
+    let mut client = conn.db.get().await?;
+    let transaction = client.transaction().await?;
+    let untrusted: i16 = PermissionLevel::UntrustedUser.into();
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    transaction
+        .execute(
+            r#"
+            DELETE FROM applications WHERE uid IN (
+                SELECT uid FROM users
+                WHERE permission_level = $1 AND approved = false AND vetted = false
+                AND private_key_pem IS NOT NULL AND deleted = false
+            );
+            "#,
+            &[&untrusted],
+        )
+        .await?;
+    let updated = transaction
+        .execute(
+            r#"
+            UPDATE users SET permission_level = $2, approved = true
+            WHERE permission_level = $1 AND approved = false AND vetted = false
+            AND private_key_pem IS NOT NULL AND deleted = false;
+            "#,
+            &[&untrusted, &trusted],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(updated)
+}
+
+pub async fn approve_user(conn: &PgConn, uid: &str) -> Result<(), DbErr> {
+    let mut client = conn.db.get().await?;
+    let transaction = client.transaction().await?;
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    let updated = transaction
+        .execute(
+            r#"
+            UPDATE users SET approved = true, vetted = true,
+                permission_level = LEAST(COALESCE(permission_level, $2), $2)
+            WHERE uid = $1 AND private_key_pem IS NOT NULL AND deleted = false;
+            "#,
+            &[&uid, &trusted],
+        )
+        .await?;
+    if updated == 0 {
+        return Err(DbErr::NotFound);
+    }
+    transaction
+        .execute("DELETE FROM applications WHERE uid = $1;", &[&uid])
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+pub async fn insert_invite(conn: &PgConn, invite: &Invite) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO invites
+        (code, created_by, max_uses, uses, expires, created_at)
+        VALUES
+        ($1, $2, $3, $4, $5, $6);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[
+                &invite.code,
+                &invite.created_by,
+                &invite.max_uses,
+                &invite.uses,
+                &invite.expires,
+                &invite.created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn use_invite(conn: &PgConn, code: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        UPDATE invites SET uses = uses + 1
+        WHERE code = $1
+        AND (max_uses IS NULL OR uses < max_uses)
+        AND (expires IS NULL OR expires > $2);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let updated = client.execute(&stmt, &[&code, &now_millis()]).await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn insert_application(conn: &PgConn, application: &Application) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO applications
+        (uid, reason, created_at)
+        VALUES
+        ($1, $2, $3)
+        ON CONFLICT (uid) DO UPDATE SET reason = $2, created_at = $3;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[
+                &application.uid,
+                &application.reason,
+                &application.created_at,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_application(conn: &PgConn, uid: &str) -> Result<Option<Application>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM applications WHERE uid = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&uid]).await?;
+    Ok(result.as_ref().map(to_application))
+}
+
+pub async fn get_applications(conn: &PgConn) -> Result<Vec<Application>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM applications ORDER BY created_at ASC;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[]).await?;
+    Ok(result.iter().map(to_application).collect())
+}
@@ -0,0 +1,270 @@
+use tokio_postgres::Row;
+
+use crate::db::{
+    conn::DbErr,
+    utility::stored_user::{now_millis, StoredUser, PREVIOUS_KEY_GRACE_MILLIS},
+};
+
+use super::pg_conn::PgConn;
+
+pub fn to_user(row: &Row) -> StoredUser {
+    let permission_level: Option<i16> = row.get("permission_level");
+    StoredUser {
+        uid: row.get("uid"),
+        resource_link: row.get("resource_link"),
+        versia_id: row.get("versia_id"),
+        url: row.get("url"),
+        domain: row.get("domain"),
+        username: row.get("username"),
+        display_name: row.get("display_name"),
+        summary: row.get("summary"),
+        avatar: row.get("avatar"),
+        public_key_pem: row.get("public_key_pem"),
+        public_key_id: row.get("public_key_id"),
+        previous_public_key_pem: row.get("previous_public_key_pem"),
+        previous_key_expires: row.get("previous_key_expires"),
+        key_delegate: row.get("key_delegate"),
+        manual_followers: row.get("manual_followers"),
+        banned: row.get("banned"),
+        deleted: row.get("deleted"),
+        reason: row.get("reason"),
+        inbox: row.get("inbox"),
+        outbox: row.get("outbox"),
+        followers: row.get("followers"),
+        following: row.get("following"),
+        shared_inbox: row.get("shared_inbox"),
+        password: row.get("password"),
+        email: row.get("email"),
+        private_key_pem: row.get("private_key_pem"),
+        permission_level: permission_level.map(|x| x.into()),
+        approved: row.get("approved"),
+        vetted: row.get("vetted"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn insert_user(conn: &PgConn, user: &StoredUser) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO users
+        (
+            uid, resource_link, versia_id, url, domain, username,
+            display_name, summary, avatar, public_key_pem, public_key_id,
+            manual_followers, banned, reason, deleted,
+            inbox, outbox, followers, following, shared_inbox,
+            password, email, private_key_pem, permission_level, created_at,
+            key_delegate, approved, vetted
+        )
+        VALUES
+        (
+            $1, $2, $3, $4, $5, $6,
+            $7, $8, $24, $9, $10,
+            $11, $12, $13, $25,
+            $14, $15, $16, $17, $23,
+            $18, $19, $20, $21, $22,
+            $26, $27, $28
+        );
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let permission_level: Option<i16> = user.permission_level.map(|x| x.into());
+
+    client
+        .execute(
+            &stmt,
+            &[
+                &user.uid,
+                &user.resource_link,
+                &user.versia_id,
+                &user.url,
+                &user.domain,
+                &user.username,
+                &user.display_name,
+                &user.summary,
+                &user.public_key_pem,
+                &user.public_key_id,
+                &user.manual_followers,
+                &user.banned,
+                &user.reason,
+                &user.inbox,
+                &user.outbox,
+                &user.followers,
+                &user.following,
+                &user.password,
+                &user.email,
+                &user.private_key_pem,
+                &permission_level,
+                &user.created_at,
+                &user.shared_inbox,
+                &user.avatar,
+                &user.deleted,
+                &user.key_delegate,
+                &user.approved,
+                &user.vetted,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_user_by_username(
+    conn: &PgConn,
+    username: &str,
+    domain: &str,
+) -> Result<Option<StoredUser>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM users WHERE username = $1 AND domain = $2;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&username, &domain]).await?;
+    Ok(result.as_ref().map(to_user))
+}
+
+/// gets a user by their resource link or the id of their key
+pub async fn get_user_by_link(conn: &PgConn, link: &str) -> Result<Option<StoredUser>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM users WHERE resource_link = $1 OR public_key_id = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&link]).await?;
+    Ok(result.first().map(to_user))
+}
+
+pub async fn get_user_by_uid(conn: &PgConn, uid: &str) -> Result<Option<StoredUser>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM users WHERE uid = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query_opt(&stmt, &[&uid]).await?;
+    Ok(result.as_ref().map(to_user))
+}
+
+/// returns not found if no user was deleted
+pub async fn delete_user(conn: &PgConn, uid: &str) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        DELETE FROM users WHERE uid = $1;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    match client.execute(&stmt, &[&uid]).await? {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// returns not found if there is no federated user with the link
+/// a changed key is kept as the previous key for the grace period
+pub async fn update_federated_user(conn: &PgConn, user: &StoredUser) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let previous_key_expires = now_millis() + PREVIOUS_KEY_GRACE_MILLIS;
+    let stmt = r#"
+        UPDATE users SET
+            url = $2, username = $3, display_name = $4, summary = $5, avatar = $6,
+            previous_public_key_pem = CASE
+                WHEN public_key_pem = $7 THEN previous_public_key_pem ELSE public_key_pem END,
+            previous_key_expires = CASE
+                WHEN public_key_pem = $7 THEN previous_key_expires ELSE $15 END,
+            public_key_pem = $7, public_key_id = $8, manual_followers = $9,
+            inbox = $10, outbox = $11, followers = $12, following = $13, shared_inbox = $14,
+            key_delegate = $16
+        WHERE resource_link = $1 AND private_key_pem IS NULL;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let updated = client
+        .execute(
+            &stmt,
+            &[
+                &user.resource_link,
+                &user.url,
+                &user.username,
+                &user.display_name,
+                &user.summary,
+                &user.avatar,
+                &user.public_key_pem,
+                &user.public_key_id,
+                &user.manual_followers,
+                &user.inbox,
+                &user.outbox,
+                &user.followers,
+                &user.following,
+                &user.shared_inbox,
+                &previous_key_expires,
+                &user.key_delegate,
+            ],
+        )
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// returns not found if there is no local user with the uid
+pub async fn rotate_user_key(
+    conn: &PgConn,
+    uid: &str,
+    private_key_pem: &str,
+    public_key_pem: &str,
+) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        UPDATE users SET
+            previous_public_key_pem = public_key_pem, previous_key_expires = $4,
+            private_key_pem = $2, public_key_pem = $3
+        WHERE uid = $1 AND private_key_pem IS NOT NULL AND deleted = false;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let previous_key_expires = now_millis() + PREVIOUS_KEY_GRACE_MILLIS;
+    let updated = client
+        .execute(
+            &stmt,
+            &[
+                &uid,
+                &private_key_pem,
+                &public_key_pem,
+                &previous_key_expires,
+            ],
+        )
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// returns not found if there is no local user with the uid
+pub async fn tombstone_user(conn: &PgConn, uid: &str) -> Result<(), DbErr> {
+    let mut client = conn.db.get().await?;
+    let transaction = client.transaction().await?;
+    let updated = transaction
+        .execute(
+            r#"
+            UPDATE users SET
+                display_name = NULL, summary = NULL, avatar = NULL,
+                password = NULL, email = NULL, deleted = true
+            WHERE uid = $1 AND private_key_pem IS NOT NULL;
+            "#,
+            &[&uid],
+        )
+        .await?;
+    if updated == 0 {
+        return Err(DbErr::NotFound);
+    }
+    for stmt in [
+        "DELETE FROM posts WHERE actor = $1;",
+        "DELETE FROM likes WHERE actor = $1;",
+        "DELETE FROM following WHERE follower = $1 OR target_user = $1;",
+        "DELETE FROM oauth_codes WHERE uid = $1;",
+        "DELETE FROM oauth_tokens WHERE uid = $1;",
+        "DELETE FROM applications WHERE uid = $1;",
+        "DELETE FROM invites WHERE created_by = $1;",
+        "DELETE FROM notifications WHERE recipient = $1 OR actor = $1;",
+        "DELETE FROM notification_markers WHERE uid = $1;",
+    ] {
+        transaction.execute(stmt, &[&uid]).await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
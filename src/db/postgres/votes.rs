@@ -0,0 +1,40 @@
+use tokio_postgres::Row;
+
+use crate::db::{conn::DbErr, utility::polls::PollVote};
+
+use super::pg_conn::PgConn;
+
+fn to_vote(row: &Row) -> PollVote {
+    PollVote {
+        poll: row.get("poll"),
+        voter: row.get("voter"),
+        choice: row.get("choice"),
+        published: row.get("published"),
+    }
+}
+
+pub async fn insert_vote(conn: &PgConn, vote: &PollVote) -> Result<(), DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        INSERT INTO poll_votes (poll, voter, choice, published)
+        VALUES ($1, $2, $3, $4);
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    client
+        .execute(
+            &stmt,
+            &[&vote.poll, &vote.voter, &vote.choice, &vote.published],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_votes(conn: &PgConn, poll: &str) -> Result<Vec<PollVote>, DbErr> {
+    let client = conn.db.get().await?;
+    let stmt = r#"
+        SELECT * FROM poll_votes WHERE poll = $1 ORDER BY published ASC;
+        "#;
+    let stmt = client.prepare(stmt).await?;
+    let result = client.query(&stmt, &[&poll]).await?;
+    Ok(result.iter().map(to_vote).collect())
+}
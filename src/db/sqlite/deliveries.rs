@@ -0,0 +1,165 @@
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+use crate::db::{
+    conn::DbErr,
+    utility::{delivery::Delivery, stored_user::now_millis},
+};
+
+use super::sqlite_conn::SqliteConn;
+
+fn to_delivery(row: &Row) -> rusqlite::Result<Delivery> {
+    let protocol: String = row.get("protocol")?;
+    let protocol = serde_json::from_str(&protocol).map_err(|x| {
+        let index = row.as_ref().column_index("protocol").unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(x))
+    })?;
+    Ok(Delivery {
+        id: row.get("id")?,
+        inbox: row.get("inbox")?,
+        protocol,
+        body: row.get("body")?,
+        signer: row.get("signer")?,
+        attempts: row.get("attempts")?,
+        created_at: row.get("created_at")?,
+        next_attempt: row.get("next_attempt")?,
+        last_error: row.get("last_error")?,
+        dead: row.get("dead")?,
+    })
+}
+
+pub async fn insert_delivery(conn: &SqliteConn, delivery: &Delivery) -> Result<(), DbErr> {
+    let delivery = delivery.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO deliveries
+            (
+                id, inbox, protocol, body, signer,
+                attempts, created_at, next_attempt, last_error, dead
+            )
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);
+            "#;
+            let protocol = serde_json::to_string(&delivery.protocol).unwrap();
+            conn.execute(
+                stmt,
+                params![
+                    delivery.id,
+                    delivery.inbox,
+                    protocol,
+                    delivery.body,
+                    delivery.signer,
+                    delivery.attempts,
+                    delivery.created_at,
+                    delivery.next_attempt,
+                    delivery.last_error,
+                    delivery.dead,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_due_deliveries(
+    conn: &SqliteConn,
+    now: i64,
+    limit: u64,
+) -> Result<Vec<Delivery>, DbErr> {
+    let limit = limit as i64;
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM deliveries
+            WHERE dead = false AND next_attempt <= ?1
+            ORDER BY next_attempt ASC
+            LIMIT ?2;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let deliveries = stmt
+                .query_map(params![now, limit], to_delivery)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(deliveries)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn update_delivery(conn: &SqliteConn, delivery: &Delivery) -> Result<(), DbErr> {
+    let delivery = delivery.clone();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE deliveries
+            SET attempts = ?2, next_attempt = ?3, last_error = ?4, dead = ?5
+            WHERE id = ?1;
+            "#;
+            Ok(conn.execute(
+                stmt,
+                params![
+                    delivery.id,
+                    delivery.attempts,
+                    delivery.next_attempt,
+                    delivery.last_error,
+                    delivery.dead,
+                ],
+            )?)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn remove_delivery(conn: &SqliteConn, id: &str) -> Result<(), DbErr> {
+    let id = id.to_string();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM deliveries WHERE id = ?1;
+            "#;
+            conn.execute(stmt, [&id])?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn mark_inbox_dead(conn: &SqliteConn, inbox: &str) -> Result<(), DbErr> {
+    let inbox = inbox.to_string();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO dead_inboxes
+            (inbox, died_at)
+            VALUES
+            (?1, ?2)
+            ON CONFLICT (inbox) DO NOTHING;
+            "#;
+            conn.execute(stmt, params![inbox, now_millis()])?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn is_inbox_dead(conn: &SqliteConn, inbox: &str) -> Result<bool, DbErr> {
+    let inbox = inbox.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT inbox FROM dead_inboxes WHERE inbox = ?1;
+            "#;
+            let result = conn
+                .query_row(stmt, [&inbox], |row| row.get::<_, String>("inbox"))
+                .optional()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(result.is_some())
+}
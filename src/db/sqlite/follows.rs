@@ -0,0 +1,156 @@
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::{conn::DbErr, utility::stored_user::StoredUser};
+
+use super::{sqlite_conn::SqliteConn, users::to_user};
+
+pub async fn create_follow(
+    conn: &SqliteConn,
+    follower: &str,
+    target: &str,
+    pending: bool,
+    activity_id: Option<&str>,
+    published: i64,
+) -> Result<(), DbErr> {
+    let follower = follower.to_string();
+    let target = target.to_string();
+    let activity_id = activity_id.map(|x| x.to_string());
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO following
+            (follower, target_user, pending, activity_id, published)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5);
+            "#;
+            conn.execute(
+                stmt,
+                params![follower, target, pending, activity_id, published],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_followers(conn: &SqliteConn, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+    let uid = uid.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT users.* FROM following
+            JOIN users ON users.uid = following.follower
+            WHERE following.target_user = ?1 AND following.pending = false;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let followers = stmt
+                .query_map([&uid], to_user)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(followers)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn get_following(conn: &SqliteConn, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+    let uid = uid.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT users.* FROM following
+            JOIN users ON users.uid = following.target_user
+            WHERE following.follower = ?1 AND following.pending = false;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let following = stmt
+                .query_map([&uid], to_user)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(following)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn is_following(conn: &SqliteConn, follower: &str, target: &str) -> Result<bool, DbErr> {
+    let follower = follower.to_string();
+    let target = target.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT pending FROM following
+            WHERE follower = ?1 AND target_user = ?2 AND pending = false;
+            "#;
+            let result = conn
+                .query_row(stmt, [&follower, &target], |row| {
+                    row.get::<_, bool>("pending")
+                })
+                .optional()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(result.is_some())
+}
+
+pub async fn accept_follow(conn: &SqliteConn, follower: &str, target: &str) -> Result<(), DbErr> {
+    let follower = follower.to_string();
+    let target = target.to_string();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE following SET pending = false
+            WHERE follower = ?1 AND target_user = ?2;
+            "#;
+            Ok(conn.execute(stmt, params![follower, target])?)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn remove_follow(conn: &SqliteConn, follower: &str, target: &str) -> Result<(), DbErr> {
+    let follower = follower.to_string();
+    let target = target.to_string();
+    let removed = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM following
+            WHERE follower = ?1 AND target_user = ?2;
+            "#;
+            Ok(conn.execute(stmt, params![follower, target])?)
+        })
+        .await?;
+    match removed {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn remove_follow_activity(
+    conn: &SqliteConn,
+    follower: &str,
+    activity_id: &str,
+) -> Result<(), DbErr> {
+    let follower = follower.to_string();
+    let activity_id = activity_id.to_string();
+    let removed = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM following
+            WHERE follower = ?1 AND activity_id = ?2;
+            "#;
+            Ok(conn.execute(stmt, params![follower, activity_id])?)
+        })
+        .await?;
+    match removed {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
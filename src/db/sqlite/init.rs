@@ -0,0 +1,37 @@
+use super::sqlite_conn::SqliteConn;
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("./migrations_sqlite");
+}
+
+pub async fn init(conn: &SqliteConn) -> Result<(), String> {
+    let report = conn
+        .db
+        .call(|conn| {
+            embedded::migrations::runner()
+                .run(conn)
+                .map_err(|x| tokio_rusqlite::Error::Other(Box::new(x)))
+        })
+        .await;
+    match report {
+        Ok(x) => {
+            println!("migrations sucessful");
+            if x.applied_migrations().is_empty() {
+                println!("no migrations applied")
+            } else {
+                println!("applied migrations: ");
+                for migration in x.applied_migrations() {
+                    match migration.applied_on() {
+                        Some(x) => println!(" - {} applied {}", migration.name(), x),
+                        None => println!(" - {} applied N/A", migration.name()),
+                    }
+                }
+            }
+        }
+        Err(x) => {
+            return Err(x.to_string());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use rusqlite::OptionalExtension;
+
+use crate::db::{
+    conn::DbErr,
+    utility::{instance_actor::InstanceActor, stored_user::now_millis},
+};
+
+use super::sqlite_conn::SqliteConn;
+
+pub async fn get_instance_actor(conn: &SqliteConn) -> Result<Option<InstanceActor>, DbErr> {
+    let result = conn
+        .db
+        .call(|conn| {
+            let stmt = r#"
+            SELECT * FROM ap_instance_actor;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let result = stmt
+                .query_row([], |row| {
+                    let versia_private_key_pem: Option<String> =
+                        row.get("versia_private_key_pem")?;
+                    let versia_public_key_pem: Option<String> = row.get("versia_public_key_pem")?;
+                    let created_at: Option<i64> = row.get("created_at")?;
+                    Ok((
+                        InstanceActor {
+                            private_key_pem: row.get("private_key_pem")?,
+                            public_key_pem: row.get("public_key_pem")?,
+                            versia_private_key_pem: versia_private_key_pem
+                                .clone()
+                                .unwrap_or_default(),
+                            versia_public_key_pem: versia_public_key_pem.unwrap_or_default(),
+                            previous_public_key_pem: row.get("previous_public_key_pem")?,
+                            previous_versia_public_key_pem: row
+                                .get("previous_versia_public_key_pem")?,
+                            previous_key_expires: row.get("previous_key_expires")?,
+                            created_at: created_at.unwrap_or_else(now_millis),
+                        },
+                        versia_private_key_pem.is_some(),
+                    ))
+                })
+                .optional()?;
+            Ok(result)
+        })
+        .await?;
+    let Some((mut actor, has_versia_key)) = result else {
+        return Ok(None);
+    };
+    // instance actors created before the versia key existed
+    if !has_versia_key {
+        actor.generate_versia_key();
+        update_instance_actor(conn, &actor).await?;
+    }
+    Ok(Some(actor))
+}
+
+pub async fn create_instance_actor(conn: &SqliteConn) -> Result<InstanceActor, DbErr> {
+    let actor = InstanceActor::generate();
+
+    let inserted = actor.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO ap_instance_actor
+            (private_key_pem, public_key_pem, versia_private_key_pem, versia_public_key_pem, created_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5);
+            "#;
+            conn.execute(
+                stmt,
+                (
+                    &inserted.private_key_pem,
+                    &inserted.public_key_pem,
+                    &inserted.versia_private_key_pem,
+                    &inserted.versia_public_key_pem,
+                    &inserted.created_at,
+                ),
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(actor)
+}
+
+/// replaces the keys of the instance actor
+pub async fn update_instance_actor(conn: &SqliteConn, actor: &InstanceActor) -> Result<(), DbErr> {
+    let actor = actor.clone();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE ap_instance_actor SET
+                private_key_pem = ?1, public_key_pem = ?2,
+                versia_private_key_pem = ?3, versia_public_key_pem = ?4, created_at = ?5,
+                previous_public_key_pem = ?6, previous_versia_public_key_pem = ?7,
+                previous_key_expires = ?8;
+            "#;
+            Ok(conn.execute(
+                stmt,
+                (
+                    &actor.private_key_pem,
+                    &actor.public_key_pem,
+                    &actor.versia_private_key_pem,
+                    &actor.versia_public_key_pem,
+                    &actor.created_at,
+                    &actor.previous_public_key_pem,
+                    &actor.previous_versia_public_key_pem,
+                    &actor.previous_key_expires,
+                ),
+            )?)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,149 @@
+use rusqlite::OptionalExtension;
+
+use crate::db::{
+    conn::DbErr,
+    utility::protocols::{Protocol, SignatureScheme},
+};
+
+use super::sqlite_conn::SqliteConn;
+
+/// creates the instance if it doesn't exist yet. users and posts
+/// reference their instance so this must be done before inserting them
+pub async fn ensure_instance(conn: &SqliteConn, domain: &str, is_local: bool) -> Result<(), DbErr> {
+    let domain = domain.to_string();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO instances
+            (domain, is_primary, is_authoratative)
+            VALUES
+            (?1, ?2, ?2)
+            ON CONFLICT (domain) DO NOTHING;
+            "#;
+            conn.execute(stmt, (&domain, is_local))?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_protocol(conn: &SqliteConn, domain: &str) -> Result<Option<Protocol>, DbErr> {
+    let domain = domain.to_string();
+    let protocol: Option<Option<String>> = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT protocol FROM instances WHERE domain = ?1;
+            "#;
+            let result = conn
+                .query_row(stmt, [&domain], |row| row.get("protocol"))
+                .optional()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(protocol
+        .flatten()
+        .and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_protocol(
+    conn: &SqliteConn,
+    domain: &str,
+    protocol: Protocol,
+) -> Result<(), DbErr> {
+    let domain = domain.to_string();
+    let protocol = serde_json::to_string(&protocol).unwrap();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO instances
+            (domain, protocol)
+            VALUES
+            (?1, ?2)
+            ON CONFLICT (domain) DO UPDATE SET protocol = ?2;
+            "#;
+            conn.execute(stmt, (&domain, &protocol))?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_instances(conn: &SqliteConn, protocol: Protocol) -> Result<Vec<String>, DbErr> {
+    let protocol = serde_json::to_string(&protocol).unwrap();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT domain FROM instances WHERE protocol = ?1 AND is_primary = false;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let result = stmt
+                .query_map([&protocol], |row| row.get("domain"))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn get_shared_inboxes(conn: &SqliteConn) -> Result<Vec<String>, DbErr> {
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT DISTINCT shared_inbox FROM users
+            WHERE shared_inbox IS NOT NULL AND private_key_pem IS NULL;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let result = stmt
+                .query_map([], |row| row.get("shared_inbox"))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn get_signature_scheme(
+    conn: &SqliteConn,
+    domain: &str,
+) -> Result<Option<SignatureScheme>, DbErr> {
+    let domain = domain.to_string();
+    let scheme: Option<Option<String>> = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT signature_scheme FROM instances WHERE domain = ?1;
+            "#;
+            let result = conn
+                .query_row(stmt, [&domain], |row| row.get("signature_scheme"))
+                .optional()?;
+            Ok(result)
+        })
+        .await?;
+    Ok(scheme.flatten().and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_signature_scheme(
+    conn: &SqliteConn,
+    domain: &str,
+    scheme: SignatureScheme,
+) -> Result<(), DbErr> {
+    let domain = domain.to_string();
+    let scheme = serde_json::to_string(&scheme).unwrap();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO instances
+            (domain, signature_scheme)
+            VALUES
+            (?1, ?2)
+            ON CONFLICT (domain) DO UPDATE SET signature_scheme = ?2;
+            "#;
+            conn.execute(stmt, (&domain, &scheme))?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use rusqlite::params;
+
+use crate::db::{conn::DbErr, utility::stored_user::now_millis};
+
+use super::sqlite_conn::SqliteConn;
+
+pub async fn remove_like(conn: &SqliteConn, actor: &str, url: &str) -> Result<(), DbErr> {
+    let actor = actor.to_string();
+    let url = url.to_string();
+    let removed = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM likes WHERE actor = ?1 AND url = ?2;
+            "#;
+            Ok(conn.execute(stmt, [&actor, &url])?)
+        })
+        .await?;
+    match removed {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// the like is identified by the link of its activity
+pub async fn insert_like(
+    conn: &SqliteConn,
+    actor: &str,
+    post: &str,
+    url: &str,
+) -> Result<(), DbErr> {
+    let actor = actor.to_string();
+    let post = post.to_string();
+    let url = url.to_string();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO likes (id, url, actor, post, published)
+            VALUES (?1, ?1, ?2, ?3, ?4);
+            "#;
+            conn.execute(stmt, params![url, actor, post, now_millis()])?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+mod deliveries;
+mod follows;
+mod init;
+mod instance_actor;
+mod instances;
+mod likes;
+mod notifications;
+mod oauth;
+mod posts;
+mod registration;
+pub mod sqlite_conn;
+mod users;
+mod votes;
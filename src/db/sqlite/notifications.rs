@@ -0,0 +1,146 @@
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+use crate::db::{
+    conn::DbErr,
+    utility::notifications::{Notification, NotificationMarker, NotificationQuery},
+};
+
+use super::sqlite_conn::SqliteConn;
+
+fn to_notification(row: &Row) -> rusqlite::Result<Notification> {
+    let kind: String = row.get("kind")?;
+    let kind = serde_json::from_str(&kind).map_err(|x| {
+        let index = row.as_ref().column_index("kind").unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(x))
+    })?;
+    Ok(Notification {
+        id: row.get("id")?,
+        recipient: row.get("recipient")?,
+        kind,
+        actor: row.get("actor")?,
+        post: row.get("post")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn insert_notification(
+    conn: &SqliteConn,
+    notification: &Notification,
+) -> Result<(), DbErr> {
+    let notification = notification.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO notifications
+            (id, recipient, kind, actor, post, created_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6);
+            "#;
+            let kind = serde_json::to_string(&notification.kind).unwrap();
+            conn.execute(
+                stmt,
+                params![
+                    notification.id,
+                    notification.recipient,
+                    kind,
+                    notification.actor,
+                    notification.post,
+                    notification.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+/// newest first, the limit has already been capped. the kinds are
+/// passed as a json array as sqlite has no arrays
+pub async fn get_notifications(
+    conn: &SqliteConn,
+    uid: &str,
+    query: &NotificationQuery,
+) -> Result<Vec<Notification>, DbErr> {
+    let ascending = query.adjacent && query.after.is_some();
+    let order = match ascending {
+        true => "ASC",
+        false => "DESC",
+    };
+    let stmt = format!(
+        r#"
+        SELECT * FROM notifications
+        WHERE recipient = ?1
+        AND (?2 IS NULL OR id < ?2)
+        AND (?3 IS NULL OR id > ?3)
+        AND (?4 IS NULL OR kind IN (SELECT value FROM json_each(?4)))
+        ORDER BY id {order}
+        LIMIT ?5;
+        "#
+    );
+    let uid = uid.to_string();
+    let before = query.before.clone();
+    let after = query.after.clone();
+    let kinds = query.kinds.as_ref().map(|x| {
+        let kinds: Vec<String> = x
+            .iter()
+            .map(|kind| serde_json::to_string(kind).unwrap())
+            .collect();
+        serde_json::to_string(&kinds).unwrap()
+    });
+    let limit = query.limit as i64;
+    let mut result = conn
+        .db
+        .call(move |conn| {
+            let mut stmt = conn.prepare(&stmt)?;
+            let notifications = stmt
+                .query_map(params![uid, before, after, kinds, limit], to_notification)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(notifications)
+        })
+        .await?;
+    if ascending {
+        result.reverse();
+    }
+    Ok(result)
+}
+
+pub async fn get_marker(conn: &SqliteConn, uid: &str) -> Result<Option<NotificationMarker>, DbErr> {
+    let uid = uid.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM notification_markers WHERE uid = ?1;
+            "#;
+            Ok(conn
+                .query_row(stmt, [&uid], |row| {
+                    Ok(NotificationMarker {
+                        uid: row.get("uid")?,
+                        last_read_id: row.get("last_read_id")?,
+                        updated_at: row.get("updated_at")?,
+                    })
+                })
+                .optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn set_marker(conn: &SqliteConn, marker: &NotificationMarker) -> Result<(), DbErr> {
+    let marker = marker.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO notification_markers (uid, last_read_id, updated_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (uid) DO UPDATE SET last_read_id = ?2, updated_at = ?3;
+            "#;
+            conn.execute(
+                stmt,
+                params![marker.uid, marker.last_read_id, marker.updated_at],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+
+use crate::db::{
+    conn::DbErr,
+    utility::oauth::{OAuthApp, OAuthCode, OAuthToken, Scopes},
+};
+
+use super::sqlite_conn::SqliteConn;
+
+fn to_scopes(row: &Row) -> rusqlite::Result<Scopes> {
+    let scopes: String = row.get("scopes")?;
+    Scopes::parse(&scopes).ok_or_else(|| {
+        let index = row.as_ref().column_index("scopes").unwrap_or_default();
+        rusqlite::Error::InvalidColumnType(index, "scopes".to_string(), Type::Text)
+    })
+}
+
+fn to_app(row: &Row) -> rusqlite::Result<OAuthApp> {
+    let redirect_uris: String = row.get("redirect_uris")?;
+    Ok(OAuthApp {
+        client_id: row.get("client_id")?,
+        client_secret: row.get("client_secret")?,
+        name: row.get("name")?,
+        redirect_uris: redirect_uris
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        scopes: to_scopes(row)?,
+        website: row.get("website")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn to_code(row: &Row) -> rusqlite::Result<OAuthCode> {
+    Ok(OAuthCode {
+        code: row.get("code")?,
+        client_id: row.get("client_id")?,
+        uid: row.get("uid")?,
+        redirect_uri: row.get("redirect_uri")?,
+        scopes: to_scopes(row)?,
+        code_challenge: row.get("code_challenge")?,
+        code_challenge_method: row.get("code_challenge_method")?,
+        expires: row.get("expires")?,
+    })
+}
+
+fn to_token(row: &Row) -> rusqlite::Result<OAuthToken> {
+    Ok(OAuthToken {
+        token_hash: row.get("token_hash")?,
+        client_id: row.get("client_id")?,
+        uid: row.get("uid")?,
+        scopes: to_scopes(row)?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn insert_app(conn: &SqliteConn, app: &OAuthApp) -> Result<(), DbErr> {
+    let app = app.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO oauth_apps
+            (client_id, client_secret, name, redirect_uris, scopes, website, created_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+            "#;
+            conn.execute(
+                stmt,
+                params![
+                    app.client_id,
+                    app.client_secret,
+                    app.name,
+                    app.redirect_uris.join(" "),
+                    app.scopes.to_string(),
+                    app.website,
+                    app.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_app(conn: &SqliteConn, client_id: &str) -> Result<Option<OAuthApp>, DbErr> {
+    let client_id = client_id.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM oauth_apps WHERE client_id = ?1;
+            "#;
+            Ok(conn.query_row(stmt, [&client_id], to_app).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn insert_code(conn: &SqliteConn, code: &OAuthCode) -> Result<(), DbErr> {
+    let code = code.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO oauth_codes
+            (
+                code, client_id, uid, redirect_uri, scopes,
+                code_challenge, code_challenge_method, expires
+            )
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
+            "#;
+            conn.execute(
+                stmt,
+                params![
+                    code.code,
+                    code.client_id,
+                    code.uid,
+                    code.redirect_uri,
+                    code.scopes.to_string(),
+                    code.code_challenge,
+                    code.code_challenge_method,
+                    code.expires,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn take_code(conn: &SqliteConn, code: &str) -> Result<Option<OAuthCode>, DbErr> {
+    let code = code.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM oauth_codes WHERE code = ?1 RETURNING *;
+            "#;
+            Ok(conn.query_row(stmt, [&code], to_code).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn insert_token(conn: &SqliteConn, token: &OAuthToken) -> Result<(), DbErr> {
+    let token = token.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO oauth_tokens
+            (token_hash, client_id, uid, scopes, created_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5);
+            "#;
+            conn.execute(
+                stmt,
+                params![
+                    token.token_hash,
+                    token.client_id,
+                    token.uid,
+                    token.scopes.to_string(),
+                    token.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_token(conn: &SqliteConn, token_hash: &str) -> Result<Option<OAuthToken>, DbErr> {
+    let token_hash = token_hash.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM oauth_tokens WHERE token_hash = ?1;
+            "#;
+            Ok(conn.query_row(stmt, [&token_hash], to_token).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn delete_token(conn: &SqliteConn, token_hash: &str) -> Result<(), DbErr> {
+    let token_hash = token_hash.to_string();
+    let deleted = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM oauth_tokens WHERE token_hash = ?1;
+            "#;
+            Ok(conn.execute(stmt, [&token_hash])?)
+        })
+        .await?;
+    match deleted {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
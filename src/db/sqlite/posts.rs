@@ -0,0 +1,424 @@
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+
+use crate::db::{
+    conn::DbErr,
+    utility::{
+        revisions::PostRevision,
+        stored_post::StoredPost,
+        timeline::{Timeline, TimelineQuery},
+    },
+};
+
+use super::sqlite_conn::SqliteConn;
+
+fn from_json<T: DeserializeOwned>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let value: String = row.get(column)?;
+    serde_json::from_str(&value).map_err(|x| {
+        let index = row.as_ref().column_index(column).unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(x))
+    })
+}
+
+fn to_post(row: &Row) -> rusqlite::Result<StoredPost> {
+    let options: Option<String> = row.get("options")?;
+    let options = match options {
+        Some(_) => Some(from_json(row, "options")?),
+        None => None,
+    };
+    Ok(StoredPost {
+        id: row.get("id")?,
+        versia_id: row.get("versia_id")?,
+        domain: row.get("domain")?,
+        surtype: from_json(row, "surtype")?,
+        subtype: row.get("subtype")?,
+        category: from_json(row, "category")?,
+        likes: row.get("likes")?,
+        boosts: row.get("boosts")?,
+        local_only: row.get("local_only")?,
+        followers_only: row.get("followers_only")?,
+        published: row.get("published")?,
+        is_reply: row.get("is_reply")?,
+        in_reply_to: row.get("in_reply_to")?,
+        content: row.get("content")?,
+        subject: row.get("subject")?,
+        is_sensitive: row.get("is_sensitive")?,
+        shared: row.get("shared")?,
+        multi_select: row.get("multi_select")?,
+        options,
+        closed: row.get("closed")?,
+        local_only_voting: row.get("local_only_voting")?,
+        edited_at: row.get("edited_at")?,
+        actor: row.get("actor")?,
+    })
+}
+
+fn to_revision(row: &Row) -> rusqlite::Result<PostRevision> {
+    let options: Option<String> = row.get("options")?;
+    let options = match options {
+        Some(_) => Some(from_json(row, "options")?),
+        None => None,
+    };
+    Ok(PostRevision {
+        id: row.get("id")?,
+        post: row.get("post")?,
+        content: row.get("content")?,
+        subject: row.get("subject")?,
+        is_sensitive: row.get("is_sensitive")?,
+        options,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn insert_post(conn: &SqliteConn, post: &StoredPost) -> Result<(), DbErr> {
+    let post = post.clone();
+    conn.db
+        .call(move |conn| {
+            // replies to posts we don't have are still stored as replies
+            // but without the link to the parent
+            let stmt = r#"
+            INSERT INTO posts
+            (
+                id, versia_id, domain, surtype, subtype, category,
+                local_only, followers_only, published,
+                is_reply, in_reply_to,
+                content, subject, is_sensitive, shared,
+                multi_select, options, closed, local_only_voting,
+                edited_at, actor
+            )
+            VALUES
+            (
+                ?1, ?2, ?3, ?4, ?5, ?6,
+                ?7, ?8, ?9,
+                ?10, (SELECT id FROM posts WHERE id = ?11),
+                ?12, ?13, ?14, ?15,
+                ?16, ?17, ?18, ?19,
+                ?20, ?21
+            );
+            "#;
+            let surtype = serde_json::to_string(&post.surtype).unwrap();
+            let category = serde_json::to_string(&post.category).unwrap();
+            let options = post
+                .options
+                .as_ref()
+                .map(|x| serde_json::to_string(x).unwrap());
+            conn.execute(
+                stmt,
+                params![
+                    post.id,
+                    post.versia_id,
+                    post.domain,
+                    surtype,
+                    post.subtype,
+                    category,
+                    post.local_only,
+                    post.followers_only,
+                    post.published,
+                    post.is_reply,
+                    post.in_reply_to,
+                    post.content,
+                    post.subject,
+                    post.is_sensitive,
+                    post.shared,
+                    post.multi_select,
+                    options,
+                    post.closed,
+                    post.local_only_voting,
+                    post.edited_at,
+                    post.actor,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+/// gets a local post by its uuid
+pub async fn get_local_post(
+    conn: &SqliteConn,
+    pid: &str,
+    domain: &str,
+) -> Result<Option<StoredPost>, DbErr> {
+    let pid = pid.to_string();
+    let domain = domain.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM posts WHERE versia_id = ?1 AND domain = ?2;
+            "#;
+            Ok(conn.query_row(stmt, [&pid, &domain], to_post).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn get_post(conn: &SqliteConn, id: &str) -> Result<Option<StoredPost>, DbErr> {
+    let id = id.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM posts WHERE id = ?1;
+            "#;
+            Ok(conn.query_row(stmt, [&id], to_post).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+/// only includes posts visible to the public. page is one based
+pub async fn get_user_posts(
+    conn: &SqliteConn,
+    uid: &str,
+    page_size: u64,
+    page: u64,
+) -> Result<Vec<StoredPost>, DbErr> {
+    let uid = uid.to_string();
+    let limit = page_size as i64;
+    let offset = (page.saturating_sub(1) * page_size) as i64;
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM posts
+            WHERE actor = ?1 AND local_only = false AND followers_only = false
+            ORDER BY published DESC
+            LIMIT ?2 OFFSET ?3;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let posts = stmt
+                .query_map(params![uid, limit, offset], to_post)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(posts)
+        })
+        .await?;
+    Ok(result)
+}
+
+/// newest first. local and federated timelines share a query,
+/// the local one being limited to our domain
+pub async fn get_timeline(
+    conn: &SqliteConn,
+    timeline: &Timeline<'_>,
+    query: &TimelineQuery,
+) -> Result<Vec<StoredPost>, DbErr> {
+    let (scope, subject) = match timeline {
+        Timeline::Home(uid) => (
+            r#"(actor = ?6 OR actor IN (
+                SELECT target_user FROM following WHERE follower = ?6 AND pending = false
+            ))"#,
+            Some(uid.to_string()),
+        ),
+        Timeline::Local => (
+            "followers_only = false AND (?6 IS NULL OR domain = ?6)",
+            Some(conn.instance_domain.clone()),
+        ),
+        Timeline::Federated => (
+            "followers_only = false AND (?6 IS NULL OR domain = ?6)",
+            None,
+        ),
+    };
+    let ascending = query.adjacent && query.after.is_some();
+    let order = match ascending {
+        true => "ASC",
+        false => "DESC",
+    };
+    let stmt = format!(
+        r#"
+        SELECT * FROM posts
+        WHERE {scope}
+        AND (local_only = false OR ?1)
+        AND (?2 IS NULL OR (published, id) < (?2, ?3))
+        AND (?4 IS NULL OR (published, id) > (?4, ?5))
+        ORDER BY published {order}, id {order}
+        LIMIT ?7;
+        "#
+    );
+    let show_local_only = matches!(timeline, Timeline::Home(_)) || query.viewer.is_some();
+    let before = query.before.clone();
+    let after = query.after.clone();
+    let limit = query.limit as i64;
+    let mut result = conn
+        .db
+        .call(move |conn| {
+            let mut stmt = conn.prepare(&stmt)?;
+            let posts = stmt
+                .query_map(
+                    params![
+                        show_local_only,
+                        before.as_ref().map(|x| x.published),
+                        before.as_ref().map(|x| x.id.as_str()),
+                        after.as_ref().map(|x| x.published),
+                        after.as_ref().map(|x| x.id.as_str()),
+                        subject,
+                        limit,
+                    ],
+                    to_post,
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(posts)
+        })
+        .await?;
+    if ascending {
+        result.reverse();
+    }
+    Ok(result)
+}
+
+pub async fn count_user_posts(conn: &SqliteConn, uid: &str) -> Result<u64, DbErr> {
+    let uid = uid.to_string();
+    let count: i64 = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT COUNT(*) FROM posts
+            WHERE actor = ?1 AND local_only = false AND followers_only = false;
+            "#;
+            Ok(conn.query_row(stmt, [&uid], |row| row.get(0))?)
+        })
+        .await?;
+    Ok(count as u64)
+}
+
+/// returns not found if no post was deleted
+pub async fn delete_post(conn: &SqliteConn, id: &str, domain: &str) -> Result<(), DbErr> {
+    let id = id.to_string();
+    let domain = domain.to_string();
+    let deleted = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            DELETE FROM posts WHERE id = ?1 AND domain = ?2;
+            "#;
+            Ok(conn.execute(stmt, [&id, &domain])?)
+        })
+        .await?;
+    match deleted {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// every poll closed by the given time that wasn't marked as ended yet
+pub async fn get_ended_polls(conn: &SqliteConn, now: i64) -> Result<Vec<StoredPost>, DbErr> {
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM posts
+            WHERE closed IS NOT NULL AND closed <= ?1 AND end_notified = false;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let posts = stmt
+                .query_map([now], to_post)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(posts)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn mark_poll_ended(conn: &SqliteConn, id: &str) -> Result<(), DbErr> {
+    let id = id.to_string();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE posts SET end_notified = true WHERE id = ?1;
+            "#;
+            Ok(conn.execute(stmt, [&id])?)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// returns not found if the author has no post with the id
+pub async fn edit_post(conn: &SqliteConn, post: &StoredPost) -> Result<(), DbErr> {
+    let post = post.clone();
+    let edited = conn
+        .db
+        .call(move |conn| {
+            let transaction = conn.transaction()?;
+            let existing = transaction
+                .query_row(
+                    "SELECT * FROM posts WHERE id = ?1 AND actor = ?2;",
+                    [&post.id, &post.actor],
+                    to_post,
+                )
+                .optional()?;
+            let Some(existing) = existing else {
+                return Ok(false);
+            };
+            let revision = PostRevision::new(&existing);
+            transaction.execute(
+                r#"
+                INSERT INTO post_revisions
+                (id, post, content, subject, is_sensitive, options, created_at)
+                VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+                "#,
+                params![
+                    revision.id,
+                    revision.post,
+                    revision.content,
+                    revision.subject,
+                    revision.is_sensitive,
+                    revision
+                        .options
+                        .as_ref()
+                        .map(|x| serde_json::to_string(x).unwrap()),
+                    revision.created_at,
+                ],
+            )?;
+            transaction.execute(
+                r#"
+                UPDATE posts SET
+                    content = ?2, subject = ?3, is_sensitive = ?4,
+                    multi_select = ?5, options = ?6, closed = ?7, edited_at = ?8
+                WHERE id = ?1;
+                "#,
+                params![
+                    post.id,
+                    post.content,
+                    post.subject,
+                    post.is_sensitive,
+                    post.multi_select,
+                    post.options
+                        .as_ref()
+                        .map(|x| serde_json::to_string(x).unwrap()),
+                    post.closed,
+                    post.edited_at,
+                ],
+            )?;
+            transaction.commit()?;
+            Ok(true)
+        })
+        .await?;
+    match edited {
+        true => Ok(()),
+        false => Err(DbErr::NotFound),
+    }
+}
+
+pub async fn get_post_revisions(conn: &SqliteConn, post: &str) -> Result<Vec<PostRevision>, DbErr> {
+    let post = post.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM post_revisions WHERE post = ?1 ORDER BY id ASC;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let revisions = stmt
+                .query_map([&post], to_revision)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(revisions)
+        })
+        .await?;
+    Ok(result)
+}
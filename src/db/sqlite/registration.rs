@@ -0,0 +1,224 @@
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::db::{
+    conn::DbErr,
+    utility::{
+        permission::PermissionLevel,
+        registration::{Application, Invite, RegistrationMode},
+        stored_user::now_millis,
+    },
+};
+
+use super::sqlite_conn::SqliteConn;
+
+fn to_application(row: &Row) -> rusqlite::Result<Application> {
+    Ok(Application {
+        uid: row.get("uid")?,
+        reason: row.get("reason")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn get_registration_mode(conn: &SqliteConn) -> Result<Option<RegistrationMode>, DbErr> {
+    let mode = conn
+        .db
+        .call(|conn| {
+            let stmt = r#"
+            SELECT mode FROM registration_mode;
+            "#;
+            Ok(conn
+                .query_row(stmt, [], |row| row.get::<_, String>("mode"))
+                .optional()?)
+        })
+        .await?;
+    Ok(mode.and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+pub async fn set_registration_mode(conn: &SqliteConn, mode: RegistrationMode) -> Result<(), DbErr> {
+    let mode = serde_json::to_string(&mode).unwrap();
+    conn.db
+        .call(move |conn| {
+            let transaction = conn.transaction()?;
+            transaction.execute("DELETE FROM registration_mode;", [])?;
+            transaction.execute("INSERT INTO registration_mode (mode) VALUES (?1);", [&mode])?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn demote_unvetted_users(conn: &SqliteConn) -> Result<u64, DbErr> {
+    let untrusted: i16 = PermissionLevel::UntrustedUser.into();
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE users SET permission_level = ?1, approved = false
+            WHERE permission_level = ?2 AND vetted = false
+            AND private_key_pem IS NOT NULL AND deleted = false;
+            "#;
+            Ok(conn.execute(stmt, params![untrusted, trusted])?)
+        })
+        .await?;
+    Ok(updated as u64)
+}
+
+pub async fn promote_unvetted_users(conn: &SqliteConn) -> Result<u64, DbErr> {
+    let untrusted: i16 = PermissionLevel::UntrustedUser.into();
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let transaction = conn.transaction()?;
+            transaction.execute(
+                r#"
+                DELETE FROM applications WHERE uid IN (
+                    SELECT uid FROM users
+                    WHERE permission_level = ?1 AND approved = false AND vetted = false
+                    AND private_key_pem IS NOT NULL AND deleted = false
+                );
+                "#,
+                params![untrusted],
+            )?;
+            let updated = transaction.execute(
+                r#"
+                UPDATE users SET permission_level = ?2, approved = true
+                WHERE permission_level = ?1 AND approved = false AND vetted = false
+                AND private_key_pem IS NOT NULL AND deleted = false;
+                "#,
+                params![untrusted, trusted],
+            )?;
+            transaction.commit()?;
+            Ok(updated)
+        })
+        .await?;
+    Ok(updated as u64)
+}
+
+pub async fn approve_user(conn: &SqliteConn, uid: &str) -> Result<(), DbErr> {
+    let uid = uid.to_string();
+    let trusted: i16 = PermissionLevel::TrustedUser.into();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let transaction = conn.transaction()?;
+            let updated = transaction.execute(
+                r#"
+                UPDATE users SET approved = true, vetted = true,
+                    permission_level = MIN(COALESCE(permission_level, ?2), ?2)
+                WHERE uid = ?1 AND private_key_pem IS NOT NULL AND deleted = false;
+                "#,
+                params![uid, trusted],
+            )?;
+            transaction.execute("DELETE FROM applications WHERE uid = ?1;", [&uid])?;
+            transaction.commit()?;
+            Ok(updated)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn insert_invite(conn: &SqliteConn, invite: &Invite) -> Result<(), DbErr> {
+    let invite = invite.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO invites
+            (code, created_by, max_uses, uses, expires, created_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6);
+            "#;
+            conn.execute(
+                stmt,
+                params![
+                    invite.code,
+                    invite.created_by,
+                    invite.max_uses,
+                    invite.uses,
+                    invite.expires,
+                    invite.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn use_invite(conn: &SqliteConn, code: &str) -> Result<(), DbErr> {
+    let code = code.to_string();
+    let now = now_millis();
+    let updated = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            UPDATE invites SET uses = uses + 1
+            WHERE code = ?1
+            AND (max_uses IS NULL OR uses < max_uses)
+            AND (expires IS NULL OR expires > ?2);
+            "#;
+            Ok(conn.execute(stmt, params![code, now])?)
+        })
+        .await?;
+    match updated {
+        0 => Err(DbErr::NotFound),
+        _ => Ok(()),
+    }
+}
+
+pub async fn insert_application(conn: &SqliteConn, application: &Application) -> Result<(), DbErr> {
+    let application = application.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO applications
+            (uid, reason, created_at)
+            VALUES
+            (?1, ?2, ?3)
+            ON CONFLICT (uid) DO UPDATE SET reason = ?2, created_at = ?3;
+            "#;
+            conn.execute(
+                stmt,
+                params![application.uid, application.reason, application.created_at],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_application(conn: &SqliteConn, uid: &str) -> Result<Option<Application>, DbErr> {
+    let uid = uid.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM applications WHERE uid = ?1;
+            "#;
+            Ok(conn.query_row(stmt, [&uid], to_application).optional()?)
+        })
+        .await?;
+    Ok(result)
+}
+
+pub async fn get_applications(conn: &SqliteConn) -> Result<Vec<Application>, DbErr> {
+    let result = conn
+        .db
+        .call(|conn| {
+            let stmt = r#"
+            SELECT * FROM applications ORDER BY created_at ASC;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let applications = stmt
+                .query_map([], to_application)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(applications)
+        })
+        .await?;
+    Ok(result)
+}
@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use rusqlite::ErrorCode;
+use url::Url;
+
+use crate::{
+    cryptography::openssl::OpenSSLPublic,
+    db::{
+        conn::{Conn, DbErr, EntityOrigin, InsertErr},
+        utility::{
+            backfill::{
+                detect_protocol, fetch_federated_handle, fetch_federated_user,
+                refresh_federated_user, versia_key_to_openssl,
+            },
+            delivery::Delivery,
+            instance_actor::InstanceActor,
+            new_actor::{local_post_id, local_username, versia_post_link, NewLocal},
+            notifications::{
+                Notification, NotificationMarker, NotificationQuery, MAX_NOTIFICATION_LIMIT,
+            },
+            oauth::{OAuthApp, OAuthCode, OAuthToken},
+            polls::PollVote,
+            protocols::{Protocol, SignatureScheme},
+            registration::{Application, Invite, RegistrationMode},
+            revisions::PostRevision,
+            stored_post::StoredPost,
+            stored_user::{new_uid, now_millis, StoredUser},
+            timeline::{Timeline, TimelineQuery, MAX_TIMELINE_LIMIT},
+        },
+    },
+    protocols::{
+        protocol::versia_protocol::{discovery::fetch_instance_metadata, requests::Signer},
+        types::{
+            activitystream_objects::{actors::Actor, postable::ApPostable},
+            versia_types::{
+                entities::{instance_metadata::InstanceMetadata, user::User},
+                postable::VersiaPostable,
+            },
+        },
+    },
+};
+
+use super::{
+    deliveries, follows, init, instance_actor, instances, likes, notifications, oauth, posts,
+    registration, users, votes,
+};
+
+/// queries are run on a single connection in a background thread
+#[derive(Clone, Debug)]
+pub struct SqliteConn {
+    pub db: tokio_rusqlite::Connection,
+    /// the domain of this instance, used to sign fetches made while backfilling
+    pub instance_domain: String,
+}
+
+impl From<tokio_rusqlite::Error> for DbErr {
+    fn from(value: tokio_rusqlite::Error) -> Self {
+        if let tokio_rusqlite::Error::Rusqlite(rusqlite::Error::SqliteFailure(err, _)) = &value {
+            // foreign key violations share the constraint error code
+            let unique = err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                || err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY;
+            if err.code == ErrorCode::ConstraintViolation && unique {
+                return DbErr::InsertErr(InsertErr::AlreadyExists);
+            }
+        }
+        DbErr::QueryErr(value.to_string())
+    }
+}
+
+impl SqliteConn {
+    /// opens or creates the database at the given path
+    pub async fn open(path: &str, instance_domain: &str) -> Result<Self, DbErr> {
+        let db = tokio_rusqlite::Connection::open(path).await?;
+        db.call(|conn| {
+            // sqlite only enforces foreign keys when asked to
+            conn.pragma_update(None, "foreign_keys", true)?;
+            Ok(())
+        })
+        .await?;
+        Ok(SqliteConn {
+            db,
+            instance_domain: instance_domain.to_string(),
+        })
+    }
+
+    async fn get_origin_user(
+        &self,
+        username: &str,
+        origin: &EntityOrigin<'_>,
+    ) -> Result<Option<StoredUser>, DbErr> {
+        let domain = match origin {
+            EntityOrigin::Local(x) => x,
+            EntityOrigin::Federated(x) => x,
+        };
+        // deleted accounts are only kept around to sign their delete
+        Ok(users::get_user_by_username(self, username, domain)
+            .await?
+            .filter(|x| !x.deleted))
+    }
+
+    async fn insert_federated_user(&self, user: StoredUser) -> Result<StoredUser, DbErr> {
+        // the user may have been fetched through their key id so
+        // make sure we don't already have them under their actual id
+        if let Some(existing) = users::get_user_by_link(self, &user.resource_link).await? {
+            return Ok(existing);
+        }
+        instances::ensure_instance(self, &user.domain, false).await?;
+        users::insert_user(self, &user).await?;
+        Ok(user)
+    }
+
+    async fn get_origin_post(
+        &self,
+        post_id: &str,
+        origin: &EntityOrigin<'_>,
+    ) -> Result<Option<(StoredPost, StoredUser)>, DbErr> {
+        let post = match origin {
+            EntityOrigin::Local(domain) => posts::get_local_post(self, post_id, domain).await?,
+            EntityOrigin::Federated(_) => posts::get_post(self, post_id).await?,
+        };
+        let Some(post) = post else {
+            return Ok(None);
+        };
+        let author = users::get_user_by_uid(self, &post.actor)
+            .await?
+            .ok_or(DbErr::NotFound)?;
+        Ok(Some((post, author)))
+    }
+
+    /// local posts get their ids generated, federated posts must come
+    /// from the instance they claim to be from
+    async fn insert_post(
+        &self,
+        mut post: StoredPost,
+        author: &StoredUser,
+        origin: &EntityOrigin<'_>,
+    ) -> Result<StoredPost, DbErr> {
+        match origin {
+            EntityOrigin::Local(domain) => {
+                if !author.is_local() {
+                    return Err(DbErr::InvalidType);
+                }
+                post.versia_id = new_uid();
+                post.id = versia_post_link(domain, &author.username, &post.versia_id).to_string();
+                post.domain = domain.to_string();
+            }
+            EntityOrigin::Federated(domain) => {
+                if post.domain.ne(domain) || author.domain.ne(domain) {
+                    return Err(DbErr::InvalidType);
+                }
+                instances::ensure_instance(self, domain, false).await?;
+            }
+        }
+        posts::insert_post(self, &post).await?;
+        Ok(post)
+    }
+
+    async fn get_user_posts(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin<'_>,
+        page_size: u64,
+        ofset: u64,
+    ) -> Result<Option<(Vec<StoredPost>, StoredUser)>, DbErr> {
+        let Some(user) = self.get_origin_user(uname, origin).await? else {
+            return Ok(None);
+        };
+        let posts = posts::get_user_posts(self, &user.uid, page_size, ofset).await?;
+        Ok(Some((posts, user)))
+    }
+}
+
+#[async_trait]
+impl Conn for SqliteConn {
+    async fn backfill_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor> {
+        if let Some(actor) = self.get_actor(username, origin).await {
+            return Some(actor);
+        }
+        let EntityOrigin::Federated(domain) = origin else {
+            return None;
+        };
+        let protocol = self.get_protocol(domain).await;
+        let instance_actor = self.get_instance_actor().await.ok()?;
+        let user = fetch_federated_handle(
+            username,
+            domain,
+            protocol,
+            &instance_actor,
+            &self.instance_domain,
+        )
+        .await
+        .ok()?;
+        self.insert_federated_user(user).await.ok()?.to_actor()
+    }
+    async fn get_actor(&self, username: &str, origin: &EntityOrigin) -> Option<Actor> {
+        self.get_origin_user(username, origin)
+            .await
+            .ok()??
+            .to_actor()
+    }
+    async fn get_user(&self, uid: &str) -> Option<StoredUser> {
+        users::get_user_by_uid(self, uid).await.ok()?
+    }
+    async fn get_local_user(&self, uid: &str) -> Option<StoredUser> {
+        users::get_user_by_uid(self, uid)
+            .await
+            .ok()?
+            .filter(|x| x.is_local())
+    }
+    async fn get_local_user_by_username(&self, username: &str) -> Option<StoredUser> {
+        users::get_user_by_username(self, username, &self.instance_domain)
+            .await
+            .ok()?
+            .filter(|x| x.is_local() && !x.deleted)
+    }
+    async fn resolve_user(&self, link: &Url) -> Result<StoredUser, DbErr> {
+        if let Some(username) = local_username(&self.instance_domain, link) {
+            return users::get_user_by_username(self, &username, &self.instance_domain)
+                .await?
+                .filter(|x| !x.deleted)
+                .ok_or(DbErr::NotFound);
+        }
+        if let Some(user) = users::get_user_by_link(self, link.as_str()).await? {
+            return Ok(user);
+        }
+        let Some(domain) = link.domain() else {
+            return Err(DbErr::NotFound);
+        };
+        let protocol = self.get_protocol(domain).await;
+        let instance_actor = self.get_instance_actor().await?;
+        let user =
+            fetch_federated_user(link, protocol, &instance_actor, &self.instance_domain).await?;
+        self.insert_federated_user(user).await
+    }
+    async fn update_federated_user(&self, user: &StoredUser) -> Result<(), DbErr> {
+        users::update_federated_user(self, user).await
+    }
+    async fn get_user_posts_ap(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin,
+        page_size: u64,
+        ofset: u64,
+    ) -> Option<Vec<ApPostable>> {
+        let (posts, user) = self
+            .get_user_posts(uname, origin, page_size, ofset)
+            .await
+            .ok()??;
+        Some(posts.iter().filter_map(|x| x.to_ap(&user)).collect())
+    }
+    async fn get_ap_post(&self, post_id: &str, origin: &EntityOrigin) -> Option<ApPostable> {
+        let (post, author) = self.get_origin_post(post_id, origin).await.ok()??;
+        post.to_ap(&author)
+    }
+    async fn create_ap_post(
+        &self,
+        post: ApPostable,
+        origin: &EntityOrigin,
+    ) -> Result<String, DbErr> {
+        let author = self.resolve_user(post.actor()).await?;
+        let stored = StoredPost::from_ap(&post, &author).ok_or(DbErr::InvalidType)?;
+        let stored = self.insert_post(stored, &author, origin).await?;
+        Ok(stored.versia_id)
+    }
+    async fn create_user(&self, domain: &str, content: &NewLocal) -> Result<String, DbErr> {
+        instances::ensure_instance(self, domain, true).await?;
+        let user = StoredUser::new_local(domain, content);
+        users::insert_user(self, &user).await?;
+        Ok(user.uid)
+    }
+    async fn get_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => users::get_user_by_link(self, link.as_str())
+                .await
+                .ok()??
+                .public_key(),
+            Signer::Instance(_) => None,
+        }
+    }
+    async fn get_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => match self.resolve_user(link).await {
+                Ok(user) => user.verifying_keys(),
+                Err(_) => Vec::new(),
+            },
+            // our own previous key stays good for the grace period
+            Signer::Instance(domain) if domain.eq(&self.instance_domain) => self
+                .get_instance_actor()
+                .await
+                .map(|x| x.versia_verifying_keys())
+                .unwrap_or_default(),
+            Signer::Instance(domain) => {
+                let metadata = self.get_versia_instance_metadata(domain).await;
+                metadata
+                    .and_then(|x| versia_key_to_openssl(&x.public_key))
+                    .into_iter()
+                    .collect()
+            }
+        }
+    }
+    async fn refetch_public_keys(&self, signed_by: &Signer) -> Vec<OpenSSLPublic> {
+        match signed_by {
+            Signer::User(link) => {
+                match refresh_federated_user(self, link, &self.instance_domain).await {
+                    Ok(user) => user.verifying_keys(),
+                    Err(_) => Vec::new(),
+                }
+            }
+            // instance metadata isn't stored so it is always fresh
+            Signer::Instance(_) => self.get_public_keys(signed_by).await,
+        }
+    }
+    async fn get_user_post_count(&self, uname: &str, origin: &EntityOrigin) -> Option<u64> {
+        let user = self.get_origin_user(uname, origin).await.ok()??;
+        posts::count_user_posts(self, &user.uid).await.ok()
+    }
+    async fn get_user_posts_versia(
+        &self,
+        uname: &str,
+        origin: &EntityOrigin,
+        page_size: u64,
+        ofset: u64,
+    ) -> Option<Vec<VersiaPostable>> {
+        let (posts, user) = self
+            .get_user_posts(uname, origin, page_size, ofset)
+            .await
+            .ok()??;
+        Some(posts.iter().filter_map(|x| x.to_versia(&user)).collect())
+    }
+    async fn get_versia_user(&self, uname: &str, origin: &EntityOrigin) -> Option<User> {
+        self.get_origin_user(uname, origin).await.ok()??.to_versia()
+    }
+    async fn create_local_post(&self, post: &StoredPost) -> Result<(), DbErr> {
+        let author = self
+            .get_local_user(&post.actor)
+            .await
+            .ok_or(DbErr::NotFound)?;
+        if author.deleted || post.domain.ne(&author.domain) {
+            return Err(DbErr::InvalidType);
+        }
+        posts::insert_post(self, post).await?;
+        Ok(())
+    }
+    async fn get_post(&self, link: &Url) -> Option<StoredPost> {
+        let post = match local_post_id(&self.instance_domain, link) {
+            Some(pid) => posts::get_local_post(self, &pid, &self.instance_domain).await,
+            None => posts::get_post(self, link.as_str()).await,
+        };
+        post.ok()?
+    }
+    async fn edit_post(&self, post: &StoredPost) -> Result<(), DbErr> {
+        posts::edit_post(self, post).await
+    }
+    async fn get_post_revisions(&self, post: &str) -> Result<Vec<PostRevision>, DbErr> {
+        posts::get_post_revisions(self, post).await
+    }
+    async fn get_ended_polls(&self, now: i64) -> Result<Vec<StoredPost>, DbErr> {
+        posts::get_ended_polls(self, now).await
+    }
+    async fn mark_poll_ended(&self, id: &str) -> Result<(), DbErr> {
+        posts::mark_poll_ended(self, id).await
+    }
+    async fn delete_user(&self, uid: &Url, origin: &EntityOrigin) -> Result<(), DbErr> {
+        let user = match origin {
+            EntityOrigin::Local(domain) => {
+                let username = local_username(domain, uid).ok_or(DbErr::NotFound)?;
+                users::get_user_by_username(self, &username, domain).await?
+            }
+            EntityOrigin::Federated(domain) => users::get_user_by_link(self, uid.as_str())
+                .await?
+                .filter(|x| x.domain.eq(domain)),
+        };
+        let user = user.ok_or(DbErr::NotFound)?;
+        users::delete_user(self, &user.uid).await
+    }
+    async fn tombstone_user(&self, uid: &str) -> Result<(), DbErr> {
+        users::tombstone_user(self, uid).await
+    }
+    async fn rotate_user_key(
+        &self,
+        uid: &str,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), DbErr> {
+        users::rotate_user_key(self, uid, private_key_pem, public_key_pem).await
+    }
+    async fn get_versia_post(&self, pid: &str, origin: &EntityOrigin) -> Option<VersiaPostable> {
+        let (post, author) = self.get_origin_post(pid, origin).await.ok()??;
+        post.to_versia(&author)
+    }
+    async fn create_versia_post(
+        &self,
+        post: VersiaPostable,
+        origin: &EntityOrigin,
+    ) -> Result<VersiaPostable, DbErr> {
+        let author = self.resolve_user(post.get_author()).await?;
+        let stored = StoredPost::from_versia(&post, &author).ok_or(DbErr::InvalidType)?;
+        let stored = self.insert_post(stored, &author, origin).await?;
+        stored.to_versia(&author).ok_or(DbErr::InvalidType)
+    }
+    async fn delete_post(&self, post_id: &str, origin: &EntityOrigin) -> Result<(), DbErr> {
+        match origin {
+            EntityOrigin::Local(domain) => {
+                let post = posts::get_local_post(self, post_id, domain)
+                    .await?
+                    .ok_or(DbErr::NotFound)?;
+                posts::delete_post(self, &post.id, domain).await
+            }
+            EntityOrigin::Federated(domain) => posts::delete_post(self, post_id, domain).await,
+        }
+    }
+
+    //-------------------follows------------------------------
+    async fn create_follow(
+        &self,
+        follower: &str,
+        target: &str,
+        pending: bool,
+        activity_id: Option<&str>,
+    ) -> Result<(), DbErr> {
+        follows::create_follow(self, follower, target, pending, activity_id, now_millis()).await
+    }
+    async fn get_followers(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+        follows::get_followers(self, uid).await
+    }
+    async fn get_following(&self, uid: &str) -> Result<Vec<StoredUser>, DbErr> {
+        follows::get_following(self, uid).await
+    }
+    async fn is_following(&self, follower: &str, target: &str) -> Result<bool, DbErr> {
+        follows::is_following(self, follower, target).await
+    }
+    async fn accept_follow(&self, follower: &str, target: &str) -> Result<(), DbErr> {
+        follows::accept_follow(self, follower, target).await
+    }
+    async fn remove_follow(&self, follower: &str, target: &str) -> Result<(), DbErr> {
+        follows::remove_follow(self, follower, target).await
+    }
+    async fn remove_follow_activity(&self, follower: &str, activity_id: &str) -> Result<(), DbErr> {
+        follows::remove_follow_activity(self, follower, activity_id).await
+    }
+
+    //-------------------likes------------------------------
+    async fn create_like(&self, actor: &str, post: &str, url: &str) -> Result<(), DbErr> {
+        likes::insert_like(self, actor, post, url).await
+    }
+    async fn remove_like(&self, actor: &str, url: &str) -> Result<(), DbErr> {
+        likes::remove_like(self, actor, url).await
+    }
+
+    //-------------------polls------------------------------
+    async fn create_vote(&self, vote: &PollVote) -> Result<(), DbErr> {
+        votes::insert_vote(self, vote).await
+    }
+    async fn get_votes(&self, poll: &str) -> Result<Vec<PollVote>, DbErr> {
+        votes::get_votes(self, poll).await
+    }
+
+    //-------------------delivery queue------------------------------
+    async fn queue_delivery(&self, delivery: &Delivery) -> Result<(), DbErr> {
+        deliveries::insert_delivery(self, delivery).await
+    }
+    async fn get_due_deliveries(&self, now: i64, limit: u64) -> Result<Vec<Delivery>, DbErr> {
+        deliveries::get_due_deliveries(self, now, limit).await
+    }
+    async fn update_delivery(&self, delivery: &Delivery) -> Result<(), DbErr> {
+        deliveries::update_delivery(self, delivery).await
+    }
+    async fn remove_delivery(&self, id: &str) -> Result<(), DbErr> {
+        deliveries::remove_delivery(self, id).await
+    }
+    async fn mark_inbox_dead(&self, inbox: &str) -> Result<(), DbErr> {
+        deliveries::mark_inbox_dead(self, inbox).await
+    }
+    async fn is_inbox_dead(&self, inbox: &str) -> Result<bool, DbErr> {
+        deliveries::is_inbox_dead(self, inbox).await
+    }
+
+    //-------------------oauth------------------------------
+    async fn create_oauth_app(&self, app: &OAuthApp) -> Result<(), DbErr> {
+        oauth::insert_app(self, app).await
+    }
+    async fn get_oauth_app(&self, client_id: &str) -> Option<OAuthApp> {
+        oauth::get_app(self, client_id).await.ok()?
+    }
+    async fn create_oauth_code(&self, code: &OAuthCode) -> Result<(), DbErr> {
+        oauth::insert_code(self, code).await
+    }
+    async fn take_oauth_code(&self, code: &str) -> Option<OAuthCode> {
+        oauth::take_code(self, code).await.ok()?
+    }
+    async fn create_oauth_token(&self, token: &OAuthToken) -> Result<(), DbErr> {
+        oauth::insert_token(self, token).await
+    }
+    async fn get_oauth_token(&self, token_hash: &str) -> Option<OAuthToken> {
+        oauth::get_token(self, token_hash).await.ok()?
+    }
+    async fn revoke_oauth_token(&self, token_hash: &str) -> Result<(), DbErr> {
+        oauth::delete_token(self, token_hash).await
+    }
+
+    //-------------------registration------------------------------
+    async fn get_registration_mode(&self) -> Result<Option<RegistrationMode>, DbErr> {
+        registration::get_registration_mode(self).await
+    }
+    async fn set_registration_mode(&self, mode: RegistrationMode) -> Result<(), DbErr> {
+        registration::set_registration_mode(self, mode).await
+    }
+    async fn demote_unvetted_users(&self) -> Result<u64, DbErr> {
+        registration::demote_unvetted_users(self).await
+    }
+    async fn promote_unvetted_users(&self) -> Result<u64, DbErr> {
+        registration::promote_unvetted_users(self).await
+    }
+    async fn approve_user(&self, uid: &str) -> Result<(), DbErr> {
+        registration::approve_user(self, uid).await
+    }
+    async fn create_invite(&self, invite: &Invite) -> Result<(), DbErr> {
+        registration::insert_invite(self, invite).await
+    }
+    async fn use_invite(&self, code: &str) -> Result<(), DbErr> {
+        registration::use_invite(self, code).await
+    }
+    async fn create_application(&self, application: &Application) -> Result<(), DbErr> {
+        registration::insert_application(self, application).await
+    }
+    async fn get_application(&self, uid: &str) -> Option<Application> {
+        registration::get_application(self, uid).await.ok()?
+    }
+    async fn get_applications(&self) -> Result<Vec<Application>, DbErr> {
+        registration::get_applications(self).await
+    }
+
+    //-------------------notifications------------------------------
+    async fn create_notification(&self, notification: &Notification) -> Result<(), DbErr> {
+        notifications::insert_notification(self, notification).await
+    }
+    async fn get_notifications(
+        &self,
+        uid: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, DbErr> {
+        let query = NotificationQuery {
+            limit: query.limit.min(MAX_NOTIFICATION_LIMIT),
+            ..query.clone()
+        };
+        notifications::get_notifications(self, uid, &query).await
+    }
+    async fn get_notification_marker(&self, uid: &str) -> Option<NotificationMarker> {
+        notifications::get_marker(self, uid).await.ok()?
+    }
+    async fn set_notification_marker(&self, marker: &NotificationMarker) -> Result<(), DbErr> {
+        notifications::set_marker(self, marker).await
+    }
+
+    //-------------------timelines------------------------------
+    async fn get_timeline(
+        &self,
+        timeline: &Timeline<'_>,
+        query: &TimelineQuery,
+    ) -> Result<Vec<(StoredPost, StoredUser)>, DbErr> {
+        let query = TimelineQuery {
+            limit: query.limit.min(MAX_TIMELINE_LIMIT),
+            ..query.clone()
+        };
+        let posts = posts::get_timeline(self, timeline, &query).await?;
+        let mut authors: HashMap<String, StoredUser> = HashMap::new();
+        let mut page = Vec::with_capacity(posts.len());
+        for post in posts {
+            if !authors.contains_key(&post.actor) {
+                let Some(author) = users::get_user_by_uid(self, &post.actor).await? else {
+                    continue;
+                };
+                authors.insert(post.actor.clone(), author);
+            }
+            let author = authors[&post.actor].clone();
+            page.push((post, author));
+        }
+        Ok(page)
+    }
+
+    async fn init(&self) -> Result<(), String> {
+        init::init(self).await
+    }
+
+    //-------------------instance actor------------------------------
+    async fn get_instance_actor(&self) -> Result<InstanceActor, DbErr> {
+        match instance_actor::get_instance_actor(self).await? {
+            Some(x) => Ok(x),
+            None => instance_actor::create_instance_actor(self).await,
+        }
+    }
+    async fn set_instance_actor(&self, actor: &InstanceActor) -> Result<(), DbErr> {
+        instance_actor::update_instance_actor(self, actor).await
+    }
+
+    async fn get_versia_instance_metadata(
+        &self,
+        instance_domain: &str,
+    ) -> Option<InstanceMetadata> {
+        if instance_domain.eq(&self.instance_domain) {
+            let instance_actor = self.get_instance_actor().await.ok()?;
+            return Some(instance_actor.to_versia_metadata(instance_domain));
+        }
+        let metadata = fetch_instance_metadata(instance_domain).await.ok()?;
+        instances::set_protocol(self, instance_domain, Protocol::Versia)
+            .await
+            .ok()?;
+        Some(metadata)
+    }
+
+    async fn get_protocol(&self, instance: &str) -> Protocol {
+        if let Ok(Some(protocol)) = instances::get_protocol(self, instance).await {
+            return protocol;
+        }
+        let protocol = detect_protocol(instance).await;
+        let _ = instances::set_protocol(self, instance, protocol).await;
+        protocol
+    }
+    async fn get_instances(&self, protocol: Protocol) -> Result<Vec<String>, DbErr> {
+        instances::get_instances(self, protocol).await
+    }
+    async fn get_shared_inboxes(&self) -> Result<Vec<String>, DbErr> {
+        instances::get_shared_inboxes(self).await
+    }
+
+    async fn get_signature_scheme(&self, instance: &str) -> Option<SignatureScheme> {
+        instances::get_signature_scheme(self, instance)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn set_signature_scheme(
+        &self,
+        instance: &str,
+        scheme: SignatureScheme,
+    ) -> Result<(), DbErr> {
+        instances::set_signature_scheme(self, instance, scheme).await
+    }
+}
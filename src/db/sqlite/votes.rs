@@ -0,0 +1,50 @@
+use rusqlite::{params, Row};
+
+use crate::db::{conn::DbErr, utility::polls::PollVote};
+
+use super::sqlite_conn::SqliteConn;
+
+fn to_vote(row: &Row) -> rusqlite::Result<PollVote> {
+    Ok(PollVote {
+        poll: row.get("poll")?,
+        voter: row.get("voter")?,
+        choice: row.get("choice")?,
+        published: row.get("published")?,
+    })
+}
+
+pub async fn insert_vote(conn: &SqliteConn, vote: &PollVote) -> Result<(), DbErr> {
+    let vote = vote.clone();
+    conn.db
+        .call(move |conn| {
+            let stmt = r#"
+            INSERT INTO poll_votes (poll, voter, choice, published)
+            VALUES (?1, ?2, ?3, ?4);
+            "#;
+            conn.execute(
+                stmt,
+                params![vote.poll, vote.voter, vote.choice, vote.published],
+            )?;
+            Ok(())
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_votes(conn: &SqliteConn, poll: &str) -> Result<Vec<PollVote>, DbErr> {
+    let poll = poll.to_string();
+    let result = conn
+        .db
+        .call(move |conn| {
+            let stmt = r#"
+            SELECT * FROM poll_votes WHERE poll = ?1 ORDER BY published ASC;
+            "#;
+            let mut stmt = conn.prepare(stmt)?;
+            let votes = stmt
+                .query_map([&poll], to_vote)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(votes)
+        })
+        .await?;
+    Ok(result)
+}
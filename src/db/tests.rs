@@ -1,97 +1,1373 @@
-// use actix_web::rt::spawn;
-// use serial_test::serial;
-// use url::Url;
-
-// use crate::{app::start_application, config::get_config};
-
-// use super::utility::new_actor::NewLocal;
-
-// #[actix_web::test]
-// #[serial]
-// #[ignore]
-// async fn create_and_retrieve_user() -> Result<(), String> {
-//     let config = get_config().unwrap();
-//     let conn = config.create_conn();
-//     conn.init().await.unwrap();
-
-//     let preferred_username = "create_and_retrieve_user".to_string();
-
-//     let uid = conn
-//         .create_local_user(&NewLocal::new(
-//             preferred_username.clone(),
-//             "filler".to_string(),
-//             None,
-//             None,
-//             None,
-//         ))
-//         .await
-//         .unwrap();
-//     let actor = conn.get_actor(uid, &config.instance_domain).await;
-//     let Some(actor) = actor else {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err(
-//             "failed to retrieve actor with get_actor, may have failed to insert".to_string(),
-//         );
-//     };
-//     let second = conn
-//         .get_local_user_actor(&preferred_username, &config.instance_domain)
-//         .await;
-//     let Some((second_actor, second_uid)) = second else {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err("failed to retrieve actor with get_local_user_actor".to_string());
-//     };
-
-//     if second_actor.id.ne(&actor.id) {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err("id's of the retrieved actors don't match".to_string());
-//     }
-
-//     if uid != second_uid {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err(
-//             "get_local_user_actor uid doesn't match uid returned from inserting".to_string(),
-//         );
-//     }
-
-//     conn.delete_actor(uid, None).await.unwrap();
-
-//     Ok(())
-// }
-
-// #[actix_web::test]
-// #[serial]
-// #[ignore]
-// async fn backfill_fedi_user() -> Result<(), String> {
-//     let config = get_config().unwrap();
-//     let conn = config.create_conn();
-//     conn.init().await.unwrap();
-//     let handle = spawn(start_application(config.clone()));
-
-//     let uid = match conn
-//         .load_new_federated_actor(
-//             &Url::parse("https://mastodon.social/@ivy_test").unwrap(),
-//             &config.instance_domain,
-//         )
-//         .await
-//     {
-//         Ok(x) => x,
-//         Err(x) => return Err(format!("failed to load the federated actor: {}", x)),
-//     };
-
-//     let Some(actor) = conn.get_actor(uid, &config.instance_domain).await else {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err("failed to get backfilled fedi actor".to_string());
-//     };
-
-//     if actor.preferred_username.ne("ivy_test") {
-//         conn.delete_actor(uid, None).await.unwrap();
-//         return Err(format!(
-//             "preferred uname doesn't match ivy_test value: {}",
-//             actor.preferred_username
-//         ));
-//     }
-
-//     conn.delete_actor(uid, None).await.unwrap();
-//     handle.abort();
-//     Ok(())
-// }
+use serial_test::serial;
+use url::Url;
+
+use crate::{
+    config::get_config,
+    cryptography::{
+        key::{Key, KeyType},
+        openssl::OpenSSLPublic,
+    },
+    protocols::{
+        protocol::versia_protocol::requests::Signer,
+        types::{
+            activitystream_objects::postable::ApPostable,
+            versia_types::{
+                entities::{notes::Category, public_key::AlgorithmsPublicKey},
+                extensions::vote::{Vote, VoteType},
+                postable::VersiaPostable,
+            },
+        },
+    },
+};
+
+use super::{
+    conn::{Conn, DbErr, EntityOrigin, InsertErr},
+    memory::memory_conn::InMemoryConn,
+    sqlite::sqlite_conn::SqliteConn,
+    utility::{
+        compose::{compose, Draft, DraftPoll},
+        delete_account::delete_local_account,
+        delivery::{backoff, process_due_deliveries, Delivery},
+        interactions::handle_vote,
+        new_actor::{ap_post_link, versia_post_link, NewLocal},
+        notifications::{
+            notify, notify_author, notify_ended_polls, notify_mentions, NotificationKind,
+            NotificationMarker, NotificationQuery,
+        },
+        oauth::{hash_token, OAuthApp, OAuthCode, OAuthToken, Scopes, OOB_REDIRECT},
+        permission::PermissionLevel,
+        polls::{tally, with_tally, PollVote},
+        post_types::PostSupertype,
+        protocols::{Protocol, SignatureScheme},
+        registration::{apply_registration_mode, Application, Invite, RegistrationMode},
+        revisions::edit_local_post,
+        rotate_key::{rotate_instance_key, rotate_local_key},
+        stored_post::StoredPost,
+        stored_user::{new_uid, now_millis, StoredUser},
+        streaming::{StreamBus, StreamEvent, STREAM_BUFFER},
+        timeline::{Timeline, TimelineCursor, TimelineQuery},
+    },
+};
+
+async fn create_and_retrieve(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let preferred_username = "create_and_retrieve_user".to_string();
+    let origin = EntityOrigin::Local(&config.instance_domain);
+
+    conn.create_user(
+        &config.instance_domain,
+        &NewLocal::new(preferred_username.clone(), "filler".to_string(), None, None),
+    )
+    .await
+    .unwrap();
+
+    let Some(actor) = conn.get_actor(&preferred_username, &origin).await else {
+        return Err(
+            "failed to retrieve actor with get_actor, may have failed to insert".to_string(),
+        );
+    };
+    let Some(user) = conn.get_versia_user(&preferred_username, &origin).await else {
+        return Err("failed to retrieve the versia user".to_string());
+    };
+
+    if actor.preferred_username.ne(&preferred_username) {
+        return Err("preferred username of the actor doesn't match".to_string());
+    }
+    if user.username.ne(&preferred_username) {
+        return Err("username of the versia user doesn't match".to_string());
+    }
+
+    conn.delete_user(&actor.id, &origin).await.unwrap();
+    if conn.get_actor(&preferred_username, &origin).await.is_some() {
+        return Err("user was still present after deleting".to_string());
+    }
+
+    Ok(())
+}
+
+async fn duplicate(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = || NewLocal::new("duplicate".to_string(), "filler".to_string(), None, None);
+    conn.create_user(&config.instance_domain, &new())
+        .await
+        .unwrap();
+    match conn.create_user(&config.instance_domain, &new()).await {
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => Ok(()),
+        Err(x) => Err(format!("wrong error creating a duplicate user: {}", x)),
+        Ok(_) => Err("created a second user with the same username".to_string()),
+    }
+}
+
+#[actix_web::test]
+#[serial]
+#[ignore]
+async fn backfill_fedi_user() -> Result<(), String> {
+    let config = get_config().unwrap();
+    let conn = InMemoryConn::new(&config.instance_domain);
+    conn.init().await.unwrap();
+
+    let Some(actor) = conn
+        .backfill_actor("ivy_test", &EntityOrigin::Federated("mastodon.social"))
+        .await
+    else {
+        return Err("failed to backfill the fedi actor".to_string());
+    };
+
+    if actor.preferred_username.ne("ivy_test") {
+        return Err(format!(
+            "preferred uname doesn't match ivy_test value: {}",
+            actor.preferred_username
+        ));
+    }
+
+    let id = Url::parse(actor.id.as_str()).unwrap();
+    conn.delete_user(&id, &EntityOrigin::Federated("mastodon.social"))
+        .await
+        .unwrap();
+    Ok(())
+}
+
+async fn failed_delivery(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    // the signer doesn't exist so this fails without hitting the network
+    let delivery = Delivery::new(
+        "https://example.com/inbox",
+        Protocol::ActivityPub,
+        "{}".to_string(),
+        "missing",
+    );
+    conn.queue_delivery(&delivery).await.unwrap();
+
+    let attempted = process_due_deliveries(conn, &config.instance_domain, 10, 1000 * 60)
+        .await
+        .unwrap();
+    if attempted != 1 {
+        return Err(format!(
+            "expected one delivery attempted, got {}",
+            attempted
+        ));
+    }
+    let due = conn.get_due_deliveries(now_millis(), 10).await.unwrap();
+    if !due.is_empty() {
+        return Err("failed delivery was not rescheduled".to_string());
+    }
+
+    let retry_at = now_millis() + backoff(1) + 1;
+    let Some(retry) = conn.get_due_deliveries(retry_at, 10).await.unwrap().pop() else {
+        return Err("failed delivery was dropped from the queue".to_string());
+    };
+    if retry.attempts != 1 || retry.last_error.is_none() {
+        return Err("failed attempt was not recorded".to_string());
+    }
+
+    // once it has been failing too long it stops being retried
+    conn.update_delivery(&Delivery {
+        next_attempt: 0,
+        ..retry.clone()
+    })
+    .await
+    .unwrap();
+    process_due_deliveries(conn, &config.instance_domain, 10, -1)
+        .await
+        .unwrap();
+    if !conn
+        .get_due_deliveries(i64::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        return Err("delivery was not marked dead".to_string());
+    }
+
+    // later deliveries to the inbox are given up on without being sent
+    if !conn.is_inbox_dead(&delivery.inbox).await.unwrap() {
+        return Err("unreachable inbox was not marked dead".to_string());
+    }
+    let later = Delivery::new(
+        &delivery.inbox,
+        Protocol::ActivityPub,
+        "{}".into(),
+        "missing",
+    );
+    conn.queue_delivery(&later).await.unwrap();
+    process_due_deliveries(conn, &config.instance_domain, 10, 1000 * 60)
+        .await
+        .unwrap();
+    if !conn
+        .get_due_deliveries(i64::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        return Err("delivery to a dead inbox was kept".to_string());
+    }
+    Ok(())
+}
+
+async fn delete_account(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let origin = EntityOrigin::Local(&config.instance_domain);
+    let uid = conn
+        .create_user(&config.instance_domain, &new("deleted"))
+        .await
+        .unwrap();
+    let follower = conn
+        .create_user(&config.instance_domain, &new("follower"))
+        .await
+        .unwrap();
+    conn.create_follow(&follower, &uid, false, None)
+        .await
+        .unwrap();
+
+    // local followers already know, nothing needs to be sent
+    assert_eq!(delete_local_account(conn, &uid).await.unwrap(), 0);
+
+    if conn.get_actor("deleted", &origin).await.is_some() {
+        return Err("deleted user is still served".to_string());
+    }
+    let Some(tombstone) = conn.get_local_user(&uid).await else {
+        return Err("the tombstone needs to be kept to sign the delete".to_string());
+    };
+    assert!(tombstone.deleted && tombstone.password.is_none());
+    assert!(conn.get_followers(&uid).await.unwrap().is_empty());
+    assert!(conn.get_following(&follower).await.unwrap().is_empty());
+    match conn
+        .create_user(&config.instance_domain, &new("deleted"))
+        .await
+    {
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => Ok(()),
+        _ => Err("the name of a deleted user was reused".to_string()),
+    }
+}
+
+async fn signature_schemes(conn: &dyn Conn) -> Result<(), String> {
+    conn.init().await.unwrap();
+
+    if conn.get_signature_scheme("scheme.example").await.is_some() {
+        return Err("got a scheme for an instance never delivered to".to_string());
+    }
+    conn.set_signature_scheme("scheme.example", SignatureScheme::Rfc9421)
+        .await
+        .unwrap();
+    conn.set_signature_scheme("scheme.example", SignatureScheme::Cavage)
+        .await
+        .unwrap();
+    match conn.get_signature_scheme("scheme.example").await {
+        Some(SignatureScheme::Cavage) => Ok(()),
+        x => Err(format!("scheme was not updated: {:?}", x)),
+    }
+}
+
+async fn key_rotation(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let uid = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new("rotated".to_string(), "filler".to_string(), None, None),
+        )
+        .await
+        .unwrap();
+    let old = conn.get_local_user(&uid).await.unwrap();
+
+    assert_eq!(
+        rotate_local_key(conn, &uid, KeyType::Ed25519)
+            .await
+            .unwrap(),
+        0
+    );
+    let rotated = conn.get_local_user(&uid).await.unwrap();
+    if rotated.public_key_pem.eq(&old.public_key_pem)
+        || rotated.private_key_pem.eq(&old.private_key_pem)
+    {
+        return Err("the keypair was not replaced".to_string());
+    }
+    if rotated
+        .previous_public_key_pem
+        .ne(&Some(old.public_key_pem.clone()))
+        || rotated.verifying_keys().len() != 2
+    {
+        return Err("the old key is not kept for the grace period".to_string());
+    }
+
+    let old = conn.get_instance_actor().await.unwrap();
+    rotate_instance_key(conn, &config.instance_domain, KeyType::Rsa256)
+        .await
+        .unwrap();
+    let rotated = conn.get_instance_actor().await.unwrap();
+    if rotated.versia_public_key_pem.eq(&old.versia_public_key_pem)
+        || rotated
+            .previous_public_key_pem
+            .ne(&Some(old.public_key_pem))
+        || rotated
+            .previous_versia_public_key_pem
+            .ne(&Some(old.versia_public_key_pem.clone()))
+    {
+        return Err("the old instance keys are not kept".to_string());
+    }
+    let old_key = OpenSSLPublic::from_pem(old.versia_public_key_pem.as_bytes())
+        .and_then(|x| x.to_pem())
+        .unwrap();
+    let keys: Vec<String> = conn
+        .get_public_keys(&Signer::Instance(config.instance_domain.clone()))
+        .await
+        .iter()
+        .filter_map(|x| x.to_pem().ok())
+        .collect();
+    if keys.len() != 2 || !keys.contains(&old_key) {
+        return Err("the old instance key is not accepted for the grace period".to_string());
+    }
+    Ok(())
+}
+
+async fn instance_metadata(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let Some(metadata) = conn
+        .get_versia_instance_metadata(&config.instance_domain)
+        .await
+    else {
+        return Err("no metadata for our own instance".to_string());
+    };
+    let instance_actor = conn.get_instance_actor().await.unwrap();
+    let key = AlgorithmsPublicKey::from_pem(instance_actor.versia_public_key_pem.as_bytes())
+        .map_err(|x| x.to_string())?;
+    if metadata.public_key.key.ne(&key) || metadata.public_key.actor.is_some() {
+        return Err("metadata doesn't carry the instance's versia key".to_string());
+    }
+    if !conn
+        .get_instances(Protocol::Versia)
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        return Err("our own instance was listed as federated".to_string());
+    }
+    conn.create_user(
+        &config.instance_domain,
+        &NewLocal::new(
+            "metadata_user".to_string(),
+            "filler".to_string(),
+            None,
+            None,
+        ),
+    )
+    .await
+    .unwrap();
+    if !conn.get_shared_inboxes().await.unwrap().is_empty() {
+        return Err("a local user was given an instance actor update".to_string());
+    }
+    Ok(())
+}
+
+async fn oauth_lifecycle(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let uid = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new("oauth_user".to_string(), "filler".to_string(), None, None),
+        )
+        .await
+        .unwrap();
+    if conn
+        .get_local_user_by_username("oauth_user")
+        .await
+        .is_none()
+    {
+        return Err("failed to find the user by their username".to_string());
+    }
+
+    let scopes = Scopes::parse("read write").unwrap();
+    let app = OAuthApp::new(
+        "test app".to_string(),
+        vec![OOB_REDIRECT.to_string()],
+        scopes.clone(),
+        None,
+    );
+    conn.create_oauth_app(&app).await.unwrap();
+    let Some(stored) = conn.get_oauth_app(&app.client_id).await else {
+        return Err("failed to retrieve the app".to_string());
+    };
+    if stored.redirect_uris.ne(&app.redirect_uris) || stored.scopes.ne(&scopes) {
+        return Err("app did not round trip".to_string());
+    }
+
+    let code = OAuthCode::new(&app.client_id, &uid, OOB_REDIRECT, scopes, None, None);
+    conn.create_oauth_code(&code).await.unwrap();
+    if conn.take_oauth_code(&code.code).await.is_none() {
+        return Err("failed to take the code".to_string());
+    }
+    if conn.take_oauth_code(&code.code).await.is_some() {
+        return Err("a code was taken twice".to_string());
+    }
+
+    let (token, issued) = OAuthToken::issue(&app.client_id, Some(&uid), code.scopes);
+    conn.create_oauth_token(&issued).await.unwrap();
+    let stored = conn.get_oauth_token(&hash_token(&token)).await;
+    if stored.and_then(|x| x.uid).as_deref() != Some(uid.as_str()) {
+        return Err("failed to retrieve the token by its hash".to_string());
+    }
+    conn.revoke_oauth_token(&issued.token_hash).await.unwrap();
+    if conn.get_oauth_token(&issued.token_hash).await.is_some() {
+        return Err("token was still usable after being revoked".to_string());
+    }
+    if !matches!(
+        conn.revoke_oauth_token(&issued.token_hash).await,
+        Err(DbErr::NotFound)
+    ) {
+        return Err("revoked a token that doesn't exist".to_string());
+    }
+
+    let (_, issued) = OAuthToken::issue(&app.client_id, Some(&uid), issued.scopes);
+    conn.create_oauth_token(&issued).await.unwrap();
+    conn.tombstone_user(&uid).await.unwrap();
+    if conn.get_oauth_token(&issued.token_hash).await.is_some() {
+        return Err("deleting the account kept its tokens".to_string());
+    }
+    if conn
+        .get_local_user_by_username("oauth_user")
+        .await
+        .is_some()
+    {
+        return Err("a deleted user can still be logged into".to_string());
+    }
+    Ok(())
+}
+
+async fn registration_modes(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let create = |username: &str, vetted: bool| {
+        let mut new = NewLocal::new(
+            username.to_string(),
+            "filler".to_string(),
+            None,
+            Some(PermissionLevel::TrustedUser),
+        );
+        new.approved = true;
+        new.vetted = vetted;
+        new
+    };
+    if RegistrationMode::Open.new_user(false) != Some((PermissionLevel::TrustedUser, true)) {
+        return Err("open signups were not approved".to_string());
+    }
+    let open = conn
+        .create_user(&config.instance_domain, &create("open_signup", false))
+        .await
+        .unwrap();
+    let invited = conn
+        .create_user(&config.instance_domain, &create("invited_signup", true))
+        .await
+        .unwrap();
+    let level = |uid: String| async move {
+        conn.get_local_user(&uid)
+            .await
+            .and_then(|x| x.permission_level)
+    };
+
+    apply_registration_mode(conn, RegistrationMode::Open)
+        .await
+        .unwrap();
+    if apply_registration_mode(conn, RegistrationMode::Approval)
+        .await
+        .unwrap()
+        != 1
+    {
+        return Err("switching to approval did not demote only the open signup".to_string());
+    }
+    if apply_registration_mode(conn, RegistrationMode::Approval)
+        .await
+        .unwrap()
+        != 0
+    {
+        return Err("restarting in the same mode demoted users again".to_string());
+    }
+    if level(open.clone()).await != Some(PermissionLevel::UntrustedUser)
+        || level(invited.clone()).await != Some(PermissionLevel::TrustedUser)
+    {
+        return Err("demoted the wrong users".to_string());
+    }
+    if conn.get_local_user(&open).await.is_none_or(|x| x.approved) {
+        return Err("a demoted user was still approved".to_string());
+    }
+
+    // switching back lets the demoted user in again
+    if apply_registration_mode(conn, RegistrationMode::Open)
+        .await
+        .unwrap()
+        != 1
+        || level(open.clone()).await != Some(PermissionLevel::TrustedUser)
+        || conn.get_local_user(&open).await.is_none_or(|x| !x.approved)
+    {
+        return Err("switching to open did not let the demoted user in".to_string());
+    }
+    if apply_registration_mode(conn, RegistrationMode::Approval)
+        .await
+        .unwrap()
+        != 1
+    {
+        return Err("switching to approval again did not demote the open signup".to_string());
+    }
+
+    conn.create_application(&Application::new(&open, "let me in"))
+        .await
+        .unwrap();
+    let pending = conn.get_applications().await.unwrap();
+    if pending.len() != 1 || pending[0].reason.ne("let me in") {
+        return Err("failed to list the application".to_string());
+    }
+    conn.approve_user(&open).await.unwrap();
+    if level(open.clone()).await != Some(PermissionLevel::TrustedUser)
+        || conn.get_application(&open).await.is_some()
+    {
+        return Err("approving did not trust the user".to_string());
+    }
+    if apply_registration_mode(conn, RegistrationMode::Open)
+        .await
+        .unwrap()
+        != 0
+        || apply_registration_mode(conn, RegistrationMode::Approval)
+            .await
+            .unwrap()
+            != 0
+    {
+        return Err("an approved user was demoted".to_string());
+    }
+
+    let invite = Invite::new(Some(&invited), Some(1), None).unwrap();
+    conn.create_invite(&invite).await.unwrap();
+    conn.use_invite(&invite.code).await.unwrap();
+    if !matches!(conn.use_invite(&invite.code).await, Err(DbErr::NotFound)) {
+        return Err("an invite was used past its limit".to_string());
+    }
+    if Invite::new(None, None, Some(-1)).is_some()
+        || Invite::new(None, None, Some(i64::MAX)).is_some()
+    {
+        return Err("made an invite that expires in the past or overflows".to_string());
+    }
+    let mut expired = Invite::new(None, None, Some(1)).unwrap();
+    expired.expires = Some(now_millis() - 1);
+    conn.create_invite(&expired).await.unwrap();
+    if !matches!(conn.use_invite(&expired.code).await, Err(DbErr::NotFound)) {
+        return Err("an expired invite was used".to_string());
+    }
+    Ok(())
+}
+
+/// posts a note labeled by its subject
+async fn timeline_post(
+    conn: &dyn Conn,
+    author: &StoredUser,
+    label: &str,
+    published: i64,
+    local_only: bool,
+    followers_only: bool,
+) {
+    let post = StoredPost {
+        id: String::new(),
+        versia_id: String::new(),
+        domain: author.domain.clone(),
+        surtype: PostSupertype::Object,
+        subtype: r#""Note""#.to_string(),
+        category: Category::Microblog,
+        likes: 0,
+        boosts: 0,
+        local_only,
+        followers_only,
+        published,
+        is_reply: false,
+        in_reply_to: None,
+        content: Some("<p>hello</p>".to_string()),
+        subject: Some(label.to_string()),
+        is_sensitive: false,
+        shared: None,
+        multi_select: None,
+        options: None,
+        closed: None,
+        local_only_voting: None,
+        edited_at: None,
+        actor: author.uid.clone(),
+    };
+    let origin = EntityOrigin::Local(&author.domain);
+    // activitypub has no local only posts, everything else goes through
+    // it as the stand ins for remote users are only found by their
+    // activitypub link
+    match local_only {
+        true => {
+            conn.create_versia_post(post.to_versia(author).unwrap(), &origin)
+                .await
+                .unwrap();
+        }
+        false => {
+            conn.create_ap_post(post.to_ap(author).unwrap(), &origin)
+                .await
+                .unwrap();
+        }
+    }
+}
+
+async fn timelines(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let reader = conn
+        .create_user(&config.instance_domain, &new("timeline_reader"))
+        .await
+        .unwrap();
+    let followed = conn
+        .create_user(&config.instance_domain, &new("timeline_followed"))
+        .await
+        .unwrap();
+    let stranger = conn
+        .create_user(&config.instance_domain, &new("timeline_stranger"))
+        .await
+        .unwrap();
+    // a user made under another domain stands in for a remote one
+    let remote = conn
+        .create_user("timeline.remote", &new("timeline_remote"))
+        .await
+        .unwrap();
+    let requested = conn
+        .create_user("timeline.remote", &new("timeline_requested"))
+        .await
+        .unwrap();
+    conn.create_follow(&reader, &followed, false, None)
+        .await
+        .unwrap();
+    conn.create_follow(&reader, &remote, false, None)
+        .await
+        .unwrap();
+    conn.create_follow(&reader, &requested, true, None)
+        .await
+        .unwrap();
+    for (follower, target, expected) in [
+        (&reader, &followed, true),
+        (&reader, &requested, false),
+        (&followed, &reader, false),
+    ] {
+        if conn.is_following(follower, target).await.unwrap() != expected {
+            return Err(format!(
+                "{} following {} wasn't {}",
+                follower, target, expected
+            ));
+        }
+    }
+
+    let user = |uid: String| async move { conn.get_local_user(&uid).await.unwrap() };
+    let reader = user(reader).await;
+    let followed = user(followed).await;
+    let stranger = user(stranger).await;
+    let remote = user(remote).await;
+    let requested = user(requested).await;
+    timeline_post(conn, &reader, "own", 1000, false, false).await;
+    timeline_post(conn, &followed, "followed", 2000, false, false).await;
+    timeline_post(conn, &followed, "followers only", 3000, false, true).await;
+    timeline_post(conn, &followed, "local only", 4000, true, false).await;
+    timeline_post(conn, &stranger, "stranger", 5000, false, false).await;
+    timeline_post(conn, &stranger, "stranger followers", 6000, false, true).await;
+    timeline_post(conn, &remote, "remote", 7000, false, false).await;
+    timeline_post(conn, &requested, "requested", 8000, false, false).await;
+
+    let labels = |page: Vec<(StoredPost, StoredUser)>| -> Vec<String> {
+        page.into_iter()
+            .map(|(post, _)| post.subject.unwrap_or_default())
+            .collect()
+    };
+    let query = TimelineQuery::default();
+    let home = labels(
+        conn.get_timeline(&Timeline::Home(&reader.uid), &query)
+            .await
+            .unwrap(),
+    );
+    if home != ["remote", "local only", "followers only", "followed", "own"] {
+        return Err(format!("wrong home timeline: {home:?}"));
+    }
+    let local = labels(conn.get_timeline(&Timeline::Local, &query).await.unwrap());
+    if local != ["stranger", "followed", "own"] {
+        return Err(format!("wrong anonymous local timeline: {local:?}"));
+    }
+    let viewer = TimelineQuery {
+        viewer: Some(reader.uid.clone()),
+        ..Default::default()
+    };
+    let federated = labels(
+        conn.get_timeline(&Timeline::Federated, &viewer)
+            .await
+            .unwrap(),
+    );
+    if federated
+        != [
+            "requested",
+            "remote",
+            "stranger",
+            "local only",
+            "followed",
+            "own",
+        ]
+    {
+        return Err(format!("wrong federated timeline: {federated:?}"));
+    }
+
+    // paging through the federated timeline two at a time
+    let page = conn
+        .get_timeline(
+            &Timeline::Federated,
+            &TimelineQuery {
+                limit: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let oldest = TimelineCursor::new(&page.last().unwrap().0);
+    let newest = TimelineCursor::new(&page.first().unwrap().0);
+    let older = TimelineQuery {
+        before: Some(oldest.clone()),
+        limit: 2,
+        ..Default::default()
+    };
+    let older = labels(
+        conn.get_timeline(&Timeline::Federated, &older)
+            .await
+            .unwrap(),
+    );
+    if older != ["stranger", "followed"] {
+        return Err(format!("wrong page before the cursor: {older:?}"));
+    }
+    let adjacent = TimelineQuery {
+        after: Some(TimelineCursor::new(&page.last().unwrap().0)),
+        adjacent: true,
+        limit: 1,
+        ..Default::default()
+    };
+    let adjacent = labels(
+        conn.get_timeline(&Timeline::Federated, &adjacent)
+            .await
+            .unwrap(),
+    );
+    if adjacent != ["requested"] {
+        return Err(format!("wrong page right after the cursor: {adjacent:?}"));
+    }
+    let newer = TimelineQuery {
+        after: Some(newest),
+        ..Default::default()
+    };
+    if !conn
+        .get_timeline(&Timeline::Federated, &newer)
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        return Err("found posts newer than the newest".to_string());
+    }
+    Ok(())
+}
+
+async fn notifications(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let author = conn
+        .create_user(&config.instance_domain, &new("notified_author"))
+        .await
+        .unwrap();
+    let fan = conn
+        .create_user(&config.instance_domain, &new("notified_fan"))
+        .await
+        .unwrap();
+    let author = conn.get_local_user(&author).await.unwrap();
+    let fan = conn.get_local_user(&fan).await.unwrap();
+    let bus = StreamBus::new(STREAM_BUFFER);
+    let mut events = bus.subscribe();
+
+    timeline_post(conn, &author, "notified", 1000, false, false).await;
+    timeline_post(conn, &fan, "mentioning", 1500, false, false).await;
+    let posts: Vec<StoredPost> = conn
+        .get_timeline(&Timeline::Local, &TimelineQuery::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(post, _)| post)
+        .collect();
+    let labeled = |label: &str| {
+        posts
+            .iter()
+            .find(|x| x.subject.as_deref() == Some(label))
+            .cloned()
+            .ok_or("failed to find the post")
+    };
+    let post = labeled("notified")?;
+    let mentioning = labeled("mentioning")?;
+
+    conn.create_like(&fan.uid, &post.id, "https://notified.example/like")
+        .await
+        .unwrap();
+    if !matches!(
+        conn.create_like(&fan.uid, &post.id, "https://notified.example/other")
+            .await,
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists))
+    ) {
+        return Err("liked the same post twice".to_string());
+    }
+    notify_author(conn, &bus, &post, NotificationKind::Like, Some(&fan))
+        .await
+        .unwrap();
+    notify(
+        conn,
+        &bus,
+        &author,
+        NotificationKind::Follow,
+        Some(&fan),
+        None,
+    )
+    .await
+    .unwrap();
+    // users aren't told about their own actions
+    notify(
+        conn,
+        &bus,
+        &author,
+        NotificationKind::Follow,
+        Some(&author),
+        None,
+    )
+    .await
+    .unwrap();
+    // mentions of the same user only notify once
+    let mention = Url::parse(&author.resource_link).unwrap();
+    notify_mentions(
+        conn,
+        &bus,
+        &Url::parse(&mentioning.id).unwrap(),
+        &[mention.clone(), mention],
+        &config.instance_domain,
+    )
+    .await
+    .unwrap();
+
+    let all = conn
+        .get_notifications(&author.uid, &NotificationQuery::default())
+        .await
+        .unwrap();
+    let kinds: Vec<NotificationKind> = all.iter().map(|x| x.kind).collect();
+    if kinds.ne(&[
+        NotificationKind::Mention,
+        NotificationKind::Follow,
+        NotificationKind::Like,
+    ]) {
+        return Err(format!("got the wrong notifications: {:?}", kinds));
+    }
+    if all[2].post.as_deref() != Some(post.id.as_str()) {
+        return Err("the like was not about the post".to_string());
+    }
+    for notification in all.iter().rev() {
+        match events.try_recv().as_deref() {
+            Ok(StreamEvent::Notification(x)) if x.id.eq(&notification.id) => {}
+            x => return Err(format!("notification was not published: {:?}", x)),
+        }
+    }
+    if !conn
+        .get_notifications(&fan.uid, &NotificationQuery::default())
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        return Err("notified the user that acted".to_string());
+    }
+
+    let query = NotificationQuery {
+        kinds: Some(vec![NotificationKind::Like, NotificationKind::Share]),
+        ..Default::default()
+    };
+    let likes = conn.get_notifications(&author.uid, &query).await.unwrap();
+    if likes.len() != 1 || likes[0].kind != NotificationKind::Like {
+        return Err("kinds were not filtered".to_string());
+    }
+    let query = NotificationQuery {
+        before: Some(all[0].id.clone()),
+        limit: 1,
+        ..Default::default()
+    };
+    let page = conn.get_notifications(&author.uid, &query).await.unwrap();
+    if page.len() != 1 || page[0].id.ne(&all[1].id) {
+        return Err("paged past the wrong notification".to_string());
+    }
+    let query = NotificationQuery {
+        after: Some(all[2].id.clone()),
+        adjacent: true,
+        limit: 1,
+        ..Default::default()
+    };
+    let page = conn.get_notifications(&author.uid, &query).await.unwrap();
+    if page.len() != 1 || page[0].id.ne(&all[1].id) {
+        return Err("did not get the notification right after the cursor".to_string());
+    }
+
+    if conn.get_notification_marker(&author.uid).await.is_some() {
+        return Err("found a marker before setting one".to_string());
+    }
+    for read in [&all[2], &all[0]] {
+        conn.set_notification_marker(&NotificationMarker::new(&author.uid, &read.id))
+            .await
+            .unwrap();
+    }
+    let marker = conn.get_notification_marker(&author.uid).await;
+    if marker.is_none_or(|x| x.last_read_id.ne(&all[0].id)) {
+        return Err("the marker was not moved".to_string());
+    }
+
+    let mut poll = post.clone();
+    poll.surtype = PostSupertype::Question;
+    poll.subject = Some("notified poll".to_string());
+    poll.options = Some(vec!["yes".to_string(), "no".to_string()]);
+    poll.multi_select = Some(false);
+    poll.closed = Some(2000);
+    conn.create_versia_post(
+        poll.to_versia(&author).unwrap(),
+        &EntityOrigin::Local(&config.instance_domain),
+    )
+    .await
+    .unwrap();
+    if notify_ended_polls(conn, &bus, 1500).await.unwrap() != 0 {
+        return Err("ended a poll before it closed".to_string());
+    }
+    let Some(closed) = conn.get_ended_polls(2500).await.unwrap().pop() else {
+        return Err("failed to find the closed poll".to_string());
+    };
+    let vote = PollVote {
+        poll: closed.id.clone(),
+        voter: fan.uid.clone(),
+        choice: 1,
+        published: 1800,
+    };
+    conn.create_vote(&vote).await.unwrap();
+    if !matches!(
+        conn.create_vote(&vote).await,
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists))
+    ) {
+        return Err("voted for the same option twice".to_string());
+    }
+    if notify_ended_polls(conn, &bus, 2500).await.unwrap() != 1
+        || notify_ended_polls(conn, &bus, 3000).await.unwrap() != 0
+    {
+        return Err("did not end the poll exactly once".to_string());
+    }
+    let query = NotificationQuery {
+        kinds: Some(vec![NotificationKind::PollEnded]),
+        ..Default::default()
+    };
+    let ended = conn.get_notifications(&author.uid, &query).await.unwrap();
+    if ended.len() != 1 || ended[0].actor.is_some() {
+        return Err("the poll ending was not notified".to_string());
+    }
+    let ended = conn.get_notifications(&fan.uid, &query).await.unwrap();
+    if ended.len() != 1 || ended[0].post.as_ref() != Some(&closed.id) {
+        return Err("the voter was not told the poll ended".to_string());
+    }
+
+    conn.tombstone_user(&fan.uid).await.unwrap();
+    let remaining = conn
+        .get_notifications(&author.uid, &NotificationQuery::default())
+        .await
+        .unwrap();
+    if remaining.iter().any(|x| x.actor.as_ref() == Some(&fan.uid)) {
+        return Err("kept notifications of a removed user".to_string());
+    }
+    Ok(())
+}
+
+async fn voting(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let mut users = Vec::new();
+    for name in ["pollster", "first_voter", "second_voter"] {
+        let uid = conn
+            .create_user(&config.instance_domain, &new(name))
+            .await
+            .unwrap();
+        users.push(conn.get_local_user(&uid).await.unwrap());
+    }
+    let bus = StreamBus::new(STREAM_BUFFER);
+    let poll = compose(
+        conn,
+        &bus,
+        &users[0],
+        Draft {
+            content: Some("<p>which?</p>".to_string()),
+            poll: Some(DraftPoll {
+                options: vec!["this".to_string(), "that".to_string()],
+                multi_select: false,
+                closed: Some(now_millis() + 60 * 60 * 1000),
+                local_only_voting: false,
+            }),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|x| format!("{:?}", x))?;
+    let poll_link = Url::parse(&poll.id).unwrap();
+
+    let vote = |voter: &StoredUser, option: u64| Vote {
+        id: new_uid(),
+        type_field: VoteType::Vote,
+        uri: Url::parse(&format!("https://example.com/votes/{}", new_uid())).unwrap(),
+        created_at: now_millis(),
+        author: voter.versia_uri().unwrap(),
+        poll: poll_link.clone(),
+        option,
+    };
+    // a retry, a second pick in a single choice poll and another voter
+    for (voter, option) in [(1, 0), (1, 0), (1, 1), (2, 1)] {
+        handle_vote(
+            conn,
+            &bus,
+            vote(&users[voter], option),
+            &config.instance_domain,
+        )
+        .await
+        .map_err(|x| format!("{:?}", x))?;
+    }
+    let votes = conn.get_votes(&poll.id).await.unwrap();
+    if votes.len() != 2 || tally(2, &votes) != vec![1, 1] {
+        return Err(format!("votes were not deduplicated: {:?}", votes));
+    }
+    let query = NotificationQuery {
+        kinds: Some(vec![NotificationKind::Vote]),
+        ..Default::default()
+    };
+    if conn
+        .get_notifications(&users[0].uid, &query)
+        .await
+        .unwrap()
+        .len()
+        != 2
+    {
+        return Err("the author was not told once per vote".to_string());
+    }
+
+    let Some(rendered) = conn
+        .get_versia_post(
+            &poll.versia_id,
+            &EntityOrigin::Local(&config.instance_domain),
+        )
+        .await
+    else {
+        return Err("failed to render the poll".to_string());
+    };
+    let VersiaPostable::Note(note) = with_tally(conn, rendered).await.unwrap() else {
+        return Err("the poll was not rendered as a note".to_string());
+    };
+    match note.extensions.and_then(|x| x.pub_versia_polls) {
+        Some(x) if x.votes == vec![1, 1] => Ok(()),
+        x => Err(format!("the rendered poll was not tallied: {:?}", x)),
+    }
+}
+
+async fn composing(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let author = conn
+        .create_user(
+            &config.instance_domain,
+            &NewLocal::new("composer".to_string(), "filler".to_string(), None, None),
+        )
+        .await
+        .unwrap();
+    let author = conn.get_local_user(&author).await.unwrap();
+    let bus = StreamBus::new(STREAM_BUFFER);
+    let mut events = bus.subscribe();
+
+    if !matches!(
+        compose(conn, &bus, &author, Draft::default()).await,
+        Err(DbErr::InvalidType)
+    ) {
+        return Err("composed a post without content".to_string());
+    }
+
+    let before = now_millis();
+    let parent = compose(
+        conn,
+        &bus,
+        &author,
+        Draft {
+            content: Some("<p>hello</p>".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|x| format!("{:?}", x))?;
+    let versia_link = versia_post_link(&author.domain, &author.username, &parent.versia_id);
+    let ap_link = ap_post_link(&author.domain, &author.username, &parent.versia_id);
+    if parent.id.ne(versia_link.as_str()) || parent.published < before {
+        return Err("composed post was not given its id and publish time".to_string());
+    }
+    match events.try_recv() {
+        Ok(x) if matches!(x.as_ref(), StreamEvent::Post { post, .. } if post.id.eq(&parent.id)) => {
+        }
+        _ => return Err("composed post was not streamed".to_string()),
+    }
+
+    // clients only know our posts by their activitypub link
+    let reply = compose(
+        conn,
+        &bus,
+        &author,
+        Draft {
+            content: Some("<p>spoilers</p>".to_string()),
+            subject: Some("cw".to_string()),
+            is_sensitive: true,
+            in_reply_to: Some(ap_link.clone()),
+            followers_only: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|x| format!("{:?}", x))?;
+    let Some(stored) = conn.get_post(&Url::parse(&reply.id).unwrap()).await else {
+        return Err("composed reply was not stored".to_string());
+    };
+    if !stored.is_reply
+        || stored.in_reply_to.as_deref() != Some(parent.id.as_str())
+        || !stored.followers_only
+        || !stored.is_sensitive
+        || stored.subject.as_deref() != Some("cw")
+    {
+        return Err(format!("stored the reply wrong: {:?}", stored));
+    }
+
+    let Some(ApPostable::Note(note)) = stored.to_ap(&author) else {
+        return Err("reply has no activitypub note".to_string());
+    };
+    if note.in_reply_to.as_ref() != Some(&ap_link)
+        || note.sensitive != Some(true)
+        || note.summary.as_deref() != Some("cw")
+    {
+        return Err(format!("rendered the activitypub note wrong: {:?}", note));
+    }
+    let Some(VersiaPostable::Note(note)) = stored.to_versia(&author) else {
+        return Err("reply has no versia note".to_string());
+    };
+    if note.replies_to.as_ref() != Some(&versia_link)
+        || note.is_sensitive != Some(true)
+        || note.subject.as_deref() != Some("cw")
+    {
+        return Err(format!("rendered the versia note wrong: {:?}", note));
+    }
+    Ok(())
+}
+
+async fn editing(conn: &dyn Conn) -> Result<(), String> {
+    let config = get_config().unwrap();
+    conn.init().await.unwrap();
+
+    let new = |name: &str| NewLocal::new(name.to_string(), "filler".to_string(), None, None);
+    let author = conn
+        .create_user(&config.instance_domain, &new("editor"))
+        .await
+        .unwrap();
+    let other = conn
+        .create_user(&config.instance_domain, &new("not_the_editor"))
+        .await
+        .unwrap();
+    let author = conn.get_local_user(&author).await.unwrap();
+    let bus = StreamBus::new(STREAM_BUFFER);
+    let draft = |content: &str| Draft {
+        content: Some(content.to_string()),
+        ..Default::default()
+    };
+
+    let post = compose(conn, &bus, &author, draft("<p>first</p>"))
+        .await
+        .map_err(|x| format!("{:?}", x))?;
+    let link = Url::parse(&post.id).unwrap();
+    let mut events = bus.subscribe();
+    let edited = edit_local_post(conn, &bus, &author, &post, draft("<p>second</p>"))
+        .await
+        .map_err(|x| format!("{:?}", x))?;
+    match events.try_recv() {
+        Ok(x) if matches!(x.as_ref(), StreamEvent::Edit { post, .. } if post.id.eq(&edited.id)) => {
+        }
+        _ => return Err("edit was not streamed".to_string()),
+    }
+    let Some(first_edit) = edited.edited_at else {
+        return Err("edit was not timed".to_string());
+    };
+    let stored = conn
+        .get_post(&link)
+        .await
+        .ok_or("edited post went missing")?;
+    if stored.content.as_deref() != Some("<p>second</p>")
+        || stored.edited_at != Some(first_edit)
+        || stored.published != post.published
+    {
+        return Err(format!("stored the edit wrong: {:?}", stored));
+    }
+    match stored.to_ap(&author) {
+        Some(ApPostable::Note(note)) if note.updated == Some(first_edit) => {}
+        x => return Err(format!("activitypub note doesn't show the edit: {:?}", x)),
+    }
+
+    // sending the same version again is not an edit
+    edit_local_post(conn, &bus, &author, &stored, draft("<p>second</p>"))
+        .await
+        .map_err(|x| format!("{:?}", x))?;
+    let stored = conn
+        .get_post(&link)
+        .await
+        .ok_or("edited post went missing")?;
+    edit_local_post(conn, &bus, &author, &stored, draft("<p>third</p>"))
+        .await
+        .map_err(|x| format!("{:?}", x))?;
+    let revisions = conn.get_post_revisions(&post.id).await.unwrap();
+    let history: Vec<(Option<&str>, i64)> = revisions
+        .iter()
+        .map(|x| (x.content.as_deref(), x.created_at))
+        .collect();
+    if history
+        != vec![
+            (Some("<p>first</p>"), post.published),
+            (Some("<p>second</p>"), first_edit),
+        ]
+    {
+        return Err(format!("kept the wrong revisions: {:?}", history));
+    }
+
+    if !matches!(
+        edit_local_post(conn, &bus, &author, &stored, Draft::default()).await,
+        Err(DbErr::InvalidType)
+    ) {
+        return Err("edited away the content of a post".to_string());
+    }
+    let stolen = StoredPost {
+        actor: other,
+        ..stored.clone()
+    };
+    if !matches!(conn.edit_post(&stolen).await, Err(DbErr::NotFound)) {
+        return Err("edited the post of another user".to_string());
+    }
+
+    // revisions go along with their post
+    conn.delete_post(
+        &post.versia_id,
+        &EntityOrigin::Local(&config.instance_domain),
+    )
+    .await
+    .unwrap();
+    if !conn.get_post_revisions(&post.id).await.unwrap().is_empty() {
+        return Err("revisions outlived their post".to_string());
+    }
+    Ok(())
+}
+
+/// runs every test over each backend, the postgres one needs a server
+/// configured through the pg_ settings and the postgres-tests feature
+macro_rules! backend_tests {
+    ($($name:ident => $test:ident),* $(,)?) => {
+        mod memory {
+            use super::*;
+            $(
+                #[actix_web::test]
+                async fn $name() -> Result<(), String> {
+                    let config = get_config().unwrap();
+                    $test(&InMemoryConn::new(&config.instance_domain)).await
+                }
+            )*
+        }
+
+        mod sqlite {
+            use super::*;
+            $(
+                #[actix_web::test]
+                async fn $name() -> Result<(), String> {
+                    let config = get_config().unwrap();
+                    let conn = SqliteConn::open(":memory:", &config.instance_domain)
+                        .await
+                        .unwrap();
+                    $test(&conn).await
+                }
+            )*
+        }
+
+        #[cfg(feature = "postgres-tests")]
+        mod postgres {
+            use super::*;
+            $(
+                #[actix_web::test]
+                #[serial]
+                async fn $name() -> Result<(), String> {
+                    let conn = fresh_pg_conn(stringify!($name)).await;
+                    $test(conn.as_ref()).await
+                }
+            )*
+        }
+    };
+}
+
+/// a connection to an empty database named after the test, created through
+/// the configured one
+#[cfg(feature = "postgres-tests")]
+async fn fresh_pg_conn(test: &str) -> Box<dyn Conn + Sync> {
+    let mut config = get_config().unwrap();
+    let (client, connection) = tokio_postgres::Config::new()
+        .user(&config.pg_user)
+        .password(&config.pg_password)
+        .host(&config.pg_host)
+        .port(config.pg_port)
+        .dbname(&config.pg_dbname)
+        .connect(tokio_postgres::NoTls)
+        .await
+        .expect("failed to connect to the test server");
+    actix_web::rt::spawn(connection);
+
+    let dbname = format!("{}_{}", config.pg_dbname, test);
+    // one statement per query, neither runs inside a transaction
+    for statement in [
+        format!("DROP DATABASE IF EXISTS {dbname}"),
+        format!("CREATE DATABASE {dbname}"),
+    ] {
+        client
+            .batch_execute(&statement)
+            .await
+            .expect("failed to create the test database");
+    }
+
+    config.pg_dbname = dbname;
+    config.db_backend = crate::config::DbBackend::Postgres;
+    config.create_conn().await
+}
+
+backend_tests! {
+    create_and_retrieve_user => create_and_retrieve,
+    duplicate_username => duplicate,
+    delivery_retries => failed_delivery,
+    delete_local_user => delete_account,
+    remember_signature_schemes => signature_schemes,
+    rotate_user_key => key_rotation,
+    serve_instance_metadata => instance_metadata,
+    oauth_tokens => oauth_lifecycle,
+    registration => registration_modes,
+    timeline_visibility => timelines,
+    notification_generation => notifications,
+    compose_local_posts => composing,
+    poll_voting => voting,
+    post_revisions => editing,
+}
@@ -0,0 +1,105 @@
+use url::Url;
+
+use crate::{
+    cryptography::{key::Key, openssl::OpenSSLPublic},
+    db::conn::{Conn, DbErr},
+    protocols::{
+        protocol::{
+            ap_protocol::fetch::{authorized_fetch, webfinger_lookup},
+            errors::FetchErr,
+            versia_protocol::discovery::{fetch_instance_metadata, fetch_user},
+        },
+        types::{
+            activitystream_objects::actors::Actor, versia_types::entities::public_key::PublicKey,
+        },
+    },
+};
+
+use super::{instance_actor::InstanceActor, protocols::Protocol, stored_user::StoredUser};
+
+/// probes the instance for versia support, falling back to activitypub
+pub async fn detect_protocol(domain: &str) -> Protocol {
+    match fetch_instance_metadata(domain).await {
+        Ok(_) => Protocol::Versia,
+        Err(_) => Protocol::ActivityPub,
+    }
+}
+
+pub fn versia_key_to_openssl(key: &PublicKey) -> Option<OpenSSLPublic> {
+    let pem = key.key.to_pem().ok()?;
+    OpenSSLPublic::from_pem(pem.as_bytes()).ok()
+}
+
+/// fetches a federated user by their id. for activitypub the link may
+/// also be the id of their key
+pub async fn fetch_federated_user(
+    link: &Url,
+    protocol: Protocol,
+    instance_actor: &InstanceActor,
+    instance_domain: &str,
+) -> Result<StoredUser, DbErr> {
+    let mut link = link.clone();
+    link.set_fragment(None);
+    let user = match protocol {
+        Protocol::ActivityPub => {
+            let actor: Actor = authorized_fetch(
+                &link,
+                &InstanceActor::get_key_id(instance_domain),
+                &mut instance_actor.get_private_key(),
+            )
+            .await
+            .map_err(DbErr::FetchErr)?;
+            StoredUser::from_actor(&actor)
+        }
+        Protocol::Versia => {
+            let user = fetch_user(&link).await.map_err(DbErr::FetchErr)?;
+            StoredUser::from_versia(&user)
+        }
+    };
+    user.ok_or(DbErr::InvalidType)
+}
+
+/// resolves a federated user by their handle through webfinger
+pub async fn fetch_federated_handle(
+    username: &str,
+    domain: &str,
+    protocol: Protocol,
+    instance_actor: &InstanceActor,
+    instance_domain: &str,
+) -> Result<StoredUser, DbErr> {
+    let webfinger = webfinger_lookup(username, domain)
+        .await
+        .map_err(DbErr::FetchErr)?;
+    let link = match protocol {
+        Protocol::ActivityPub => webfinger.get_self_link("application/activity+json"),
+        Protocol::Versia => webfinger.get_self_link("application/json"),
+    };
+    let Some(link) = link else {
+        return Err(DbErr::NotFound);
+    };
+    let Ok(link) = Url::parse(link) else {
+        return Err(DbErr::FetchErr(FetchErr::InvalidUrl(link.to_string())));
+    };
+    fetch_federated_user(&link, protocol, instance_actor, instance_domain).await
+}
+
+/// fetches a stored federated user from their instance again so a
+/// changed key or profile gets picked up. local users are returned as is
+pub async fn refresh_federated_user(
+    conn: &dyn Conn,
+    link: &Url,
+    instance_domain: &str,
+) -> Result<StoredUser, DbErr> {
+    let stored = conn.resolve_user(link).await?;
+    if stored.is_local() {
+        return Ok(stored);
+    }
+    let Some(domain) = link.domain() else {
+        return Err(DbErr::NotFound);
+    };
+    let protocol = conn.get_protocol(domain).await;
+    let instance_actor = conn.get_instance_actor().await?;
+    let user = fetch_federated_user(link, protocol, &instance_actor, instance_domain).await?;
+    conn.update_federated_user(&user).await?;
+    conn.resolve_user(link).await
+}
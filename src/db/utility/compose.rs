@@ -0,0 +1,206 @@
+use chrono::DateTime;
+use url::Url;
+
+use crate::{
+    db::conn::{Conn, DbErr},
+    protocols::types::{
+        activitystream_objects::{new_post::NewPost, question::ChoiceType},
+        versia_types::entities::notes::Category,
+    },
+};
+
+use super::{
+    new_actor::versia_post_link,
+    notify_followers::notify_followers,
+    post_types::PostSupertype,
+    stored_post::{is_public, StoredPost},
+    stored_user::{new_uid, now_millis, StoredUser},
+    streaming::{StreamBus, StreamEvent},
+};
+
+pub const MAX_POLL_OPTIONS: usize = 10;
+
+/// a poll being attached to a post
+#[derive(Debug, Clone)]
+pub struct DraftPoll {
+    pub options: Vec<String>,
+    pub multi_select: bool,
+    /// when voting ends, never when none
+    pub closed: Option<i64>,
+    /// if only local users can vote
+    pub local_only_voting: bool,
+}
+
+/// a post a local user is writing, in whichever way their client sent
+/// it. it only gets an id and publish time once composed
+#[derive(Debug, Clone, Default)]
+pub struct Draft {
+    /// html, plain text should already be converted
+    pub content: Option<String>,
+    /// shown in place of the content when sensitive, as a content warning
+    pub subject: Option<String>,
+    pub is_sensitive: bool,
+    pub in_reply_to: Option<Url>,
+    pub followers_only: bool,
+    pub local_only: bool,
+    pub poll: Option<DraftPoll>,
+}
+
+impl Draft {
+    /// drafts from activitypub clients posting to the outbox. notes not
+    /// addressed to the public are followers only
+    pub fn from_new_post(post: NewPost) -> Self {
+        match post {
+            NewPost::NewNote(note) => Draft {
+                is_sensitive: note.summary.is_some(),
+                subject: note.summary,
+                content: note.content,
+                in_reply_to: note.in_reply_to,
+                followers_only: !is_public(&note.to),
+                ..Default::default()
+            },
+            NewPost::NewQuestion(question) => {
+                let (multi_select, options) = match question.options {
+                    ChoiceType::AnyOf(x) => (true, x),
+                    ChoiceType::OneOf(x) => (false, x),
+                };
+                let closed = question
+                    .closed
+                    .as_deref()
+                    .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+                    .map(|x| x.timestamp_millis());
+                Draft {
+                    poll: Some(DraftPoll {
+                        options: options.into_iter().map(|x| x.name).collect(),
+                        multi_select,
+                        closed,
+                        local_only_voting: question.local_only.unwrap_or(false),
+                    }),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// posts need content or a poll, polls need at least two options and
+    /// can't already be closed
+    pub fn is_valid(&self, now: i64) -> bool {
+        let has_content = self
+            .content
+            .as_deref()
+            .is_some_and(|x| !x.trim().is_empty());
+        match &self.poll {
+            None => has_content,
+            Some(poll) => {
+                (2..=MAX_POLL_OPTIONS).contains(&poll.options.len())
+                    && poll.options.iter().all(|x| !x.trim().is_empty())
+                    && poll.closed.is_none_or(|x| x > now)
+            }
+        }
+    }
+
+    /// gives the draft its id and publish time, stored under the versia
+    /// link like every local post
+    pub fn into_post(self, author: &StoredUser, published: i64) -> StoredPost {
+        let versia_id = new_uid();
+        let (surtype, subtype) = match self.poll {
+            Some(_) => (PostSupertype::Question, r#""Question""#),
+            None => (PostSupertype::Object, r#""Note""#),
+        };
+        StoredPost {
+            id: versia_post_link(&author.domain, &author.username, &versia_id).to_string(),
+            versia_id,
+            domain: author.domain.clone(),
+            surtype,
+            subtype: subtype.to_string(),
+            category: Category::Microblog,
+            likes: 0,
+            boosts: 0,
+            local_only: self.local_only,
+            followers_only: self.followers_only,
+            published,
+            is_reply: self.in_reply_to.is_some(),
+            in_reply_to: self.in_reply_to.map(|x| x.to_string()),
+            content: self.content,
+            subject: self.subject,
+            is_sensitive: self.is_sensitive,
+            shared: None,
+            multi_select: self.poll.as_ref().map(|x| x.multi_select),
+            options: self.poll.as_ref().map(|x| x.options.clone()),
+            closed: self.poll.as_ref().and_then(|x| x.closed),
+            local_only_voting: self.poll.as_ref().map(|x| x.local_only_voting),
+            edited_at: None,
+            actor: author.uid.clone(),
+        }
+    }
+}
+
+/// publishes a post for a local user, no matter which protocol or api
+/// it was written through. once stored, the post is queued for delivery
+/// to remote followers in the protocol of their instance and published
+/// onto open streams
+pub async fn compose(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    author: &StoredUser,
+    mut draft: Draft,
+) -> Result<StoredPost, DbErr> {
+    let now = now_millis();
+    if !author.is_local() || author.deleted || !draft.is_valid(now) {
+        return Err(DbErr::InvalidType);
+    }
+    // replies are stored under the id of the parent, which for our own
+    // posts isn't the activitypub link a client may have used
+    if let Some(parent) = &draft.in_reply_to {
+        if let Some(parent) = conn.get_post(parent).await {
+            draft.in_reply_to = Url::parse(&parent.id).ok();
+        }
+    }
+    let post = draft.into_post(author, now);
+    conn.create_local_post(&post).await?;
+    // the post is already stored so failing to queue deliveries
+    // shouldn't fail creating it
+    if let Err(x) = notify_followers(conn, &post, author).await {
+        eprintln!("failed to notify followers of {}: {}", post.id, x);
+    }
+    bus.publish(StreamEvent::Post {
+        post: post.clone(),
+        author: author.clone(),
+    });
+    Ok(post)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draft_validation() -> Result<(), String> {
+        let note = Draft {
+            content: Some("<p>hello</p>".to_string()),
+            ..Default::default()
+        };
+        if !note.is_valid(0) || Draft::default().is_valid(0) {
+            return Err("content was not required".to_string());
+        }
+        let poll = |options: &[&str], closed: Option<i64>| Draft {
+            poll: Some(DraftPoll {
+                options: options.iter().map(|x| x.to_string()).collect(),
+                multi_select: false,
+                closed,
+                local_only_voting: false,
+            }),
+            ..Default::default()
+        };
+        if !poll(&["yes", "no"], Some(2000)).is_valid(1000) {
+            return Err("rejected a valid poll".to_string());
+        }
+        if poll(&["yes"], None).is_valid(0)
+            || poll(&["yes", " "], None).is_valid(0)
+            || poll(&["yes", "no"], Some(500)).is_valid(1000)
+        {
+            return Err("accepted an invalid poll".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    db::conn::{Conn, DbErr},
+    protocols::types::{
+        activitystream_objects::delete::Delete,
+        versia_types::entities::delete::{Delete as VersiaDelete, DeleteTypeField, DeletedType},
+    },
+};
+
+use super::{
+    delivery::{remote_inboxes, Delivery, INSTANCE_SIGNER},
+    protocols::Protocol,
+    stored_user::{new_uid, now_millis},
+};
+
+/// deletes a local account and queues a delete of the user to every
+/// instance that follows them or is followed by them. returns the
+/// number of deliveries queued
+pub async fn delete_local_account(conn: &dyn Conn, uid: &str) -> Result<usize, DbErr> {
+    let user = conn
+        .get_local_user(uid)
+        .await
+        .filter(|x| !x.deleted)
+        .ok_or(DbErr::NotFound)?;
+    // the follows are gone once the user is tombstoned
+    let mut known = conn.get_followers(uid).await?;
+    known.extend(conn.get_following(uid).await?);
+    let inboxes = remote_inboxes(conn, &known).await;
+    conn.tombstone_user(uid).await?;
+
+    let ap_body = user
+        .ap_id()
+        .map(|x| serde_json::to_string(&Delete::new_actor(x).wrap_context()).unwrap());
+    // the profile of the user stops being served so remote versia instances
+    // couldn't fetch their key to check the delete. the instance signs it instead
+    let versia_body = user.versia_uri().map(|uri| {
+        let delete = VersiaDelete {
+            type_field: DeleteTypeField::Delete,
+            id: new_uid(),
+            created_at: now_millis(),
+            author: None,
+            deleted_type: DeletedType::User,
+            deleted: uri,
+        };
+        serde_json::to_string(&delete).unwrap()
+    });
+
+    let mut queued = 0;
+    for (inbox, protocol) in inboxes {
+        let (body, signer) = match protocol {
+            Protocol::ActivityPub => (&ap_body, uid),
+            Protocol::Versia => (&versia_body, INSTANCE_SIGNER),
+        };
+        let Some(body) = body else {
+            continue;
+        };
+        conn.queue_delivery(&Delivery::new(&inbox, protocol, body.clone(), signer))
+            .await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
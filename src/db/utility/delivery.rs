@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap};
+
+use url::Url;
+
+use crate::{
+    cryptography::openssl::OpenSSLPrivate,
+    db::conn::{Conn, DbErr},
+    protocols::protocol::{
+        ap_protocol::outgoing::post_to_inbox,
+        errors::FetchErr,
+        versia_protocol::requests::{versia_post, Signer},
+    },
+};
+
+use super::{
+    instance_actor::InstanceActor,
+    protocols::{Protocol, SignatureScheme},
+    stored_user::{new_uid, now_millis, StoredUser},
+};
+
+/// the first retry happens this long after the first failure
+const BASE_BACKOFF_MILLIS: i64 = 30 * 1000;
+/// retries never wait longer than this
+const MAX_BACKOFF_MILLIS: i64 = 6 * 60 * 60 * 1000;
+
+/// the signer of deliveries made by the instance itself rather than
+/// one of its users
+pub const INSTANCE_SIGNER: &str = "instance";
+
+/// a request waiting in the delivery queue
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub id: String,
+    pub inbox: String,
+    pub protocol: Protocol,
+    pub body: String,
+    /// uid of the local user whose key signs the request
+    /// or [`INSTANCE_SIGNER`]
+    pub signer: String,
+    pub attempts: i32,
+    pub created_at: i64,
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+    pub dead: bool,
+}
+
+impl Delivery {
+    /// a new delivery that is due immediately
+    pub fn new(inbox: &str, protocol: Protocol, body: String, signer: &str) -> Self {
+        let now = now_millis();
+        Delivery {
+            id: new_uid(),
+            inbox: inbox.to_string(),
+            protocol,
+            body,
+            signer: signer.to_string(),
+            attempts: 0,
+            created_at: now,
+            next_attempt: now,
+            last_error: None,
+            dead: false,
+        }
+    }
+
+    /// records a failed attempt and schedules the next one. the delivery
+    /// is marked dead once it has been failing for longer than dead_after
+    pub fn failed(&mut self, error: String, now: i64, dead_after: i64) {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        self.next_attempt = now + backoff(self.attempts);
+        if now - self.created_at > dead_after {
+            self.dead = true;
+        }
+    }
+
+    /// records an attempt the target refused in a way that sending it
+    /// again won't change, so the delivery is marked dead right away
+    pub fn rejected(&mut self, error: String) {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        self.dead = true;
+    }
+}
+
+/// signs the delivery as its signer and sends it in the protocol it was queued for
+pub async fn send_delivery(
+    conn: &dyn Conn,
+    instance_domain: &str,
+    delivery: &Delivery,
+) -> Result<(), FetchErr> {
+    if delivery.signer.eq(INSTANCE_SIGNER) {
+        return send_instance_delivery(conn, instance_domain, delivery).await;
+    }
+    let Some(signer) = conn.get_local_user(&delivery.signer).await else {
+        return Err(FetchErr::RequestErr(format!(
+            "signer {} is not a local user",
+            delivery.signer
+        )));
+    };
+    let Some(mut key) = signer.private_key() else {
+        return Err(FetchErr::RequestErr(format!(
+            "signer {} has an invalid key",
+            delivery.signer
+        )));
+    };
+    let inbox = parse_inbox(delivery)?;
+    match delivery.protocol {
+        Protocol::ActivityPub => {
+            post_ap_delivery(
+                conn,
+                &delivery.body,
+                &signer.public_key_id,
+                &inbox,
+                &mut key,
+            )
+            .await
+        }
+        Protocol::Versia => {
+            let Some(signed_by) = signer.versia_uri() else {
+                return Err(FetchErr::RequestErr(format!(
+                    "signer {} has an invalid link",
+                    delivery.signer
+                )));
+            };
+            versia_post(inbox, &delivery.body, key, &Signer::User(signed_by)).await
+        }
+    }
+}
+
+fn parse_inbox(delivery: &Delivery) -> Result<Url, FetchErr> {
+    Url::parse(&delivery.inbox).map_err(|_| FetchErr::InvalidUrl(delivery.inbox.clone()))
+}
+
+/// activitypub deliveries are signed by the instance actor, versia
+/// ones with the instance's own key
+async fn send_instance_delivery(
+    conn: &dyn Conn,
+    instance_domain: &str,
+    delivery: &Delivery,
+) -> Result<(), FetchErr> {
+    let instance_actor = conn
+        .get_instance_actor()
+        .await
+        .map_err(|x| FetchErr::RequestErr(x.to_string()))?;
+    let inbox = parse_inbox(delivery)?;
+    match delivery.protocol {
+        Protocol::ActivityPub => {
+            post_ap_delivery(
+                conn,
+                &delivery.body,
+                &InstanceActor::get_key_id(instance_domain),
+                &inbox,
+                &mut instance_actor.get_private_key(),
+            )
+            .await
+        }
+        Protocol::Versia => {
+            versia_post(
+                inbox,
+                &delivery.body,
+                instance_actor.get_versia_private_key(),
+                &Signer::Instance(instance_domain.to_string()),
+            )
+            .await
+        }
+    }
+}
+
+/// deliveries are signed with rfc 9421 unless the instance is known to
+/// only accept draft-cavage. an instance rejecting our first rfc 9421
+/// signature is retried with cavage and remembered either way
+async fn post_ap_delivery(
+    conn: &dyn Conn,
+    body: &str,
+    key_id: &str,
+    inbox: &Url,
+    key: &mut OpenSSLPrivate,
+) -> Result<(), FetchErr> {
+    let Some(domain) = inbox.host_str() else {
+        return Err(FetchErr::InvalidUrl(inbox.to_string()));
+    };
+    let known = conn.get_signature_scheme(domain).await;
+    let scheme = known.unwrap_or(SignatureScheme::Rfc9421);
+    let (result, scheme) = match post_to_inbox(body, key_id, inbox, key, scheme).await {
+        Err(FetchErr::Unauthorized(_)) if known.is_none() => {
+            let scheme = SignatureScheme::Cavage;
+            (
+                post_to_inbox(body, key_id, inbox, key, scheme).await,
+                scheme,
+            )
+        }
+        x => (x, scheme),
+    };
+    if result.is_ok() && known.ne(&Some(scheme)) {
+        // forgetting only costs an extra attempt next time
+        let _ = conn.set_signature_scheme(domain, scheme).await;
+    }
+    result
+}
+
+/// the inboxes that reach every remote user given, along with the protocol
+/// each one speaks. users sharing an inbox only get one entry
+pub async fn remote_inboxes(conn: &dyn Conn, users: &[StoredUser]) -> BTreeMap<String, Protocol> {
+    let mut inboxes: BTreeMap<String, Protocol> = BTreeMap::new();
+    // versia instances list their shared inbox in their metadata
+    let mut versia_shared: HashMap<String, Option<String>> = HashMap::new();
+    for user in users.iter().filter(|x| !x.is_local()) {
+        let protocol = conn.get_protocol(&user.domain).await;
+        let shared_inbox = match protocol {
+            Protocol::ActivityPub => user.shared_inbox.clone(),
+            Protocol::Versia => match versia_shared.get(&user.domain) {
+                Some(x) => x.clone(),
+                None => {
+                    let shared = conn
+                        .get_versia_instance_metadata(&user.domain)
+                        .await
+                        .and_then(|x| x.shared_inbox)
+                        .map(|x| x.to_string());
+                    versia_shared.insert(user.domain.clone(), shared.clone());
+                    shared
+                }
+            },
+        };
+        let inbox = shared_inbox.unwrap_or_else(|| user.inbox.clone());
+        inboxes.insert(inbox, protocol);
+    }
+    inboxes
+}
+
+/// attempts every delivery that is due, removing the ones that succeed
+/// and rescheduling the ones that fail. deliveries the target refused
+/// for good are marked dead right away, and so is every delivery to an
+/// inbox that is gone or stayed unreachable for the whole window.
+/// returns how many were attempted
+pub async fn process_due_deliveries(
+    conn: &dyn Conn,
+    instance_domain: &str,
+    batch_size: u64,
+    dead_after: i64,
+) -> Result<usize, DbErr> {
+    let due = conn.get_due_deliveries(now_millis(), batch_size).await?;
+    for mut delivery in due.iter().cloned() {
+        if conn.is_inbox_dead(&delivery.inbox).await? {
+            delivery.rejected(format!("{} is dead", delivery.inbox));
+            conn.update_delivery(&delivery).await?;
+            continue;
+        }
+        match send_delivery(conn, instance_domain, &delivery).await {
+            Ok(()) => conn.remove_delivery(&delivery.id).await?,
+            Err(err) if !err.is_retryable() => {
+                delivery.rejected(err.to_string());
+                if matches!(err, FetchErr::Status(410, _)) {
+                    conn.mark_inbox_dead(&delivery.inbox).await?;
+                }
+                conn.update_delivery(&delivery).await?;
+            }
+            Err(err) => {
+                delivery.failed(err.to_string(), now_millis(), dead_after);
+                if delivery.dead {
+                    conn.mark_inbox_dead(&delivery.inbox).await?;
+                }
+                conn.update_delivery(&delivery).await?;
+            }
+        }
+    }
+    Ok(due.len())
+}
+
+/// exponential backoff in millis for the given number of failed attempts
+pub fn backoff(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    BASE_BACKOFF_MILLIS
+        .saturating_mul(2_i64.pow(exponent))
+        .min(MAX_BACKOFF_MILLIS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() -> Result<(), String> {
+        assert_eq!(backoff(1), BASE_BACKOFF_MILLIS);
+        assert_eq!(backoff(2), BASE_BACKOFF_MILLIS * 2);
+        assert_eq!(backoff(3), BASE_BACKOFF_MILLIS * 4);
+        assert_eq!(backoff(100), MAX_BACKOFF_MILLIS);
+        Ok(())
+    }
+
+    #[test]
+    fn test_marked_dead() -> Result<(), String> {
+        let mut delivery = Delivery::new("https://a.com/inbox", Protocol::Versia, "".into(), "x");
+        let start = delivery.created_at;
+        delivery.failed("down".to_string(), start + 10, 1000);
+        if delivery.dead {
+            return Err("delivery marked dead before the window passed".to_string());
+        }
+        delivery.failed("down".to_string(), start + 2000, 1000);
+        if !delivery.dead {
+            return Err("delivery not marked dead after the window passed".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejected() -> Result<(), String> {
+        let status = |x: u16| FetchErr::Status(x, String::new());
+        for retryable in [status(408), status(429), status(503)] {
+            if !retryable.is_retryable() {
+                return Err(format!("{} was not retried", retryable));
+            }
+        }
+        if !FetchErr::RequestErr("connection refused".to_string()).is_retryable() {
+            return Err("network error was not retried".to_string());
+        }
+        let refused = [
+            status(400),
+            FetchErr::Unauthorized(String::new()),
+            status(403),
+            status(404),
+            status(410),
+        ];
+        for err in refused {
+            if err.is_retryable() {
+                return Err(format!("{} was retried", err));
+            }
+        }
+
+        let mut delivery = Delivery::new("https://a.com/inbox", Protocol::Versia, "".into(), "x");
+        delivery.rejected(status(403).to_string());
+        match delivery.dead && delivery.attempts == 1 {
+            true => Ok(()),
+            false => Err("rejected delivery was not marked dead".to_string()),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use url::Url;
+
+use crate::{
+    db::conn::{Conn, DbErr, InsertErr},
+    protocols::types::{
+        activitystream_objects::{
+            follow_and_response::{Follow, FollowResponse, FollowType, ResponseType},
+            link::RangeLinkItem,
+        },
+        versia_types::entities::{
+            change_follow::{ChangeFollowType, ChangeFollowing},
+            follow_response::{FollowResponse as VersiaFollowResponse, FollowResponseType},
+        },
+    },
+};
+
+use super::{
+    delivery::Delivery,
+    new_actor::local_username,
+    notifications::{notify, NotificationKind},
+    protocols::Protocol,
+    stored_user::{new_uid, now_millis, StoredUser},
+    streaming::StreamBus,
+};
+
+/// creates the follow, treating one that already exists as a retry of
+/// the original. returns if the follow still needs to be approved
+async fn store_follow(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    follower: &StoredUser,
+    target: &StoredUser,
+    activity_id: Option<&str>,
+) -> Result<bool, DbErr> {
+    match conn
+        .create_follow(
+            &follower.uid,
+            &target.uid,
+            target.manual_followers,
+            activity_id,
+        )
+        .await
+    {
+        Ok(()) => {
+            let kind = match target.manual_followers {
+                true => NotificationKind::FollowRequest,
+                false => NotificationKind::Follow,
+            };
+            notify(conn, bus, target, kind, Some(follower), None).await?;
+            Ok(target.manual_followers)
+        }
+        // the follower may not have gotten our accept the first time
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => Ok(target.manual_followers),
+        Err(x) => Err(x),
+    }
+}
+
+/// stores an incoming activitypub follow of a local user. users that
+/// don't approve followers manually get an accept queued straight away
+pub async fn handle_follow(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    follow: Follow,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    // only resolve the target if it's ours so a follow can't make us fetch
+    if local_username(instance_domain, &follow.object).is_none() {
+        return Err(DbErr::NotFound);
+    }
+    let target = conn.resolve_user(&follow.object).await?;
+    let follower = conn.resolve_user(&follow.actor).await?;
+
+    if let FollowType::Unfollow = follow.type_field {
+        return conn.remove_follow(&follower.uid, &target.uid).await;
+    }
+
+    if store_follow(conn, bus, &follower, &target, Some(follow.id.as_str())).await? {
+        return Ok(());
+    }
+
+    let accept = FollowResponse::new(ResponseType::Accept, follow);
+    let body = serde_json::to_string(&accept.wrap_context()).unwrap();
+    conn.queue_delivery(&Delivery::new(
+        &follower.inbox,
+        Protocol::ActivityPub,
+        body,
+        &target.uid,
+    ))
+    .await
+}
+
+/// settles a follow sent by one of our users. accepted follows stop
+/// being pending and rejected ones are removed
+pub async fn handle_follow_response(
+    conn: &dyn Conn,
+    response: FollowResponse,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let follower = match &response.object {
+        RangeLinkItem::Item(follow) => {
+            if follow.object.ne(&response.actor) {
+                return Err(DbErr::InvalidType);
+            }
+            follow.actor.clone()
+        }
+        // our follows are a fragment of the follower's id
+        RangeLinkItem::Link(_) => {
+            let mut link: Url = response.follow_id().clone();
+            link.set_fragment(None);
+            link
+        }
+    };
+    if local_username(instance_domain, &follower).is_none() {
+        return Err(DbErr::NotFound);
+    }
+    let follower = conn.resolve_user(&follower).await?;
+    let target = conn.resolve_user(&response.actor).await?;
+
+    match response.type_field {
+        ResponseType::Accept => conn.accept_follow(&follower.uid, &target.uid).await,
+        ResponseType::Reject => conn.remove_follow(&follower.uid, &target.uid).await,
+    }
+}
+
+/// the versia counterpart of [`handle_follow`], also handling unfollows
+pub async fn handle_versia_follow(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    change: ChangeFollowing,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    if local_username(instance_domain, &change.followee).is_none() {
+        return Err(DbErr::NotFound);
+    }
+    let target = conn.resolve_user(&change.followee).await?;
+    let follower = conn.resolve_user(&change.author).await?;
+
+    if let ChangeFollowType::Unfollow = change.type_field {
+        return conn.remove_follow(&follower.uid, &target.uid).await;
+    }
+    // versia unfollows name the followee rather than the follow
+    if store_follow(conn, bus, &follower, &target, None).await? {
+        return Ok(());
+    }
+
+    let Some(author) = target.versia_uri() else {
+        return Err(DbErr::InvalidType);
+    };
+    let accept = VersiaFollowResponse {
+        type_field: FollowResponseType::FollowAccept,
+        id: new_uid(),
+        author,
+        created_at: now_millis(),
+        follower: change.author,
+    };
+    let body = serde_json::to_string(&accept).unwrap();
+    conn.queue_delivery(&Delivery::new(
+        &follower.inbox,
+        Protocol::Versia,
+        body,
+        &target.uid,
+    ))
+    .await
+}
+
+/// versia responses name the follower directly. a reject may also
+/// come after the follow was accepted to remove the follower
+pub async fn handle_versia_follow_response(
+    conn: &dyn Conn,
+    response: VersiaFollowResponse,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    if local_username(instance_domain, &response.follower).is_none() {
+        return Err(DbErr::NotFound);
+    }
+    let follower = conn.resolve_user(&response.follower).await?;
+    let target = conn.resolve_user(&response.author).await?;
+
+    match response.type_field {
+        FollowResponseType::FollowAccept => conn.accept_follow(&follower.uid, &target.uid).await,
+        FollowResponseType::FollowReject => conn.remove_follow(&follower.uid, &target.uid).await,
+    }
+}
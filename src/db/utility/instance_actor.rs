@@ -1,57 +1,203 @@
 use crate::{
-    cryptography::openssl::OpenSSLPrivate, protocols::types::activitystream_objects::actors::Actor,
+    cryptography::{
+        key::{Key, KeyType, PrivateKey},
+        openssl::{OpenSSLPrivate, OpenSSLPublic},
+    },
+    db::conn::{Conn, DbErr},
+    protocols::types::{
+        activitystream_objects::{
+            actors::{Actor, ActorType, Endpoints},
+            public_key::PublicKey,
+            update::Update,
+        },
+        versia_types::entities::{
+            instance_metadata::InstanceMetadata,
+            public_key::{AlgorithmsPublicKey, PublicKey as VersiaPublicKey},
+        },
+    },
 };
 
+use super::{
+    delivery::{Delivery, INSTANCE_SIGNER},
+    new_actor::instance_actor_links,
+    protocols::Protocol,
+    stored_user::{now_millis, PREVIOUS_KEY_GRACE_MILLIS},
+};
+
+#[derive(Clone, Debug)]
 pub struct InstanceActor {
     pub private_key_pem: String,
     pub public_key_pem: String,
+    /// the key the instance signs versia requests with as itself
+    pub versia_private_key_pem: String,
+    pub versia_public_key_pem: String,
+    /// the keys replaced by the last rotation, accepted until
+    /// `previous_key_expires`
+    pub previous_public_key_pem: Option<String>,
+    pub previous_versia_public_key_pem: Option<String>,
+    pub previous_key_expires: Option<i64>,
+    pub created_at: i64,
 }
 
 impl InstanceActor {
+    /// the instance actor uses rsa as it is what most activitypub
+    /// software expects for authorized fetch
+    pub fn generate() -> Self {
+        let key = OpenSSLPrivate::generate(KeyType::Rsa256);
+        let mut actor = InstanceActor {
+            private_key_pem: key.to_pem().expect("generated an invalid key"),
+            public_key_pem: key.public_key_pem().expect("generated an invalid key"),
+            versia_private_key_pem: String::new(),
+            versia_public_key_pem: String::new(),
+            previous_public_key_pem: None,
+            previous_versia_public_key_pem: None,
+            previous_key_expires: None,
+            created_at: now_millis(),
+        };
+        actor.generate_versia_key();
+        actor
+    }
+    /// replaces the versia key, which can only be ed25519
+    pub fn generate_versia_key(&mut self) {
+        let key = OpenSSLPrivate::generate(KeyType::Ed25519);
+        self.versia_private_key_pem = key.to_pem().expect("generated an invalid key");
+        self.versia_public_key_pem = key.public_key_pem().expect("generated an invalid key");
+    }
+    /// replaces both keypairs, `algorithm` being used for the activitypub
+    /// one. the old public keys are kept for the grace period
+    pub fn rotate(&mut self, algorithm: KeyType) {
+        let key = OpenSSLPrivate::generate(algorithm);
+        self.private_key_pem = key.to_pem().expect("generated an invalid key");
+        let public_key_pem = key.public_key_pem().expect("generated an invalid key");
+        let previous = std::mem::replace(&mut self.public_key_pem, public_key_pem);
+        self.previous_public_key_pem = Some(previous);
+        self.previous_versia_public_key_pem = Some(self.versia_public_key_pem.clone());
+        self.generate_versia_key();
+        self.previous_key_expires = Some(now_millis() + PREVIOUS_KEY_GRACE_MILLIS);
+    }
+    /// the current versia key along with the previous one while it is
+    /// still within its grace period
+    pub fn versia_verifying_keys(&self) -> Vec<OpenSSLPublic> {
+        let previous = match (
+            &self.previous_versia_public_key_pem,
+            self.previous_key_expires,
+        ) {
+            (Some(pem), Some(expires)) if expires > now_millis() => {
+                OpenSSLPublic::from_pem(pem.as_bytes()).ok()
+            }
+            _ => None,
+        };
+        OpenSSLPublic::from_pem(self.versia_public_key_pem.as_bytes())
+            .ok()
+            .into_iter()
+            .chain(previous)
+            .collect()
+    }
     pub fn pub_key_id(domain: &str) -> String {
-        format!("https://{domain}/actor/ap#main-key")
-    }
-    // pub async fn init_instance_actor(conn: &dyn Conn) {
-    //     if conn.get_instance_actor().await.is_none() {
-    //         let key = OpenSSLPrivate::generate();
-    //         conn.create_instance_actor(key.private_key_pem(), key.public_key_pem())
-    //             .await;
-    //     }
-    // }
+        instance_actor_links(domain).pub_key_id.to_string()
+    }
     pub fn get_private_key(&self) -> OpenSSLPrivate {
-        // AlgorithmsPrivateKey::from_pem(
-        //     &self.private_key_pem,
-        //     crate::cryptography::key::KeyType::Ed25519,
-        // )
-        // .unwrap()
-        todo!()
+        OpenSSLPrivate::from_pem(self.private_key_pem.as_bytes())
+            .expect("instance actor has an invalid private key")
+    }
+    pub fn get_versia_private_key(&self) -> OpenSSLPrivate {
+        OpenSSLPrivate::from_pem(self.versia_private_key_pem.as_bytes())
+            .expect("instance actor has an invalid versia key")
     }
+    /// the metadata we serve at /.well-known/versia
+    pub fn to_versia_metadata(&self, domain: &str) -> InstanceMetadata {
+        let key = AlgorithmsPublicKey::from_pem(self.versia_public_key_pem.as_bytes())
+            .expect("instance actor has an invalid versia key");
+        InstanceMetadata::new(
+            domain.to_string(),
+            None,
+            domain.to_string(),
+            None,
+            VersiaPublicKey { actor: None, key },
+            None,
+            self.created_at,
+        )
+    }
+    /// the key id used when the instance actor signs a request
     pub fn get_key_id(domain: &str) -> String {
-        format!("https://{}/{}", domain, domain)
-    }
-    pub fn to_actor(&self, _domain: &str) -> Actor {
-        // let links = instance_actor_links(domain);
-        todo!()
-        // Actor {
-        //     type_field: crate::activitystream_objects::actors::ActorType::Application,
-        //     id: links.id.clone(),
-        //     preferred_username: domain.to_string(),
-        //     summary: None,
-        //     name: None,
-        //     url: Some(
-        //         Url::parse(&format!("https://{domain}/about/more?instance_actor=true")).unwrap(),
-        //     ),
-        //     public_key: PublicKey {
-        //         id: links.pub_key_id,
-        //         owner: links.id,
-        //         public_key_pem: self.public_key_pem.clone(),
-        //     },
-        //     inbox: links.inbox,
-        //     outbox: links.outbox,
-        //     followers: links.followers,
-        //     following: links.following,
-        //     domain: Some(domain.to_string()),
-        //     liked: Some(links.liked),
-        // }
+        Self::pub_key_id(domain)
+    }
+    pub fn to_actor(&self, domain: &str) -> Actor {
+        let links = instance_actor_links(domain);
+        Actor {
+            type_field: ActorType::Application,
+            id: links.id.clone(),
+            preferred_username: domain.to_string(),
+            summary: None,
+            name: None,
+            url: Some(links.url),
+            icon: None,
+            public_key: PublicKey {
+                id: links.pub_key_id,
+                owner: links.id,
+                public_key_pem: OpenSSLPublic::from_pem(self.public_key_pem.as_bytes())
+                    .expect("instance actor has an invalid public key"),
+            },
+            endpoints: Some(Endpoints {
+                shared_inbox: Some(links.inbox.clone()),
+            }),
+            inbox: links.inbox,
+            outbox: links.outbox,
+            followers: links.followers,
+            following: links.following,
+            versia_url: None,
+        }
+    }
+}
+
+/// queues our metadata to the shared inbox of every known versia
+/// instance so they pick up changes such as a new key. returns the
+/// number of deliveries queued
+pub async fn push_instance_metadata(
+    conn: &dyn Conn,
+    instance_domain: &str,
+) -> Result<usize, DbErr> {
+    let metadata = conn
+        .get_instance_actor()
+        .await?
+        .to_versia_metadata(instance_domain);
+    let body = serde_json::to_string(&metadata).unwrap();
+    let mut queued = 0;
+    for domain in conn.get_instances(Protocol::Versia).await? {
+        let Some(inbox) = conn
+            .get_versia_instance_metadata(&domain)
+            .await
+            .and_then(|x| x.shared_inbox)
+        else {
+            continue;
+        };
+        conn.queue_delivery(&Delivery::new(
+            inbox.as_str(),
+            Protocol::Versia,
+            body.clone(),
+            INSTANCE_SIGNER,
+        ))
+        .await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// queues an update of the instance actor to the shared inbox of every
+/// known activitypub user, as they cache its key like any other actor's.
+/// returns the number of deliveries queued
+pub async fn push_instance_actor(conn: &dyn Conn, instance_domain: &str) -> Result<usize, DbErr> {
+    let actor = conn.get_instance_actor().await?.to_actor(instance_domain);
+    let body = serde_json::to_string(&Update::new(actor).wrap_context()).unwrap();
+    let inboxes = conn.get_shared_inboxes().await?;
+    for inbox in &inboxes {
+        conn.queue_delivery(&Delivery::new(
+            inbox,
+            Protocol::ActivityPub,
+            body.clone(),
+            INSTANCE_SIGNER,
+        ))
+        .await?;
     }
+    Ok(inboxes.len())
 }
@@ -0,0 +1,215 @@
+use chrono::DateTime;
+use url::Url;
+
+use crate::{
+    db::conn::{Conn, DbErr, EntityOrigin, InsertErr},
+    protocols::types::{
+        activitystream_objects::{
+            announce::Announce,
+            like::{Like, LikeType},
+        },
+        versia_types::{
+            extensions::{
+                dislike::Dislike as VersiaDislike,
+                like::Like as VersiaLike,
+                share::{Share, ShareType},
+                vote::Vote,
+            },
+            postable::VersiaPostable,
+        },
+    },
+};
+
+use super::{
+    new_actor::local_post_id,
+    notifications::{notify_author, NotificationKind},
+    polls::PollVote,
+    stored_post::StoredPost,
+    stored_user::now_millis,
+    streaming::{publish_post, StreamBus},
+};
+
+/// only our own posts are looked up so an interaction can't make us fetch
+async fn local_post(
+    conn: &dyn Conn,
+    link: &Url,
+    instance_domain: &str,
+) -> Result<StoredPost, DbErr> {
+    if local_post_id(instance_domain, link).is_none() {
+        return Err(DbErr::NotFound);
+    }
+    conn.get_post(link).await.ok_or(DbErr::NotFound)
+}
+
+/// likes are stored so they can be undone, dislikes only reach the
+/// author. url is the link of the activity
+async fn react(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    kind: NotificationKind,
+    actor: &Url,
+    object: &Url,
+    url: &str,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let post = local_post(conn, object, instance_domain).await?;
+    let actor = conn.resolve_user(actor).await?;
+    if let NotificationKind::Like = kind {
+        match conn.create_like(&actor.uid, &post.id, url).await {
+            Ok(()) => {}
+            // a retry of a like we already have
+            Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => return Ok(()),
+            Err(x) => return Err(x),
+        }
+    }
+    notify_author(conn, bus, &post, kind, Some(&actor)).await
+}
+
+/// an activitypub like or dislike of one of our posts
+pub async fn handle_like(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    like: Like,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let kind = match like.type_field {
+        LikeType::Like => NotificationKind::Like,
+        LikeType::Dislike => NotificationKind::Dislike,
+    };
+    react(
+        conn,
+        bus,
+        kind,
+        &like.actor,
+        &like.object,
+        like.id.as_str(),
+        instance_domain,
+    )
+    .await
+}
+
+pub async fn handle_versia_like(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    like: VersiaLike,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    react(
+        conn,
+        bus,
+        NotificationKind::Like,
+        &like.author,
+        &like.liked,
+        like.uri.as_str(),
+        instance_domain,
+    )
+    .await
+}
+
+pub async fn handle_versia_dislike(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    dislike: VersiaDislike,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    react(
+        conn,
+        bus,
+        NotificationKind::Dislike,
+        &dislike.author,
+        &dislike.disliked,
+        dislike.uri.as_str(),
+        instance_domain,
+    )
+    .await
+}
+
+/// stores a federated share and notifies the author of the shared
+/// post if it is one of ours
+pub async fn handle_share(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    share: Share,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let domain = share.uri.domain().ok_or(DbErr::InvalidType)?.to_string();
+    let sharer = share.author.clone();
+    let shared = share.shared.clone();
+    let uri = share.uri.clone();
+    conn.create_versia_post(
+        VersiaPostable::Share(share),
+        &EntityOrigin::Federated(&domain),
+    )
+    .await?;
+    publish_post(conn, bus, &uri).await?;
+    let Ok(post) = local_post(conn, &shared, instance_domain).await else {
+        return Ok(());
+    };
+    let sharer = conn.resolve_user(&sharer).await?;
+    notify_author(conn, bus, &post, NotificationKind::Share, Some(&sharer)).await
+}
+
+/// boosts are stored the same way as versia shares, under the id of
+/// the announce so they can be undone
+pub async fn handle_announce(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    announce: Announce,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let created_at = announce
+        .published
+        .as_deref()
+        .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+        .map(|x| x.timestamp_millis())
+        .unwrap_or_else(now_millis);
+    let share = Share {
+        id: announce.id.to_string(),
+        type_field: ShareType::Share,
+        created_at,
+        author: announce.actor,
+        uri: announce.id,
+        shared: announce.object,
+    };
+    handle_share(conn, bus, share, instance_domain).await
+}
+
+/// a vote in one of our open polls. a voter counts once per option and
+/// only once at all in single choice polls, further votes are ignored
+pub async fn handle_vote(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    vote: Vote,
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let poll = local_post(conn, &vote.poll, instance_domain).await?;
+    let options = poll.options.as_ref().map(Vec::len).unwrap_or_default();
+    let closed = poll.closed.is_some_and(|x| x <= now_millis());
+    if vote.option as usize >= options || closed {
+        return Err(DbErr::InvalidType);
+    }
+    let voter = conn.resolve_user(&vote.author).await?;
+    let choice = vote.option as i32;
+    let multi_select = poll.multi_select.unwrap_or(false);
+    let voted = conn
+        .get_votes(&poll.id)
+        .await?
+        .into_iter()
+        .any(|x| x.voter.eq(&voter.uid) && (x.choice == choice || !multi_select));
+    if voted {
+        return Ok(());
+    }
+    let stored = PollVote {
+        poll: poll.id.clone(),
+        voter: voter.uid.clone(),
+        choice,
+        published: vote.created_at,
+    };
+    match conn.create_vote(&stored).await {
+        Ok(()) => {}
+        // a retry racing the one we already have
+        Err(DbErr::InsertErr(InsertErr::AlreadyExists)) => return Ok(()),
+        Err(x) => return Err(x),
+    }
+    notify_author(conn, bus, &poll, NotificationKind::Vote, Some(&voter)).await
+}
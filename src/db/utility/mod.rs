@@ -1,5 +1,23 @@
+pub mod backfill;
+pub mod compose;
+pub mod delete_account;
+pub mod delivery;
+pub mod follows;
 pub mod instance_actor;
+pub mod interactions;
 pub mod new_actor;
+pub mod notifications;
+pub mod notify_followers;
+pub mod oauth;
 pub mod permission;
+pub mod polls;
 pub mod post_types;
 pub mod protocols;
+pub mod registration;
+pub mod revisions;
+pub mod rotate_key;
+pub mod stored_post;
+pub mod stored_user;
+pub mod streaming;
+pub mod timeline;
+pub mod undo;
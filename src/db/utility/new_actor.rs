@@ -24,40 +24,109 @@ pub struct UserLinks {
 
 pub fn generate_ap_links(domain: &str, uname: &str) -> UserLinks {
     UserLinks {
-        id: Url::parse(&format!("https://{domain}/users/{uname}/ap")).unwrap(),
-        inbox: Url::parse(&format!("https://{domain}/users/{uname}/inbox/ap")).unwrap(),
-        outbox: Url::parse(&format!("https://{domain}/users/{uname}/outbox/ap")).unwrap(),
-        followers: Url::parse(&format!("https://{domain}/users/{uname}/followers/ap")).unwrap(),
-        following: Url::parse(&format!("https://{domain}/users/{uname}/following/ap")).unwrap(),
-        liked: Url::parse(&format!("https://{domain}/users/{uname}/liked/ap")).unwrap(),
+        id: Url::parse(&format!("https://{domain}/ap/users/{uname}")).unwrap(),
+        inbox: Url::parse(&format!("https://{domain}/ap/users/{uname}/inbox")).unwrap(),
+        outbox: Url::parse(&format!("https://{domain}/ap/users/{uname}/outbox")).unwrap(),
+        followers: Url::parse(&format!("https://{domain}/ap/users/{uname}/followers")).unwrap(),
+        following: Url::parse(&format!("https://{domain}/ap/users/{uname}/following")).unwrap(),
+        liked: Url::parse(&format!("https://{domain}/ap/users/{uname}/liked")).unwrap(),
         url: Url::parse(&format!("https://{domain}/@{uname}")).unwrap(),
-        pub_key_id: Url::parse(&format!("https://{domain}/users/{uname}/ap#main-key")).unwrap(),
+        pub_key_id: Url::parse(&format!("https://{domain}/ap/users/{uname}#main-key")).unwrap(),
     }
 }
 
 pub fn generate_versia_links(domain: &str, uname: &str) -> UserLinks {
     UserLinks {
-        id: Url::parse(&format!("https://{domain}/users/{uname}/versia")).unwrap(),
-        inbox: Url::parse(&format!("https://{domain}/users/{uname}/inbox/versia")).unwrap(),
-        outbox: Url::parse(&format!("https://{domain}/users/{uname}/outbox/versia")).unwrap(),
-        followers: Url::parse(&format!("https://{domain}/users/{uname}/followers/versia")).unwrap(),
-        following: Url::parse(&format!("https://{domain}/users/{uname}/following/versia")).unwrap(),
-        liked: Url::parse(&format!("https://{domain}/users/{uname}/liked/versia")).unwrap(),
+        id: Url::parse(&format!("https://{domain}/versia/users/{uname}/versia")).unwrap(),
+        inbox: Url::parse(&format!("https://{domain}/versia/users/{uname}/inbox")).unwrap(),
+        outbox: Url::parse(&format!(
+            "https://{domain}/versia/users/{uname}/outbox/versia"
+        ))
+        .unwrap(),
+        followers: Url::parse(&format!(
+            "https://{domain}/versia/users/{uname}/followers/versia"
+        ))
+        .unwrap(),
+        following: Url::parse(&format!(
+            "https://{domain}/versia/users/{uname}/following/versia"
+        ))
+        .unwrap(),
+        liked: Url::parse(&format!(
+            "https://{domain}/versia/users/{uname}/liked/versia"
+        ))
+        .unwrap(),
         url: Url::parse(&format!("https://{domain}/@{uname}")).unwrap(),
-        pub_key_id: Url::parse(&format!("https://{domain}/users/{uname}/versia#main-key")).unwrap(),
+        pub_key_id: Url::parse(&format!("https://{domain}/versia/users/{uname}/versia")).unwrap(),
     }
 }
 
 pub fn instance_actor_links(domain: &str) -> UserLinks {
     UserLinks {
-        id: Url::parse(&format!("https://{domain}/actor/ap")).unwrap(),
-        inbox: Url::parse(&format!("https://{domain}/actor/inbox/ap")).unwrap(),
-        outbox: Url::parse(&format!("https://{domain}/actor/outbox/ap")).unwrap(),
-        followers: Url::parse(&format!("https://{domain}/actor/followers/ap")).unwrap(),
-        following: Url::parse(&format!("https://{domain}/actor/following/ap")).unwrap(),
-        liked: Url::parse(&format!("https://{domain}/actor/liked/ap")).unwrap(),
-        url: Url::parse(&format!("https://{domain}:")).unwrap(),
-        pub_key_id: Url::parse(&format!("https://{domain}/actor/ap#main-key")).unwrap(),
+        id: Url::parse(&format!("https://{domain}/ap/actor")).unwrap(),
+        inbox: Url::parse(&format!("https://{domain}/ap/inbox")).unwrap(),
+        outbox: Url::parse(&format!("https://{domain}/ap/actor/outbox")).unwrap(),
+        followers: Url::parse(&format!("https://{domain}/ap/actor/followers")).unwrap(),
+        following: Url::parse(&format!("https://{domain}/ap/actor/following")).unwrap(),
+        liked: Url::parse(&format!("https://{domain}/ap/actor/liked")).unwrap(),
+        url: Url::parse(&format!("https://{domain}/about/more?instance_actor=true")).unwrap(),
+        pub_key_id: Url::parse(&format!("https://{domain}/ap/actor#main-key")).unwrap(),
+    }
+}
+
+pub fn ap_post_link(domain: &str, uname: &str, pid: &str) -> Url {
+    Url::parse(&format!("https://{domain}/ap/users/{uname}/statuses/{pid}")).unwrap()
+}
+
+pub fn versia_post_link(domain: &str, uname: &str, pid: &str) -> Url {
+    Url::parse(&format!(
+        "https://{domain}/versia/users/@{uname}/statuses/{pid}/versia"
+    ))
+    .unwrap()
+}
+
+/// gets the username out of a link to one of our own users in
+/// either protocol. returns none if the link is not a user on
+/// the given domain
+pub fn local_username(domain: &str, link: &Url) -> Option<String> {
+    if link.domain().ne(&Some(domain)) {
+        return None;
+    }
+    let segments: Vec<&str> = link.path_segments()?.collect();
+    match segments.as_slice() {
+        ["ap", "users", uname] => Some(uname.to_string()),
+        ["versia", "users", uname, "versia"] => Some(uname.to_string()),
+        _ => None,
+    }
+}
+
+/// gets the uuid out of a link to one of our own posts in either
+/// protocol. returns none if the link is not a post on the given domain
+pub fn local_post_id(domain: &str, link: &Url) -> Option<String> {
+    if link.domain().ne(&Some(domain)) {
+        return None;
+    }
+    let segments: Vec<&str> = link.path_segments()?.collect();
+    match segments.as_slice() {
+        ["ap", "users", _, "statuses", pid] => Some(pid.to_string()),
+        ["versia", "users", _, "statuses", pid, "versia"] => Some(pid.to_string()),
+        _ => None,
+    }
+}
+
+/// the activitypub link of one of our posts given its versia link,
+/// which is what local posts are stored under
+pub fn versia_to_ap_post_link(domain: &str, link: &Url) -> Option<Url> {
+    if link.domain().ne(&Some(domain)) {
+        return None;
+    }
+    let segments: Vec<&str> = link.path_segments()?.collect();
+    match segments.as_slice() {
+        ["versia", "users", uname, "statuses", pid, "versia"] => Some(ap_post_link(
+            domain,
+            uname.strip_prefix('@').unwrap_or(uname),
+            pid,
+        )),
+        _ => None,
     }
 }
 
@@ -70,6 +139,10 @@ pub struct NewLocal {
     pub password: String,
     pub email: Option<String>,
     pub permission_level: PermissionLevel,
+    /// see [`super::stored_user::StoredUser::approved`]
+    pub approved: bool,
+    /// see [`super::stored_user::StoredUser::vetted`]
+    pub vetted: bool,
     pub private_key_pem: String,
     pub public_key_pem: String,
     // pub custom_domain: Option<String>,
@@ -103,6 +176,8 @@ impl NewLocal {
             password: password_hash,
             email,
             permission_level,
+            approved: false,
+            vetted: false,
             private_key_pem,
             public_key_pem,
         }
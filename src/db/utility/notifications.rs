@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::db::conn::{Conn, DbErr};
+
+use super::{
+    new_actor::local_username,
+    polls::voters,
+    stored_post::StoredPost,
+    stored_user::{new_uid, now_millis, StoredUser},
+    streaming::{StreamBus, StreamEvent},
+};
+
+/// most notifications returned for a single page
+pub const MAX_NOTIFICATION_LIMIT: u64 = 80;
+pub const DEFAULT_NOTIFICATION_LIMIT: u64 = 40;
+
+/// what a notification is about. the names double as the type
+/// filters clients send
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// the user was mentioned in the post
+    Mention,
+    Follow,
+    /// someone asked to follow a user that approves followers manually
+    FollowRequest,
+    Like,
+    Dislike,
+    Share,
+    /// someone voted in a poll of the user
+    Vote,
+    /// a poll the user made or voted in closed
+    PollEnded,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 8] = [
+        NotificationKind::Mention,
+        NotificationKind::Follow,
+        NotificationKind::FollowRequest,
+        NotificationKind::Like,
+        NotificationKind::Dislike,
+        NotificationKind::Share,
+        NotificationKind::Vote,
+        NotificationKind::PollEnded,
+    ];
+    pub fn parse_str(value: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// a uuid v7, ordering by it orders by creation
+    pub id: String,
+    /// the uid of the local user being notified
+    pub recipient: String,
+    pub kind: NotificationKind,
+    /// the uid of the user that caused the notification
+    pub actor: Option<String>,
+    /// the id of the post it is about
+    pub post: Option<String>,
+    pub created_at: i64,
+}
+
+impl Notification {
+    pub fn new(
+        recipient: &str,
+        kind: NotificationKind,
+        actor: Option<&str>,
+        post: Option<&str>,
+    ) -> Self {
+        Notification {
+            id: new_uid(),
+            recipient: recipient.to_string(),
+            kind,
+            actor: actor.map(str::to_string),
+            post: post.map(str::to_string),
+            created_at: now_millis(),
+        }
+    }
+}
+
+/// which notifications of a user to get. they always come newest first
+#[derive(Debug, Clone)]
+pub struct NotificationQuery {
+    /// only notifications older than this id
+    pub before: Option<String>,
+    /// only notifications newer than this id
+    pub after: Option<String>,
+    /// with `after`, the notifications right after it rather than
+    /// the newest ones
+    pub adjacent: bool,
+    pub limit: u64,
+    /// only these kinds, every kind when none
+    pub kinds: Option<Vec<NotificationKind>>,
+}
+
+impl Default for NotificationQuery {
+    fn default() -> Self {
+        NotificationQuery {
+            before: None,
+            after: None,
+            adjacent: false,
+            limit: DEFAULT_NOTIFICATION_LIMIT,
+            kinds: None,
+        }
+    }
+}
+
+impl NotificationQuery {
+    /// if the notification falls inside of the cursors and filters
+    pub fn matches(&self, notification: &Notification) -> bool {
+        let id = notification.id.as_str();
+        self.before.as_deref().is_none_or(|x| id < x)
+            && self.after.as_deref().is_none_or(|x| id > x)
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|x| x.contains(&notification.kind))
+    }
+}
+
+/// how far a user has read their notifications
+#[derive(Debug, Clone)]
+pub struct NotificationMarker {
+    pub uid: String,
+    /// the id of the newest notification the user has seen
+    pub last_read_id: String,
+    pub updated_at: i64,
+}
+
+impl NotificationMarker {
+    pub fn new(uid: &str, last_read_id: &str) -> Self {
+        NotificationMarker {
+            uid: uid.to_string(),
+            last_read_id: last_read_id.to_string(),
+            updated_at: now_millis(),
+        }
+    }
+}
+
+/// notifies a local user and pushes it to their open streams. users
+/// aren't told about their own actions
+pub async fn notify(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    recipient: &StoredUser,
+    kind: NotificationKind,
+    actor: Option<&StoredUser>,
+    post: Option<&str>,
+) -> Result<(), DbErr> {
+    if !recipient.is_local() || recipient.deleted {
+        return Ok(());
+    }
+    if actor.is_some_and(|x| x.uid.eq(&recipient.uid)) {
+        return Ok(());
+    }
+    let notification = Notification::new(&recipient.uid, kind, actor.map(|x| x.uid.as_str()), post);
+    conn.create_notification(&notification).await?;
+    bus.publish(StreamEvent::Notification(notification));
+    Ok(())
+}
+
+/// notifies the author of a post if they are one of ours
+pub async fn notify_author(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    post: &StoredPost,
+    kind: NotificationKind,
+    actor: Option<&StoredUser>,
+) -> Result<(), DbErr> {
+    let Some(author) = conn.get_local_user(&post.actor).await else {
+        return Ok(());
+    };
+    notify(conn, bus, &author, kind, actor, Some(&post.id)).await
+}
+
+/// notifies the local users mentioned in a stored post. links to
+/// anyone else are ignored so mentions can't make us fetch
+pub async fn notify_mentions(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    post: &Url,
+    mentions: &[Url],
+    instance_domain: &str,
+) -> Result<(), DbErr> {
+    let mut mentioned: Vec<StoredUser> = Vec::new();
+    for link in mentions {
+        if local_username(instance_domain, link).is_none() {
+            continue;
+        }
+        let Ok(user) = conn.resolve_user(link).await else {
+            continue;
+        };
+        if !mentioned.iter().any(|x| x.uid.eq(&user.uid)) {
+            mentioned.push(user);
+        }
+    }
+    if mentioned.is_empty() {
+        return Ok(());
+    }
+    let post = conn.get_post(post).await.ok_or(DbErr::NotFound)?;
+    let author = conn.get_user(&post.actor).await;
+    for user in mentioned {
+        notify(
+            conn,
+            bus,
+            &user,
+            NotificationKind::Mention,
+            author.as_ref(),
+            Some(&post.id),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// tells the authors and local voters of polls that closed by the given
+/// time. a poll is only marked as ended once everyone was notified, so
+/// a failure leaves it to be picked up again. returns how many polls closed
+pub async fn notify_ended_polls(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    now: i64,
+) -> Result<usize, DbErr> {
+    let ended = conn.get_ended_polls(now).await?;
+    for poll in &ended {
+        notify_author(conn, bus, poll, NotificationKind::PollEnded, None).await?;
+        let votes = conn.get_votes(&poll.id).await?;
+        for voter in voters(&votes).into_iter().filter(|x| poll.actor.ne(x)) {
+            let Some(voter) = conn.get_local_user(voter).await else {
+                continue;
+            };
+            notify(
+                conn,
+                bus,
+                &voter,
+                NotificationKind::PollEnded,
+                None,
+                Some(&poll.id),
+            )
+            .await?;
+        }
+        conn.mark_poll_ended(&poll.id).await?;
+    }
+    Ok(ended.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_query() -> Result<(), String> {
+        let older = Notification::new("uid", NotificationKind::Like, None, None);
+        let newer = Notification::new("uid", NotificationKind::Mention, None, None);
+        let query = NotificationQuery {
+            before: Some(newer.id.clone()),
+            ..Default::default()
+        };
+        if !query.matches(&older) || query.matches(&newer) {
+            return Err("ids did not order by creation".to_string());
+        }
+        let query = NotificationQuery {
+            kinds: Some(vec![NotificationKind::Mention]),
+            ..Default::default()
+        };
+        if query.matches(&older) || !query.matches(&newer) {
+            return Err("kinds were not filtered".to_string());
+        }
+        if NotificationKind::parse_str("poll_ended") != Some(NotificationKind::PollEnded) {
+            return Err("failed to parse a kind".to_string());
+        }
+        Ok(())
+    }
+}
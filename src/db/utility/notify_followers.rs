@@ -0,0 +1,72 @@
+use crate::{
+    db::conn::{Conn, DbErr},
+    protocols::types::activitystream_objects::{create::Create, update::Update},
+};
+
+use super::{
+    delivery::{remote_inboxes, Delivery},
+    protocols::Protocol,
+    stored_post::StoredPost,
+    stored_user::StoredUser,
+};
+
+/// queues a new local post for delivery to the author's remote followers.
+/// each instance gets the post in the protocol it speaks and followers
+/// sharing an inbox only get it once. returns the number of deliveries queued
+pub async fn notify_followers(
+    conn: &dyn Conn,
+    post: &StoredPost,
+    author: &StoredUser,
+) -> Result<usize, DbErr> {
+    let ap_body = post
+        .to_ap(author)
+        .map(|x| serde_json::to_string(&Create::new(x).wrap_context()).unwrap());
+    queue_for_followers(conn, post, author, ap_body).await
+}
+
+/// queues an edited local post the same way. activitypub instances get
+/// an update of the post, versia has no updates and gets the note again
+pub async fn notify_followers_of_edit(
+    conn: &dyn Conn,
+    post: &StoredPost,
+    author: &StoredUser,
+) -> Result<usize, DbErr> {
+    let ap_body = post
+        .to_ap(author)
+        .map(|x| serde_json::to_string(&Update::new_post(x).wrap_context()).unwrap());
+    queue_for_followers(conn, post, author, ap_body).await
+}
+
+async fn queue_for_followers(
+    conn: &dyn Conn,
+    post: &StoredPost,
+    author: &StoredUser,
+    ap_body: Option<String>,
+) -> Result<usize, DbErr> {
+    if post.local_only {
+        return Ok(0);
+    }
+    let followers = conn.get_followers(&author.uid).await?;
+
+    let inboxes = remote_inboxes(conn, &followers).await;
+
+    let versia_body = post
+        .to_versia(author)
+        .map(|x| serde_json::to_string(&x).unwrap());
+
+    let mut queued = 0;
+    for (inbox, protocol) in inboxes {
+        let body = match protocol {
+            Protocol::ActivityPub => &ap_body,
+            Protocol::Versia => &versia_body,
+        };
+        // shares can't be represented in activitypub
+        let Some(body) = body else {
+            continue;
+        };
+        conn.queue_delivery(&Delivery::new(&inbox, protocol, body.clone(), &author.uid))
+            .await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
@@ -0,0 +1,276 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use super::stored_user::now_millis;
+
+/// the scopes an app can be granted. each of them also covers its
+/// narrower forms, "read" allows "read:statuses"
+pub const SCOPES: [&str; 4] = ["read", "write", "follow", "admin"];
+/// used when an app doesn't ask for any scope
+pub const DEFAULT_SCOPE: &str = "read";
+/// redirect uri of apps that can't receive a redirect, the code is
+/// shown to the user to paste into the app instead
+pub const OOB_REDIRECT: &str = "urn:ietf:wg:oauth:2.0:oob";
+/// how long an authorization code can be exchanged for a token
+pub const CODE_LIFETIME_MILLIS: i64 = 10 * 60 * 1000;
+
+/// a url safe random string used for client ids, secrets, codes and tokens
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    BASE64_URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// tokens are only stored hashed so they can't be taken from the db
+pub fn hash_token(token: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// a set of scopes as they appear in oauth requests, separated by
+/// whitespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scopes(Vec<String>);
+
+impl Scopes {
+    /// fails if any of the scopes isn't one of [`SCOPES`] or a narrower
+    /// form of one. empty input gives the [`DEFAULT_SCOPE`]
+    pub fn parse(scopes: &str) -> Option<Self> {
+        let mut parsed: Vec<String> = Vec::new();
+        for scope in scopes.split_whitespace() {
+            let base = scope.split_once(':').map(|(x, _)| x).unwrap_or(scope);
+            if !SCOPES.contains(&base) || scope.ends_with(':') {
+                return None;
+            }
+            if !parsed.iter().any(|x| x.eq(scope)) {
+                parsed.push(scope.to_string());
+            }
+        }
+        if parsed.is_empty() {
+            parsed.push(DEFAULT_SCOPE.to_string());
+        }
+        Some(Scopes(parsed))
+    }
+
+    /// if the scope is granted directly or through a broader scope
+    pub fn allows(&self, scope: &str) -> bool {
+        self.0.iter().any(|granted| {
+            scope.eq(granted)
+                || scope
+                    .strip_prefix(granted.as_str())
+                    .is_some_and(|x| x.starts_with(':'))
+        })
+    }
+
+    /// if every scope of self is allowed by other
+    pub fn is_subset_of(&self, other: &Scopes) -> bool {
+        self.0.iter().all(|x| other.allows(x))
+    }
+
+    /// the scopes without any admin ones, for users that aren't
+    /// moderators. falls back to the [`DEFAULT_SCOPE`] when nothing is left
+    pub fn without_admin(&self) -> Self {
+        let mut kept: Vec<String> = self
+            .0
+            .iter()
+            .filter(|x| x.split(':').next().ne(&Some("admin")))
+            .cloned()
+            .collect();
+        if kept.is_empty() {
+            kept.push(DEFAULT_SCOPE.to_string());
+        }
+        Scopes(kept)
+    }
+}
+
+impl std::fmt::Display for Scopes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(" "))
+    }
+}
+
+/// a client app registered to act on behalf of local users
+#[derive(Debug, Clone)]
+pub struct OAuthApp {
+    pub client_id: String,
+    pub client_secret: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    /// the most an authorization of the app can be granted
+    pub scopes: Scopes,
+    pub website: Option<String>,
+    pub created_at: i64,
+}
+
+impl OAuthApp {
+    pub fn new(
+        name: String,
+        redirect_uris: Vec<String>,
+        scopes: Scopes,
+        website: Option<String>,
+    ) -> Self {
+        OAuthApp {
+            client_id: generate_secret(),
+            client_secret: generate_secret(),
+            name,
+            redirect_uris,
+            scopes,
+            website,
+            created_at: now_millis(),
+        }
+    }
+    /// redirects have to match one of the registered uris exactly
+    pub fn allows_redirect(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|x| x.eq(redirect_uri))
+    }
+}
+
+/// a code given to an app after a user approves it, exchanged once
+/// for an access token
+#[derive(Debug, Clone)]
+pub struct OAuthCode {
+    pub code: String,
+    pub client_id: String,
+    /// the user that approved the app
+    pub uid: String,
+    pub redirect_uri: String,
+    pub scopes: Scopes,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub expires: i64,
+}
+
+impl OAuthCode {
+    pub fn new(
+        client_id: &str,
+        uid: &str,
+        redirect_uri: &str,
+        scopes: Scopes,
+        code_challenge: Option<String>,
+        code_challenge_method: Option<String>,
+    ) -> Self {
+        OAuthCode {
+            code: generate_secret(),
+            client_id: client_id.to_string(),
+            uid: uid.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes,
+            code_challenge,
+            code_challenge_method,
+            expires: now_millis() + CODE_LIFETIME_MILLIS,
+        }
+    }
+    pub fn is_expired(&self) -> bool {
+        self.expires <= now_millis()
+    }
+    /// checks the pkce verifier against the challenge the code was
+    /// requested with. codes requested without a challenge don't take
+    /// a verifier
+    pub fn verify_pkce(&self, code_verifier: Option<&str>) -> bool {
+        match (&self.code_challenge, code_verifier) {
+            (None, None) => true,
+            (Some(challenge), Some(verifier)) => {
+                self.code_challenge_method.as_deref() == Some("S256")
+                    && BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+                        == *challenge
+            }
+            _ => false,
+        }
+    }
+}
+
+/// an issued access token, the token itself is only known to the app
+#[derive(Debug, Clone)]
+pub struct OAuthToken {
+    pub token_hash: String,
+    pub client_id: String,
+    /// none for tokens of the app itself rather than a user
+    pub uid: Option<String>,
+    pub scopes: Scopes,
+    pub created_at: i64,
+}
+
+impl OAuthToken {
+    /// returns the token to hand to the app along with what gets stored
+    pub fn issue(client_id: &str, uid: Option<&str>, scopes: Scopes) -> (String, Self) {
+        let token = generate_secret();
+        let stored = OAuthToken {
+            token_hash: hash_token(&token),
+            client_id: client_id.to_string(),
+            uid: uid.map(str::to_string),
+            scopes,
+            created_at: now_millis(),
+        };
+        (token, stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() -> Result<(), String> {
+        let granted = Scopes::parse("read write:statuses").ok_or("failed to parse scopes")?;
+        if !granted.allows("read:accounts") || !granted.allows("write:statuses") {
+            return Err("granted scopes did not cover their narrower forms".to_string());
+        }
+        if granted.allows("write") || granted.allows("readwrite") || granted.allows("admin") {
+            return Err("allowed a scope that wasn't granted".to_string());
+        }
+        if Scopes::parse("read push").is_some() || Scopes::parse("read:").is_some() {
+            return Err("accepted an unknown scope".to_string());
+        }
+        if Scopes::parse("")
+            .ok_or("failed to parse no scopes")?
+            .to_string()
+            != DEFAULT_SCOPE
+        {
+            return Err("no scopes did not give the default".to_string());
+        }
+        let requested = Scopes::parse("read:statuses").ok_or("failed to parse scopes")?;
+        if !requested.is_subset_of(&granted) || granted.is_subset_of(&requested) {
+            return Err("subsets were not checked against broader scopes".to_string());
+        }
+        let user = Scopes::parse("read admin:write:accounts admin")
+            .ok_or("failed to parse scopes")?
+            .without_admin();
+        if user.to_string().ne("read") {
+            return Err("admin scopes were kept for a user".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pkce() -> Result<(), String> {
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+        let scopes = Scopes::parse("read").ok_or("failed to parse scopes")?;
+        let code = OAuthCode::new(
+            "client",
+            "uid",
+            OOB_REDIRECT,
+            scopes.clone(),
+            Some(challenge.to_string()),
+            Some("S256".to_string()),
+        );
+        if !code.verify_pkce(Some(verifier)) {
+            return Err("rejected the verifier of the challenge".to_string());
+        }
+        if code.verify_pkce(Some("wrong")) || code.verify_pkce(None) {
+            return Err("accepted a missing or wrong verifier".to_string());
+        }
+        let plain = OAuthCode::new(
+            "client",
+            "uid",
+            OOB_REDIRECT,
+            scopes,
+            Some(verifier.to_string()),
+            Some("plain".to_string()),
+        );
+        if plain.verify_pkce(Some(verifier)) {
+            return Err("accepted the plain challenge method".to_string());
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PermissionLevel {
     /// intended for the main admin account(s) of the server, will be
     /// featured and considered the pont of contact for the instance,
@@ -50,3 +50,14 @@ impl From<PermissionLevel> for i16 {
         }
     }
 }
+
+impl PermissionLevel {
+    /// if self has at least the permissions of level
+    pub fn is_at_least(self, level: PermissionLevel) -> bool {
+        i16::from(self) <= i16::from(level)
+    }
+    /// mods and admins, anyone that can review users
+    pub fn is_moderator(self) -> bool {
+        self.is_at_least(PermissionLevel::ModTwo)
+    }
+}
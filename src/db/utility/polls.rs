@@ -0,0 +1,80 @@
+use crate::{
+    db::conn::{Conn, DbErr},
+    protocols::types::versia_types::postable::VersiaPostable,
+};
+
+/// a vote of a user in one of our polls
+#[derive(Debug, Clone)]
+pub struct PollVote {
+    /// the id of the poll
+    pub poll: String,
+    /// the uid of the voter
+    pub voter: String,
+    /// the index of the option voted for
+    pub choice: i32,
+    pub published: i64,
+}
+
+/// how many votes each of the options got. votes for options the poll
+/// no longer has are left out
+pub fn tally(options: usize, votes: &[PollVote]) -> Vec<u64> {
+    let mut tally = vec![0; options];
+    for vote in votes {
+        if let Some(count) = usize::try_from(vote.choice)
+            .ok()
+            .and_then(|x| tally.get_mut(x))
+        {
+            *count += 1;
+        }
+    }
+    tally
+}
+
+/// the users that voted in the poll, each once
+pub fn voters(votes: &[PollVote]) -> Vec<&str> {
+    let mut voters: Vec<&str> = votes.iter().map(|x| x.voter.as_str()).collect();
+    voters.sort_unstable();
+    voters.dedup();
+    voters
+}
+
+/// fills in the votes of a poll rendered for versia, which are left at
+/// zero as rendering doesn't touch the db
+pub async fn with_tally(conn: &dyn Conn, post: VersiaPostable) -> Result<VersiaPostable, DbErr> {
+    let VersiaPostable::Note(mut note) = post else {
+        return Ok(post);
+    };
+    let Some(poll) = note
+        .extensions
+        .as_mut()
+        .and_then(|x| x.pub_versia_polls.as_mut())
+    else {
+        return Ok(VersiaPostable::Note(note));
+    };
+    let stored = conn.get_post(&note.uri).await.ok_or(DbErr::NotFound)?;
+    poll.votes = tally(poll.options.len(), &conn.get_votes(&stored.id).await?);
+    Ok(VersiaPostable::Note(note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally() -> Result<(), String> {
+        let vote = |voter: &str, choice: i32| PollVote {
+            poll: "poll".to_string(),
+            voter: voter.to_string(),
+            choice,
+            published: 0,
+        };
+        let votes = [vote("a", 0), vote("a", 1), vote("b", 1), vote("c", 5)];
+        if tally(2, &votes) != vec![1, 2] {
+            return Err(format!("wrong tally: {:?}", tally(2, &votes)));
+        }
+        match voters(&votes) == vec!["a", "b", "c"] {
+            true => Ok(()),
+            false => Err("voters were not listed once each".to_string()),
+        }
+    }
+}
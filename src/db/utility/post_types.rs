@@ -2,10 +2,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::protocols::types::activitystream_objects::{object::Object, question::Question};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PostSupertype {
     Object,
     Question,
+    /// a versia share of another post
+    Share,
 }
 impl PostSupertype {
     pub fn parse_str(value: &str) -> Result<Self, serde_json::Error> {
@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Protocol {
     ActivityPub,
     Versia,
 }
+
+/// how requests to an activitypub instance are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    /// RFC 9421 HTTP Message Signatures
+    Rfc9421,
+    /// the older draft-cavage-http-signatures most software understands
+    Cavage,
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::conn::{Conn, DbErr};
+
+use super::{
+    oauth::generate_secret,
+    permission::PermissionLevel,
+    stored_user::{now_millis, StoredUser},
+};
+
+/// how new users can sign up
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// only admins can create accounts
+    #[default]
+    Closed,
+    /// anyone can sign up and is trusted right away
+    Open,
+    /// signing up takes an invite code from an existing user
+    InviteOnly,
+    /// new users send an application and are untrusted until a
+    /// moderator approves it. an invite skips the application
+    Approval,
+}
+
+impl RegistrationMode {
+    /// the permission level and approval of a user signing up in this
+    /// mode, none if they can't sign up
+    pub fn new_user(self, invited: bool) -> Option<(PermissionLevel, bool)> {
+        match (self, invited) {
+            (RegistrationMode::Closed, _) => None,
+            (RegistrationMode::Open, false) => Some((PermissionLevel::TrustedUser, true)),
+            (RegistrationMode::InviteOnly, false) => None,
+            (RegistrationMode::Approval, false) => Some((PermissionLevel::UntrustedUser, false)),
+            (_, true) => Some((PermissionLevel::TrustedUser, true)),
+        }
+    }
+}
+
+/// a code letting someone sign up while registration needs an invite
+#[derive(Debug, Clone)]
+pub struct Invite {
+    pub code: String,
+    /// the uid of the user that made the invite
+    pub created_by: Option<String>,
+    /// unlimited when none
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires: Option<i64>,
+    pub created_at: i64,
+}
+
+impl Invite {
+    /// `expires_in` is in milliseconds. none when it isn't positive or
+    /// the expiry can't be represented
+    pub fn new(
+        created_by: Option<&str>,
+        max_uses: Option<i32>,
+        expires_in: Option<i64>,
+    ) -> Option<Self> {
+        let now = now_millis();
+        let expires = match expires_in {
+            Some(x) if x <= 0 => return None,
+            Some(x) => Some(now.checked_add(x)?),
+            None => None,
+        };
+        Some(Invite {
+            code: generate_secret(),
+            created_by: created_by.map(str::to_string),
+            max_uses,
+            uses: 0,
+            expires,
+            created_at: now,
+        })
+    }
+}
+
+/// what a user told the moderators when asking to join
+#[derive(Debug, Clone)]
+pub struct Application {
+    pub uid: String,
+    pub reason: String,
+    pub created_at: i64,
+}
+
+impl Application {
+    pub fn new(uid: &str, reason: &str) -> Self {
+        Application {
+            uid: uid.to_string(),
+            reason: reason.to_string(),
+            created_at: now_millis(),
+        }
+    }
+}
+
+/// if the user is waiting on a moderator, either with an application
+/// or demoted while registration needs approval. they can't use the api
+/// until they are approved
+pub async fn is_pending(conn: &dyn Conn, user: &StoredUser) -> bool {
+    if user.approved
+        || user
+            .permission_level
+            .ne(&Some(PermissionLevel::UntrustedUser))
+    {
+        return false;
+    }
+    conn.get_application(&user.uid).await.is_some()
+        || matches!(
+            conn.get_registration_mode().await,
+            Ok(Some(RegistrationMode::Approval))
+        )
+}
+
+/// records the mode the instance is starting with. switching to manual
+/// approval demotes users that signed up without being vetted to
+/// [`PermissionLevel::UntrustedUser`], they are asked for an application
+/// the next time they log in. switching to open registration lets in
+/// everyone still waiting, the same as if they signed up then. returns
+/// the number of users demoted or let in
+pub async fn apply_registration_mode(
+    conn: &dyn Conn,
+    mode: RegistrationMode,
+) -> Result<u64, DbErr> {
+    let previous = conn.get_registration_mode().await?;
+    if previous == Some(mode) {
+        return Ok(0);
+    }
+    conn.set_registration_mode(mode).await?;
+    match mode {
+        RegistrationMode::Approval => conn.demote_unvetted_users().await,
+        RegistrationMode::Open => conn.promote_unvetted_users().await,
+        _ => Ok(0),
+    }
+}
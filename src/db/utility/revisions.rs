@@ -0,0 +1,201 @@
+use crate::{
+    db::conn::{Conn, DbErr},
+    protocols::types::{
+        activitystream_objects::postable::ApPostable, versia_types::postable::VersiaPostable,
+    },
+};
+
+use super::{
+    compose::{Draft, DraftPoll},
+    notify_followers::notify_followers_of_edit,
+    post_types::PostSupertype,
+    stored_post::StoredPost,
+    stored_user::{new_uid, now_millis, StoredUser},
+    streaming::{StreamBus, StreamEvent},
+};
+
+/// an earlier version of an edited post. only what can be edited is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostRevision {
+    /// uuid v7, so ordering by id orders by creation
+    pub id: String,
+    /// the id of the post
+    pub post: String,
+    pub content: Option<String>,
+    pub subject: Option<String>,
+    pub is_sensitive: bool,
+    pub options: Option<Vec<String>>,
+    /// when this version was published or edited into the post
+    pub created_at: i64,
+}
+
+impl PostRevision {
+    /// keeps the post as it is before it gets edited
+    pub fn new(post: &StoredPost) -> Self {
+        PostRevision {
+            id: new_uid(),
+            post: post.id.clone(),
+            content: post.content.clone(),
+            subject: post.subject.clone(),
+            is_sensitive: post.is_sensitive,
+            options: post.options.clone(),
+            created_at: post.edited_at.unwrap_or(post.published),
+        }
+    }
+}
+
+/// the post with the edit applied. who can see it, what it replies to
+/// and when it was published stay as they were
+fn apply_edit(existing: &StoredPost, edited: &StoredPost, edited_at: i64) -> StoredPost {
+    StoredPost {
+        content: edited.content.clone(),
+        subject: edited.subject.clone(),
+        is_sensitive: edited.is_sensitive,
+        multi_select: edited.multi_select,
+        options: edited.options.clone(),
+        closed: edited.closed,
+        edited_at: Some(edited_at),
+        ..existing.clone()
+    }
+}
+
+/// if the edit changes nothing, as with posts being delivered again
+fn is_unchanged(existing: &StoredPost, edited: &StoredPost) -> bool {
+    existing.content.eq(&edited.content)
+        && existing.subject.eq(&edited.subject)
+        && existing.is_sensitive.eq(&edited.is_sensitive)
+        && existing.multi_select.eq(&edited.multi_select)
+        && existing.options.eq(&edited.options)
+        && existing.closed.eq(&edited.closed)
+}
+
+/// stores the edit and lets open streams know. none if there was
+/// nothing to change
+async fn store_edit(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    existing: &StoredPost,
+    edited: &StoredPost,
+    author: &StoredUser,
+) -> Result<Option<StoredPost>, DbErr> {
+    if existing.surtype.ne(&edited.surtype) || existing.actor.ne(&author.uid) {
+        return Err(DbErr::InvalidType);
+    }
+    if is_unchanged(existing, edited) {
+        return Ok(None);
+    }
+    let edited_at = match edited.edited_at {
+        // older updates arriving late are dropped
+        Some(x) if existing.edited_at.is_some_and(|existing| existing >= x) => return Ok(None),
+        Some(x) => x,
+        None => now_millis(),
+    };
+    let post = apply_edit(existing, edited, edited_at);
+    conn.edit_post(&post).await?;
+    bus.publish(StreamEvent::Edit {
+        post: post.clone(),
+        author: author.clone(),
+    });
+    Ok(Some(post))
+}
+
+/// edits a post of a local user with what they sent from a client. the
+/// poll is kept as it was when the draft has none. followers get the
+/// edit in the protocol they speak
+pub async fn edit_local_post(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    author: &StoredUser,
+    existing: &StoredPost,
+    draft: Draft,
+) -> Result<StoredPost, DbErr> {
+    if !author.is_local() || author.deleted {
+        return Err(DbErr::InvalidType);
+    }
+    let poll = match (draft.poll.clone(), &existing.surtype) {
+        (Some(poll), PostSupertype::Question) => Some(poll),
+        (None, PostSupertype::Question) => Some(DraftPoll {
+            options: existing.options.clone().unwrap_or_default(),
+            multi_select: existing.multi_select.unwrap_or(false),
+            closed: None,
+            local_only_voting: existing.local_only_voting.unwrap_or(false),
+        }),
+        (None, _) => None,
+        // notes can't become polls
+        (Some(_), _) => return Err(DbErr::InvalidType),
+    };
+    let valid = Draft {
+        poll: poll.clone(),
+        ..draft.clone()
+    };
+    if !valid.is_valid(now_millis()) {
+        return Err(DbErr::InvalidType);
+    }
+    let edited = StoredPost {
+        content: draft.content,
+        subject: draft.subject,
+        is_sensitive: draft.is_sensitive,
+        multi_select: poll.as_ref().map(|x| x.multi_select),
+        options: poll.as_ref().map(|x| x.options.clone()),
+        closed: match draft.poll {
+            Some(poll) => poll.closed,
+            None => existing.closed,
+        },
+        edited_at: None,
+        ..existing.clone()
+    };
+    let Some(post) = store_edit(conn, bus, existing, &edited, author).await? else {
+        return Ok(existing.clone());
+    };
+    if let Err(x) = notify_followers_of_edit(conn, &post, author).await {
+        eprintln!(
+            "failed to notify followers of the edit of {}: {}",
+            post.id, x
+        );
+    }
+    Ok(post)
+}
+
+/// an activitypub post we already have was sent again, either in an
+/// update or a create being retried
+pub async fn handle_ap_edit(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    existing: &StoredPost,
+    postable: &ApPostable,
+) -> Result<(), DbErr> {
+    let author = conn
+        .get_user(&existing.actor)
+        .await
+        .ok_or(DbErr::NotFound)?;
+    if author.is_local() || author.ap_id().as_ref().ne(&Some(postable.actor())) {
+        return Err(DbErr::InvalidType);
+    }
+    let edited = StoredPost::from_ap(postable, &author).ok_or(DbErr::InvalidType)?;
+    store_edit(conn, bus, existing, &edited, &author).await?;
+    Ok(())
+}
+
+/// versia has no updates, an edited note is sent again in full
+pub async fn handle_versia_edit(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    existing: &StoredPost,
+    postable: &VersiaPostable,
+) -> Result<(), DbErr> {
+    let author = conn
+        .get_user(&existing.actor)
+        .await
+        .ok_or(DbErr::NotFound)?;
+    if author.is_local()
+        || author
+            .versia_uri()
+            .as_ref()
+            .ne(&Some(postable.get_author()))
+    {
+        return Err(DbErr::InvalidType);
+    }
+    let edited = StoredPost::from_versia(postable, &author).ok_or(DbErr::InvalidType)?;
+    store_edit(conn, bus, existing, &edited, &author).await?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use crate::{
+    cryptography::{
+        key::{Key, KeyType, PrivateKey},
+        openssl::OpenSSLPrivate,
+    },
+    db::conn::{Conn, DbErr},
+    protocols::types::activitystream_objects::update::Update,
+};
+
+use super::{
+    delivery::{remote_inboxes, Delivery},
+    instance_actor::{push_instance_actor, push_instance_metadata},
+    protocols::Protocol,
+};
+
+/// replaces the keypair of a local user and queues their updated
+/// profile to every instance that follows them or is followed by them.
+/// only the new key is published, deliveries still queued are signed
+/// with it as they are sent. returns the number of deliveries queued
+pub async fn rotate_local_key(
+    conn: &dyn Conn,
+    uid: &str,
+    algorithm: KeyType,
+) -> Result<usize, DbErr> {
+    let key = OpenSSLPrivate::generate(algorithm);
+    let private_key_pem = key.to_pem().expect("generated an invalid key");
+    let public_key_pem = key.public_key_pem().expect("generated an invalid key");
+    conn.rotate_user_key(uid, &private_key_pem, &public_key_pem)
+        .await?;
+
+    let user = conn.get_local_user(uid).await.ok_or(DbErr::NotFound)?;
+    let mut known = conn.get_followers(uid).await?;
+    known.extend(conn.get_following(uid).await?);
+    let inboxes = remote_inboxes(conn, &known).await;
+
+    let ap_body = user
+        .to_actor()
+        .map(|x| serde_json::to_string(&Update::new(x).wrap_context()).unwrap());
+    let versia_body = user.to_versia().map(|x| serde_json::to_string(&x).unwrap());
+
+    let mut queued = 0;
+    for (inbox, protocol) in inboxes {
+        let body = match protocol {
+            Protocol::ActivityPub => &ap_body,
+            Protocol::Versia => &versia_body,
+        };
+        let Some(body) = body else {
+            continue;
+        };
+        conn.queue_delivery(&Delivery::new(&inbox, protocol, body.clone(), uid))
+            .await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// replaces the keypairs of the instance actor, `algorithm` being used
+/// for the activitypub one. the old keys are kept for the grace period
+/// the same way as for users. the new versia key is pushed to known
+/// versia instances and the updated instance actor to known activitypub
+/// ones. returns the number of deliveries queued
+pub async fn rotate_instance_key(
+    conn: &dyn Conn,
+    instance_domain: &str,
+    algorithm: KeyType,
+) -> Result<usize, DbErr> {
+    let mut actor = conn.get_instance_actor().await?;
+    actor.rotate(algorithm);
+    conn.set_instance_actor(&actor).await?;
+    let versia = push_instance_metadata(conn, instance_domain).await?;
+    let ap = push_instance_actor(conn, instance_domain).await?;
+    Ok(versia + ap)
+}
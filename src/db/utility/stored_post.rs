@@ -0,0 +1,394 @@
+use chrono::{DateTime, SecondsFormat};
+use url::Url;
+
+use crate::protocols::types::{
+    activitystream_objects::{
+        core_types::OptionalArray,
+        link::LinkSimpleOrExpanded,
+        note::{MediaType, Note, NoteType},
+        postable::ApPostable,
+        question::{ChoiceType, Question, QuestionOption, QuestionOptionType, QuestionType},
+    },
+    versia_types::{
+        entities::notes::{
+            Category, Expiry, GroupType, Groups, Note as VersiaNote, NoteExtensions, NoteTyoe,
+            PubVersiaPolls,
+        },
+        extensions::share::{Share, ShareType},
+        postable::VersiaPostable,
+        structures::content_format::TextContentFormat,
+    },
+};
+
+use super::{
+    new_actor::{ap_post_link, versia_post_link, versia_to_ap_post_link},
+    post_types::PostSupertype,
+    stored_user::{now_millis, StoredUser},
+};
+
+const PUBLIC_ALIASES: [&str; 3] = [
+    "https://www.w3.org/ns/activitystreams#Public",
+    "as:Public",
+    "Public",
+];
+
+/// a row of the posts table, shared between the database backends
+///
+/// local posts are stored under their versia link with the generated
+/// uuid as the versia_id. federated posts are stored under the id they
+/// were federated with
+#[derive(Debug, Clone)]
+pub struct StoredPost {
+    pub id: String,
+    pub versia_id: String,
+    pub domain: String,
+    pub surtype: PostSupertype,
+    pub subtype: String,
+    pub category: Category,
+    pub likes: i64,
+    pub boosts: i64,
+    pub local_only: bool,
+    pub followers_only: bool,
+    pub published: i64,
+    pub is_reply: bool,
+    pub in_reply_to: Option<String>,
+    /// the html content of the post
+    pub content: Option<String>,
+    pub subject: Option<String>,
+    pub is_sensitive: bool,
+    /// the post being shared if this is a versia share
+    pub shared: Option<String>,
+    pub multi_select: Option<bool>,
+    pub options: Option<Vec<String>>,
+    pub closed: Option<i64>,
+    pub local_only_voting: Option<bool>,
+    /// when the post was last edited
+    pub edited_at: Option<i64>,
+    /// the uid of the author
+    pub actor: String,
+}
+
+/// if the audience includes the public collection
+pub fn is_public(audience: &Option<OptionalArray<LinkSimpleOrExpanded>>) -> bool {
+    let links = match audience {
+        Some(OptionalArray::Single(x)) => vec![x],
+        Some(OptionalArray::Multiple(x)) => x.iter().collect(),
+        None => vec![],
+    };
+    links
+        .iter()
+        .any(|x| PUBLIC_ALIASES.contains(&x.get_id().as_str()))
+}
+
+pub fn format_time(millis: i64) -> Option<String> {
+    DateTime::from_timestamp_millis(millis).map(|x| x.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+impl StoredPost {
+    pub fn from_ap(post: &ApPostable, author: &StoredUser) -> Option<Self> {
+        let id = post.id().to_string();
+        let domain = post.id().domain()?.to_string();
+        let stored = match post {
+            ApPostable::Note(note) => {
+                let public = is_public(&note.to) || is_public(&note.cc);
+                StoredPost {
+                    versia_id: id.clone(),
+                    id,
+                    domain,
+                    surtype: PostSupertype::Object,
+                    subtype: serde_json::to_string(&note.type_field).unwrap(),
+                    category: Category::Microblog,
+                    likes: 0,
+                    boosts: 0,
+                    local_only: false,
+                    followers_only: !public,
+                    published: note.published,
+                    is_reply: note.in_reply_to.is_some(),
+                    in_reply_to: note.in_reply_to.as_ref().map(|x| x.to_string()),
+                    content: note.content.clone(),
+                    subject: note.summary.clone(),
+                    // servers without the extension only mark sensitive
+                    // posts with a summary
+                    is_sensitive: note.sensitive.unwrap_or(note.summary.is_some()),
+                    shared: None,
+                    multi_select: None,
+                    options: None,
+                    closed: None,
+                    local_only_voting: None,
+                    edited_at: note.updated,
+                    actor: author.uid.clone(),
+                }
+            }
+            ApPostable::Question(question) => {
+                let (multi_select, options) = match &question.options {
+                    ChoiceType::AnyOf(x) => (true, x),
+                    ChoiceType::OneOf(x) => (false, x),
+                };
+                let closed = question
+                    .closed
+                    .as_ref()
+                    .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+                    .map(|x| x.timestamp_millis());
+                StoredPost {
+                    versia_id: id.clone(),
+                    id,
+                    domain,
+                    surtype: PostSupertype::Question,
+                    subtype: serde_json::to_string(&question.type_field).unwrap(),
+                    category: Category::Microblog,
+                    likes: 0,
+                    boosts: 0,
+                    local_only: false,
+                    followers_only: false,
+                    published: now_millis(),
+                    is_reply: false,
+                    in_reply_to: None,
+                    content: None,
+                    subject: None,
+                    is_sensitive: false,
+                    shared: None,
+                    multi_select: Some(multi_select),
+                    options: Some(options.iter().map(|x| x.name.clone()).collect()),
+                    closed,
+                    local_only_voting: question.local_only,
+                    edited_at: None,
+                    actor: author.uid.clone(),
+                }
+            }
+        };
+        Some(stored)
+    }
+
+    pub fn from_versia(post: &VersiaPostable, author: &StoredUser) -> Option<Self> {
+        let stored = match post {
+            VersiaPostable::Note(note) => {
+                let (local_only, followers_only) = match &note.group {
+                    Some(GroupType::Simple(Groups::Local)) => (true, false),
+                    Some(GroupType::Simple(Groups::Followers)) => (false, true),
+                    _ => (false, false),
+                };
+                let poll = note
+                    .extensions
+                    .as_ref()
+                    .and_then(|x| x.pub_versia_polls.as_ref());
+                let surtype = match poll {
+                    Some(_) => PostSupertype::Question,
+                    None => PostSupertype::Object,
+                };
+                StoredPost {
+                    id: note.uri.to_string(),
+                    versia_id: note.id.clone(),
+                    domain: note.uri.domain()?.to_string(),
+                    surtype,
+                    subtype: serde_json::to_string(&note.type_field).unwrap(),
+                    category: note.category.clone().unwrap_or(Category::Microblog),
+                    likes: 0,
+                    boosts: 0,
+                    local_only,
+                    followers_only,
+                    published: note.created_at,
+                    is_reply: note.replies_to.is_some(),
+                    in_reply_to: note.replies_to.as_ref().map(|x| x.to_string()),
+                    content: note.content.as_ref().and_then(|x| x.to_html()),
+                    subject: note.subject.clone(),
+                    is_sensitive: note.is_sensitive.unwrap_or(false),
+                    shared: None,
+                    multi_select: poll.map(|x| x.multiple_choice),
+                    options: poll.map(|x| x.options.iter().filter_map(|x| x.to_html()).collect()),
+                    closed: poll.and_then(|x| x.expires_at.as_ref().map(|x| x.expires_at)),
+                    local_only_voting: poll.map(|_| false),
+                    edited_at: None,
+                    actor: author.uid.clone(),
+                }
+            }
+            VersiaPostable::Share(share) => StoredPost {
+                id: share.uri.to_string(),
+                versia_id: share.id.clone(),
+                domain: share.uri.domain()?.to_string(),
+                surtype: PostSupertype::Share,
+                subtype: serde_json::to_string(&share.type_field).unwrap(),
+                category: Category::Microblog,
+                likes: 0,
+                boosts: 0,
+                local_only: false,
+                followers_only: false,
+                published: share.created_at,
+                is_reply: false,
+                in_reply_to: None,
+                content: None,
+                subject: None,
+                is_sensitive: false,
+                shared: Some(share.shared.to_string()),
+                multi_select: None,
+                options: None,
+                closed: None,
+                local_only_voting: None,
+                edited_at: None,
+                actor: author.uid.clone(),
+            },
+        };
+        Some(stored)
+    }
+
+    /// the link activitypub servers know this post by
+    pub fn ap_id(&self, author: &StoredUser) -> Option<Url> {
+        match author.is_local() {
+            true => Some(ap_post_link(
+                &author.domain,
+                &author.username,
+                &self.versia_id,
+            )),
+            false => Url::parse(&self.id).ok(),
+        }
+    }
+
+    /// the link versia servers know this post by
+    pub fn versia_uri(&self, author: &StoredUser) -> Option<Url> {
+        match author.is_local() {
+            true => Some(versia_post_link(
+                &author.domain,
+                &author.username,
+                &self.versia_id,
+            )),
+            false => Url::parse(&self.id).ok(),
+        }
+    }
+
+    /// replies to our own posts are stored under the versia link of the
+    /// parent, activitypub servers know it by its activitypub link
+    fn ap_reply_link(&self, author: &StoredUser) -> Option<Url> {
+        let parent = Url::parse(self.in_reply_to.as_ref()?).ok()?;
+        match author.is_local() {
+            true => versia_to_ap_post_link(&author.domain, &parent).or(Some(parent)),
+            false => Some(parent),
+        }
+    }
+
+    /// shares have no activitypub representation and return none
+    pub fn to_ap(&self, author: &StoredUser) -> Option<ApPostable> {
+        let id = self.ap_id(author)?;
+        let actor = author.ap_id()?;
+        let versia_url = match author.is_local() {
+            true => self.versia_uri(author),
+            false => None,
+        };
+        match self.surtype {
+            PostSupertype::Object => {
+                let followers = Url::parse(&author.followers).ok()?;
+                let (to, cc) = match self.followers_only || self.local_only {
+                    true => (LinkSimpleOrExpanded::Simple(followers), None),
+                    false => (
+                        LinkSimpleOrExpanded::Simple(Url::parse(PUBLIC_ALIASES[0]).unwrap()),
+                        Some(OptionalArray::Single(LinkSimpleOrExpanded::Simple(
+                            followers,
+                        ))),
+                    ),
+                };
+                Some(ApPostable::Note(Note {
+                    type_field: serde_json::from_str(&self.subtype).unwrap_or(NoteType::Note),
+                    id,
+                    attributed_to: actor,
+                    published: self.published,
+                    content: self.content.clone(),
+                    media_type: Some(MediaType::Html),
+                    in_reply_to: self.ap_reply_link(author),
+                    to: Some(OptionalArray::Multiple(vec![to])),
+                    updated: self.edited_at,
+                    summary: self.subject.clone(),
+                    sensitive: Some(self.is_sensitive),
+                    tag: None,
+                    url: None,
+                    bto: None,
+                    cc,
+                    bcc: None,
+                    replies: None,
+                    versia_url,
+                }))
+            }
+            PostSupertype::Question => {
+                let options = self
+                    .options
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|name| QuestionOption {
+                        name,
+                        type_field: QuestionOptionType::Note,
+                    })
+                    .collect();
+                let options = match self.multi_select {
+                    Some(true) => ChoiceType::AnyOf(options),
+                    _ => ChoiceType::OneOf(options),
+                };
+                Some(ApPostable::Question(Question {
+                    id,
+                    actor,
+                    type_field: QuestionType::Question,
+                    options,
+                    local_only: self.local_only_voting,
+                    closed: self.closed.and_then(format_time),
+                    versia_url,
+                }))
+            }
+            PostSupertype::Share => None,
+        }
+    }
+
+    pub fn to_versia(&self, author: &StoredUser) -> Option<VersiaPostable> {
+        let uri = self.versia_uri(author)?;
+        let author_uri = author.versia_uri()?;
+        if let PostSupertype::Share = self.surtype {
+            return Some(VersiaPostable::Share(Share {
+                id: self.versia_id.clone(),
+                type_field: ShareType::Share,
+                created_at: self.published,
+                author: author_uri,
+                uri,
+                shared: Url::parse(self.shared.as_ref()?).ok()?,
+            }));
+        }
+        let group = match (self.local_only, self.followers_only) {
+            (true, _) => Groups::Local,
+            (false, true) => Groups::Followers,
+            (false, false) => Groups::Public,
+        };
+        let polls = match self.surtype {
+            PostSupertype::Question => {
+                let options: Vec<TextContentFormat> = self
+                    .options
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(TextContentFormat::from_html)
+                    .collect();
+                Some(PubVersiaPolls {
+                    votes: vec![0; options.len()],
+                    options,
+                    multiple_choice: self.multi_select.unwrap_or(false),
+                    expires_at: self.closed.map(|expires_at| Expiry { expires_at }),
+                })
+            }
+            _ => None,
+        };
+        Some(VersiaPostable::Note(Box::new(VersiaNote {
+            id: self.versia_id.clone(),
+            type_field: NoteTyoe::Note,
+            uri,
+            created_at: self.published,
+            attachments: None,
+            author: author_uri,
+            category: Some(self.category.clone()),
+            content: self.content.clone().map(TextContentFormat::from_html),
+            device: None,
+            extensions: polls.map(|polls| NoteExtensions {
+                pub_versia_custom_emojis: None,
+                pub_versia_polls: Some(polls),
+            }),
+            group: Some(GroupType::Simple(group)),
+            is_sensitive: Some(self.is_sensitive),
+            mentions: None,
+            replies_to: self.in_reply_to.as_ref().and_then(|x| Url::parse(x).ok()),
+            subject: self.subject.clone(),
+        })))
+    }
+}
@@ -0,0 +1,444 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use ed25519_dalek::{
+    pkcs8::{spki::der::pem::LineEnding, DecodePublicKey, EncodePublicKey},
+    VerifyingKey,
+};
+use url::Url;
+
+use crate::{
+    cryptography::{
+        key::Key,
+        openssl::{OpenSSLPrivate, OpenSSLPublic},
+    },
+    protocols::types::{
+        activitystream_objects::{
+            actors::{Actor, ActorType, Endpoints, Image, ImageType},
+            public_key::PublicKey,
+        },
+        versia_types::{
+            entities::{
+                public_key::{AlgorithmsPublicKey, Ed25519Public, PublicKey as VersiaPublicKey},
+                user::{User, UserCollections, UserType},
+            },
+            structures::content_format::TextContentFormat,
+        },
+    },
+};
+
+use super::{
+    new_actor::{generate_ap_links, generate_versia_links, instance_actor_links, NewLocal},
+    permission::PermissionLevel,
+};
+
+/// a row of the users table, shared between the database backends so
+/// that converting to and from the protocol types only lives in one place
+///
+/// local users are stored with their activitypub links and have their
+/// versia links generated on the fly. federated users are stored with
+/// the links of the protocol they were fetched over
+#[derive(Debug, Clone)]
+pub struct StoredUser {
+    pub uid: String,
+    pub resource_link: String,
+    pub versia_id: String,
+    pub url: String,
+    pub domain: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub summary: Option<String>,
+    pub avatar: Option<String>,
+    pub public_key_pem: String,
+    pub public_key_id: String,
+    /// the key replaced by the last rotation, accepted until
+    /// `previous_key_expires`
+    pub previous_public_key_pem: Option<String>,
+    pub previous_key_expires: Option<i64>,
+    /// the user this versia user's key belongs to when it isn't their
+    /// own. they are allowed to act on behalf of this user
+    pub key_delegate: Option<String>,
+    pub manual_followers: bool,
+    pub banned: bool,
+    pub reason: Option<String>,
+    /// local users that deleted their account, see [`crate::db::conn::Conn::tombstone_user`]
+    pub deleted: bool,
+    pub inbox: String,
+    pub outbox: String,
+    pub followers: String,
+    pub following: String,
+    /// only known for activitypub users, versia instances
+    /// list their shared inbox in the instance metadata
+    pub shared_inbox: Option<String>,
+    pub password: Option<String>,
+    pub email: Option<String>,
+    pub private_key_pem: Option<String>,
+    pub permission_level: Option<PermissionLevel>,
+    /// local users that may use the instance, false while they wait on a
+    /// moderator. see [`super::registration::RegistrationMode`]
+    pub approved: bool,
+    /// local users that were vetted through an invite or a moderator
+    /// rather than signing up while registration was open
+    pub vetted: bool,
+    pub created_at: i64,
+}
+
+/// how long the key replaced by a rotation keeps being accepted
+pub const PREVIOUS_KEY_GRACE_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+pub fn new_uid() -> String {
+    uuid::Uuid::now_v7().to_string()
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+impl StoredUser {
+    pub fn new_local(domain: &str, content: &NewLocal) -> Self {
+        let uid = new_uid();
+        let links = generate_ap_links(domain, &content.username);
+        StoredUser {
+            resource_link: links.id.to_string(),
+            versia_id: uid.clone(),
+            uid,
+            url: links.url.to_string(),
+            domain: domain.to_string(),
+            username: content.username.clone(),
+            display_name: None,
+            summary: None,
+            avatar: None,
+            public_key_pem: content.public_key_pem.clone(),
+            public_key_id: links.pub_key_id.to_string(),
+            previous_public_key_pem: None,
+            previous_key_expires: None,
+            key_delegate: None,
+            manual_followers: false,
+            banned: false,
+            reason: None,
+            deleted: false,
+            inbox: links.inbox.to_string(),
+            outbox: links.outbox.to_string(),
+            followers: links.followers.to_string(),
+            following: links.following.to_string(),
+            shared_inbox: Some(instance_actor_links(domain).inbox.to_string()),
+            password: Some(content.password.clone()),
+            email: content.email.clone(),
+            private_key_pem: Some(content.private_key_pem.clone()),
+            permission_level: Some(content.permission_level),
+            approved: content.approved,
+            vetted: content.vetted,
+            created_at: now_millis(),
+        }
+    }
+
+    pub fn from_actor(actor: &Actor) -> Option<Self> {
+        let domain = actor.id.domain()?.to_string();
+        Some(StoredUser {
+            uid: new_uid(),
+            resource_link: actor.id.to_string(),
+            versia_id: actor.id.to_string(),
+            url: actor.url.as_ref().unwrap_or(&actor.id).to_string(),
+            domain,
+            username: actor.preferred_username.clone(),
+            display_name: actor.name.clone(),
+            summary: actor.summary.clone(),
+            avatar: actor.icon.as_ref().map(|x| x.url.to_string()),
+            public_key_pem: actor.public_key.public_key_pem.to_pem().ok()?,
+            public_key_id: actor.public_key.id.to_string(),
+            previous_public_key_pem: None,
+            previous_key_expires: None,
+            key_delegate: None,
+            manual_followers: false,
+            banned: false,
+            reason: None,
+            deleted: false,
+            inbox: actor.inbox.to_string(),
+            outbox: actor.outbox.to_string(),
+            followers: actor.followers.to_string(),
+            following: actor.following.to_string(),
+            shared_inbox: actor
+                .endpoints
+                .as_ref()
+                .and_then(|x| x.shared_inbox.as_ref())
+                .map(|x| x.to_string()),
+            password: None,
+            email: None,
+            private_key_pem: None,
+            permission_level: None,
+            approved: true,
+            vetted: true,
+            created_at: now_millis(),
+        })
+    }
+
+    pub fn from_versia(user: &User) -> Option<Self> {
+        let domain = user.uri.domain()?.to_string();
+        let AlgorithmsPublicKey::Ed25519(key) = &user.public_key.key;
+        let public_key_pem = key.key.to_public_key_pem(LineEnding::LF).ok()?;
+        Some(StoredUser {
+            uid: new_uid(),
+            resource_link: user.uri.to_string(),
+            versia_id: user.id.clone(),
+            url: user.uri.to_string(),
+            domain,
+            username: user.username.clone(),
+            display_name: user.display_name.clone(),
+            summary: user.bio.as_ref().and_then(|x| x.to_html()),
+            avatar: user
+                .avatar
+                .as_ref()
+                .and_then(|x| x.best())
+                .map(|x| x.content.to_string()),
+            public_key_pem,
+            public_key_id: user.uri.to_string(),
+            previous_public_key_pem: None,
+            previous_key_expires: None,
+            key_delegate: user
+                .public_key
+                .actor
+                .as_ref()
+                .filter(|x| x.ne(&&user.uri))
+                .map(|x| x.to_string()),
+            manual_followers: user.manually_approves_followers,
+            banned: false,
+            reason: None,
+            deleted: false,
+            inbox: user.inbox.to_string(),
+            outbox: user.collections.outbox.to_string(),
+            followers: user.collections.followers.to_string(),
+            following: user.collections.following.to_string(),
+            shared_inbox: None,
+            password: None,
+            email: None,
+            private_key_pem: None,
+            permission_level: None,
+            approved: true,
+            vetted: true,
+            created_at: user.created_at,
+        })
+    }
+
+    /// takes everything a federated profile update may change
+    /// from a freshly received copy of the user
+    pub fn update_profile(&mut self, other: &StoredUser) {
+        self.url = other.url.clone();
+        self.username = other.username.clone();
+        self.display_name = other.display_name.clone();
+        self.summary = other.summary.clone();
+        self.avatar = other.avatar.clone();
+        if self.public_key_pem.ne(&other.public_key_pem) {
+            self.rotate_public_key(other.public_key_pem.clone());
+        }
+        self.public_key_id = other.public_key_id.clone();
+        self.key_delegate = other.key_delegate.clone();
+        self.manual_followers = other.manual_followers;
+        self.inbox = other.inbox.clone();
+        self.outbox = other.outbox.clone();
+        self.followers = other.followers.clone();
+        self.following = other.following.clone();
+        self.shared_inbox = other.shared_inbox.clone();
+    }
+
+    /// we only hold the private keys of users we are authoratative over
+    pub fn is_local(&self) -> bool {
+        self.private_key_pem.is_some()
+    }
+
+    /// checks a login attempt against the argon2 hash of the password.
+    /// always fails for federated and deleted users as they have none
+    pub fn check_password(&self, password: &str) -> bool {
+        let Some(hash) = &self.password else {
+            return false;
+        };
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return false;
+        };
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    }
+
+    pub fn public_key(&self) -> Option<OpenSSLPublic> {
+        OpenSSLPublic::from_pem(self.public_key_pem.as_bytes()).ok()
+    }
+
+    /// the current key along with the previous one while it is still
+    /// within its grace period
+    pub fn verifying_keys(&self) -> Vec<OpenSSLPublic> {
+        let previous = match (&self.previous_public_key_pem, self.previous_key_expires) {
+            (Some(pem), Some(expires)) if expires > now_millis() => {
+                OpenSSLPublic::from_pem(pem.as_bytes()).ok()
+            }
+            _ => None,
+        };
+        self.public_key().into_iter().chain(previous).collect()
+    }
+
+    /// replaces the public key, keeping the old one for the grace period
+    pub fn rotate_public_key(&mut self, public_key_pem: String) {
+        let previous = std::mem::replace(&mut self.public_key_pem, public_key_pem);
+        self.previous_public_key_pem = Some(previous);
+        self.previous_key_expires = Some(now_millis() + PREVIOUS_KEY_GRACE_MILLIS);
+    }
+
+    pub fn private_key(&self) -> Option<OpenSSLPrivate> {
+        OpenSSLPrivate::from_pem(self.private_key_pem.as_ref()?.as_bytes()).ok()
+    }
+
+    /// the link other activitypub servers know this user by
+    pub fn ap_id(&self) -> Option<Url> {
+        Url::parse(&self.resource_link).ok()
+    }
+
+    /// the link other versia servers know this user by
+    pub fn versia_uri(&self) -> Option<Url> {
+        match self.is_local() {
+            true => Some(generate_versia_links(&self.domain, &self.username).id),
+            false => Url::parse(&self.resource_link).ok(),
+        }
+    }
+
+    pub fn to_actor(&self) -> Option<Actor> {
+        let id = self.ap_id()?;
+        let versia_url = match self.is_local() {
+            true => self.versia_uri(),
+            false => None,
+        };
+        Some(Actor {
+            type_field: ActorType::Person,
+            id: id.clone(),
+            preferred_username: self.username.clone(),
+            summary: self.summary.clone(),
+            name: self.display_name.clone(),
+            url: Url::parse(&self.url).ok(),
+            icon: self
+                .avatar
+                .as_ref()
+                .and_then(|x| Url::parse(x).ok())
+                .map(|url| Image {
+                    type_field: ImageType::Image,
+                    media_type: None,
+                    url,
+                }),
+            public_key: PublicKey {
+                id: Url::parse(&self.public_key_id).ok()?,
+                owner: id,
+                public_key_pem: self.public_key()?,
+            },
+            inbox: Url::parse(&self.inbox).ok()?,
+            outbox: Url::parse(&self.outbox).ok()?,
+            followers: Url::parse(&self.followers).ok()?,
+            following: Url::parse(&self.following).ok()?,
+            endpoints: self.shared_inbox.as_ref().map(|x| Endpoints {
+                shared_inbox: Url::parse(x).ok(),
+            }),
+            versia_url,
+        })
+    }
+
+    /// returns none for users whose key cannot be represented in versia
+    pub fn to_versia(&self) -> Option<User> {
+        let key = VerifyingKey::from_public_key_pem(&self.public_key_pem).ok()?;
+        let uri = self.versia_uri()?;
+        let collections = match self.is_local() {
+            true => {
+                let links = generate_versia_links(&self.domain, &self.username);
+                UserCollections {
+                    outbox: links.outbox,
+                    followers: links.followers,
+                    following: links.following,
+                    featured: Url::parse(&format!(
+                        "https://{}/versia/users/{}/featured/versia",
+                        self.domain, self.username
+                    ))
+                    .ok()?,
+                    pub_versia_likes_dislikes: None,
+                    pub_versia_likes_likes: Some(links.liked),
+                }
+            }
+            false => UserCollections {
+                outbox: Url::parse(&self.outbox).ok()?,
+                followers: Url::parse(&self.followers).ok()?,
+                following: Url::parse(&self.following).ok()?,
+                featured: Url::parse(&self.outbox).ok()?,
+                pub_versia_likes_dislikes: None,
+                pub_versia_likes_likes: None,
+            },
+        };
+        let inbox = match self.is_local() {
+            true => generate_versia_links(&self.domain, &self.username).inbox,
+            false => Url::parse(&self.inbox).ok()?,
+        };
+        Some(User {
+            id: self.versia_id.clone(),
+            type_field: UserType::User,
+            uri: uri.clone(),
+            created_at: self.created_at,
+            // versia needs the media type of the avatar which we don't keep
+            avatar: None,
+            bio: self.summary.clone().map(TextContentFormat::from_html),
+            display_name: self.display_name.clone(),
+            fields: None,
+            username: self.username.clone(),
+            header: None,
+            public_key: VersiaPublicKey {
+                actor: self
+                    .key_delegate
+                    .as_ref()
+                    .and_then(|x| Url::parse(x).ok())
+                    .or(Some(uri)),
+                key: AlgorithmsPublicKey::Ed25519(Ed25519Public { key }),
+            },
+            manually_approves_followers: self.manual_followers,
+            indexable: true,
+            inbox,
+            collections,
+            extensions: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+    use crate::db::utility::new_actor::local_username;
+
+    #[test]
+    fn test_local_user_links() -> Result<(), String> {
+        let new = NewLocal::new("test".to_string(), "password".to_string(), None, None);
+        let user = StoredUser::new_local("example.com", &new);
+
+        let actor = user.to_actor().ok_or("failed to render actor")?;
+        let versia = user.to_versia().ok_or("failed to render versia user")?;
+
+        for link in [actor.id.as_str(), versia.uri.as_str()] {
+            let link = Url::parse(link).map_err(|x| x.to_string())?;
+            assert_eq!(
+                local_username("example.com", &link).as_deref(),
+                Some("test")
+            );
+            assert_eq!(local_username("other.com", &link), None);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_password() -> Result<(), String> {
+        let new = NewLocal::new("test".to_string(), "password".to_string(), None, None);
+        let mut user = StoredUser::new_local("example.com", &new);
+        if !user.check_password("password") || user.check_password("wrong") {
+            return Err("password was not checked against its hash".to_string());
+        }
+        user.password = None;
+        if user.check_password("password") {
+            return Err("logged in a user without a password".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use url::Url;
+
+use crate::db::conn::{Conn, DbErr, EntityOrigin};
+
+use super::{notifications::Notification, stored_post::StoredPost, stored_user::StoredUser};
+
+/// how many events a subscriber can fall behind before it starts
+/// missing them
+pub const STREAM_BUFFER: usize = 1024;
+
+/// something that happened that clients may want pushed to them
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Post {
+        post: StoredPost,
+        author: StoredUser,
+    },
+    /// the post as it is after the edit
+    Edit {
+        post: StoredPost,
+        author: StoredUser,
+    },
+    /// the post as it was before it got deleted
+    Delete {
+        post: StoredPost,
+        author: StoredUser,
+    },
+    Notification(Notification),
+}
+
+/// the in process bus events are published to. every open stream
+/// subscribes and picks out the events it shows
+pub struct StreamBus {
+    sender: Sender<Arc<StreamEvent>>,
+}
+
+impl StreamBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        StreamBus { sender }
+    }
+    /// events published while nobody is subscribed are dropped
+    pub fn publish(&self, event: StreamEvent) {
+        let _ = self.sender.send(Arc::new(event));
+    }
+    pub fn subscribe(&self) -> Receiver<Arc<StreamEvent>> {
+        self.sender.subscribe()
+    }
+}
+
+/// the streams a client can subscribe to, named the way mastodon
+/// clients ask for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// the home timeline along with notifications
+    User,
+    UserNotification,
+    Public,
+    PublicLocal,
+}
+
+impl StreamKind {
+    pub fn parse_str(name: &str) -> Option<Self> {
+        match name {
+            "user" => Some(StreamKind::User),
+            "user:notification" => Some(StreamKind::UserNotification),
+            "public" => Some(StreamKind::Public),
+            "public:local" => Some(StreamKind::PublicLocal),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamKind::User => "user",
+            StreamKind::UserNotification => "user:notification",
+            StreamKind::Public => "public",
+            StreamKind::PublicLocal => "public:local",
+        }
+    }
+    /// if the post belongs on the stream of the viewer, the same way it
+    /// would on the matching timeline. streams are only opened by local
+    /// users so local only posts are always shown
+    pub fn shows_post(
+        &self,
+        post: &StoredPost,
+        viewer: &str,
+        follows_author: bool,
+        instance_domain: &str,
+    ) -> bool {
+        match self {
+            StreamKind::User => post.actor.eq(viewer) || follows_author,
+            StreamKind::UserNotification => false,
+            StreamKind::Public => !post.followers_only,
+            StreamKind::PublicLocal => !post.followers_only && post.domain.eq(instance_domain),
+        }
+    }
+    pub fn shows_notification(&self, notification: &Notification, viewer: &str) -> bool {
+        match self {
+            StreamKind::User | StreamKind::UserNotification => notification.recipient.eq(viewer),
+            StreamKind::Public | StreamKind::PublicLocal => false,
+        }
+    }
+}
+
+/// publishes a newly stored post, found by its link
+pub async fn publish_post(conn: &dyn Conn, bus: &StreamBus, link: &Url) -> Result<(), DbErr> {
+    let post = conn.get_post(link).await.ok_or(DbErr::NotFound)?;
+    let author = conn.get_user(&post.actor).await.ok_or(DbErr::NotFound)?;
+    bus.publish(StreamEvent::Post { post, author });
+    Ok(())
+}
+
+/// deletes the post and lets streams that showed it know. the post is
+/// looked up first as there is nothing left to describe it afterwards
+pub async fn delete_and_publish(
+    conn: &dyn Conn,
+    bus: &StreamBus,
+    link: &Url,
+    origin: &EntityOrigin<'_>,
+) -> Result<(), DbErr> {
+    let post = conn.get_post(link).await;
+    conn.delete_post(link.as_str(), origin).await?;
+    let Some(post) = post else {
+        return Ok(());
+    };
+    if let Some(author) = conn.get_user(&post.actor).await {
+        bus.publish(StreamEvent::Delete { post, author });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::utility::notifications::NotificationKind;
+
+    #[test]
+    fn test_stream_kinds() -> Result<(), String> {
+        let notification = Notification::new("viewer", NotificationKind::Follow, None, None);
+        if !StreamKind::UserNotification.shows_notification(&notification, "viewer")
+            || StreamKind::User.shows_notification(&notification, "someone")
+            || StreamKind::Public.shows_notification(&notification, "viewer")
+        {
+            return Err("notifications reached the wrong streams".to_string());
+        }
+        for kind in [
+            StreamKind::User,
+            StreamKind::UserNotification,
+            StreamKind::Public,
+            StreamKind::PublicLocal,
+        ] {
+            if StreamKind::parse_str(kind.name()) != Some(kind) {
+                return Err(format!("failed to parse {}", kind.name()));
+            }
+        }
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_stream_bus() -> Result<(), String> {
+        let bus = StreamBus::new(1);
+        // nobody is listening yet
+        bus.publish(StreamEvent::Notification(Notification::new(
+            "viewer",
+            NotificationKind::Like,
+            None,
+            None,
+        )));
+        let mut receiver = bus.subscribe();
+        let notification = Notification::new("viewer", NotificationKind::Follow, None, None);
+        bus.publish(StreamEvent::Notification(notification.clone()));
+        match receiver.recv().await.map_err(|x| x.to_string())?.as_ref() {
+            StreamEvent::Notification(x) if x.id.eq(&notification.id) => Ok(()),
+            x => Err(format!("received the wrong event: {:?}", x)),
+        }
+    }
+}
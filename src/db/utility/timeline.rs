@@ -0,0 +1,113 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+use super::stored_post::StoredPost;
+
+/// most posts returned for a single page of a timeline
+pub const MAX_TIMELINE_LIMIT: u64 = 40;
+pub const DEFAULT_TIMELINE_LIMIT: u64 = 20;
+
+/// the feeds a user can read
+pub enum Timeline<'a> {
+    /// posts of the users the user follows along with their own,
+    /// contains the uid of the user
+    Home(&'a str),
+    /// public posts made on this instance
+    Local,
+    /// every public post we know of
+    Federated,
+}
+
+/// a position in a timeline. posts are ordered by when they were
+/// published, their id breaks ties between posts made at the same time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineCursor {
+    pub published: i64,
+    pub id: String,
+}
+
+impl TimelineCursor {
+    pub fn new(post: &StoredPost) -> Self {
+        TimelineCursor {
+            published: post.published,
+            id: post.id.clone(),
+        }
+    }
+    /// an opaque string clients can pass back to continue from the post
+    pub fn encode(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(format!("{} {}", self.published, self.id))
+    }
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let decoded = BASE64_URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (published, id) = decoded.split_once(' ')?;
+        Some(TimelineCursor {
+            published: published.parse().ok()?,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// which part of a timeline to get. posts always come newest first
+#[derive(Debug, Clone)]
+pub struct TimelineQuery {
+    /// only posts older than the cursor
+    pub before: Option<TimelineCursor>,
+    /// only posts newer than the cursor
+    pub after: Option<TimelineCursor>,
+    /// with `after`, the posts right after the cursor rather than
+    /// the newest ones
+    pub adjacent: bool,
+    pub limit: u64,
+    /// the local user reading the timeline. local only posts are
+    /// hidden from everyone else
+    pub viewer: Option<String>,
+}
+
+impl Default for TimelineQuery {
+    fn default() -> Self {
+        TimelineQuery {
+            before: None,
+            after: None,
+            adjacent: false,
+            limit: DEFAULT_TIMELINE_LIMIT,
+            viewer: None,
+        }
+    }
+}
+
+impl TimelineQuery {
+    /// if the post falls inside of the cursors
+    pub fn in_range(&self, post: &StoredPost) -> bool {
+        let position = (post.published, post.id.as_str());
+        let before = self
+            .before
+            .as_ref()
+            .is_none_or(|x| position < (x.published, x.id.as_str()));
+        let after = self
+            .after
+            .as_ref()
+            .is_none_or(|x| position > (x.published, x.id.as_str()));
+        before && after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() -> Result<(), String> {
+        let cursor = TimelineCursor {
+            published: 1_700_000_000_000,
+            id: "https://example.com/versia/posts/a b".to_string(),
+        };
+        let decoded = TimelineCursor::decode(&cursor.encode()).ok_or("failed to decode")?;
+        if decoded.ne(&cursor) {
+            return Err("cursor did not round trip".to_string());
+        }
+        if TimelineCursor::decode("not a cursor").is_some() {
+            return Err("decoded garbage".to_string());
+        }
+        Ok(())
+    }
+}
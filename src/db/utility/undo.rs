@@ -0,0 +1,56 @@
+use url::Url;
+
+use crate::{
+    db::conn::{Conn, DbErr, EntityOrigin},
+    protocols::types::activitystream_objects::{
+        link::RangeLinkItem,
+        undo::{Undo, UndoneType},
+    },
+};
+
+use super::{new_actor::local_username, post_types::PostSupertype, stored_user::StoredUser};
+
+/// removes whatever the undone activity created. the undo has already
+/// been checked to come from the actor of the activity
+pub async fn handle_undo(conn: &dyn Conn, undo: Undo, instance_domain: &str) -> Result<(), DbErr> {
+    let actor = conn.resolve_user(&undo.actor).await?;
+    match undo.object {
+        RangeLinkItem::Item(undone) => match undone.type_field {
+            UndoneType::Follow => {
+                if local_username(instance_domain, &undone.object).is_none() {
+                    return Err(DbErr::NotFound);
+                }
+                let target = conn.resolve_user(&undone.object).await?;
+                conn.remove_follow(&actor.uid, &target.uid).await
+            }
+            UndoneType::Like => conn.remove_like(&actor.uid, undone.id.as_str()).await,
+            UndoneType::Announce => remove_share(conn, undone.id.as_str(), &actor).await,
+        },
+        // without the activity we don't know what it was, so
+        // try everything it could have created
+        RangeLinkItem::Link(link) => {
+            let id = link.get_id().as_str();
+            match conn.remove_follow_activity(&actor.uid, id).await {
+                Err(DbErr::NotFound) => {}
+                x => return x,
+            }
+            match conn.remove_like(&actor.uid, id).await {
+                Err(DbErr::NotFound) => {}
+                x => return x,
+            }
+            remove_share(conn, id, &actor).await
+        }
+    }
+}
+
+/// shares are stored as posts under the id of the announce. only the
+/// actor that shared may take it back, and only shares can be undone
+async fn remove_share(conn: &dyn Conn, id: &str, actor: &StoredUser) -> Result<(), DbErr> {
+    let link = Url::parse(id).map_err(|_| DbErr::NotFound)?;
+    let share = conn.get_post(&link).await.ok_or(DbErr::NotFound)?;
+    if share.surtype.ne(&PostSupertype::Share) || share.actor.ne(&actor.uid) {
+        return Err(DbErr::NotFound);
+    }
+    conn.delete_post(id, &EntityOrigin::Federated(&actor.domain))
+        .await
+}
@@ -3,7 +3,7 @@ use serde::Deserialize;
 use std::time::SystemTime;
 use url::Url;
 
-use crate::cryptography::key::PrivateKey;
+use crate::{api::webfinger::WebfingerResult, cryptography::key::PrivateKey};
 
 /// key_id and private_key are the properties of the key
 /// being used to perform the fetch. usually done by the
@@ -67,3 +67,34 @@ pub async fn authorized_fetch<T: PrivateKey, F: for<'a> Deserialize<'a>>(
 
     Ok(object)
 }
+
+/// resolves acct:{username}@{domain} on the given domain
+pub async fn webfinger_lookup(username: &str, domain: &str) -> Result<WebfingerResult, FetchErr> {
+    let Ok(url) = Url::parse_with_params(
+        &format!("https://{domain}/.well-known/webfinger"),
+        &[("resource", format!("acct:{username}@{domain}"))],
+    ) else {
+        return Err(FetchErr::InvalidUrl(domain.to_string()));
+    };
+
+    let res = reqwest::Client::new()
+        .get(url)
+        .header("accept", "application/jrd+json")
+        .send()
+        .await;
+    let res = match res {
+        Ok(x) => x,
+        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
+    };
+
+    let response = res.text().await;
+    let response = match response {
+        Ok(x) => x,
+        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
+    };
+
+    match serde_json::from_str(&response) {
+        Ok(x) => Ok(x),
+        Err(x) => Err(FetchErr::DeserializationErr(x.to_string())),
+    }
+}
@@ -0,0 +1,394 @@
+// RFC 9421 HTTP Message Signatures, the successor of draft-cavage
+// https://www.rfc-editor.org/rfc/rfc9421
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::cryptography::{
+    digest::{sha256_hash, sha512_hash},
+    key::PrivateKey,
+};
+
+use super::{
+    super::{headers::Headers, http_method::HttpMethod},
+    signature::SignatureErr,
+};
+
+/// the label we sign our requests under
+const LABEL: &str = "sig1";
+
+/// a parsed `Signature-Input` member along with its `Signature`
+///
+/// `Signature-Input: sig1=("@method" "@target-uri" "content-digest");created=1618884473;keyid="https://my.example.com/actor#main-key"`
+/// `Signature: sig1=:Y2FiYW...IxNGRiZDk4ZA==:`
+#[derive(Debug, Clone)]
+pub struct MessageSignature {
+    /// the covered components in the order they are signed
+    pub components: Vec<String>,
+    /// the inner list and parameters exactly as they were sent, used
+    /// as the value of `@signature-params`
+    pub params: String,
+    pub key_id: Url,
+    pub key_domain: String,
+    /// unix timestamp in seconds
+    pub created: Option<i64>,
+    /// the contained signature
+    pub signature: Vec<u8>,
+}
+
+impl MessageSignature {
+    /// parses the first signature present in both headers
+    pub fn parse(signature_input: &str, signature: &str) -> Result<Self, SignatureErr> {
+        let signatures = dictionary_members(signature);
+        let (params, signature) = dictionary_members(signature_input)
+            .into_iter()
+            .find_map(|(label, input)| {
+                let signature = signatures.iter().find(|(x, _)| x.eq(&label))?;
+                Some((input, signature.1.clone()))
+            })
+            .ok_or(SignatureErr::NoSignature)?;
+
+        let Some(signature) = signature
+            .strip_prefix(':')
+            .and_then(|x| x.strip_suffix(':'))
+        else {
+            return Err(SignatureErr::BadSignature);
+        };
+        let Ok(signature) = openssl::base64::decode_block(signature) else {
+            return Err(SignatureErr::BadSignature);
+        };
+
+        let Some(inner) = params.strip_prefix('(') else {
+            return Err(SignatureErr::NoHeaders);
+        };
+        let Some((inner, parameters)) = inner.split_once(')') else {
+            return Err(SignatureErr::NoHeaders);
+        };
+        let mut components = Vec::new();
+        for component in inner.split_ascii_whitespace() {
+            // components with parameters such as ;sf aren't supported
+            let Some(name) = component
+                .strip_prefix('"')
+                .and_then(|x| x.strip_suffix('"'))
+            else {
+                return Err(SignatureErr::MissingHeader(component.to_string()));
+            };
+            components.push(name.to_lowercase());
+        }
+
+        let mut key_id = None;
+        let mut created = None;
+        for parameter in split_outside_quotes(parameters, ';') {
+            let Some((key, value)) = parameter.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "keyid" => key_id = Some(value.trim().trim_matches('"').to_string()),
+                "created" => created = value.trim().parse().ok(),
+                "alg" => match value.trim().trim_matches('"') {
+                    "rsa-v1_5-sha256" | "ed25519" => {}
+                    _ => return Err(SignatureErr::UnkownAlgorithm),
+                },
+                _ => {}
+            }
+        }
+
+        let Some(key_id) = key_id else {
+            return Err(SignatureErr::NoKey);
+        };
+        let Ok(key_id) = Url::parse(&key_id) else {
+            return Err(SignatureErr::InvalidKey);
+        };
+        let Some(key_domain) = key_id.domain() else {
+            return Err(SignatureErr::InvalidDomain);
+        };
+        let key_domain = key_domain.to_string();
+
+        Ok(MessageSignature {
+            components,
+            params,
+            key_id,
+            key_domain,
+            created,
+            signature,
+        })
+    }
+
+    pub fn covers(&self, component: &str) -> bool {
+        self.components.iter().any(|x| x.eq(component))
+    }
+
+    /// the signature has to be bound to the request it came with, through
+    /// its method and either the whole target uri or its authority and path
+    pub fn covers_request(&self) -> Result<(), SignatureErr> {
+        if !self.covers("@method") {
+            return Err(SignatureErr::MissingHeader("@method".to_string()));
+        }
+        if self.covers("@target-uri") || (self.covers("@authority") && self.covers("@path")) {
+            return Ok(());
+        }
+        Err(SignatureErr::MissingHeader("@target-uri".to_string()))
+    }
+
+    /// generates the signature base from the actual request
+    pub fn signature_base<H: Headers>(
+        &self,
+        method: &HttpMethod,
+        target_uri: &Url,
+        request_headers: &H,
+    ) -> Result<String, SignatureErr> {
+        signature_base(
+            method,
+            target_uri,
+            &self.components,
+            &self.params,
+            request_headers,
+        )
+    }
+}
+
+/// builds the signature base of a request, with `params` being the
+/// serialized value of `@signature-params`
+fn signature_base<H: Headers>(
+    method: &HttpMethod,
+    target_uri: &Url,
+    components: &[String],
+    params: &str,
+    request_headers: &H,
+) -> Result<String, SignatureErr> {
+    let mut lines: Vec<String> = Vec::new();
+    for component in components {
+        let value = match component.as_str() {
+            "@method" => method.stringify().to_uppercase(),
+            "@target-uri" => target_uri.to_string(),
+            "@authority" => match target_uri.port() {
+                Some(port) => format!("{}:{}", target_uri.host_str().unwrap_or_default(), port),
+                None => target_uri.host_str().unwrap_or_default().to_string(),
+            },
+            "@scheme" => target_uri.scheme().to_string(),
+            "@path" => target_uri.path().to_string(),
+            "@query" => format!("?{}", target_uri.query().unwrap_or_default()),
+            "@request-target" => match target_uri.query() {
+                Some(query) => format!("{}?{}", target_uri.path(), query),
+                None => target_uri.path().to_string(),
+            },
+            x if x.starts_with('@') => return Err(SignatureErr::MissingHeader(x.to_string())),
+            x => match request_headers.get(x) {
+                Some(value) => value.trim().to_string(),
+                None => return Err(SignatureErr::MissingHeader(x.to_string())),
+            },
+        };
+        lines.push(format!("\"{}\": {}", component, value));
+    }
+    lines.push(format!("\"@signature-params\": {}", params));
+    Ok(lines.join("\n"))
+}
+
+/// the `Content-Digest` of a body as defined by RFC 9530
+pub fn content_digest(body: &[u8]) -> String {
+    format!("sha-256=:{}:", sha256_hash(body))
+}
+
+/// checks a `Content-Digest` header against the body. any of the
+/// digests we understand matching is enough
+pub fn content_digest_matches(header: &str, body: &[u8]) -> bool {
+    dictionary_members(header)
+        .iter()
+        .any(|(algorithm, value)| match algorithm.as_str() {
+            "sha-256" => value.eq(&format!(":{}:", sha256_hash(body))),
+            "sha-512" => value.eq(&format!(":{}:", sha512_hash(body))),
+            _ => false,
+        })
+}
+
+/// signs a request, returning the `Signature-Input` and `Signature`
+/// headers. posts must already have their `Content-Digest` in the
+/// headers given so it gets covered
+pub fn sign_request<K: PrivateKey, H: Headers>(
+    method: HttpMethod,
+    target_uri: &Url,
+    request_headers: &H,
+    key_id: &str,
+    private_key: &mut K,
+) -> Result<(String, String), SignatureErr> {
+    let mut components = vec!["@method".to_string(), "@target-uri".to_string()];
+    if let HttpMethod::Post = method {
+        components.push("content-digest".to_string());
+    }
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let covered: Vec<String> = components.iter().map(|x| format!("\"{}\"", x)).collect();
+    let params = format!(
+        "({});created={};keyid=\"{}\"",
+        covered.join(" "),
+        created,
+        key_id
+    );
+
+    let base = signature_base(&method, target_uri, &components, &params, request_headers)?;
+    let signature = private_key.sign(&base);
+    Ok((
+        format!("{}={}", LABEL, params),
+        format!("{}=:{}:", LABEL, signature),
+    ))
+}
+
+/// splits a structured field dictionary into its keys and raw values
+fn dictionary_members(header: &str) -> Vec<(String, String)> {
+    split_outside_quotes(header, ',')
+        .iter()
+        .filter_map(|member| {
+            let (key, value) = member.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// splits on the separator, ignoring any inside quoted strings
+fn split_outside_quotes(value: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            x if x == separator && !quoted => parts.push(std::mem::take(&mut current)),
+            x => current.push(x),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::super::headers::HashMapHeaders;
+    use super::*;
+    use crate::cryptography::{
+        key::{Key, KeyType, PublicKey},
+        openssl::{OpenSSLPrivate, OpenSSLPublic},
+    };
+
+    #[test]
+    fn parse_signature() -> Result<(), String> {
+        let signature = MessageSignature::parse(
+            r#"sig1=("@method" "@target-uri" "content-digest");created=1618884473;keyid="https://my.example.com/actor#main-key""#,
+            "sig1=:Y2FiYWIxNGRiZDk4ZA==:",
+        )
+        .map_err(|x| serde_json::to_string(&x).unwrap())?;
+
+        assert_eq!(
+            signature.components,
+            vec!["@method", "@target-uri", "content-digest"]
+        );
+        assert_eq!(signature.created, Some(1618884473));
+        assert_eq!(signature.key_domain, "my.example.com");
+        assert!(signature.covers_request().is_ok());
+
+        let headers = HashMapHeaders {
+            headermap: HashMap::from([(
+                "content-digest".to_string(),
+                "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:".to_string(),
+            )]),
+        };
+        let base = signature
+            .signature_base(
+                &HttpMethod::Post,
+                &Url::parse("https://example.com/inbox").unwrap(),
+                &headers,
+            )
+            .map_err(|x| serde_json::to_string(&x).unwrap())?;
+        let correct = r#""@method": POST
+"@target-uri": https://example.com/inbox
+"content-digest": sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:
+"@signature-params": ("@method" "@target-uri" "content-digest");created=1618884473;keyid="https://my.example.com/actor#main-key""#;
+        if base.ne(correct) {
+            return Err(format!("bad signature base: {}", base));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unbound_signature() -> Result<(), String> {
+        let parse = |components: &str| {
+            MessageSignature::parse(
+                &format!(
+                    r#"sig1=({});created=1618884473;keyid="https://my.example.com/actor#main-key""#,
+                    components
+                ),
+                "sig1=:Y2FiYWIxNGRiZDk4ZA==:",
+            )
+            .map_err(|x| serde_json::to_string(&x).unwrap())
+        };
+        for unbound in [
+            r#""content-digest""#,
+            r#""@target-uri" "content-digest""#,
+            r#""@method" "content-digest""#,
+            r#""@method" "@path" "content-digest""#,
+        ] {
+            if parse(unbound)?.covers_request().is_ok() {
+                return Err(format!("accepted a signature covering {}", unbound));
+            }
+        }
+        if parse(r#""@method" "@authority" "@path" "content-digest""#)?
+            .covers_request()
+            .is_err()
+        {
+            return Err("rejected a signature covering the authority and path".to_string());
+        }
+        Ok(())
+    }
+
+    fn round_trip(algorithm: KeyType) -> Result<(), String> {
+        let mut key = OpenSSLPrivate::generate(algorithm);
+        let public = key.public_key_pem().map_err(|x| x.to_string())?;
+        let public = OpenSSLPublic::from_pem(public.as_bytes()).map_err(|x| x.to_string())?;
+        let target = Url::parse("https://example.com/inbox").unwrap();
+        let body = br#"{"type":"Follow"}"#;
+
+        let headers = HashMapHeaders {
+            headermap: HashMap::from([("content-digest".to_string(), content_digest(body))]),
+        };
+        let (input, signature) = sign_request(
+            HttpMethod::Post,
+            &target,
+            &headers,
+            "https://example.com/actor#main-key",
+            &mut key,
+        )
+        .map_err(|x| serde_json::to_string(&x).unwrap())?;
+
+        let parsed = MessageSignature::parse(&input, &signature)
+            .map_err(|x| serde_json::to_string(&x).unwrap())?;
+        assert!(parsed.covers("content-digest"));
+        assert!(content_digest_matches(
+            &headers.get("content-digest").unwrap(),
+            body
+        ));
+        let base = parsed
+            .signature_base(&HttpMethod::Post, &target, &headers)
+            .map_err(|x| serde_json::to_string(&x).unwrap())?;
+        if !public.verify(base.as_bytes(), &parsed.signature) {
+            return Err("signature failed to verify".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rsa_round_trip() -> Result<(), String> {
+        round_trip(KeyType::Rsa256)
+    }
+
+    #[test]
+    fn ed25519_round_trip() -> Result<(), String> {
+        round_trip(KeyType::Ed25519)
+    }
+}
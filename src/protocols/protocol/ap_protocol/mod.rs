@@ -1,6 +1,6 @@
 pub mod fetch;
 // pub mod incoming;
-pub mod notify_followers;
+pub mod message_signature;
 pub mod outgoing;
 pub mod signature;
 pub mod verification;
@@ -1,68 +1,80 @@
-use std::time::SystemTime;
+use std::{collections::HashMap, time::SystemTime};
 
-// use openssl::{
-//     hash::MessageDigest,
-//     pkey::{PKey, Private},
-// };
+use reqwest::StatusCode;
+use url::Url;
 
-use crate::cryptography::{digest::sha256_hash, key::PrivateKey};
+use crate::{
+    cryptography::{digest::sha256_hash, key::PrivateKey},
+    db::utility::protocols::SignatureScheme,
+    protocols::protocol::{errors::FetchErr, headers::HashMapHeaders, http_method::HttpMethod},
+};
 
+use super::message_signature::{content_digest, sign_request};
+
+/// signs the activity with the given key and posts it to the inbox.
+/// anything other than a success status is returned as an error
 pub async fn post_to_inbox<T: PrivateKey>(
-    // activity: &ActivityStream,
     activity: &str,
-    from_id: &str,
-    to_domain: &str,
-    to_inbox: &str,
+    key_id: &str,
+    inbox: &Url,
     keypair: &mut T,
-) {
-    // let keypair: PKey<Private> = PKey::from_rsa(private_key).unwrap();
-
-    // let document = serde_json::to_string(activity).unwrap();
+    scheme: SignatureScheme,
+) -> Result<(), FetchErr> {
+    let path = inbox.path();
+    let Some(to_domain) = inbox.host_str() else {
+        return Err(FetchErr::InvalidUrl(inbox.to_string()));
+    };
 
     let date = httpdate::fmt_http_date(SystemTime::now());
 
-    let digest_base64 = &sha256_hash(activity.as_bytes());
-
-    //string to be signed
-    let signed_string = format!("(request-target): post /inbox\nhost: {to_domain}\ndate: {date}\ndigest: SHA-256={digest_base64}");
-    let signature = keypair.sign(&signed_string);
-    // let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), keypair).unwrap();
-    // signer.update(signed_string.as_bytes()).unwrap();
-    // let signature = openssl::base64::encode_block(&signer.sign_to_vec().unwrap());
-
-    // dbg!(&from_id);
-
-    // let header: String = r#"keyId=""#.to_string()
-    //     + from_id
-    //     + r#"#main-key",headers="(request-target) host date digest",signature=""#
-    //     + &signature
-    //     + r#"""#;
-    let header = format!(
-        r#"keyId="{}#main-key",headers="(request-target) host date digest",signature="{}""#,
-        from_id, &signature,
-    );
-
-    println!("{}", &header);
-
     let client = reqwest::Client::new();
     let client = client
-        .post(to_inbox)
+        .post(inbox.clone())
         .header("Host", to_domain)
-        .header("Date", date)
-        .header("Signature", header)
-        .header("Digest", "SHA-256=".to_owned() + digest_base64)
-        .body(activity.to_string());
-
-    dbg!(&client);
-
-    let res = client.send().await;
-    // dbg!(&res);
-
-    let response = res.unwrap().text().await;
-
-    dbg!(&response);
-
-    if let Ok(x) = response {
-        println!("{}", x);
+        .header("Date", &date)
+        .header("Content-Type", "application/activity+json");
+
+    let client = match scheme {
+        SignatureScheme::Rfc9421 => {
+            let digest = content_digest(activity.as_bytes());
+            let headers = HashMapHeaders {
+                headermap: HashMap::from([("content-digest".to_string(), digest.clone())]),
+            };
+            let (input, signature) =
+                sign_request(HttpMethod::Post, inbox, &headers, key_id, keypair)
+                    .map_err(|x| FetchErr::RequestErr(serde_json::to_string(&x).unwrap()))?;
+            client
+                .header("Content-Digest", digest)
+                .header("Signature-Input", input)
+                .header("Signature", signature)
+        }
+        SignatureScheme::Cavage => {
+            let digest_base64 = &sha256_hash(activity.as_bytes());
+
+            //string to be signed
+            let signed_string = format!("(request-target): post {path}\nhost: {to_domain}\ndate: {date}\ndigest: SHA-256={digest_base64}");
+            let signature = keypair.sign(&signed_string);
+
+            let header = format!(
+                r#"keyId="{key_id}",headers="(request-target) host date digest",signature="{signature}""#
+            );
+            client
+                .header("Signature", header)
+                .header("Digest", "SHA-256=".to_owned() + digest_base64)
+        }
+    };
+
+    let res = match client.body(activity.to_string()).send().await {
+        Ok(x) => x,
+        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
+    };
+
+    match res.status() {
+        x if x.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED => Err(FetchErr::Unauthorized(inbox.to_string())),
+        x => Err(FetchErr::Status(
+            x.as_u16(),
+            format!("{} responded with {}", inbox, x),
+        )),
     }
 }
@@ -26,6 +26,10 @@ pub enum SignatureErr {
     UnkownAlgorithm,
     MissingHeader(String),
     NoDate,
+    /// the date couldn't be parsed
+    InvalidDate,
+    /// the date is too far from our clock
+    StaleDate,
     BadSignature,
 }
 
@@ -120,6 +124,9 @@ impl Signature {
             signature_header: SignatureHeader::parse(signature_header)?,
         })
     }
+    pub fn covers(&self, header: &str) -> bool {
+        self.signature_header.headers.iter().any(|x| x.eq(header))
+    }
     pub fn generate_sign_string<H: Headers>(
         &self,
         request_headers: &H,
@@ -158,8 +165,8 @@ pub fn get_signature_headers(signature_header: &str) -> HashMap<String, String>
         .filter_map(|pair| {
             pair.split_once('=').map(|(key, value)| {
                 (
-                    key.replace(|c| strip_values(c), ""),
-                    value.replace(|c| strip_values(c), ""),
+                    key.replace(strip_values, ""),
+                    value.replace(strip_values, ""),
                 )
             })
         })
@@ -1,9 +1,13 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use crate::{
     cryptography::{
         digest::{sha256_hash, sha512_hash},
         key::{PrivateKey, PublicKey},
+        openssl::OpenSSLPublic,
     },
     protocols::types::activitystream_objects::{
         actors::Actor,
@@ -12,8 +16,9 @@ use crate::{
 };
 
 use super::{
-    super::{errors::FetchErr, headers::Headers, http_method::HttpMethod},
+    super::{errors::FetchErr, headers::Headers, http_method::HttpMethod, key_cache::KeyCache},
     fetch::authorized_fetch,
+    message_signature::{content_digest_matches, MessageSignature},
     signature::{Signature, SignatureErr},
 };
 
@@ -30,11 +35,260 @@ pub enum RequestVerificationError {
     BodyDeserializeErr,
     ContentErr(InboxableVerifyErr),
     SignatureErr(SignatureErr),
+    /// the actor fetched for the key id doesn't own a key by that id
+    KeyNotOwned,
+}
+
+/// the signature of a request in whichever scheme it was signed with
+enum RequestSignature {
+    Cavage(Signature),
+    Rfc9421(MessageSignature),
+}
+
+impl RequestSignature {
+    /// rfc 9421 signatures come with a `Signature-Input`, otherwise
+    /// the `Signature` header is draft-cavage
+    fn from_headers<H: Headers>(
+        request_headers: &H,
+        method: HttpMethod,
+        instance_domain: &str,
+        path: &str,
+    ) -> Result<Self, RequestVerificationError> {
+        let Some(signature_header) = request_headers.get("Signature") else {
+            return Err(RequestVerificationError::NoSignatureHeader);
+        };
+        let signature = match request_headers.get("Signature-Input") {
+            Some(input) => {
+                MessageSignature::parse(&input, &signature_header).map(RequestSignature::Rfc9421)
+            }
+            None => Signature::from_request(
+                method,
+                instance_domain.to_string(),
+                path.to_string(),
+                &signature_header,
+            )
+            .map(RequestSignature::Cavage),
+        };
+        signature.map_err(RequestVerificationError::SignatureErr)
+    }
+
+    fn key_id(&self) -> &Url {
+        match self {
+            RequestSignature::Cavage(x) => &x.signature_header.key_id,
+            RequestSignature::Rfc9421(x) => &x.key_id,
+        }
+    }
+
+    fn key_domain(&self) -> &str {
+        match self {
+            RequestSignature::Cavage(x) => &x.signature_header.key_domain,
+            RequestSignature::Rfc9421(x) => &x.key_domain,
+        }
+    }
+
+    /// checks the body against the digest the signature covers
+    fn check_digest<H: Headers>(
+        &self,
+        request_headers: &H,
+        body: &str,
+    ) -> Result<(), RequestVerificationError> {
+        match self {
+            RequestSignature::Cavage(signature) => {
+                let Some(digest) = request_headers.get("Digest") else {
+                    return Err(RequestVerificationError::NoMessageDigest);
+                };
+                if !signature.covers("digest") {
+                    return Err(RequestVerificationError::NoMessageDigest);
+                }
+                if !digest_matches(&digest, body.as_bytes())? {
+                    return Err(RequestVerificationError::DigestDoesNotMatch);
+                }
+            }
+            RequestSignature::Rfc9421(signature) => {
+                let Some(digest) = request_headers.get("Content-Digest") else {
+                    return Err(RequestVerificationError::NoMessageDigest);
+                };
+                if !signature.covers("content-digest") {
+                    return Err(RequestVerificationError::NoMessageDigest);
+                }
+                if !content_digest_matches(&digest, body.as_bytes()) {
+                    return Err(RequestVerificationError::DigestDoesNotMatch);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the signed time of the request must be within the skew of our
+    /// clock so old requests can't be replayed
+    fn check_date<H: Headers>(
+        &self,
+        request_headers: &H,
+        skew: u64,
+    ) -> Result<(), RequestVerificationError> {
+        let signed_at = match self {
+            RequestSignature::Cavage(signature) => {
+                let Some(date) = request_headers.get("date") else {
+                    return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
+                };
+                if !signature.covers("date") {
+                    return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
+                }
+                match httpdate::parse_http_date(&date) {
+                    Ok(x) => x,
+                    Err(_) => {
+                        return Err(RequestVerificationError::SignatureErr(
+                            SignatureErr::InvalidDate,
+                        ))
+                    }
+                }
+            }
+            RequestSignature::Rfc9421(signature) => {
+                let Some(created) = signature.created else {
+                    return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
+                };
+                let Ok(created) = u64::try_from(created) else {
+                    return Err(RequestVerificationError::SignatureErr(
+                        SignatureErr::InvalidDate,
+                    ));
+                };
+                UNIX_EPOCH + Duration::from_secs(created)
+            }
+        };
+        let difference = match SystemTime::now().duration_since(signed_at) {
+            Ok(x) => x,
+            Err(x) => x.duration(),
+        };
+        if difference > Duration::from_secs(skew) {
+            return Err(RequestVerificationError::SignatureErr(
+                SignatureErr::StaleDate,
+            ));
+        }
+        Ok(())
+    }
+
+    /// checks the signature against the keys of the signer
+    fn verify<H: Headers>(
+        &self,
+        request_headers: &H,
+        method: &HttpMethod,
+        instance_domain: &str,
+        path: &str,
+        keys: &[OpenSSLPublic],
+    ) -> Result<(), RequestVerificationError> {
+        //generate a sign string of the actual request's headers with the real header values mentoned in the provided sign string
+        let (comparison_string, signature) = match self {
+            RequestSignature::Cavage(signature) => {
+                let Some(_) = request_headers.get("date") else {
+                    return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
+                };
+                let comparison_string = signature.generate_sign_string(request_headers);
+                (comparison_string, &signature.signature_header.signature)
+            }
+            RequestSignature::Rfc9421(signature) => {
+                signature
+                    .covers_request()
+                    .map_err(RequestVerificationError::SignatureErr)?;
+                let Ok(target_uri) = Url::parse(&format!("https://{}{}", instance_domain, path))
+                else {
+                    return Err(RequestVerificationError::SignatureErr(
+                        SignatureErr::InvalidDomain,
+                    ));
+                };
+                let comparison_string =
+                    signature.signature_base(method, &target_uri, request_headers);
+                (comparison_string, &signature.signature)
+            }
+        };
+        let comparison_string = match comparison_string {
+            Ok(x) => x,
+            Err(x) => return Err(RequestVerificationError::SignatureErr(x)),
+        };
+
+        let accepted = keys
+            .iter()
+            .any(|x| x.verify(comparison_string.as_bytes(), signature));
+
+        if !accepted {
+            return Err(RequestVerificationError::SignatureVerifyFailed);
+        }
+        Ok(())
+    }
+
+    /// verifies with the cached key of the signer. the actor is fetched
+    /// when their key isn't cached or once more when it fails to verify,
+    /// as they may have rotated it since it was cached
+    #[allow(clippy::too_many_arguments)]
+    async fn verify_cached<K: PrivateKey, H: Headers>(
+        &self,
+        request_headers: &H,
+        method: HttpMethod,
+        instance_domain: &str,
+        path: &str,
+        keys: &KeyCache,
+        instance_key_id: &str,
+        instance_private_key: &mut K,
+    ) -> Result<(), RequestVerificationError> {
+        let key_id = self.key_id().to_string();
+        if let Some(cached) = keys.get(&key_id) {
+            match self.verify(request_headers, &method, instance_domain, path, &cached) {
+                Err(RequestVerificationError::SignatureVerifyFailed) => {}
+                x => return x,
+            }
+        }
+
+        let fetched: Result<Actor, FetchErr> =
+            authorized_fetch(self.key_id(), instance_key_id, instance_private_key).await;
+        let actor = match fetched {
+            Ok(x) => x,
+            Err(x) => return Err(RequestVerificationError::ActorFetchFailed(x)),
+        };
+        let fetched = vec![owned_key(actor, self.key_id())?];
+        keys.insert(&key_id, fetched.clone());
+        self.verify(request_headers, &method, instance_domain, path, &fetched)
+    }
+}
+
+/// the key of the actor fetched for a key id, as long as it is the key by
+/// that id and belongs to them. keys are cached across requests so one
+/// must never end up under the id of another
+fn owned_key(actor: Actor, key_id: &Url) -> Result<OpenSSLPublic, RequestVerificationError> {
+    if actor.public_key.id.ne(key_id) || actor.public_key.owner.ne(&actor.id) {
+        return Err(RequestVerificationError::KeyNotOwned);
+    }
+    Ok(actor.public_key.public_key_pem)
+}
+
+/// checks a `Digest` header against the body. only sha-256 and sha-512
+/// are accepted, older algorithms are rejected outright
+fn digest_matches(header: &str, body: &[u8]) -> Result<bool, RequestVerificationError> {
+    let mut supported = false;
+    for digest in header.split(',') {
+        let Some((algorithm, value)) = digest.trim().split_once('=') else {
+            continue;
+        };
+        let generated = match algorithm.to_uppercase().as_str() {
+            "SHA-256" => sha256_hash(body),
+            "SHA-512" => sha512_hash(body),
+            _ => continue,
+        };
+        if value.eq(&generated) {
+            return Ok(true);
+        }
+        supported = true;
+    }
+    match supported {
+        true => Ok(false),
+        false => Err(RequestVerificationError::BadMessageDigest),
+    }
 }
 
 /// verifys a request and returns the Inboxable if its valid
 /// create activites are stripped and turned into their inner
 /// postable so we don't have to deal with the added complexity
+///
+/// `skew` is how many seconds the signed date may be from our clock
+#[allow(clippy::too_many_arguments)]
 pub async fn verify_post<K: PrivateKey, H: Headers>(
     request_headers: &H,
     body: &str,
@@ -42,79 +296,37 @@ pub async fn verify_post<K: PrivateKey, H: Headers>(
     instance_domain: &str,
     instance_key_id: &str,
     instance_private_key: &mut K,
+    keys: &KeyCache,
+    skew: u64,
 ) -> Result<VerifiedInboxable, RequestVerificationError> {
-    //check digest matches
-
-    let Some(digest) = request_headers.get("Digest") else {
-        return Err(RequestVerificationError::NoMessageDigest);
-    };
-
-    //get the signature header
-    let Some(signature_header) = request_headers.get("Signature") else {
-        return Err(RequestVerificationError::NoSignatureHeader);
-    };
-    let signature = match Signature::from_request(
-        HttpMethod::Post,
-        instance_domain.to_string(),
-        path.to_string(),
-        &signature_header,
-    ) {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::SignatureErr(x)),
-    };
+    let signature =
+        RequestSignature::from_headers(request_headers, HttpMethod::Post, instance_domain, path)?;
+    signature.check_date(request_headers, skew)?;
 
     let object: Result<Inboxable, _> = serde_json::from_str(body);
     let Ok(object) = object else {
         println!("deserialize failure\n{}", body);
         return Err(RequestVerificationError::BodyDeserializeErr);
     };
-    let generated_digest = match signature.signature_header.algorithm {
-        super::signature::Algorithms::RsaSha256 => {
-            "SHA-256=".to_owned() + &sha256_hash(body.as_bytes())
-        }
-        super::signature::Algorithms::Hs2019 => {
-            "SHA-512=".to_owned() + &sha512_hash(body.as_bytes())
-        }
-    };
-
-    if !digest.eq(&generated_digest) {
-        return Err(RequestVerificationError::DigestDoesNotMatch);
-    }
-
-    let fetched: Result<Actor, FetchErr> = authorized_fetch(
-        &signature.signature_header.key_id,
-        instance_key_id,
-        instance_private_key,
-    )
-    .await;
-
-    let actor = match fetched {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::ActorFetchFailed(x)),
-    };
-
-    let Some(_) = request_headers.get("date") else {
-        return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
-    };
-
-    //generate a sign string of the actual request's headers with the real header values mentoned in the provided sign string
-    let comparison_string = match signature.generate_sign_string(request_headers) {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::SignatureErr(x)),
-    };
 
-    let accepted = actor.public_key.public_key_pem.verify(
-        comparison_string.as_bytes(),
-        &signature.signature_header.signature,
-    );
+    //check digest matches
+    signature.check_digest(request_headers, body)?;
 
-    if !accepted {
-        return Err(RequestVerificationError::SignatureVerifyFailed);
-    }
+    signature
+        .verify_cached(
+            request_headers,
+            HttpMethod::Post,
+            instance_domain,
+            path,
+            keys,
+            instance_key_id,
+            instance_private_key,
+        )
+        .await?;
 
     let object = match object
         .verify(
-            &signature.signature_header.key_domain,
+            signature.key_domain(),
             instance_key_id,
             instance_private_key,
         )
@@ -133,50 +345,104 @@ pub async fn verify_get<K: PrivateKey, H: Headers>(
     instance_domain: &str,
     instance_key_id: &str,
     instance_private_key: &mut K,
+    keys: &KeyCache,
 ) -> Result<(), RequestVerificationError> {
-    //get the signature header
-    let Some(signature_header) = request_headers.get("Signature") else {
-        return Err(RequestVerificationError::NoSignatureHeader);
-    };
-    let signature = match Signature::from_request(
-        HttpMethod::Post,
-        instance_domain.to_string(),
-        path.to_string(),
-        &signature_header,
-    ) {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::SignatureErr(x)),
-    };
+    let signature =
+        RequestSignature::from_headers(request_headers, HttpMethod::Get, instance_domain, path)?;
+
+    signature
+        .verify_cached(
+            request_headers,
+            HttpMethod::Get,
+            instance_domain,
+            path,
+            keys,
+            instance_key_id,
+            instance_private_key,
+        )
+        .await
+}
 
-    let fetched: Result<Actor, FetchErr> = authorized_fetch(
-        &signature.signature_header.key_id,
-        instance_key_id,
-        instance_private_key,
-    )
-    .await;
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-    let actor = match fetched {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::ActorFetchFailed(x)),
-    };
+    use super::super::super::headers::HashMapHeaders;
+    use super::*;
+    use crate::db::utility::instance_actor::InstanceActor;
 
-    let Some(_) = request_headers.get("date") else {
-        return Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate));
-    };
+    fn cavage(headers: &str, date: SystemTime) -> (RequestSignature, HashMapHeaders) {
+        let signature = Signature::from_request(
+            HttpMethod::Post,
+            "example.com".to_string(),
+            "/inbox".to_string(),
+            &format!(
+                r#"keyId="https://my.example.com/actor#main-key",headers="{}",signature="Y2FiYWIxNGRiZDk4ZA==""#,
+                headers
+            ),
+        )
+        .unwrap();
+        let headers = HashMapHeaders {
+            headermap: HashMap::from([("date".to_string(), httpdate::fmt_http_date(date))]),
+        };
+        (RequestSignature::Cavage(signature), headers)
+    }
 
-    //generate a sign string of the actual request's headers with the real header values mentoned in the provided sign string
-    let comparison_string = match signature.generate_sign_string(request_headers) {
-        Ok(x) => x,
-        Err(x) => return Err(RequestVerificationError::SignatureErr(x)),
-    };
+    #[test]
+    fn test_date() -> Result<(), String> {
+        let now = SystemTime::now();
+        let (signature, headers) = cavage("(request-target) host date digest", now);
+        signature
+            .check_date(&headers, 300)
+            .map_err(|x| serde_json::to_string(&x).unwrap())?;
 
-    let accepted = actor.public_key.public_key_pem.verify(
-        comparison_string.as_bytes(),
-        &signature.signature_header.signature,
-    );
+        let (signature, headers) = cavage("(request-target) host digest", now);
+        match signature.check_date(&headers, 300) {
+            Err(RequestVerificationError::SignatureErr(SignatureErr::NoDate)) => {}
+            x => return Err(format!("unsigned date was accepted: {:?}", x)),
+        }
+
+        let stale = now - Duration::from_secs(600);
+        let (signature, headers) = cavage("(request-target) host date digest", stale);
+        match signature.check_date(&headers, 300) {
+            Err(RequestVerificationError::SignatureErr(SignatureErr::StaleDate)) => Ok(()),
+            x => Err(format!("stale date was accepted: {:?}", x)),
+        }
+    }
 
-    if !accepted {
-        return Err(RequestVerificationError::SignatureVerifyFailed);
+    #[test]
+    fn test_digest() -> Result<(), String> {
+        let body = br#"{"type":"Follow"}"#;
+        let sha256 = format!("SHA-256={}", sha256_hash(body));
+        let sha512 = format!("sha-512={}", sha512_hash(body));
+        assert!(matches!(digest_matches(&sha256, body), Ok(true)));
+        assert!(matches!(digest_matches(&sha512, body), Ok(true)));
+        assert!(matches!(digest_matches(&sha256, b"{}"), Ok(false)));
+        match digest_matches("MD5=Q2hlY2sgSW50ZWdyaXR5IQ==", body) {
+            Err(RequestVerificationError::BadMessageDigest) => Ok(()),
+            x => Err(format!("md5 digest was accepted: {:?}", x)),
+        }
+    }
+
+    #[test]
+    fn test_owned_key() -> Result<(), String> {
+        let actor = InstanceActor::generate().to_actor("example.com");
+        let key_id = actor.public_key.id.clone();
+        owned_key(actor.clone(), &key_id).map_err(|x| format!("{:?}", x))?;
+
+        let other = Url::parse("https://example.com/actor#other-key").unwrap();
+        if !matches!(
+            owned_key(actor.clone(), &other),
+            Err(RequestVerificationError::KeyNotOwned)
+        ) {
+            return Err("key was cached under another id".to_string());
+        }
+
+        let mut stolen = actor;
+        stolen.public_key.owner = Url::parse("https://evil.com/actor").unwrap();
+        match owned_key(stolen, &key_id) {
+            Err(RequestVerificationError::KeyNotOwned) => Ok(()),
+            _ => Err("key owned by another actor was accepted".to_string()),
+        }
     }
-    Ok(())
 }
@@ -10,6 +10,22 @@ pub enum FetchErr {
     InvalidUrl(String),
     MissingHeader(String),
     VerifyErr(VerifyRequestErr),
+    /// the server rejected our signature
+    Unauthorized(String),
+    /// the server responded with an unsuccessful status other than 401
+    Status(u16, String),
+}
+
+impl FetchErr {
+    /// whether the request may succeed if sent again later. only timeouts,
+    /// rate limits, server errors and failed connections are worth retrying
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchErr::RequestErr(_) => true,
+            FetchErr::Status(status, _) => matches!(status, 408 | 429 | 500..=599),
+            _ => false,
+        }
+    }
 }
 
 impl Display for FetchErr {
@@ -23,6 +39,8 @@ impl Display for FetchErr {
             FetchErr::VerifyErr(verify_request_err) => {
                 write!(f, "VerifyErr: {}", verify_request_err)
             }
+            FetchErr::Unauthorized(x) => write!(f, "Unauthorized: {}", x),
+            FetchErr::Status(status, x) => write!(f, "Status {}: {}", status, x),
         }
     }
 }
@@ -33,8 +51,14 @@ pub enum VerifyRequestErr {
     InvalidTimestamp,
     SignatureVerificationFailure,
     TooOld,
+    /// the nonce was already used within the validity window
+    Replayed,
+    /// too many nonces are within the validity window to remember another
+    Busy,
     UnableToObtainKey,
     InvalidSigner,
+    /// the signer is neither the author nor their delegate
+    ForgedAttribution,
     NoDomain,
 }
 
@@ -47,8 +71,11 @@ impl std::fmt::Display for VerifyRequestErr {
                 write!(f, "SignatureVerificationFailure")
             }
             VerifyRequestErr::TooOld => write!(f, "TooOld"),
+            VerifyRequestErr::Replayed => write!(f, "Replayed"),
+            VerifyRequestErr::Busy => write!(f, "Busy"),
             VerifyRequestErr::UnableToObtainKey => write!(f, "UnableToObtainKey"),
             VerifyRequestErr::InvalidSigner => write!(f, "InvalidSigner"),
+            VerifyRequestErr::ForgedAttribution => write!(f, "ForgedAttribution"),
             VerifyRequestErr::NoDomain => write!(f, "NoDomain"),
         }
     }
@@ -26,10 +26,7 @@ impl Headers for ActixHeaders {
     fn get(&self, key: &str) -> Option<String> {
         let val = self.headermap.get(key)?;
         let val = String::from_utf8(val.as_bytes().to_vec());
-        match val {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        }
+        val.ok()
     }
 }
 
@@ -0,0 +1,108 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use chrono::Utc;
+
+use crate::cryptography::openssl::OpenSSLPublic;
+
+/// most signers kept at once. past this the least recently added
+/// are dropped and simply get fetched again
+pub const KEY_CACHE_SIZE: usize = 10_000;
+
+/// keeps the public keys of recent signers so busy inboxes don't look
+/// up the same actor for every delivery. entries are keyed by the
+/// activitypub key id or the versia signer
+pub struct KeyCache {
+    /// milliseconds an entry is trusted before it is fetched again
+    ttl: i64,
+    capacity: usize,
+    entries: Mutex<CachedKeys>,
+}
+
+#[derive(Default)]
+struct CachedKeys {
+    /// signer to their keys and the time they expire
+    keys: HashMap<String, (Vec<OpenSSLPublic>, i64)>,
+    /// signers in the order they were added
+    order: VecDeque<String>,
+}
+
+impl KeyCache {
+    pub fn new(ttl_secs: u64, capacity: usize) -> Self {
+        KeyCache {
+            ttl: ttl_secs as i64 * 1000,
+            capacity,
+            entries: Mutex::new(CachedKeys::default()),
+        }
+    }
+
+    /// the cached keys of the signer if they haven't expired
+    pub fn get(&self, signer: &str) -> Option<Vec<OpenSSLPublic>> {
+        let now = Utc::now().timestamp_millis();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .keys
+            .get(signer)
+            .filter(|(_, expires)| *expires > now)
+            .map(|(keys, _)| keys.clone())
+    }
+
+    /// caches the keys of the signer, replacing any already present.
+    /// signers without any keys aren't cached so they get retried
+    pub fn insert(&self, signer: &str, keys: Vec<OpenSSLPublic>) {
+        if keys.is_empty() {
+            return;
+        }
+        let expires = Utc::now().timestamp_millis() + self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.keys.contains_key(signer) {
+            entries.order.retain(|x| x.ne(signer));
+        }
+        while entries.order.len() >= self.capacity {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+            entries.keys.remove(&oldest);
+        }
+        entries.keys.insert(signer.to_string(), (keys, expires));
+        entries.order.push_back(signer.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::key::{Key, KeyType, PrivateKey};
+    use crate::cryptography::openssl::OpenSSLPrivate;
+
+    fn key() -> OpenSSLPublic {
+        let pem = OpenSSLPrivate::generate(KeyType::Ed25519)
+            .public_key_pem()
+            .unwrap();
+        OpenSSLPublic::from_pem(pem.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_key_cache() -> Result<(), String> {
+        let cache = KeyCache::new(60, 2);
+        cache.insert("first", vec![key()]);
+        cache.insert("second", vec![key()]);
+        cache.insert("empty", Vec::new());
+        if cache.get("first").is_none() || cache.get("empty").is_some() {
+            return Err("cache did not keep the right signers".to_string());
+        }
+        cache.insert("third", vec![key()]);
+        if cache.get("first").is_some() || cache.get("third").is_none() {
+            return Err("the oldest signer was not dropped at capacity".to_string());
+        }
+
+        let expired = KeyCache::new(0, 2);
+        expired.insert("first", vec![key()]);
+        if expired.get("first").is_some() {
+            return Err("served keys past their ttl".to_string());
+        }
+        Ok(())
+    }
+}
@@ -2,5 +2,6 @@ pub mod ap_protocol;
 pub mod errors;
 pub mod headers;
 pub mod http_method;
+pub mod key_cache;
 pub mod protocols;
 pub mod versia_protocol;
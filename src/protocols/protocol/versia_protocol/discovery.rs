@@ -1 +1,53 @@
+use serde::Deserialize;
+use url::Url;
 
+use crate::protocols::{
+    protocol::errors::FetchErr,
+    types::versia_types::entities::{instance_metadata::InstanceMetadata, user::User},
+};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const SOFTWARE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// performs an unsigned get for a public versia entity. used during
+/// discovery when we don't know anything about the remote instance
+/// yet, so there is no key to check the response against
+async fn unsigned_fetch<T: for<'a> Deserialize<'a>>(target: Url) -> Result<T, FetchErr> {
+    let res = reqwest::Client::new()
+        .get(target)
+        .header("Accept", "application/json")
+        .header("User-Agent", format!("{}/{}", SOFTWARE_NAME, VERSION))
+        .send()
+        .await;
+    let res = match res {
+        Ok(x) => x,
+        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
+    };
+    if !res.status().is_success() {
+        return Err(FetchErr::RequestErr(res.status().to_string()));
+    }
+
+    let response = res.text().await;
+    let response = match response {
+        Ok(x) => x,
+        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
+    };
+
+    match serde_json::from_str(&response) {
+        Ok(x) => Ok(x),
+        Err(x) => Err(FetchErr::DeserializationErr(x.to_string())),
+    }
+}
+
+/// fetches the metadata of a versia instance from /.well-known/versia
+/// an error usually means the instance does not speak versia
+pub async fn fetch_instance_metadata(domain: &str) -> Result<InstanceMetadata, FetchErr> {
+    let Ok(target) = Url::parse(&format!("https://{domain}/.well-known/versia")) else {
+        return Err(FetchErr::InvalidUrl(domain.to_string()));
+    };
+    unsigned_fetch(target).await
+}
+
+pub async fn fetch_user(uri: &Url) -> Result<User, FetchErr> {
+    unsigned_fetch(uri.clone()).await
+}
@@ -1,4 +1,5 @@
 pub mod discovery;
+pub mod nonce_cache;
 pub mod requests;
 pub mod signatures;
 pub mod verify;
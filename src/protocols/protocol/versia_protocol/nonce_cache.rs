@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use chrono::Utc;
+
+use super::super::errors::VerifyRequestErr;
+
+/// most nonces kept at once. nonces still within the window are never
+/// forgotten early, past this requests are turned away until some expire
+pub const NONCE_CACHE_SIZE: usize = 100_000;
+
+/// remembers the nonces of recently verified requests so a captured
+/// request can't be sent again while its timestamp is still accepted.
+/// nonces are kept per signer, so nobody can use up the nonces of another
+/// signer or get their requests rejected by picking the same ones
+pub struct NonceCache {
+    /// milliseconds a timestamp may be away from our clock
+    skew: i64,
+    capacity: usize,
+    seen: Mutex<SeenNonces>,
+}
+
+/// the signer of a request along with its nonce
+type SignedNonce = (String, String);
+
+#[derive(Default)]
+struct SeenNonces {
+    /// nonce to the time it can be forgotten
+    expires: HashMap<SignedNonce, i64>,
+    /// nonces in the order they were seen, along with the expiry they were
+    /// seen with. a nonce seen again after expiring is in here twice and
+    /// only the entry matching `expires` is live
+    order: VecDeque<(SignedNonce, i64)>,
+}
+
+impl NonceCache {
+    pub fn new(skew_secs: u64, capacity: usize) -> Self {
+        NonceCache {
+            skew: skew_secs as i64 * 1000,
+            capacity,
+            seen: Mutex::new(SeenNonces::default()),
+        }
+    }
+
+    /// checks that a timestamp in milliseconds is within the allowed skew
+    pub fn check_timestamp(&self, signed_at: i64) -> Result<(), VerifyRequestErr> {
+        let now = Utc::now().timestamp_millis();
+        if now - signed_at > self.skew {
+            return Err(VerifyRequestErr::TooOld);
+        }
+        if signed_at - now > self.skew {
+            return Err(VerifyRequestErr::InvalidTimestamp);
+        }
+        Ok(())
+    }
+
+    /// records the nonce, failing if the signer already used it for a
+    /// request that is still within the window or if the cache is full of
+    /// nonces that are
+    pub fn remember(
+        &self,
+        signer: &str,
+        nonce: &str,
+        signed_at: i64,
+    ) -> Result<(), VerifyRequestErr> {
+        let now = Utc::now().timestamp_millis();
+        let mut seen = self.seen.lock().unwrap();
+        seen.forget_expired(now);
+
+        let key = (signer.to_string(), nonce.to_string());
+        if seen.expires.get(&key).is_some_and(|x| *x > now) {
+            return Err(VerifyRequestErr::Replayed);
+        }
+        if seen.order.len() >= self.capacity {
+            seen.forget_all_expired(now);
+            // forgetting a live nonce would let its request be replayed
+            if seen.order.len() >= self.capacity {
+                return Err(VerifyRequestErr::Busy);
+            }
+        }
+        // the timestamp stops being accepted once it is skew in the past
+        let expiry = signed_at + self.skew;
+        seen.expires.insert(key.clone(), expiry);
+        seen.order.push_back((key, expiry));
+        Ok(())
+    }
+}
+
+impl SeenNonces {
+    /// timestamps vary within the skew so this stops at the first
+    /// nonce still needed rather than scanning everything
+    fn forget_expired(&mut self, now: i64) {
+        while let Some((oldest, expiry)) = self.order.front() {
+            if self.is_live(oldest, *expiry, now) {
+                break;
+            }
+            let (oldest, expiry) = self.order.pop_front().unwrap();
+            self.forget(&oldest, expiry);
+        }
+    }
+
+    /// scans everything, for when the expired nonces are stuck behind
+    /// one that is still needed
+    fn forget_all_expired(&mut self, now: i64) {
+        let order = std::mem::take(&mut self.order);
+        for (key, expiry) in order {
+            if self.is_live(&key, expiry, now) {
+                self.order.push_back((key, expiry));
+            } else {
+                self.forget(&key, expiry);
+            }
+        }
+    }
+
+    /// stale entries of a nonce seen again don't count
+    fn is_live(&self, key: &SignedNonce, expiry: i64, now: i64) -> bool {
+        expiry > now && self.expires.get(key) == Some(&expiry)
+    }
+
+    fn forget(&mut self, key: &SignedNonce, expiry: i64) {
+        if self.expires.get(key) == Some(&expiry) {
+            self.expires.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() -> Result<(), String> {
+        let cache = NonceCache::new(60, 10);
+        let now = Utc::now().timestamp_millis();
+        cache
+            .remember("alice", "first", now)
+            .map_err(|x| format!("rejected a new nonce: {}", x))?;
+        match cache.remember("alice", "first", now) {
+            Err(VerifyRequestErr::Replayed) => {}
+            x => return Err(format!("replayed nonce was not rejected: {:?}", x)),
+        }
+        cache
+            .remember("alice", "second", now)
+            .map_err(|x| format!("rejected a new nonce: {}", x))?;
+        cache
+            .remember("bob", "first", now)
+            .map_err(|x| format!("rejected the nonce of another signer: {}", x))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp() -> Result<(), String> {
+        let cache = NonceCache::new(60, 10);
+        let now = Utc::now().timestamp_millis();
+        cache
+            .check_timestamp(now - 30_000)
+            .map_err(|x| format!("rejected a timestamp within the skew: {}", x))?;
+        match cache.check_timestamp(now - 120_000) {
+            Err(VerifyRequestErr::TooOld) => {}
+            x => return Err(format!("stale timestamp was not rejected: {:?}", x)),
+        }
+        match cache.check_timestamp(now + 120_000) {
+            Err(VerifyRequestErr::InvalidTimestamp) => {}
+            x => return Err(format!("future timestamp was not rejected: {:?}", x)),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounded() -> Result<(), String> {
+        let cache = NonceCache::new(60, 2);
+        let now = Utc::now().timestamp_millis();
+        for nonce in ["a", "b"] {
+            cache
+                .remember("signer", nonce, now)
+                .map_err(|x| x.to_string())?;
+        }
+        // flooding the cache must not push out the nonce of a victim
+        match cache.remember("flood", "c", now) {
+            Err(VerifyRequestErr::Busy) => {}
+            x => return Err(format!("a live nonce was evicted: {:?}", x)),
+        }
+        match cache.remember("signer", "a", now) {
+            Err(VerifyRequestErr::Replayed) => {}
+            x => return Err(format!("replayed nonce was not rejected: {:?}", x)),
+        }
+
+        // expired nonces make room, even behind one that is still live
+        let cache = NonceCache::new(60, 2);
+        cache
+            .remember("signer", "live", now)
+            .map_err(|x| x.to_string())?;
+        cache
+            .remember("signer", "old", now - 120_000)
+            .map_err(|x| x.to_string())?;
+        cache
+            .remember("signer", "new", now)
+            .map_err(|x| format!("expired nonces were kept: {}", x))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_seen_again() -> Result<(), String> {
+        let cache = NonceCache::new(60, 3);
+        let now = Utc::now().timestamp_millis();
+        // an expired nonce behind a live one isn't pruned, so seeing it
+        // again leaves a stale entry for it
+        for (nonce, signed_at) in [("live", now), ("again", now - 120_000), ("again", now)] {
+            cache
+                .remember("signer", nonce, signed_at)
+                .map_err(|x| format!("rejected a new nonce: {}", x))?;
+        }
+        // making room must drop the stale entry without the live expiry
+        cache
+            .remember("signer", "more", now)
+            .map_err(|x| format!("the stale entry was kept: {}", x))?;
+        {
+            let seen = cache.seen.lock().unwrap();
+            if seen
+                .order
+                .iter()
+                .any(|(x, expiry)| seen.expires.get(x) != Some(expiry))
+            {
+                return Err("a stale entry was kept over a live nonce".to_string());
+            }
+        }
+        match cache.remember("signer", "again", now) {
+            Err(VerifyRequestErr::Replayed) => Ok(()),
+            x => Err(format!("replayed nonce was not rejected: {:?}", x)),
+        }
+    }
+}
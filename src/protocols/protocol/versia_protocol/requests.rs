@@ -11,6 +11,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use textnonce::TextNonce;
 use url::Url;
 
+#[derive(Debug, Clone)]
 pub enum Signer {
     User(Url),
     Instance(String),
@@ -27,7 +28,8 @@ impl std::fmt::Display for Signer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Signer::User(url) => write!(f, "{}", url),
-            Signer::Instance(x) => write!(f, "{}", x),
+            // the form of X-Signed-By for requests signed by an instance
+            Signer::Instance(x) => write!(f, "instance {}", x),
         }
     }
 }
@@ -94,12 +96,13 @@ pub async fn versia_fetch<T: for<'a> Deserialize<'a>, K: PrivateKey, V: VersiaVe
     Ok(object)
 }
 
-pub async fn versia_post<K: PrivateKey, V: VersiaVerificationCache>(
+/// signs the content and posts it to the target. the response is
+/// not checked beyond its status as we don't need anything from it
+pub async fn versia_post<K: PrivateKey>(
     target: Url,
     content: &str,
     mut signing_key: K,
     signed_by: &Signer,
-    conn: &V,
 ) -> Result<(), FetchErr> {
     let nonce = TextNonce::new().into_string();
     let path = target.path();
@@ -115,35 +118,24 @@ pub async fn versia_post<K: PrivateKey, V: VersiaVerificationCache>(
     let client = client
         .post(target.clone())
         .header("Accept", "application/json")
+        .header("Content-Type", "application/json; charset=utf-8")
         .header("X-Signature", signature)
         .header("X-Signed-By", signed_by.to_string())
         .header("X-Nonce", nonce)
         .header("User-Agent", format!("{}/{}", SOFTWARE_NAME, VERSION))
         .header("Signed-milis", signed_at)
-        .body("");
-
-    let res = client.send().await;
-
-    let res = match res {
-        Ok(x) => x,
-        Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
-    };
-
-    let headers = ReqwestHeaders {
-        headermap: res.headers().clone(),
-    };
+        .body(content.to_string());
 
-    let response = res.text().await;
-    let response = match response {
+    let res = match client.send().await {
         Ok(x) => x,
         Err(x) => return Err(FetchErr::RequestErr(x.to_string())),
     };
 
-    let hash = digest::sha256_hash(response.as_bytes());
-
-    if let Err(val) = verify_request(&headers, HttpMethod::Get, path, &hash, conn).await {
-        return Err(FetchErr::VerifyErr(val));
+    if !res.status().is_success() {
+        return Err(FetchErr::Status(
+            res.status().as_u16(),
+            format!("{} responded with {}", target, res.status()),
+        ));
     }
-
     Ok(())
 }
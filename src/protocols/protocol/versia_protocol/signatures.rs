@@ -5,17 +5,16 @@ pub fn signature_string(
     path: &str,
     nonce: &str,
     hash: &str,
-    // currently versia does not include timestamps but it will in the future
-    // once its added to the protocol, we should just need to add _timestamp
-    // the _timestamp field is here to ensure a smooth transition for when its time
-    _timestamp: i64,
+    // signed so the Signed-milis header can't be moved forward to
+    // replay a request once its nonce has been forgotten
+    timestamp: i64,
 ) -> String {
     format!(
-        "{} {} {} {}",
+        "{} {} {} {} {}",
         method.stringify(),
         path,
         nonce,
         hash,
-        // timestamp
+        timestamp
     )
 }
@@ -1,16 +1,28 @@
+use base64::Engine;
 use url::Url;
 
-use crate::cryptography::{key::PublicKey, openssl::OpenSSLPublic};
+use crate::cryptography::key::PublicKey;
 
 use super::super::{errors::VerifyRequestErr, headers::Headers, http_method::HttpMethod};
 
-use super::{requests::Signer, signatures::signature_string};
+use super::{nonce_cache::NonceCache, requests::Signer, signatures::signature_string};
 
 /// note that the warning is junk as following it breaks everything with
 /// the use of async trait and trait objects with async
 #[allow(async_fn_in_trait)]
 pub trait VersiaVerificationCache {
-    async fn get_key(&self, signed_by: &Signer) -> Option<OpenSSLPublic>;
+    type Key: PublicKey;
+    /// every key currently accepted for the signer
+    async fn get_keys(&self, signed_by: &Signer) -> Vec<Self::Key>;
+    /// fetches the keys from the signer's instance again, bypassing
+    /// anything cached. used when the known keys fail to verify as the
+    /// signer may have rotated their key
+    async fn refetch_keys(&self, signed_by: &Signer) -> Vec<Self::Key>;
+    /// the user the profile key of the given user belongs to when it
+    /// isn't their own. that user may act on their behalf
+    async fn get_delegate(&self, user: &Url) -> Option<Url>;
+    /// the nonces of requests already verified
+    fn nonces(&self) -> &NonceCache;
 }
 
 /// returns the signer if successful
@@ -48,31 +60,223 @@ pub async fn verify_request<H: Headers, V: VersiaVerificationCache>(
         return Err(VerifyRequestErr::MissingHeader("X-Nonce".to_string()));
     };
 
-    // see the comment on signature_string
-    // let Some(signed_milis) = headers.get("Signed-milis") else {
-    //     return Err(VerifyRequestErr::MissingHeader("Signed-milis".to_string()));
-    // };
-    // let signed_milis: Result<i64, _> = signed_milis.parse();
-    // let signed_milis = match signed_milis {
-    //     Ok(x) => x,
-    //     Err(_) => return Err(VerifyRequestErr::InvalidTimestamp),
-    // };
-    // let Some(provided_time) = DateTime::from_timestamp_millis(signed_milis) else {
-    //     return Err(VerifyRequestErr::InvalidTimestamp);
-    // };
-    // let current_time = Utc::now();
-    // let duration = current_time - provided_time;
-    // if duration.num_minutes() > 3 {
-    //     return Err(VerifyRequestErr::TooOld);
-    // }
-
-    let Some(verifying_key) = conn.get_key(&signed_by).await else {
-        return Err(VerifyRequestErr::UnableToObtainKey);
+    let Some(signed_milis) = headers.get("Signed-milis") else {
+        return Err(VerifyRequestErr::MissingHeader("Signed-milis".to_string()));
     };
+    let Ok(signed_milis) = signed_milis.parse::<i64>() else {
+        return Err(VerifyRequestErr::InvalidTimestamp);
+    };
+    conn.nonces().check_timestamp(signed_milis)?;
+
+    let Ok(signature) = base64::prelude::BASE64_STANDARD.decode(signature) else {
+        return Err(VerifyRequestErr::SignatureVerificationFailure);
+    };
+    let verify_string = signature_string(method, path, &nonce, hash, signed_milis);
+    let verifies = |keys: &[V::Key]| {
+        keys.iter()
+            .any(|x| x.verify(verify_string.as_bytes(), &signature))
+    };
+
+    // a delegated key has to be the one of the user it points to
+    let key_owner = match &signed_by {
+        Signer::User(user) => match conn.get_delegate(user).await {
+            Some(delegate) => Signer::User(delegate),
+            None => signed_by.clone(),
+        },
+        Signer::Instance(_) => signed_by.clone(),
+    };
+    let verifying_keys = conn.get_keys(&key_owner).await;
+    if !verifies(&verifying_keys) {
+        // the keys we know may be outdated, give the signer one more chance
+        let verifying_keys = conn.refetch_keys(&key_owner).await;
+        if verifying_keys.is_empty() {
+            return Err(VerifyRequestErr::UnableToObtainKey);
+        }
+        if !verifies(&verifying_keys) {
+            return Err(VerifyRequestErr::SignatureVerificationFailure);
+        }
+    }
+    // only remember nonces of genuine requests so others can't use them up
+    conn.nonces()
+        .remember(&signed_by.to_string(), &nonce, signed_milis)?;
+    Ok(signed_by)
+}
+
+/// checks that the signer of a request may act as the author of its
+/// entity. that is anyone from the author's instance or a user the
+/// author delegated to through their key
+pub async fn authorize_author<V: VersiaVerificationCache>(
+    conn: &V,
+    signer: &Signer,
+    author: &Url,
+) -> Result<(), VerifyRequestErr> {
+    if signer.domain().is_some() && signer.domain().eq(&author.domain()) {
+        return Ok(());
+    }
+    let Signer::User(signer) = signer else {
+        return Err(VerifyRequestErr::ForgedAttribution);
+    };
+    match conn.get_delegate(author).await {
+        Some(delegate) if delegate.eq(signer) => Ok(()),
+        _ => Err(VerifyRequestErr::ForgedAttribution),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::cryptography::{
+        digest::sha256_hash,
+        key::{Key, KeyType, PrivateKey},
+        openssl::{OpenSSLPrivate, OpenSSLPublic},
+    };
+    use crate::protocols::protocol::headers::HashMapHeaders;
+
+    struct TestCache {
+        /// what is known before refetching
+        cached: OpenSSLPublic,
+        key: OpenSSLPublic,
+        nonces: NonceCache,
+        refetches: AtomicUsize,
+        /// author and the user they delegated to
+        delegation: Option<(Url, Url)>,
+    }
+
+    impl VersiaVerificationCache for TestCache {
+        type Key = OpenSSLPublic;
+        async fn get_keys(&self, _signed_by: &Signer) -> Vec<OpenSSLPublic> {
+            vec![self.cached.clone()]
+        }
+        async fn refetch_keys(&self, _signed_by: &Signer) -> Vec<OpenSSLPublic> {
+            self.refetches.fetch_add(1, Ordering::SeqCst);
+            vec![self.key.clone()]
+        }
+        async fn get_delegate(&self, user: &Url) -> Option<Url> {
+            let (author, delegate) = self.delegation.as_ref()?;
+            author.eq(user).then(|| delegate.clone())
+        }
+        fn nonces(&self) -> &NonceCache {
+            &self.nonces
+        }
+    }
+
+    fn signed_headers(key: &mut OpenSSLPrivate, hash: &str, signed_at: i64) -> HashMapHeaders {
+        let signature = key.sign(&signature_string(
+            HttpMethod::Post,
+            "/inbox",
+            "nonce",
+            hash,
+            signed_at,
+        ));
+        let headermap = HashMap::from([
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("X-Signature".to_string(), signature),
+            (
+                "X-Signed-By".to_string(),
+                "https://example.com/users/test".to_string(),
+            ),
+            ("X-Nonce".to_string(), "nonce".to_string()),
+            ("Signed-milis".to_string(), signed_at.to_string()),
+        ]);
+        HashMapHeaders { headermap }
+    }
+
+    #[actix_web::test]
+    async fn test_replay_rejected() -> Result<(), String> {
+        let mut key = OpenSSLPrivate::generate(KeyType::Ed25519);
+        let public = key.public_key_pem().map_err(|x| x.to_string())?;
+        let public = OpenSSLPublic::from_pem(public.as_bytes()).map_err(|x| x.to_string())?;
+        let cache = TestCache {
+            cached: public.clone(),
+            key: public,
+            nonces: NonceCache::new(60, 10),
+            refetches: AtomicUsize::new(0),
+            delegation: None,
+        };
+        let hash = sha256_hash(b"{}");
+
+        let stale = signed_headers(&mut key, &hash, Utc::now().timestamp_millis() - 120_000);
+        match verify_request(&stale, HttpMethod::Post, "/inbox", &hash, &cache).await {
+            Err(VerifyRequestErr::TooOld) => {}
+            x => return Err(format!("stale request was not rejected: {:?}", x.err())),
+        }
+
+        let headers = signed_headers(&mut key, &hash, Utc::now().timestamp_millis());
+        verify_request(&headers, HttpMethod::Post, "/inbox", &hash, &cache)
+            .await
+            .map_err(|x| format!("valid request was rejected: {}", x))?;
+        match verify_request(&headers, HttpMethod::Post, "/inbox", &hash, &cache).await {
+            Err(VerifyRequestErr::Replayed) => Ok(()),
+            x => Err(format!("replayed request was not rejected: {:?}", x.err())),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rotated_key_refetched() -> Result<(), String> {
+        let mut key = OpenSSLPrivate::generate(KeyType::Ed25519);
+        let public = key.public_key_pem().map_err(|x| x.to_string())?;
+        let old = OpenSSLPrivate::generate(KeyType::Ed25519)
+            .public_key_pem()
+            .map_err(|x| x.to_string())?;
+        let cache = TestCache {
+            cached: OpenSSLPublic::from_pem(old.as_bytes()).map_err(|x| x.to_string())?,
+            key: OpenSSLPublic::from_pem(public.as_bytes()).map_err(|x| x.to_string())?,
+            nonces: NonceCache::new(60, 10),
+            refetches: AtomicUsize::new(0),
+            delegation: None,
+        };
+        let hash = sha256_hash(b"{}");
+
+        let headers = signed_headers(&mut key, &hash, Utc::now().timestamp_millis());
+        verify_request(&headers, HttpMethod::Post, "/inbox", &hash, &cache)
+            .await
+            .map_err(|x| format!("rotated key was not picked up: {}", x))?;
+        assert_eq!(cache.refetches.load(Ordering::SeqCst), 1);
 
-    let verify_string = signature_string(method, path, &nonce, hash, 0);
-    if verifying_key.verify(verify_string.as_bytes(), signature.as_bytes()) {
-        return Ok(signed_by);
+        let mut other = OpenSSLPrivate::generate(KeyType::Ed25519);
+        let headers = signed_headers(&mut other, &hash, Utc::now().timestamp_millis());
+        match verify_request(&headers, HttpMethod::Post, "/inbox", &hash, &cache).await {
+            Err(VerifyRequestErr::SignatureVerificationFailure) => {}
+            x => return Err(format!("forged request was not rejected: {:?}", x.err())),
+        }
+        assert_eq!(cache.refetches.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_delegation() -> Result<(), String> {
+        let key = OpenSSLPrivate::generate(KeyType::Ed25519)
+            .public_key_pem()
+            .map_err(|x| x.to_string())?;
+        let key = OpenSSLPublic::from_pem(key.as_bytes()).map_err(|x| x.to_string())?;
+        let author = Url::parse("https://example.com/users/author").unwrap();
+        let delegate = Url::parse("https://other.example/users/delegate").unwrap();
+        let cache = TestCache {
+            cached: key.clone(),
+            key,
+            nonces: NonceCache::new(60, 10),
+            refetches: AtomicUsize::new(0),
+            delegation: Some((author.clone(), delegate.clone())),
+        };
+
+        let same_instance = Signer::User(Url::parse("https://example.com/users/mod").unwrap());
+        authorize_author(&cache, &same_instance, &author)
+            .await
+            .map_err(|x| format!("rejected the author's instance: {}", x))?;
+        authorize_author(&cache, &Signer::User(delegate), &author)
+            .await
+            .map_err(|x| format!("rejected the delegate: {}", x))?;
+        let forged = Signer::User(Url::parse("https://other.example/users/forger").unwrap());
+        match authorize_author(&cache, &forged, &author).await {
+            Err(VerifyRequestErr::ForgedAttribution) => Ok(()),
+            x => Err(format!("forged attribution was accepted: {:?}", x)),
+        }
     }
-    Err(VerifyRequestErr::SignatureVerificationFailure)
 }
@@ -1,4 +1,7 @@
-use super::{context::ContextWrap, public_key::PublicKey};
+use super::{
+    context::{Context, ContextItem, ContextWrap, ACTIVITYSTREAMS, SECURITY},
+    public_key::PublicKey,
+};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -54,6 +57,9 @@ pub struct Actor {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<Url>,
+    /// the avatar of the actor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<Image>,
 
     pub public_key: PublicKey,
 
@@ -62,13 +68,46 @@ pub struct Actor {
     pub followers: Url,
     pub following: Url,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Endpoints>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub versia_url: Option<Url>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// an inbox that can be used to deliver to every
+    /// user of the instance at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_inbox: Option<Url>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ImageType {
+    Image,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Image {
+    #[serde(rename = "type")]
+    pub type_field: ImageType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    pub url: Url,
+}
+
 impl Actor {
     pub fn wrap_context(self) -> ContextWrap<Self> {
-        todo!()
+        ContextWrap {
+            context: Context::Array(vec![
+                ContextItem::String(ACTIVITYSTREAMS.to_string()),
+                ContextItem::String(SECURITY.to_string()),
+            ]),
+            item: self,
+        }
     }
 }
 
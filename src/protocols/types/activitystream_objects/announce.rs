@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AnnounceType {
+    Announce,
+}
+
+/// Indicates that the actor is calling the target's attention the
+/// object, used for boosts. the shared post is only linked
+///
+/// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-announce
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Announce {
+    #[serde(rename = "type")]
+    pub type_field: AnnounceType,
+    pub id: Url,
+    pub actor: Url,
+    pub object: Url,
+    /// rfc 3339
+    pub published: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() -> Result<(), String> {
+        let announce = r#"
+{
+    "@context": "https://www.w3.org/ns/activitystreams",
+    "id": "https://mastodon.social/users/Gargron/statuses/1234/activity",
+    "type": "Announce",
+    "actor": "https://mastodon.social/users/Gargron",
+    "published": "2024-07-11T16:11:14Z",
+    "to": ["https://www.w3.org/ns/activitystreams#Public"],
+    "cc": ["https://mastodon.social/users/Gargron/followers"],
+    "object": "https://example.com/ap/users/sally/statuses/1"
+}
+"#;
+        let deserialized: Result<Announce, serde_json::Error> = serde_json::from_str(announce);
+        match deserialized {
+            Ok(_) => Ok(()),
+            Err(x) => Err(format!("announce deserialize failed: {}", x)),
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use super::{context::ContextWrap, link::RangeLinkItem, postable::ApPostable};
+use super::{
+    context::{Context, ContextWrap, ACTIVITYSTREAMS},
+    link::RangeLinkItem,
+    postable::ApPostable,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum CreateType {
@@ -40,8 +44,21 @@ pub struct Create {
 }
 
 impl Create {
+    /// the create of a post is served under the post's id
+    pub fn new(object: ApPostable) -> Self {
+        let id = Url::parse(&format!("{}/activity", object.id())).expect("generated invalid url");
+        Create {
+            type_field: CreateType::Create,
+            id,
+            actor: object.actor().clone(),
+            object: RangeLinkItem::Item(object),
+        }
+    }
     pub fn wrap_context(self) -> ContextWrap<Self> {
-        todo!()
+        ContextWrap {
+            context: Context::Single(ACTIVITYSTREAMS.to_string()),
+            item: self,
+        }
     }
 }
 
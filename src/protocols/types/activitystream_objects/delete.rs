@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use super::{
+    context::{Context, ContextWrap, ACTIVITYSTREAMS},
+    link::{LinkSimpleOrExpanded, RangeLinkItem},
+    tombstone::Tombstone,
+};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum DeleteType {
     Delete,
@@ -56,12 +62,40 @@ pub enum DeleteType {
 pub struct Delete {
     #[serde(rename = "type")]
     pub type_field: DeleteType,
+    pub id: Url,
     pub actor: Url, //TODO
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub origin: Option<String>, //TODO
 
-    pub object: Url,
+    pub object: RangeLinkItem<Tombstone>,
+}
+
+impl Delete {
+    /// the delete sent out when an actor deletes their account
+    pub fn new_actor(actor: Url) -> Self {
+        let id = Url::parse(&format!("{}#delete", actor)).expect("generated invalid url");
+        Delete {
+            type_field: DeleteType::Delete,
+            id,
+            actor: actor.clone(),
+            origin: None,
+            object: RangeLinkItem::Link(LinkSimpleOrExpanded::Simple(actor)),
+        }
+    }
+    /// the id of the deleted object
+    pub fn object_id(&self) -> &Url {
+        match &self.object {
+            RangeLinkItem::Item(x) => &x.id,
+            RangeLinkItem::Link(x) => x.get_id(),
+        }
+    }
+    pub fn wrap_context(self) -> ContextWrap<Self> {
+        ContextWrap {
+            context: Context::Single(ACTIVITYSTREAMS.to_string()),
+            item: self,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -101,4 +135,31 @@ mod tests {
             )),
         }
     }
+    #[test]
+    fn deserialize_post_delete() -> Result<(), String> {
+        let example = r##"
+{
+  "@context": "https://www.w3.org/ns/activitystreams",
+  "id": "https://mastodon.social/users/Hibur/statuses/112963514254848346#delete",
+  "type": "Delete",
+  "actor": "https://mastodon.social/users/Hibur",
+  "to": [
+    "https://www.w3.org/ns/activitystreams#Public"
+  ],
+  "object": {
+    "id": "https://mastodon.social/users/Hibur/statuses/112963514254848346",
+    "type": "Tombstone",
+    "atomUri": "https://mastodon.social/users/Hibur/statuses/112963514254848346"
+  }
+}
+        "##;
+
+        let deserialized: ContextWrap<Delete> =
+            serde_json::from_str(example).map_err(|x| x.to_string())?;
+        assert_eq!(
+            deserialized.item.object_id().as_str(),
+            "https://mastodon.social/users/Hibur/statuses/112963514254848346"
+        );
+        Ok(())
+    }
 }
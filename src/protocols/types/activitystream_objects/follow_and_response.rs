@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use super::{
+    context::{Context, ContextWrap, ACTIVITYSTREAMS},
+    link::RangeLinkItem,
+};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ResponseType {
     /// Indicates that the actor accepts the object. The target property
@@ -46,7 +51,39 @@ pub struct FollowResponse {
     pub type_field: ResponseType,
     pub id: Url,
     pub actor: Url,
-    pub object: Url,
+    /// most software embeds the follow being responded to
+    pub object: RangeLinkItem<Follow>,
+}
+
+impl FollowResponse {
+    /// responds to a follow on behalf of the user that was followed
+    pub fn new(type_field: ResponseType, follow: Follow) -> Self {
+        let id = Url::parse(&format!(
+            "{}#responses/follows/{}",
+            follow.object,
+            uuid::Uuid::now_v7()
+        ))
+        .expect("generated invalid url");
+        FollowResponse {
+            type_field,
+            id,
+            actor: follow.object.clone(),
+            object: RangeLinkItem::Item(follow),
+        }
+    }
+    /// the id of the follow being responded to
+    pub fn follow_id(&self) -> &Url {
+        match &self.object {
+            RangeLinkItem::Item(x) => &x.id,
+            RangeLinkItem::Link(x) => x.get_id(),
+        }
+    }
+    pub fn wrap_context(self) -> ContextWrap<Self> {
+        ContextWrap {
+            context: Context::Single(ACTIVITYSTREAMS.to_string()),
+            item: self,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -107,3 +144,18 @@ pub struct Follow {
     pub actor: Url,
     pub object: Url,
 }
+
+impl Follow {
+    /// follows we send out are identified by a fragment of the follower's
+    /// id so that responses which only link the follow can be traced back
+    pub fn new(actor: Url, object: Url) -> Self {
+        let id = Url::parse(&format!("{}#follows/{}", actor, uuid::Uuid::now_v7()))
+            .expect("generated invalid url");
+        Follow {
+            type_field: FollowType::Follow,
+            id,
+            actor,
+            object,
+        }
+    }
+}
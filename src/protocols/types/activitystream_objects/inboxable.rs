@@ -6,11 +6,15 @@ use crate::{
 };
 
 use super::{
+    announce::Announce,
     create::Create,
     delete::Delete,
     follow_and_response::{Follow, FollowResponse},
+    like::Like,
     link::RangeLinkItem,
     postable::ApPostable,
+    undo::Undo,
+    update::{Update, UpdateObject},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -32,6 +36,10 @@ pub enum Inboxable {
     Delete(Delete),
     Follow(Follow),
     FollowResponse(FollowResponse),
+    Undo(Undo),
+    Update(Update),
+    Like(Like),
+    Announce(Announce),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -46,6 +54,11 @@ pub enum VerifiedInboxable {
     Delete(Delete),
     Follow(Follow),
     FollowResponse(FollowResponse),
+    Undo(Undo),
+    Update(Update),
+    /// likes and dislikes
+    Like(Like),
+    Announce(Announce),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -64,7 +77,7 @@ impl Inboxable {
         match self {
             Inboxable::Postable(postable) => match postable.verify(origin_domain) {
                 Ok(x) => Ok(VerifiedInboxable::Postable(x)),
-                Err(x) => return Err(x),
+                Err(x) => Err(x),
             },
             Inboxable::Create(create) => Ok({
                 let postable = match create.object {
@@ -89,7 +102,8 @@ impl Inboxable {
             }),
             Inboxable::Delete(delete) => {
                 if delete.actor.domain().ne(&Some(origin_domain))
-                    || delete.object.domain().ne(&Some(origin_domain))
+                    || delete.id.domain().ne(&Some(origin_domain))
+                    || delete.object_id().domain().ne(&Some(origin_domain))
                 {
                     return Err(InboxableVerifyErr::ForgedAttribution);
                 }
@@ -111,6 +125,56 @@ impl Inboxable {
                 }
                 Ok(VerifiedInboxable::FollowResponse(follow_response))
             }
+            Inboxable::Undo(undo) => {
+                if undo.actor.domain().ne(&Some(origin_domain))
+                    || undo.id.domain().ne(&Some(origin_domain))
+                {
+                    return Err(InboxableVerifyErr::ForgedAttribution);
+                }
+                // only the actor of an activity may undo it
+                let owned = match &undo.object {
+                    RangeLinkItem::Item(x) => x.actor.eq(&undo.actor),
+                    RangeLinkItem::Link(x) => x.get_id().domain().eq(&Some(origin_domain)),
+                };
+                if !owned {
+                    return Err(InboxableVerifyErr::ForgedAttribution);
+                }
+                Ok(VerifiedInboxable::Undo(undo))
+            }
+            Inboxable::Update(update) => {
+                if update.actor.domain().ne(&Some(origin_domain))
+                    || update.id.domain().ne(&Some(origin_domain))
+                {
+                    return Err(InboxableVerifyErr::ForgedAttribution);
+                }
+                // actors may only update themselves and their own posts,
+                // edited posts are handled like ones being created
+                match &update.object {
+                    UpdateObject::Actor(actor) if actor.id.eq(&update.actor) => {
+                        Ok(VerifiedInboxable::Update(update))
+                    }
+                    UpdateObject::Postable(postable) if postable.actor().eq(&update.actor) => Ok(
+                        VerifiedInboxable::Postable(postable.clone().verify(origin_domain)?),
+                    ),
+                    _ => Err(InboxableVerifyErr::ForgedAttribution),
+                }
+            }
+            Inboxable::Like(like) => {
+                if like.actor.domain().ne(&Some(origin_domain))
+                    || like.id.domain().ne(&Some(origin_domain))
+                {
+                    return Err(InboxableVerifyErr::ForgedAttribution);
+                }
+                Ok(VerifiedInboxable::Like(like))
+            }
+            Inboxable::Announce(announce) => {
+                if announce.actor.domain().ne(&Some(origin_domain))
+                    || announce.id.domain().ne(&Some(origin_domain))
+                {
+                    return Err(InboxableVerifyErr::ForgedAttribution);
+                }
+                Ok(VerifiedInboxable::Announce(announce))
+            }
         }
     }
 }
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum LikeType {
+    /// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-like
+    Like,
+    /// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-dislike
+    Dislike,
+}
+
+/// Indicates that the actor likes, recommends or endorses the object,
+/// or with a dislike the opposite.
+///
+/// ```json
+/// {
+///   "@context": "https://www.w3.org/ns/activitystreams",
+///   "id": "https://mastodon.social/users/Gargron#likes/1234",
+///   "type": "Like",
+///   "actor": "https://mastodon.social/users/Gargron",
+///   "object": "https://example.com/ap/users/sally/statuses/1"
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Like {
+    #[serde(rename = "type")]
+    pub type_field: LikeType,
+    pub id: Url,
+    pub actor: Url,
+    pub object: Url,
+}
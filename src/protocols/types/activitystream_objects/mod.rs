@@ -1,5 +1,6 @@
 pub mod activities;
 pub mod actors;
+pub mod announce;
 pub mod collections;
 pub mod context;
 pub mod core_types;
@@ -7,6 +8,7 @@ pub mod create;
 pub mod delete;
 pub mod follow_and_response;
 pub mod inboxable;
+pub mod like;
 pub mod link;
 pub mod new_post;
 pub mod note;
@@ -15,3 +17,5 @@ pub mod postable;
 pub mod public_key;
 pub mod question;
 pub mod tombstone;
+pub mod undo;
+pub mod update;
@@ -1,8 +1,11 @@
-use super::super::versia_types::serde_fns::{deserialize_time, serialize_time};
+use super::super::versia_types::serde_fns::{
+    deserialize_optional_time, deserialize_time, serialize_optional_time, serialize_time,
+};
 use super::collections::Collection;
 use super::postable::ApPostable;
 use super::{
-    collections::ExtendsCollection, context::Context, core_types::OptionalArray,
+    context::{Context, ACTIVITYSTREAMS},
+    core_types::OptionalArray,
     link::LinkSimpleOrExpanded,
 };
 use serde::{Deserialize, Serialize};
@@ -52,12 +55,21 @@ pub struct Note {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub to: Option<OptionalArray<LinkSimpleOrExpanded>>,
 
+    /// when the note was last edited
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_optional_time")]
+    #[serde(serialize_with = "serialize_optional_time")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub updated: Option<String>,
+    pub updated: Option<i64>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<String>,
 
+    /// the mastodon extension marking the content as sensitive, used
+    /// with [`Note::summary`] as a content warning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensitive: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tag: Option<OptionalArray<LinkSimpleOrExpanded>>,
 
@@ -85,7 +97,7 @@ pub struct Note {
 
 impl Note {
     pub fn get_context() -> Context {
-        todo!()
+        Context::Single(ACTIVITYSTREAMS.to_string())
     }
 }
 
@@ -1,7 +1,14 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use super::{context::ContextWrap, inboxable::InboxableVerifyErr, note::Note, question::Question};
+use super::{
+    context::ContextWrap,
+    core_types::OptionalArray,
+    inboxable::InboxableVerifyErr,
+    link::{LinkSimpleOrExpanded, LinkType},
+    note::Note,
+    question::Question,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
@@ -24,6 +31,26 @@ impl ApPostable {
             ApPostable::Note(note) => &note.attributed_to,
         }
     }
+    /// the users linked by mention tags. questions don't carry tags
+    pub fn mentions(&self) -> Vec<Url> {
+        let tags = match self {
+            ApPostable::Note(note) => match &note.tag {
+                Some(OptionalArray::Single(x)) => vec![x],
+                Some(OptionalArray::Multiple(x)) => x.iter().collect(),
+                None => vec![],
+            },
+            ApPostable::Question(_) => vec![],
+        };
+        tags.into_iter()
+            .filter_map(|x| match x {
+                LinkSimpleOrExpanded::Expanded(link) => match link.type_field {
+                    LinkType::Mention => Some(link.href.clone()),
+                    _ => None,
+                },
+                LinkSimpleOrExpanded::Simple(_) => None,
+            })
+            .collect()
+    }
     pub fn verify(self, origin_domain: &str) -> Result<Self, InboxableVerifyErr> {
         if self.id().domain().ne(&Some(origin_domain))
             || self.actor().domain().ne(&Some(origin_domain))
@@ -1 +1,19 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum TombstoneType {
+    Tombstone,
+}
+
+/// A Tombstone represents a content object that has been deleted.
+/// deletes of posts usually embed one in place of the post
+///
+/// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-tombstone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    #[serde(rename = "type")]
+    pub type_field: TombstoneType,
+    pub id: Url,
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::link::RangeLinkItem;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum UndoType {
+    Undo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum UndoneType {
+    Follow,
+    Like,
+    Announce,
+}
+
+/// the activity being undone. we only keep what is needed to
+/// find whatever the activity created
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoneActivity {
+    #[serde(rename = "type")]
+    pub type_field: UndoneType,
+    pub id: Url,
+    pub actor: Url,
+    pub object: Url,
+}
+
+/// Indicates that the actor is undoing the object. In most cases,
+/// the object will be an Activity describing some previously performed
+/// action (for instance, a person may have previously "liked" an article
+/// but, for whatever reason, might choose to undo that like at some
+/// later point in time).
+///
+/// ```json
+/// {
+///   "@context": "https://www.w3.org/ns/activitystreams",
+///   "id": "https://mastodon.social/users/Gargron#likes/1234/undo",
+///   "type": "Undo",
+///   "actor": "https://mastodon.social/users/Gargron",
+///   "object": {
+///     "id": "https://mastodon.social/users/Gargron#likes/1234",
+///     "type": "Like",
+///     "actor": "https://mastodon.social/users/Gargron",
+///     "object": "https://example.com/users/sally/statuses/1"
+///   }
+/// }
+/// ```
+///
+/// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-undo
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Undo {
+    #[serde(rename = "type")]
+    pub type_field: UndoType,
+    pub id: Url,
+    pub actor: Url,
+    /// some software only links the activity being undone
+    pub object: RangeLinkItem<UndoneActivity>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{context::ContextWrap, link::RangeLinkItem};
+
+    use super::{Undo, UndoneType};
+
+    #[test]
+    fn deserialize_undo() -> Result<(), String> {
+        let example = r##"
+{
+  "@context": "https://www.w3.org/ns/activitystreams",
+  "id": "https://mastodon.social/users/Hibur#follows/3605/undo",
+  "type": "Undo",
+  "actor": "https://mastodon.social/users/Hibur",
+  "object": {
+    "id": "https://mastodon.social/2f7e7b3c-3b43-4a1c-a9a1-6bbf1e5e3b14",
+    "type": "Follow",
+    "actor": "https://mastodon.social/users/Hibur",
+    "object": "https://example.com/ap/users/sally"
+  }
+}
+        "##;
+
+        let deserialized: ContextWrap<Undo> =
+            serde_json::from_str(example).map_err(|x| x.to_string())?;
+        match deserialized.item.object {
+            RangeLinkItem::Item(x) if matches!(x.type_field, UndoneType::Follow) => Ok(()),
+            x => Err(format!("undo deserialized with the wrong object: {:?}", x)),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{
+    actors::Actor,
+    context::{Context, ContextItem, ContextWrap, ACTIVITYSTREAMS, SECURITY},
+    postable::ApPostable,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum UpdateType {
+    Update,
+}
+
+/// Indicates that the actor has updated the object. Note, however,
+/// that this vocabulary does not define a mechanism for describing
+/// the actual set of modifications made to object.
+///
+/// https://www.w3.org/TR/activitystreams-vocabulary/#dfn-update
+///
+/// actors update their own profile and edit their own posts, the
+/// object is the full actor or post after the update
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Update {
+    #[serde(rename = "type")]
+    pub type_field: UpdateType,
+    pub id: Url,
+    pub actor: Url,
+    pub object: UpdateObject,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum UpdateObject {
+    Actor(Actor),
+    Postable(ApPostable),
+}
+
+impl Update {
+    /// announces the new state of the actor's own profile
+    pub fn new(actor: Actor) -> Self {
+        let id = Url::parse(&format!("{}#updates/{}", actor.id, uuid::Uuid::now_v7()))
+            .expect("generated invalid url");
+        Update {
+            type_field: UpdateType::Update,
+            id,
+            actor: actor.id.clone(),
+            object: UpdateObject::Actor(actor),
+        }
+    }
+    /// announces the edited version of a post
+    pub fn new_post(post: ApPostable) -> Self {
+        let id = Url::parse(&format!("{}#updates/{}", post.id(), uuid::Uuid::now_v7()))
+            .expect("generated invalid url");
+        Update {
+            type_field: UpdateType::Update,
+            id,
+            actor: post.actor().clone(),
+            object: UpdateObject::Postable(post),
+        }
+    }
+    /// the security context is needed for the key of the actor
+    pub fn wrap_context(self) -> ContextWrap<Self> {
+        ContextWrap {
+            context: Context::Array(vec![
+                ContextItem::String(ACTIVITYSTREAMS.to_string()),
+                ContextItem::String(SECURITY.to_string()),
+            ]),
+            item: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_edit() -> Result<(), String> {
+        let update = r#"
+{
+    "@context": "https://www.w3.org/ns/activitystreams",
+    "id": "https://mastodon.social/users/Gargron/statuses/1234#updates/1720716000",
+    "type": "Update",
+    "actor": "https://mastodon.social/users/Gargron",
+    "object": {
+        "id": "https://mastodon.social/users/Gargron/statuses/1234",
+        "type": "Note",
+        "attributedTo": "https://mastodon.social/users/Gargron",
+        "published": "2024-07-11T16:11:14Z",
+        "updated": "2024-07-11T16:40:00Z",
+        "content": "<p>edited</p>"
+    }
+}
+"#;
+        let deserialized: Update = serde_json::from_str(update)
+            .map_err(|x| format!("update deserialize failed: {}", x))?;
+        match deserialized.object {
+            UpdateObject::Postable(ApPostable::Note(note)) if note.updated.is_some() => Ok(()),
+            x => Err(format!("update was not of an edited note: {:?}", x)),
+        }
+    }
+}
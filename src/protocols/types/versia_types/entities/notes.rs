@@ -60,7 +60,7 @@ pub struct Note {
     pub category: Option<Category>,
     /// The content of the note. Must be text format
     /// (text/html, text/markdown, etc). Must not be remote.
-    pub content: Option<ContentFormat>,
+    pub content: Option<TextContentFormat>,
     /// Device used to post the note. Useful for functionality such as Twitter's "posted via" feature.
     pub device: Option<Device>,
     pub extensions: Option<NoteExtensions>,
@@ -76,6 +76,8 @@ pub struct Note {
     /// contain mentions in the content, however only the
     /// mentions in this field should trigger notifications.
     pub mentions: Option<Vec<Url>>,
+    /// URI of the note this note is a reply to.
+    pub replies_to: Option<Url>,
     /// used with [`Note::is_sensitive`] as a "content warning" feature.
     pub subject: Option<String>,
 }
@@ -1,11 +1,14 @@
 use base64::Engine;
-// use ed25519::pkcs8::DecodePublicKey;
-// use ed25519::Signature;
-// use ed25519_dalek::Verifier;
+use ed25519_dalek::{
+    pkcs8::{spki::der::pem::LineEnding, DecodePublicKey, EncodePublicKey},
+    Signature, Verifier, VerifyingKey,
+};
 use serde::{de::Error as DeError, Deserializer, Serializer};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::cryptography::{error::Error, key::Key};
+
 /// The user's public key. Must follow the Versia Public Key format.
 /// actor may be a URI to another user's profile, in which case this
 /// key may allow the other user act on behalf of this user (see delegation).
@@ -29,35 +32,34 @@ pub enum AlgorithmsPublicKey {
     Ed25519(Ed25519Public),
 }
 
-// impl crate::cryptography::key::PublicKey for AlgorithmsPublicKey {
-//     fn verify(&self, plain_content: &str, signature: &str) -> bool {
-//         match self {
-//             AlgorithmsPublicKey::Ed25519(ed25519_public) => {
-//                 let Ok(signature) = Signature::from_slice(signature.as_bytes()) else {
-//                     return false;
-//                 };
-//                 ed25519_public
-//                     .key
-//                     .verify(plain_content.as_bytes(), &signature)
-//                     .is_ok()
-//             }
-//         }
-//     }
-
-//     fn from_pem(
-//         pem: &str,
-//         algorithm: crate::cryptography::key::KeyType,
-//     ) -> Result<Self, crate::cryptography::key::ParseErr> {
-//         match algorithm {
-//             crate::cryptography::key::KeyType::Ed25519 => {
-//                 let Ok(val) = ed25519_dalek::VerifyingKey::from_public_key_pem(pem) else {
-//                     return Err(crate::cryptography::key::ParseErr::Failure);
-//                 };
-//                 Ok(AlgorithmsPublicKey::Ed25519(Ed25519Public { key: val }))
-//             }
-//         }
-//     }
-// }
+impl Key for AlgorithmsPublicKey {
+    fn from_pem(pem: &[u8]) -> Result<Self, Error> {
+        let pem = String::from_utf8(pem.to_vec())?;
+        let key = VerifyingKey::from_public_key_pem(&pem)?;
+        Ok(AlgorithmsPublicKey::Ed25519(Ed25519Public { key }))
+    }
+
+    fn to_pem(&self) -> Result<String, Error> {
+        match self {
+            AlgorithmsPublicKey::Ed25519(public) => {
+                Ok(public.key.to_public_key_pem(LineEnding::LF)?)
+            }
+        }
+    }
+}
+
+impl crate::cryptography::key::PublicKey for AlgorithmsPublicKey {
+    fn verify(&self, plain_content: &[u8], signature: &[u8]) -> bool {
+        match self {
+            AlgorithmsPublicKey::Ed25519(public) => {
+                let Ok(signature) = Signature::from_slice(signature) else {
+                    return false;
+                };
+                public.key.verify(plain_content, &signature).is_ok()
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Ed25519Public {
@@ -69,8 +71,11 @@ impl Serialize for Ed25519Public {
     where
         S: Serializer,
     {
-        let binary = self.key.as_bytes();
-        let base64_val = base64::prelude::BASE64_STANDARD.encode(binary);
+        let binary = match self.key.to_public_key_der() {
+            Ok(ok) => ok,
+            Err(err) => return Err(serde::ser::Error::custom(err)),
+        };
+        let base64_val = base64::prelude::BASE64_STANDARD.encode(binary.as_bytes());
         serializer.serialize_str(&base64_val)
     }
 }
@@ -85,10 +90,11 @@ impl<'de> Deserialize<'de> for Ed25519Public {
             Ok(ok) => ok,
             Err(err) => return Err(D::Error::custom(err)),
         };
-        let Ok(binary) = binary.try_into() else {
-            return Err(D::Error::custom("invalid binary length"));
+        // keys should be spki encoded but some implementations send the raw key
+        let key = match <[u8; 32]>::try_from(binary.as_slice()) {
+            Ok(raw) => VerifyingKey::from_bytes(&raw).map_err(D::Error::custom),
+            Err(_) => VerifyingKey::from_public_key_der(&binary).map_err(D::Error::custom),
         };
-        let key = ed25519_dalek::VerifyingKey::from_bytes(&binary);
         let key = match key {
             Ok(ok) => ok,
             Err(err) => return Err(D::Error::custom(err)),
@@ -128,6 +134,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_spki_round_trip() -> Result<(), String> {
+        let key = Ed25519Public {
+            key: generate_verifying_key(),
+        };
+        let serialized = serde_json::to_string(&key).map_err(|x| x.to_string())?;
+        let der = key.key.to_public_key_der().map_err(|x| x.to_string())?;
+        let expected = base64::prelude::BASE64_STANDARD.encode(der.as_bytes());
+        assert_eq!(serialized, format!("\"{}\"", expected));
+
+        let deserialized: Ed25519Public =
+            serde_json::from_str(&serialized).map_err(|x| x.to_string())?;
+        assert_eq!(deserialized, key);
+
+        let pem = AlgorithmsPublicKey::Ed25519(key.clone())
+            .to_pem()
+            .map_err(|x| x.to_string())?;
+        let from_pem = AlgorithmsPublicKey::from_pem(pem.as_bytes()).map_err(|x| x.to_string())?;
+        assert_eq!(from_pem, AlgorithmsPublicKey::Ed25519(key));
+        Ok(())
+    }
+
     #[test]
     fn test_deserialize() -> Result<(), String> {
         //taken from the versia protocol examples
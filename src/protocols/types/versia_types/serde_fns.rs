@@ -23,6 +23,30 @@ where
     s.serialize_str(&time.to_rfc3339_opts(SecondsFormat::Millis, true))
 }
 
+/// for optional times, used along with `#[serde(default)]`
+pub fn deserialize_optional_time<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<&str>::deserialize(deserializer)? {
+        Some(input) => match DateTime::parse_from_rfc3339(input) {
+            Ok(ok) => Ok(Some(ok.timestamp_millis())),
+            Err(err) => Err(D::Error::custom(err)),
+        },
+        None => Ok(None),
+    }
+}
+
+pub fn serialize_optional_time<S>(x: &Option<i64>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match x {
+        Some(x) => serialize_time(x, s),
+        None => s.serialize_none(),
+    }
+}
+
 pub fn default_true() -> bool {
     true
 }
@@ -35,8 +35,22 @@ pub struct ImageContentFormat {
     pub gif: Option<ImageContent>,
 }
 
+impl ImageContentFormat {
+    /// any one of the provided images, preferring the most widely supported formats
+    pub fn best(&self) -> Option<&ImageContent> {
+        [
+            &self.png, &self.jpg, &self.webp, &self.gif, &self.avif, &self.heif,
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TextContentFormat {
+    #[serde(rename = "text/plain")]
+    pub plain: Option<TextContent>,
     #[serde(rename = "text/html")]
     pub html: Option<TextContent>,
     #[serde(rename = "text/markdown")]
@@ -45,6 +59,33 @@ pub struct TextContentFormat {
     pub misskeymarkdown: Option<TextContent>,
 }
 
+impl TextContentFormat {
+    pub fn from_html(html: String) -> Self {
+        TextContentFormat {
+            plain: None,
+            html: Some(TextContent::simple(html)),
+            markdown: None,
+            misskeymarkdown: None,
+        }
+    }
+    /// the best inline representation of this content as html.
+    /// plain text and markdown are used as is if no html is present
+    pub fn to_html(&self) -> Option<String> {
+        [
+            &self.html,
+            &self.markdown,
+            &self.misskeymarkdown,
+            &self.plain,
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|x| match &x.content {
+            TextOption::Simple(content) => Some(content.clone()),
+            TextOption::Remote(_) => None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AudioContentFormat {
     #[serde(rename = "audio/mp3")]
@@ -94,6 +135,18 @@ pub struct TextContent {
     pub hash: Option<Hash>,
 }
 
+impl TextContent {
+    pub fn simple(content: String) -> Self {
+        TextContent {
+            content: TextOption::Simple(content),
+            remote: false,
+            description: None,
+            size: None,
+            hash: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImageContent {
     pub content: Url,